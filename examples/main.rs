@@ -1,8 +1,22 @@
 extern crate lintparser;
+use std::env;
+use std::process;
+
 use lintparser::Check;
 
+/// Checks the project in the directory given as the argument, by default
+/// the sample project with warnings.
 fn main() {
-    let res = lintparser::cargo_check().expect("Oh noes!");
+    let dir = env::args().nth(1).unwrap_or_else(|| {
+        String::from(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/projects/warnings"))
+    });
+    let res = match lintparser::cargo_check_in(&dir) {
+        Ok(res) => res,
+        Err(err) => {
+            eprintln!("Could not check '{}': {:?}", dir, err);
+            process::exit(2);
+        },
+    };
     match res {
         Check::Perfect => {
             println!("No problems found");
@@ -20,4 +34,4 @@ fn main() {
             }
         },
     }
-}
\ No newline at end of file
+}
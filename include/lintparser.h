@@ -0,0 +1,50 @@
+/* The C interface of lintparser, built with the ffi feature.
+ *
+ * A check is an opaque handle, freed with lintparser_check_free. The strings
+ * of its problems are UTF-8 and NUL-terminated, owned by the handle and valid
+ * until it is freed. Every function accepts a null handle.
+ *
+ * The library is built as a cdylib (or a staticlib) with
+ * cargo rustc --lib --release --features ffi --crate-type cdylib */
+
+#ifndef LINTPARSER_H
+#define LINTPARSER_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The severities of lintparser_problem */
+#define LINTPARSER_ERROR 0
+#define LINTPARSER_WARNING 1
+#define LINTPARSER_NOTE 2
+#define LINTPARSER_HELP 3
+
+typedef struct LintCheck LintCheck;
+
+/* Checks the project in the directory, or returns null if the path is
+ * invalid or the check fails. */
+LintCheck *lintparser_check(const char *dir);
+
+/* Parses the stderr output of cargo, or returns null if it is invalid. */
+LintCheck *lintparser_parse(const char *output);
+
+void lintparser_check_free(LintCheck *check);
+
+size_t lintparser_problem_count(const LintCheck *check);
+size_t lintparser_error_count(const LintCheck *check);
+size_t lintparser_warning_count(const LintCheck *check);
+
+/* Writes a problem to the out-params that aren't null, and returns 1, or 0
+ * if the index is out of range. */
+int lintparser_problem(const LintCheck *check, size_t index,
+                       size_t *line, size_t *col, int *severity,
+                       const char **file, const char **message);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
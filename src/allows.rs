@@ -0,0 +1,164 @@
+//! Counting the lints that the source suppresses with ```#[allow]```, so that
+//! reports can show them next to the problems that are reported.
+//!
+//! Only attributes written in the source are found: allows that macros
+//! (e.g. derives and other proc-macros) generate are out of scope.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use scan;
+
+/// Where an allow attribute applies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllowScope {
+    /// ```#[allow(...)]```, for the item that follows it.
+    Outer,
+    /// ```#![allow(...)]```, for the enclosing module or crate.
+    Inner,
+}
+
+/// A lint allowed by an attribute in the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllowSite {
+    /// The file, relative to the scanned root.
+    pub file: PathBuf,
+    /// The line of the attribute (1-based).
+    pub line: usize,
+    pub lint: String,
+    pub scope: AllowScope,
+}
+
+/// Finds the ```#[allow(...)]``` and ```#![allow(...)]``` attributes, also
+/// inside ```cfg_attr```, in the Rust files under the root (see
+/// ```rust_files``` for the ignored paths), grouped by lint.
+pub fn count_allows<P: AsRef<Path>>(root: P) -> io::Result<BTreeMap<String, Vec<AllowSite>>> {
+    let root = root.as_ref();
+    let mut allows = BTreeMap::new();
+    for path in scan::rust_files(root)? {
+        let source = fs::read_to_string(&path)?;
+        let file = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+        for site in allows_in_source(&source, &file) {
+            allows.entry(site.lint.clone()).or_insert_with(Vec::new).push(site);
+        }
+    }
+    Ok(allows)
+}
+
+/// Finds the allow attributes of a single source file.
+fn allows_in_source(source: &str, file: &Path) -> Vec<AllowSite> {
+    let text = scan::blank_comments_and_strings(source);
+    let mut sites = Vec::new();
+    let mut line = 1;
+    let mut rest = &text[..];
+    while let Some(pos) = rest.find(['#', '\n']) {
+        let after = &rest[pos..];
+        if let Some(next) = after.strip_prefix('\n') {
+            line += 1;
+            rest = next;
+            continue;
+        }
+        let attr = after[1..].trim_start();
+        let (scope, attr) = match attr.strip_prefix('!') {
+            Some(attr) => (AllowScope::Inner, attr.trim_start()),
+            None => (AllowScope::Outer, attr),
+        };
+        rest = &after[1..];
+        if let Some(content) = attr.strip_prefix('[').and_then(bracketed) {
+            for lint in allowed_lints(content) {
+                sites.push(AllowSite { file: file.to_path_buf(), line, lint, scope });
+            }
+        }
+    }
+    sites
+}
+
+/// Returns the text up to the bracket that closes an already opened one.
+fn bracketed(text: &str) -> Option<&str> {
+    let mut depth = 0;
+    for (i, ch) in text.char_indices() {
+        match ch {
+            '[' | '(' => depth += 1,
+            ')' => depth -= 1,
+            ']' if depth == 0 => return Some(&text[..i]),
+            ']' => depth -= 1,
+            _ => {},
+        }
+    }
+    None
+}
+
+/// Splits a list at the commas that aren't nested in parentheses.
+fn split_list(list: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, ch) in list.char_indices() {
+        match ch {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(list[start..i].trim());
+                start = i + 1;
+            },
+            _ => {},
+        }
+    }
+    items.push(list[start..].trim());
+    items.retain(|item| ! item.is_empty());
+    items
+}
+
+/// Returns the arguments of an attribute like ```name(...)```.
+fn arguments<'a>(attr: &'a str, name: &str) -> Option<&'a str> {
+    let args = attr.strip_prefix(name)?.trim_start().strip_prefix('(')?;
+    args.rfind(')').map(|end| &args[..end])
+}
+
+/// Returns the lints allowed by the contents of an attribute.
+fn allowed_lints(attr: &str) -> Vec<String> {
+    let attr = attr.trim();
+    if let Some(args) = arguments(attr, "allow") {
+        split_list(args).into_iter()
+            // Skips the 'reason = "..."' of the allow
+            .filter(|lint| ! lint.contains('='))
+            .map(|lint| lint.split_whitespace().collect())
+            .collect()
+    } else if let Some(args) = arguments(attr, "cfg_attr") {
+        // The first argument is the condition
+        split_list(args).into_iter().skip(1).flat_map(allowed_lints).collect()
+    } else {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_allows_in_lists_and_cfg_attr() {
+        let source = "#![allow(dead_code)]\n\
+            // #[allow(commented_out)]\n\
+            const S: &str = \"#[allow(in_a_string)]\";\n\
+            #[allow(unused_imports, clippy::needless_return,\n    reason = \"legacy\")]\n\
+            fn f() {}\n\
+            #[cfg_attr(feature = \"x\", allow(unused_mut), deny(missing_docs))]\n\
+            #[cfg_attr(test, cfg_attr(unix, allow(clippy :: eq_op)))]\n\
+            #[derive(Debug)] #[warn(dead_code)]\n\
+            struct S;";
+        let sites = allows_in_source(source, Path::new("src/lib.rs"));
+        let found: Vec<(usize, &str, AllowScope)> = sites.iter()
+            .map(|site| (site.line, &site.lint[..], site.scope))
+            .collect();
+        assert_eq!(found, vec![
+            (1, "dead_code", AllowScope::Inner),
+            (4, "unused_imports", AllowScope::Outer),
+            (4, "clippy::needless_return", AllowScope::Outer),
+            (7, "unused_mut", AllowScope::Outer),
+            (8, "clippy::eq_op", AllowScope::Outer),
+        ]);
+    }
+}
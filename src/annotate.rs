@@ -0,0 +1,291 @@
+//! Source text annotated with the problems in it, for pages that show the
+//! source of a project with its problems.
+//!
+//! Columns are counted in characters, as rustc does, so a tab is a single
+//! column. Rendering expands tabs to the next multiple of the tab width, and
+//! underlines are shifted to stay under the characters of their spans.
+
+use std::collections::BTreeMap;
+
+use drift::quoted_line_matches;
+use ProblemDescription;
+
+/// How problems are marked in annotated source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnnotationStyle {
+    /// A ```// ^ warning: ...``` comment line after each line with problems.
+    Comment,
+    /// HTML-escaped source, with the spans of the problems wrapped in
+    /// ```<span class="lint-warning" title="...">``` elements.
+    Html,
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Options for annotating source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnnotateOptions {
+    pub style: AnnotationStyle,
+    /// The width that tabs are expanded to, or ```None``` to keep them.
+    pub tab_width: Option<usize>,
+}
+
+/// Returns the line with its tabs expanded to the next multiple of the width.
+fn expand_tabs(line: &str, tab_width: usize) -> String {
+    let tab_width = tab_width.max(1);
+    let mut expanded = String::with_capacity(line.len());
+    let mut column = 0;
+    for ch in line.chars() {
+        if ch == '\t' {
+            let width = tab_width - column % tab_width;
+            expanded.extend((0..width).map(|_| ' '));
+            column += width;
+        } else {
+            expanded.push(ch);
+            column += 1;
+        }
+    }
+    expanded
+}
+
+/// Returns the 0-based display column of the character column (1-based) of
+/// the line, when tabs are expanded to the width.
+fn display_column(line: &str, col: usize, tab_width: usize) -> usize {
+    let before: String = line.chars().take(col.saturating_sub(1)).collect();
+    expand_tabs(&before, tab_width).chars().count()
+}
+
+/// Returns the source with markers for the problems, which should all be in
+/// the file of the source. A span over several lines is marked on its first
+/// line, and problems outside of the source or drifted from it are left
+/// out.
+pub fn annotate_source(source: &str, problems: &[&ProblemDescription], style: AnnotationStyle)
+        -> String {
+    annotate_source_with(source, problems, &AnnotateOptions { style, tab_width: None })
+}
+
+/// Returns the source with markers for the problems (see
+/// ```annotate_source```), with the options.
+pub fn annotate_source_with(source: &str, problems: &[&ProblemDescription],
+        options: &AnnotateOptions) -> String {
+    let style = options.style;
+    // The problems by the 0-based index of their first line
+    let mut by_line: BTreeMap<usize, Vec<&ProblemDescription>> = BTreeMap::new();
+    for &problem in problems.iter().filter(|problem| ! problem.drifted) {
+        if let Some(index) = problem.message.start_line.checked_sub(1) {
+            by_line.entry(index).or_default().push(problem);
+        }
+    }
+
+    let mut lines: Vec<String> = source.split_inclusive('\n').map(|line| {
+        let line = match options.tab_width {
+            Some(tab_width) => expand_tabs(line, tab_width),
+            None => String::from(line),
+        };
+        match style {
+            AnnotationStyle::Comment => line,
+            AnnotationStyle::Html => escape_html(&line),
+        }
+    }).collect();
+    // From the bottom up, so that the lines above keep their indices
+    for (&index, problems) in by_line.iter().rev() {
+        if index >= lines.len() {
+            continue;
+        }
+        let line = source.split_inclusive('\n').nth(index).unwrap_or("");
+        lines[index] = match (style, options.tab_width) {
+            (AnnotationStyle::Comment, Some(tab_width)) =>
+                comment_markers(&expand_tabs(line, tab_width), problems),
+            (AnnotationStyle::Comment, None) => comment_markers(line, problems),
+            (AnnotationStyle::Html, tab_width) => html_spans(line, problems, tab_width),
+        };
+    }
+    lines.concat()
+}
+
+/// Returns the first line of the span of the problem like rustc shows it,
+/// with its line number and the span underlined, or ```None``` if the line
+/// isn't in the source or the problem drifted from it. Tabs are expanded to
+/// the width.
+///
+/// ```text
+/// 2 |     let x = 1;
+///   |         ^ warning: unused variable: `x`
+/// ```
+pub fn render_snippet(source: &str, problem: &ProblemDescription, tab_width: usize)
+        -> Option<String> {
+    let note = &problem.message;
+    let line = source.lines().nth(note.start_line.checked_sub(1)?)?;
+    // Code that the problem isn't about would mislead
+    let quoted = problem.source_line.as_ref();
+    if problem.drifted || quoted.is_some_and(|quoted| ! quoted_line_matches(quoted, line)) {
+        return None;
+    }
+    let length = line.chars().count();
+    let start = note.start_col.clamp(1, length + 1);
+    let end = if note.end_line == note.start_line {
+        note.end_col.clamp(start, length + 1)
+    } else {
+        length + 1
+    };
+    let from = display_column(line, start, tab_width);
+    let to = display_column(line, end, tab_width).max(from + 1);
+    let number = note.start_line.to_string();
+    let gutter = " ".repeat(number.len());
+    Some(format!("{} | {}\n{} | {}{} {}: {}\n", number, expand_tabs(line, tab_width), gutter,
+        " ".repeat(from), "^".repeat(to - from), problem.severity.name(),
+        note.message.lines().next().unwrap_or("")))
+}
+
+/// Returns the line followed by a comment line for each problem.
+fn comment_markers(line: &str, problems: &[&ProblemDescription]) -> String {
+    let content = line.trim_end_matches(['\r', '\n']);
+    let ending = &line[content.len()..];
+    let indentation = &content[..content.len() - content.trim_start().len()];
+    let mut annotated = String::from(content);
+    for problem in problems {
+        let message = problem.message.message.lines().next().unwrap_or("");
+        annotated.push_str(if ending.is_empty() { "\n" } else { ending });
+        annotated.push_str(&format!("{}// ^ {}: {}", indentation,
+            problem.severity.name(), message));
+    }
+    annotated.push_str(ending);
+    annotated
+}
+
+/// Returns the escaped line with the spans of the problems wrapped, and its
+/// tabs expanded to the width if any. Where spans overlap, the later one
+/// starts where the earlier one ends.
+fn html_spans(line: &str, problems: &[&ProblemDescription], tab_width: Option<usize>)
+        -> String {
+    let content = line.trim_end_matches(['\r', '\n']);
+    let content_len = content.chars().count();
+    // The character index in the rendered line of a 1-based column
+    let index = |col: usize| match tab_width {
+        Some(tab_width) => display_column(content, col, tab_width),
+        None => col.saturating_sub(1),
+    };
+    let expanded = tab_width.map(|tab_width| expand_tabs(line, tab_width));
+    let chars: Vec<char> = expanded.as_deref().unwrap_or(line).chars().collect();
+    let content_end = index(content_len + 1);
+    let mut spans: Vec<(usize, usize, &ProblemDescription)> = problems.iter().map(|&problem| {
+        let note = &problem.message;
+        let start = index(note.start_col.clamp(1, content_len + 1));
+        let end = if note.end_line == note.start_line {
+            index(note.end_col.clamp(1, content_len + 1)).max(start)
+        } else {
+            content_end
+        };
+        (start, end, problem)
+    }).collect();
+    spans.sort_by_key(|&(start, end, _)| (start, end));
+
+    let mut annotated = String::new();
+    let mut position = 0;
+    for (start, end, problem) in spans {
+        let start = start.max(position);
+        let end = end.max(start);
+        annotated.push_str(&escape_html(&chars[position..start].iter().collect::<String>()));
+        let title = problem.message.message.lines().next().unwrap_or("");
+        annotated.push_str(&format!("<span class=\"lint-{}\" title=\"{}\">{}</span>",
+            problem.severity.name(), escape_html(title),
+            escape_html(&chars[start..end].iter().collect::<String>())));
+        position = end;
+    }
+    annotated.push_str(&escape_html(&chars[position..].iter().collect::<String>()));
+    annotated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parse_output;
+
+    const SOURCE: &str = "\
+fn main() {
+    let x = if a < b { 1 } else { 2 };
+    let y = \"<&>\";
+}
+";
+
+    const OUTPUT: &str = "\
+src/main.rs:2:9: 2:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+src/main.rs:3:9: 3:10 warning: unused variable: `y`, #[warn(unused_variables)] on by default
+src/main.rs:2:16: 2:17 error: cannot find value `a` in this scope [E0425]
+src/main.rs:1:1: 4:2 warning: function `main` is never used
+src/main.rs:9:1: 9:2 warning: out of the source
+";
+
+    #[test]
+    fn comments_follow_their_lines() {
+        let check = parse_output(OUTPUT);
+        let problems: Vec<&ProblemDescription> = check.problems().iter().collect();
+        assert_eq!(annotate_source(SOURCE, &problems, AnnotationStyle::Comment), "\
+fn main() {
+// ^ warning: function `main` is never used
+    let x = if a < b { 1 } else { 2 };
+    // ^ warning: unused variable: `x`, #[warn(unused_variables)] on by default
+    // ^ error: cannot find value `a` in this scope [E0425]
+    let y = \"<&>\";
+    // ^ warning: unused variable: `y`, #[warn(unused_variables)] on by default
+}
+");
+    }
+
+    /// Indented with a space and two tabs, and a tab inside the line.
+    const TABBED_SOURCE: &str = "fn main() {\n \t\tlet\tx = 1;\n}\n";
+
+    #[test]
+    fn snippets_expand_tabs_and_shift_the_underline() {
+        let check = parse_output("\
+src/main.rs:2:8: 2:9 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+");
+        let problem = &check.problems()[0];
+        assert_eq!(render_snippet(TABBED_SOURCE, problem, 4).unwrap(), "\
+2 |         let x = 1;
+  |             ^ warning: unused variable: `x`, #[warn(unused_variables)] on by default
+");
+        for &tab_width in &[4, 8] {
+            let snippet = render_snippet(TABBED_SOURCE, problem, tab_width).unwrap();
+            let lines: Vec<&str> = snippet.lines().collect();
+            let caret = lines[1].find('^').unwrap();
+            assert_eq!(lines[0][caret..].chars().next(), Some('x'), "{}", snippet);
+        }
+        let mut outside = problem.clone();
+        outside.message.start_line = 9;
+        assert_eq!(render_snippet(TABBED_SOURCE, &outside, 4), None);
+
+        let options = AnnotateOptions { style: AnnotationStyle::Html, tab_width: Some(8) };
+        assert_eq!(annotate_source_with(TABBED_SOURCE, &[problem], &options), format!("\
+fn main() {{
+{}let{}<span class=\"lint-warning\" \
+title=\"unused variable: `x`, #[warn(unused_variables)] on by default\">x</span> = 1;
+}}
+", " ".repeat(16), " ".repeat(5)));
+    }
+
+    #[test]
+    fn html_wraps_spans_and_escapes_the_source() {
+        let check = parse_output(OUTPUT);
+        let problems: Vec<&ProblemDescription> = check.problems()[1..4].iter().collect();
+        assert_eq!(annotate_source(SOURCE, &problems, AnnotationStyle::Html), "\
+<span class=\"lint-warning\" title=\"function `main` is never used\">fn main() {</span>
+    let x = if <span class=\"lint-error\" title=\"cannot find value `a` in this scope [E0425]\">a</span> &lt; b { 1 } else { 2 };
+    let <span class=\"lint-warning\" title=\"unused variable: `y`, #[warn(unused_variables)] on by default\">y</span> = &quot;&lt;&amp;&gt;&quot;;
+}
+");
+    }
+}
@@ -0,0 +1,211 @@
+//! Checking a project on the runtime of Tokio, with the ```tokio``` feature,
+//! for applications that are asynchronous throughout.
+//!
+//! The futures and the stream read the JSON messages of cargo with the
+//! parser of ```CheckStream```. They are written by hand rather than with
+//! ```async fn```, which the 2015 edition of the crate doesn't have, and run
+//! on any Tokio runtime with its I/O driver enabled.
+
+use std::future::Future;
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
+use std::process::{ExitStatus, Output};
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use tokio::io::{AsyncBufReadExt, BufReader, Lines};
+use tokio::process::{Child, ChildStderr, ChildStdout, Command};
+
+use checker::json_command;
+use stream::MessageParser;
+use {Check, CheckError, Checker, ParseError, ProblemDescription};
+
+/// Waiting for cargo to exit, once its output has been read.
+type Wait = Pin<Box<dyn Future<Output = io::Result<Output>> + Send>>;
+
+/// A running ```cargo check``` on a Tokio runtime that yields the problems
+/// of its JSON messages as cargo writes them, like ```CheckStream```.
+///
+/// Dropping the stream, or the future of its ```finish```, kills cargo.
+pub struct AsyncCheckStream {
+    parser: MessageParser,
+    child: Option<Child>,
+    stdout: Lines<BufReader<ChildStdout>>,
+    /// Read along with stdout, so that cargo never blocks on it.
+    stderr: Option<Lines<BufReader<ChildStderr>>>,
+    stderr_text: String,
+    ended: bool,
+    read_error: Option<io::Error>,
+}
+
+/// Starts ```cargo check``` on the current directory on the Tokio runtime
+/// (see ```cargo_check_stream_async_in```).
+pub fn cargo_check_stream_async() -> Result<AsyncCheckStream, CheckError> {
+    cargo_check_stream_async_in(".")
+}
+
+/// Starts ```cargo check``` on the project in the directory, which must
+/// contain a ```Cargo.toml```, with its problems given as they are found.
+/// This must be called on a Tokio runtime.
+pub fn cargo_check_stream_async_in<P: AsRef<Path>>(dir: P)
+        -> Result<AsyncCheckStream, CheckError> {
+    Checker::new().dir(dir.as_ref()).stream_async()
+}
+
+impl Checker {
+    /// Starts the check on the Tokio runtime, with the problems of its JSON
+    /// messages given as they are found. The directory must contain a
+    /// ```Cargo.toml```. The fallbacks, retries, timeout and path maps of a
+    /// ```run``` don't apply.
+    pub fn stream_async(&self) -> Result<AsyncCheckStream, CheckError> {
+        let (command, dir) = json_command(self)?;
+        let mut child = Command::from(command).kill_on_drop(true).spawn()
+            .map_err(CheckError::spawning_cargo)?;
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped")).lines();
+        let stderr = child.stderr.take().map(|stderr| BufReader::new(stderr).lines());
+        Ok(AsyncCheckStream {
+            parser: MessageParser::new(dir),
+            child: Some(child),
+            stdout,
+            stderr,
+            stderr_text: String::new(),
+            ended: false,
+            read_error: None,
+        })
+    }
+
+    /// Runs the check on the Tokio runtime (see ```stream_async```), and
+    /// returns the problems with absolute paths. Cargo is started when the
+    /// future is first polled, and killed if the future is dropped before
+    /// it is done.
+    pub fn run_async(&self) -> CheckFuture {
+        CheckFuture { checker: self.clone(), finish: None }
+    }
+}
+
+impl AsyncCheckStream {
+    /// Returns a future that reads the rest of the output and waits for
+    /// cargo, as ```CheckStream::finish``` does.
+    pub fn finish(self) -> FinishCheck {
+        FinishCheck { stream: self, wait: None }
+    }
+
+    /// Reads the lines of stderr that are ready, and returns whether it has
+    /// ended.
+    fn poll_stderr(&mut self, cx: &mut Context) -> bool {
+        while let Some(ref mut lines) = self.stderr {
+            match Pin::new(lines).poll_next_line(cx) {
+                Poll::Ready(Ok(Some(line))) => {
+                    self.stderr_text.push_str(&line);
+                    self.stderr_text.push('\n');
+                },
+                // An error only loses the rest of the text
+                Poll::Ready(_) => self.stderr = None,
+                Poll::Pending => return false,
+            }
+        }
+        true
+    }
+}
+
+impl Stream for AsyncCheckStream {
+    type Item = Result<ProblemDescription, ParseError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let stream = self.get_mut();
+        stream.poll_stderr(cx);
+        while ! stream.ended {
+            match Pin::new(&mut stream.stdout).poll_next_line(cx) {
+                Poll::Ready(Ok(Some(line))) => {
+                    if let Some(result) = stream.parser.feed(&line) {
+                        return Poll::Ready(Some(result));
+                    }
+                },
+                Poll::Ready(Ok(None)) => stream.ended = true,
+                Poll::Ready(Err(err)) => {
+                    stream.read_error = Some(err);
+                    stream.ended = true;
+                },
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(None)
+    }
+}
+
+/// The rest of a check on a Tokio runtime, from ```AsyncCheckStream::finish```,
+/// with the check of all the problems and the exit status of cargo.
+pub struct FinishCheck {
+    stream: AsyncCheckStream,
+    wait: Option<Wait>,
+}
+
+impl Future for FinishCheck {
+    type Output = Result<(Check, ExitStatus), CheckError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let finish = self.get_mut();
+        let stream = &mut finish.stream;
+        loop {
+            match Pin::new(&mut *stream).poll_next(cx) {
+                Poll::Ready(Some(_)) => {},
+                Poll::Ready(None) => break,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        if let Some(err) = stream.read_error.take() {
+            return Poll::Ready(Err(err.into()));
+        }
+        if ! stream.poll_stderr(cx) {
+            return Poll::Pending;
+        }
+        // The future owns cargo, which is killed if it is dropped
+        let wait = finish.wait.get_or_insert_with(|| {
+            Box::pin(stream.child.take().expect("cargo is waited for once").wait_with_output())
+        });
+        match wait.as_mut().poll(cx) {
+            Poll::Ready(Ok(output)) => {
+                Poll::Ready(stream.parser.finish(output.status, &stream.stderr_text))
+            },
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err.into())),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Checks the current directory on the Tokio runtime (see
+/// ```cargo_check_async_in```).
+pub fn cargo_check_async() -> CheckFuture {
+    cargo_check_async_in(".")
+}
+
+/// Runs ```cargo check``` on the project in the directory on the Tokio
+/// runtime, and returns the problems of its JSON messages, with absolute
+/// paths. Cargo is started when the future is first polled, and killed if
+/// the future is dropped before it is done.
+pub fn cargo_check_async_in<P: AsRef<Path>>(dir: P) -> CheckFuture {
+    Checker::new().dir(dir.as_ref()).run_async()
+}
+
+/// A check of a project on a Tokio runtime, from ```Checker::run_async```.
+pub struct CheckFuture {
+    checker: Checker,
+    finish: Option<FinishCheck>,
+}
+
+impl Future for CheckFuture {
+    type Output = Result<Check, CheckError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let future = self.get_mut();
+        let finish = match future.finish {
+            Some(ref mut finish) => finish,
+            None => match future.checker.stream_async() {
+                Ok(stream) => future.finish.get_or_insert(stream.finish()),
+                Err(err) => return Poll::Ready(Err(err)),
+            },
+        };
+        Pin::new(finish).poll(cx).map(|result| result.map(|(check, _)| check))
+    }
+}
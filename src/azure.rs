@@ -0,0 +1,88 @@
+//! Output as Azure DevOps logging commands.
+
+use std::fmt::Write;
+
+use {Check, SanitizeRules, Severity};
+
+/// Escapes a property value or message of a logging command, as documented
+/// for the Azure Pipelines agent.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '%' => escaped.push_str("%AZP25"),
+            ';' => escaped.push_str("%3B"),
+            ']' => escaped.push_str("%5D"),
+            '\r' => escaped.push_str("%0D"),
+            '\n' => escaped.push_str("%0A"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl Check {
+    /// Returns one ```task.logissue``` logging command per problem, followed
+    /// by a ```task.complete``` command with the result of the check: 
+    /// ```Failed``` if there are errors and ```SucceededWithIssues``` if
+    /// there are only warnings. The problems are redacted with the rules,
+    /// if any.
+    pub fn to_azure_devops(&self, sanitize: Option<&SanitizeRules>) -> String {
+        if let Some(rules) = sanitize {
+            return self.sanitized(rules).to_azure_devops(None);
+        }
+        let mut out = String::new();
+        for problem in self.problems() {
+            let kind = match problem.severity {
+                Severity::Error => "error",
+                // Azure only has errors and warnings
+                Severity::Warning | Severity::Note | Severity::Help => "warning",
+            };
+            let _ = writeln!(out, 
+                "##vso[task.logissue type={};sourcepath={};linenumber={};columnnumber={}]{}",
+                kind, escape(&problem.filepath), problem.message.start_line, 
+                problem.message.start_col, escape(&problem.message.message));
+        }
+        let result = match *self {
+            Check::Perfect => "Succeeded",
+            Check::Warning(_) => "SucceededWithIssues",
+            Check::Error(_) => "Failed",
+        };
+        let _ = writeln!(out, "##vso[task.complete result={};]{}", result, 
+            escape(&self.summary_line()));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {Check, ProblemDescription, Severity};
+
+    #[test]
+    fn to_azure_devops_escapes_properties_and_messages() {
+        let mut error = ProblemDescription::new("src/we;ird].rs", 10, 5, 10, 9, 
+            "mismatched types: 100% wrong;\nexpected `[u8]`", vec![], vec![]);
+        error.severity = Severity::Error;
+        let warning = ProblemDescription::new("src/lib.rs", 2, 9, 2, 10, 
+            "unused variable: `x`", vec![], vec![]);
+        let check = Check::Error(vec![error, warning]);
+        assert_eq!(check.to_azure_devops(None), "\
+##vso[task.logissue type=error;sourcepath=src/we%3Bird%5D.rs;linenumber=10;columnnumber=5]\
+mismatched types: 100%AZP25 wrong%3B%0Aexpected `[u8%5D`
+##vso[task.logissue type=warning;sourcepath=src/lib.rs;linenumber=2;columnnumber=9]\
+unused variable: `x`
+##vso[task.complete result=Failed;]lintparser: 1 errors, 1 warnings, 2 files
+");
+    }
+
+    #[test]
+    fn to_azure_devops_result_follows_the_check() {
+        assert_eq!(Check::Perfect.to_azure_devops(None), 
+            "##vso[task.complete result=Succeeded;]lintparser: clean\n");
+        let warning = ProblemDescription::new("src/lib.rs", 2, 9, 2, 10, "unused", 
+            vec![], vec![]);
+        assert!(Check::Warning(vec![warning]).to_azure_devops(None)
+            .ends_with("##vso[task.complete result=SucceededWithIssues;]\
+                lintparser: 0 errors, 1 warnings, 1 files\n"));
+    }
+}
@@ -0,0 +1,132 @@
+//! The chains of requirements of trait-bound errors like ```E0277```, whose
+//! notes lead from the type in the code of the user to the bound that it
+//! doesn't satisfy:
+//!
+//! ```text
+//!   = note: required for `Wrapper<Config>` to implement `Serialize`
+//! note: required by a bound in `serde_json::to_string`
+//! ```
+
+use Note;
+
+/// A step of the chain of requirements of a trait-bound error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct BoundStep {
+    /// The note, like ```required by a bound in `serde_json::to_string` ```.
+    pub description: String,
+    /// The span of the note, if it has one of its own.
+    pub span: Option<Note>,
+    /// Whether the span is in a dependency rather than in the workspace.
+    pub in_dependency: bool,
+}
+
+impl BoundStep {
+    /// Creates the step of a note, with the path of its span if it has one.
+    pub fn new(description: &str, span: Option<(&str, Note)>) -> BoundStep {
+        BoundStep {
+            description: String::from(description),
+            in_dependency: span.as_ref().is_some_and(|&(path, _)| is_dependency_path(path)),
+            span: span.map(|(_, note)| note),
+        }
+    }
+}
+
+/// Returns whether the note is a step of the chain of requirements.
+pub fn is_bound_step(message: &str) -> bool {
+    ["required for `", "required by a bound in `", "required by this bound in `",
+        "required because it appears within the type `", "required because of the "]
+        .iter()
+        .any(|prefix| message.starts_with(prefix))
+}
+
+/// Returns whether the path is in the sources of a dependency or of the
+/// standard library: in the registry or the git checkouts of cargo, or in
+/// the sources of rustc.
+pub fn is_dependency_path(path: &str) -> bool {
+    let path = path.replace('\\', "/");
+    path.contains("/registry/src/") || path.contains("/git/checkouts/")
+        || path.starts_with("/rustc/") || path.contains("/lib/rustlib/src/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {parse_json_messages, parse_output};
+
+    /// An E0277 through serde, with a step in the workspace, one without a
+    /// span and one in the registry.
+    const BOUND_ERROR: &str = "\
+error[E0277]: the trait bound `Config: Serialize` is not satisfied
+    --> src/main.rs:14:39
+     |
+14   |     let text = serde_json::to_string(&wrapper).unwrap();
+     |                ---------------------  ^^^^^^^^ the trait `Serialize` is not implemented for `Config`
+     |                |
+     |                required by a bound introduced by this call
+     |
+     = note: for local types consider adding `#[derive(serde::Serialize)]` to your `Config` type
+     = help: the following other types implement trait `Serialize`:
+               &'a T
+               &'a mut T
+             and 128 others
+note: required for `Wrapper<Config>` to implement `Serialize`
+    --> src/main.rs:4:10
+     |
+4    | #[derive(Serialize)]
+     |          ^^^^^^^^^ unsatisfied trait bound introduced in this `derive` macro
+5    | struct Wrapper<T> {
+     |                - type parameter would need to implement `Serialize`
+     = note: 1 redundant requirement hidden
+     = note: required for `&Wrapper<Config>` to implement `Serialize`
+note: required by a bound in `serde_json::to_string`
+    --> /home/me/.cargo/registry/src/index.crates.io-6f17d22bba15001f/serde_json-1.0.108/src/ser.rs:2209:17
+     |
+2207 | pub fn to_string<T>(value: &T) -> Result<String>
+     |        --------- required by a bound in this function
+2208 | where
+2209 |     T: ?Sized + Serialize,
+     |                 ^^^^^^^^^ required by this bound in `to_string`
+     = note: this error originates in the derive macro `Serialize` (in Nightly builds, run with -Z macro-backtrace for more info)
+";
+
+    /// The notes of the error in the JSON messages.
+    const BOUND_MESSAGE: &str = r#"{"reason":"compiler-message","package_id":"path+file:///home/me/app#0.1.0","target":{"kind":["bin"],"name":"app"},"message":{"rendered":"...","$message_type":"diagnostic","children":[{"children":[],"code":null,"level":"note","message":"required for `Wrapper<Config>` to implement `Serialize`","rendered":null,"spans":[{"byte_end":45,"byte_start":36,"column_end":19,"column_start":10,"expansion":null,"file_name":"src/main.rs","is_primary":true,"label":"unsatisfied trait bound introduced in this `derive` macro","line_end":4,"line_start":4,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}]},{"children":[],"code":null,"level":"note","message":"1 redundant requirement hidden","rendered":null,"spans":[]},{"children":[],"code":null,"level":"note","message":"required for `&Wrapper<Config>` to implement `Serialize`","rendered":null,"spans":[]},{"children":[],"code":null,"level":"note","message":"required by a bound in `serde_json::to_string`","rendered":null,"spans":[{"byte_end":70912,"byte_start":70903,"column_end":26,"column_start":17,"expansion":null,"file_name":"/home/me/.cargo/registry/src/index.crates.io-6f17d22bba15001f/serde_json-1.0.108/src/ser.rs","is_primary":true,"label":"required by this bound in `to_string`","line_end":2209,"line_start":2209,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}]}],"level":"error","message":"the trait bound `Config: Serialize` is not satisfied","spans":[{"byte_end":320,"byte_start":312,"column_end":47,"column_start":39,"expansion":null,"file_name":"src/main.rs","is_primary":true,"label":"the trait `Serialize` is not implemented for `Config`","line_end":14,"line_start":14,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}],"code":{"code":"E0277","explanation":"..."}}}
+"#;
+
+    #[test]
+    fn requirements_of_bounds_are_chained_in_order() {
+        let check = parse_output(BOUND_ERROR);
+        let problem = &check.problems()[0];
+        let step = |description: &str, span: Option<(&str, usize, usize, usize)>,
+                in_dependency| {
+            BoundStep {
+                description: String::from(description),
+                span: span.map(|(file, line, col, end_col)| Note::new(line, col, line, end_col,
+                    description).with_file(file)),
+                in_dependency,
+            }
+        };
+        let serde = "/home/me/.cargo/registry/src/index.crates.io-6f17d22bba15001f/\
+            serde_json-1.0.108/src/ser.rs";
+        assert_eq!(problem.bound_chain, vec![
+            step("required for `Wrapper<Config>` to implement `Serialize`",
+                Some(("src/main.rs", 4, 10, 19)), false),
+            step("required for `&Wrapper<Config>` to implement `Serialize`", None, false),
+            step("required by a bound in `serde_json::to_string`", Some((serde, 2209, 17, 26)),
+                true),
+        ]);
+        // The notes are all kept as they were
+        assert_eq!(problem.notes.len(), 6);
+        // The JSON messages have the byte offsets of the spans as well
+        let mut json_chain = parse_json_messages(BOUND_MESSAGE).problems()[0].bound_chain.clone();
+        assert_eq!(json_chain[0].span.as_ref().and_then(|span| span.byte_start), Some(36));
+        for span in json_chain.iter_mut().filter_map(|step| step.span.as_mut()) {
+            span.byte_start = None;
+            span.byte_end = None;
+        }
+        assert_eq!(json_chain, problem.bound_chain);
+        assert!(is_dependency_path("C:\\Users\\me\\.cargo\\git\\checkouts\\fork\\lib.rs"));
+        assert!(! is_dependency_path("/work/registry/lib.rs"));
+    }
+}
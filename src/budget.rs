@@ -0,0 +1,188 @@
+//! Quotas for the number of problems of each lint, which ratchet down with
+//! the history: a lint may never have more problems than its quota, nor
+//! more than the last recorded check had.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use gate::new_problems;
+use history::History;
+use {Check, ConfigWarning, ProblemDescription};
+
+/// The most problems that each lint may have.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Budget {
+    quotas: BTreeMap<String, usize>,
+}
+
+/// A lint with more problems than its budget allows.
+#[derive(Debug, Clone)]
+pub struct Overrun {
+    pub lint: String,
+    pub quota: usize,
+    /// The problems of the lint in the last recorded check, if any.
+    pub previous: Option<usize>,
+    /// The problems of the lint, in the order of the check.
+    pub problems: Vec<ProblemDescription>,
+}
+
+impl Overrun {
+    /// Returns the number of problems allowed: the quota, or fewer if the
+    /// last recorded check had fewer.
+    pub fn limit(&self) -> usize {
+        self.previous.map_or(self.quota, |previous| previous.min(self.quota))
+    }
+
+    /// Returns how many problems the lint has over its limit.
+    pub fn delta(&self) -> usize {
+        self.problems.len() - self.limit()
+    }
+}
+
+/// Formats the overrun, e.g. ```dead_code has 27 problems, 2 over its
+/// budget of 25```.
+impl fmt::Display for Overrun {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} has {} problems, {} over its budget of {}", self.lint, self.problems.len(),
+            self.delta(), self.limit())?;
+        if self.limit() < self.quota {
+            write!(f, " (the quota is {}, but the last check had {})", self.quota, self.limit())?;
+        }
+        Ok(())
+    }
+}
+
+/// The lints of a check that are over budget.
+#[derive(Debug, Clone, Default)]
+pub struct BudgetResult {
+    pub overruns: Vec<Overrun>,
+}
+
+impl BudgetResult {
+    pub fn is_within_budget(&self) -> bool {
+        self.overruns.is_empty()
+    }
+
+    /// Returns the problems of the lints over budget that aren't in the
+    /// baseline (e.g. the published check of the main branch), by their
+    /// fingerprints. Of identical problems, the ones past the number in
+    /// the baseline are new.
+    pub fn new_problems(&self, baseline: &Check) -> Vec<&ProblemDescription> {
+        new_problems(self.overruns.iter().flat_map(|overrun| &overrun.problems), baseline)
+    }
+}
+
+impl Budget {
+    /// Creates a budget without quotas.
+    pub fn new() -> Budget {
+        Budget::default()
+    }
+
+    /// Reads the ```[budget]``` section of a TOML-like config, with a
+    /// ```lint = count``` line per quota. Other sections are skipped, and the
+    /// lines of the section that aren't quotas are returned as warnings.
+    pub fn parse(config: &str) -> (Budget, Vec<ConfigWarning>) {
+        let mut budget = Budget::new();
+        let mut warnings = Vec::new();
+        let mut in_budget = false;
+        for (i, line) in config.lines().enumerate() {
+            let text = line.split('#').next().unwrap_or("").trim();
+            if text.starts_with('[') {
+                in_budget = text == "[budget]";
+                continue;
+            }
+            if ! in_budget || text.is_empty() {
+                continue;
+            }
+            let mut parts = text.splitn(2, '=');
+            let lint = parts.next().unwrap_or("").trim().trim_matches('"');
+            let quota = parts.next().and_then(|quota| quota.trim().parse().ok());
+            match quota {
+                Some(quota) if ! lint.is_empty() => budget.set(lint, quota),
+                _ => warnings.push(ConfigWarning::InvalidQuota {
+                    line: i + 1,
+                    text: String::from(text),
+                }),
+            }
+        }
+        (budget, warnings)
+    }
+
+    /// Sets the most problems that the lint may have.
+    pub fn set<T: Into<String>>(&mut self, lint: T, quota: usize) {
+        self.quotas.insert(lint.into(), quota);
+    }
+
+    /// Returns the lints of the check with more problems than their quota,
+    /// or than the latest entry of the history has.
+    pub fn enforce(&self, check: &Check, history: &History) -> BudgetResult {
+        let latest = history.entries().last();
+        let overruns = self.quotas.iter().filter_map(|(lint, &quota)| {
+            let overrun = Overrun {
+                lint: lint.clone(),
+                quota,
+                previous: latest.map(|entry| entry.count(Some(lint))),
+                problems: check.problems().iter()
+                    .filter(|problem| problem.lint() == Some(lint))
+                    .cloned()
+                    .collect(),
+            };
+            if overrun.problems.len() > overrun.limit() { Some(overrun) } else { None }
+        }).collect();
+        BudgetResult { overruns }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+    use {parse_output, CheckReport};
+
+    fn unused(lines: &[usize]) -> String {
+        lines.iter()
+            .map(|line| format!("src/lib.rs:{}:5: {}:8 warning: unused import: `a{}`, \
+                #[warn(unused_imports)] on by default\n", line, line, line))
+            .collect()
+    }
+
+    #[test]
+    fn budgets_ratchet_with_the_history() {
+        let (budget, warnings) = Budget::parse("\
+[severity]
+dead_code = \"error\"
+
+[budget]
+dead_code = 25  # for now
+\"unused_imports\" = 2
+unused_variables =
+");
+        assert_eq!(warnings, vec![ConfigWarning::InvalidQuota {
+            line: 7,
+            text: String::from("unused_variables ="),
+        }]);
+
+        let path = env::temp_dir().join(format!("lintparser-budget-{}.jsonl", process::id()));
+        let _ = fs::remove_file(&path);
+        let mut history = History::open(&path).unwrap();
+        let three = parse_output(&unused(&[1, 2, 3]));
+        assert_eq!(budget.enforce(&three, &history).overruns[0].to_string(),
+            "unused_imports has 3 problems, 1 over its budget of 2");
+
+        history.record(&CheckReport::new(parse_output(&unused(&[1])))).unwrap();
+        let result = budget.enforce(&three, &history);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(result.overruns.len(), 1);
+        assert_eq!(result.overruns[0].delta(), 2);
+        assert_eq!(result.overruns[0].to_string(), "unused_imports has 3 problems, 2 over its \
+            budget of 1 (the quota is 2, but the last check had 1)");
+
+        let new: Vec<usize> = result.new_problems(&parse_output(&unused(&[2, 4]))).iter()
+            .map(|problem| problem.message.start_line)
+            .collect();
+        assert_eq!(new, vec![1, 3]);
+        assert!(budget.enforce(&Check::Perfect, &history).is_within_budget());
+    }
+}
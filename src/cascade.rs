@@ -0,0 +1,158 @@
+//! Grouping follow-on errors under the error that caused them.
+//!
+//! The heuristic is conservative, as folding an independent error would hide
+//! it. An error is only folded into a cluster when
+//!
+//! - it is in the same file as a syntax error (a parse error like
+//!   ```expected one of ...``` or an unclosed delimiter), and starts at most
+//!   ```CascadeRules::window``` lines after it, or
+//! - it is a ```cannot find``` error (like E0412 or E0433) about the same
+//!   missing name as an earlier error of the same kind.
+//!
+//! Warnings are never folded.
+
+use std::ptr;
+
+use {Check, ProblemDescription, Severity};
+
+/// The rules for telling follow-on errors from independent ones.
+#[derive(Debug, Clone)]
+pub struct CascadeRules {
+    /// How many lines after a syntax error the errors it causes may start.
+    pub window: usize,
+    /// Texts of the messages of syntax errors.
+    pub syntax_messages: Vec<String>,
+    /// The error codes of syntax errors.
+    pub syntax_codes: Vec<String>,
+    /// The error codes of errors about missing names, which are merged when
+    /// they repeat the same name.
+    pub missing_name_codes: Vec<String>,
+}
+
+impl Default for CascadeRules {
+    fn default() -> CascadeRules {
+        let strings = |texts: &[&str]| texts.iter().map(|&text| String::from(text)).collect();
+        CascadeRules {
+            window: 10,
+            syntax_messages: strings(&["expected one of", "unclosed delimiter",
+                "unexpected closing delimiter", "mismatched closing delimiter",
+                "this file contains an unclosed delimiter"]),
+            syntax_codes: strings(&["E0585", "E0586"]),
+            missing_name_codes: strings(&["E0405", "E0412", "E0422", "E0425", "E0433"]),
+        }
+    }
+}
+
+impl CascadeRules {
+    fn is_syntax_error(&self, problem: &ProblemDescription) -> bool {
+        let message = &problem.message.message;
+        self.syntax_messages.iter().any(|text| message.contains(&text[..]))
+            || problem.error_code().is_some_and(|code| self.syntax_codes.iter().any(|c| c == code))
+    }
+
+    /// Returns the code and name of an error about a missing name.
+    fn missing_name<'a>(&self, problem: &'a ProblemDescription) -> Option<(&'a str, &'a str)> {
+        let code = problem.error_code()?;
+        if ! self.missing_name_codes.iter().any(|c| c == code) {
+            return None;
+        }
+        let message = &problem.message.message;
+        let start = message.find('`')? + 1;
+        let end = start + message[start..].find('`')?;
+        Some((code, &message[start..end]))
+    }
+}
+
+/// An error and the errors that most likely follow from it.
+#[derive(Debug, Clone)]
+pub struct ErrorCluster<'a> {
+    pub root: &'a ProblemDescription,
+    pub cascade: Vec<&'a ProblemDescription>,
+}
+
+impl Check {
+    /// Groups the errors into clusters with the default rules. Every error
+    /// is in exactly one cluster, in the order of the first error of each.
+    pub fn cluster_cascades(&self) -> Vec<ErrorCluster<'_>> {
+        self.cluster_cascades_with(&CascadeRules::default())
+    }
+
+    /// Groups the errors into clusters with the given rules.
+    pub fn cluster_cascades_with(&self, rules: &CascadeRules) -> Vec<ErrorCluster<'_>> {
+        let mut clusters: Vec<ErrorCluster> = Vec::new();
+        let errors = self.problems().iter().filter(|problem| problem.severity == Severity::Error);
+        for error in errors {
+            let line = error.message.start_line;
+            let parent = clusters.iter().position(|cluster| {
+                let root = cluster.root;
+                let after_syntax_error = rules.is_syntax_error(root)
+                    && root.filepath == error.filepath
+                    && line >= root.message.start_line
+                    && line - root.message.start_line <= rules.window;
+                let same_missing_name = rules.missing_name(error).is_some()
+                    && rules.missing_name(error) == rules.missing_name(root);
+                after_syntax_error || same_missing_name
+            });
+            match parent {
+                Some(index) => clusters[index].cascade.push(error),
+                None => clusters.push(ErrorCluster { root: error, cascade: Vec::new() }),
+            }
+        }
+        clusters
+    }
+}
+
+/// Returns whether the problem is folded into the cascade of a cluster.
+pub fn is_folded(clusters: &[ErrorCluster], problem: &ProblemDescription) -> bool {
+    clusters.iter().any(|cluster| cluster.cascade.iter().any(|&folded| ptr::eq(folded, problem)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parse_output;
+
+    const CASCADE_OUTPUT: &str = "\
+src/lib.rs:3:14: 3:15 error: expected one of `.`, `;`, `?`, or an operator, found `let`
+src/lib.rs:5:5: 5:8 error: cannot find value `foo` in this scope [E0425]
+src/lib.rs:9:5: 9:8 error: mismatched types [E0308]
+src/lib.rs:30:5: 30:8 error: mismatched types [E0308]
+src/main.rs:4:12: 4:18 error: cannot find type `Config` in this scope [E0412]
+src/lib.rs:31:5: 31:8 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+src/cli.rs:8:12: 8:18 error: cannot find type `Config` in this scope [E0412]
+src/cli.rs:9:12: 9:18 error: cannot find type `Other` in this scope [E0412]
+";
+
+    fn lines(clusters: &[ErrorCluster]) -> Vec<(usize, Vec<usize>)> {
+        clusters.iter()
+            .map(|cluster| (cluster.root.message.start_line,
+                cluster.cascade.iter().map(|problem| problem.message.start_line).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn cascades_follow_syntax_errors_and_repeated_missing_names() {
+        let check = parse_output(CASCADE_OUTPUT);
+        let clusters = check.cluster_cascades();
+        assert_eq!(lines(&clusters), vec![
+            (3, vec![5, 9]),
+            (30, vec![]),
+            (4, vec![8]),
+            (9, vec![]),
+        ]);
+        assert!(is_folded(&clusters, &check.problems()[1]));
+        assert!(! is_folded(&clusters, &check.problems()[0]));
+    }
+
+    #[test]
+    fn rules_can_be_overridden() {
+        let check = parse_output(CASCADE_OUTPUT);
+        let rules = CascadeRules { window: 30, missing_name_codes: vec![], ..CascadeRules::default() };
+        assert_eq!(lines(&check.cluster_cascades_with(&rules)), vec![
+            (3, vec![5, 9, 30]),
+            (4, vec![]),
+            (8, vec![]),
+            (9, vec![]),
+        ]);
+    }
+}
@@ -0,0 +1,206 @@
+//! Categories of lints, e.g. for auditing the security-relevant ones.
+
+use std::collections::{BTreeMap, HashMap};
+
+use {Check, ProblemDescription};
+use Category::*;
+
+/// What kind of issue a lint reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Category {
+    Correctness,
+    Style,
+    Security,
+    Performance,
+    Docs,
+    /// The lint isn't in the table, or the problem has no lint.
+    Uncategorized,
+}
+
+/// The built-in categories of lints. Clippy lints that aren't listed are
+/// categorized by their clippy group when that is known.
+const BUILTIN_CATEGORIES: &[(&str, Category)] = &[
+    // rustc
+    ("arithmetic_overflow", Correctness),
+    ("dropping_references", Correctness),
+    ("for_loops_over_fallibles", Correctness),
+    ("invalid_from_utf8", Correctness),
+    ("let_underscore_lock", Correctness),
+    ("overflowing_literals", Correctness),
+    ("path_statements", Correctness),
+    ("unconditional_panic", Correctness),
+    ("unconditional_recursion", Correctness),
+    ("unreachable_code", Correctness),
+    ("unreachable_patterns", Correctness),
+    ("unused_comparisons", Correctness),
+    ("unused_must_use", Correctness),
+    ("bare_trait_objects", Style),
+    ("dead_code", Style),
+    ("ellipsis_inclusive_range_patterns", Style),
+    ("non_camel_case_types", Style),
+    ("non_snake_case", Style),
+    ("non_upper_case_globals", Style),
+    ("redundant_semicolons", Style),
+    ("unused_assignments", Style),
+    ("unused_braces", Style),
+    ("unused_imports", Style),
+    ("unused_labels", Style),
+    ("unused_mut", Style),
+    ("unused_parens", Style),
+    ("unused_variables", Style),
+    ("while_true", Style),
+    ("deref_nullptr", Security),
+    ("improper_ctypes", Security),
+    ("improper_ctypes_definitions", Security),
+    ("invalid_value", Security),
+    ("mutable_transmutes", Security),
+    ("static_mut_refs", Security),
+    ("unsafe_code", Security),
+    ("unsafe_op_in_unsafe_fn", Security),
+    ("unused_unsafe", Security),
+    ("large_assignments", Performance),
+    ("variant_size_differences", Performance),
+    ("missing_docs", Docs),
+    ("unused_doc_comments", Docs),
+    ("rustdoc::broken_intra_doc_links", Docs),
+    ("rustdoc::invalid_html_tags", Docs),
+    ("rustdoc::missing_crate_level_docs", Docs),
+    ("rustdoc::private_intra_doc_links", Docs),
+    // clippy groups
+    ("clippy::correctness", Correctness),
+    ("clippy::suspicious", Correctness),
+    ("clippy::complexity", Style),
+    ("clippy::pedantic", Style),
+    ("clippy::style", Style),
+    ("clippy::perf", Performance),
+    // clippy lints that deserve a category other than their group's
+    ("clippy::multiple_unsafe_ops_per_block", Security),
+    ("clippy::not_unsafe_ptr_arg_deref", Security),
+    ("clippy::undocumented_unsafe_blocks", Security),
+    ("clippy::missing_errors_doc", Docs),
+    ("clippy::missing_panics_doc", Docs),
+    ("clippy::missing_safety_doc", Docs),
+    ("clippy::doc_markdown", Docs),
+    // common clippy lints, by their group
+    ("clippy::approx_constant", Correctness),
+    ("clippy::eq_op", Correctness),
+    ("clippy::clone_on_copy", Style),
+    ("clippy::needless_return", Style),
+    ("clippy::redundant_field_names", Style),
+    ("clippy::too_many_arguments", Style),
+    ("clippy::box_collection", Performance),
+    ("clippy::large_enum_variant", Performance),
+    ("clippy::redundant_clone", Performance),
+];
+
+/// A table of lint categories: the built-in one, with overrides.
+#[derive(Debug, Clone, Default)]
+pub struct CategoryTable {
+    overrides: HashMap<String, Category>,
+}
+
+impl CategoryTable {
+    /// Creates a table with the built-in categories.
+    pub fn new() -> CategoryTable {
+        CategoryTable::default()
+    }
+
+    /// Sets the category of a lint (or clippy group), overriding the built-in
+    /// one.
+    pub fn set<T: Into<String>>(&mut self, lint: T, category: Category) {
+        self.overrides.insert(lint.into(), category);
+    }
+
+    /// Returns the category of a lint or clippy group, if it is known.
+    pub fn get(&self, lint: &str) -> Option<Category> {
+        self.overrides.get(lint).cloned().or_else(|| {
+            BUILTIN_CATEGORIES.iter()
+                .find(|&&(name, _)| name == lint)
+                .map(|&(_, category)| category)
+        })
+    }
+
+    /// Returns the category of a problem, from its lint or else the lint
+    /// group that enabled it.
+    pub fn categorize(&self, problem: &ProblemDescription) -> Category {
+        problem.lint().and_then(|lint| self.get(lint))
+            .or_else(|| problem.lint_group().and_then(|group| self.get(group)))
+            .unwrap_or(Uncategorized)
+    }
+}
+
+impl Check {
+    /// Groups the problems by the built-in category of their lints.
+    pub fn by_category(&self) -> BTreeMap<Category, Vec<&ProblemDescription>> {
+        self.by_category_with(&CategoryTable::new())
+    }
+
+    /// Groups the problems by the category of their lints in the table.
+    pub fn by_category_with(&self, table: &CategoryTable)
+            -> BTreeMap<Category, Vec<&ProblemDescription>> {
+        let mut categories = BTreeMap::new();
+        for problem in self.problems() {
+            categories.entry(table.categorize(problem))
+                .or_insert_with(Vec::new)
+                .push(problem);
+        }
+        categories
+    }
+
+    /// Returns the problems from security-relevant lints.
+    pub fn security_findings(&self) -> Vec<&ProblemDescription> {
+        self.by_category().remove(&Security).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Note;
+
+    fn problem(message: &str, notes: &[&str]) -> ProblemDescription {
+        let notes: Vec<Note> = notes.iter().map(|note| Note::new(1, 1, 1, 2, *note)).collect();
+        ProblemDescription::new(String::from("src/lib.rs"), 1, 1, 1, 2, String::from(message),
+            vec![], notes)
+    }
+
+    #[test]
+    fn categorize_by_lint_and_group() {
+        let table = CategoryTable::new();
+        let categorize = |message, notes| table.categorize(&problem(message, notes));
+        assert_eq!(categorize("unnecessary `unsafe` block, #[warn(unused_unsafe)] on by default", &[]),
+            Security);
+        assert_eq!(categorize("unsafe block missing a safety comment",
+            &["`#[warn(clippy::undocumented_unsafe_blocks)]` on by default"]), Security);
+        assert_eq!(categorize("`Vec<T>` is already on the heap", &[
+            "`#[warn(clippy::some_new_lint)]` implied by `#[warn(clippy::perf)]`",
+        ]), Performance);
+        assert_eq!(categorize("something", &["`#[warn(clippy::some_new_lint)]` on by default"]),
+            Uncategorized);
+        assert_eq!(categorize("mismatched types", &[]), Uncategorized);
+    }
+
+    #[test]
+    fn overrides_win() {
+        let mut table = CategoryTable::new();
+        table.set("dead_code", Correctness);
+        table.set("my_tool::custom", Docs);
+        assert_eq!(table.get("dead_code"), Some(Correctness));
+        assert_eq!(table.get("my_tool::custom"), Some(Docs));
+        assert_eq!(table.get("unused_imports"), Some(Style));
+        assert_eq!(table.get("dead-code"), None);
+    }
+
+    #[test]
+    fn security_findings_and_groups() {
+        let check = Check::Warning(vec![
+            problem("unused, #[warn(dead_code)] on by default", &[]),
+            problem("null, #[warn(deref_nullptr)] on by default", &[]),
+            problem("unused, #[warn(unused_imports)] on by default", &[]),
+        ]);
+        assert_eq!(check.security_findings().len(), 1);
+        let categories = check.by_category();
+        assert_eq!(categories.keys().cloned().collect::<Vec<_>>(), vec![Style, Security]);
+        assert_eq!(categories[&Style].len(), 2);
+    }
+}
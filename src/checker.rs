@@ -0,0 +1,1179 @@
+//! Configurable checks.
+
+use std::env;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use {absolute_paths, cargo_failure, find_project_root, line_starts_block, parse_json_messages,
+    parse_output_lenient, project_root, spans_mut, CargoConfig, Check, CheckError, CheckReport, Fallback};
+use resource::{wait_with_usage, ResourceUsage};
+use trace::{generate_id, Progress, TraceEvent, TraceKind, Tracer};
+use watch::{watch, NotifyPolicy, WatchHandle};
+use workspace::{estimate_units, failed_packages, WorkspaceGraph};
+
+/// Configures and runs a check of a project, by default the one in the
+/// current directory.
+#[derive(Debug, Clone, Default)]
+pub struct Checker {
+    dir: Option<PathBuf>,
+    kind: CommandKind,
+    clippy: bool,
+    keep_going: bool,
+    path_maps: Vec<PathMap>,
+    correlation_id: Option<String>,
+    trace: Option<Sender<TraceEvent>>,
+    timeout: Option<Duration>,
+    cancel: Option<CancelToken>,
+    strict_timeout: bool,
+    retries: usize,
+    backoff: Duration,
+    progress: bool,
+    target_dir: Option<PathBuf>,
+    package: Option<String>,
+    features: Vec<String>,
+    all_features: bool,
+    no_default_features: bool,
+    all_targets: bool,
+    release: bool,
+    target: Option<String>,
+    envs: Vec<(String, String)>,
+    args: Vec<String>,
+    rustflags: Vec<String>,
+    /// Whether ```watch``` leaves its reports unpublished, so that the
+    /// default publishes them.
+    skip_publish: bool,
+    /// The subcommand to run instead of the one of ```kind```.
+    subcommand: Option<String>,
+}
+
+/// Returns a checker of the project in the directory that runs the cargo
+/// subcommand, like ```test```, with the arguments, for the functions of the
+/// crate root that run other subcommands than a ```Checker``` can.
+pub fn subcommand_checker<P: Into<PathBuf>>(dir: P, subcommand: &str, args: &[&str]) -> Checker {
+    Checker {
+        subcommand: Some(String::from(subcommand)),
+        args: args.iter().map(|&arg| String::from(arg)).collect(),
+        ..Checker::new().dir(dir)
+    }
+}
+
+/// Returns the command of the check of the checker with JSON messages, for
+/// the checks that read them as cargo writes them, and the canonical
+/// directory of its project.
+pub fn json_command(checker: &Checker) -> Result<(Command, PathBuf), CheckError> {
+    let root = project_root(checker.project_dir())?;
+    let (args, _) = checker.arguments(&InstalledCargo);
+    let mut command = checker.cargo_command(&args);
+    command.current_dir(&root);
+    Ok((command, root))
+}
+
+/// The cargo subcommand that a check runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CommandKind {
+    /// ```cargo check```.
+    #[default]
+    Check,
+    /// ```cargo build```, for toolchains and build wrappers without
+    /// ```check```. The diagnostics are the same.
+    Build,
+    /// ```cargo check``` if it is supported, and ```cargo build``` otherwise.
+    Auto,
+}
+
+/// Cancels the checks that it was given to, from any thread.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    /// Cancels the checks. A running check stops shortly after.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// Why cargo was killed before it finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Interruption {
+    TimedOut,
+    Cancelled,
+}
+
+/// The result of a run of cargo.
+struct CargoRun {
+    success: bool,
+    /// The exit code, or ```None``` if cargo was killed.
+    code: Option<i32>,
+    /// The JSON messages, if cargo was asked for them.
+    stdout: String,
+    stderr: String,
+    interruption: Option<Interruption>,
+    usage: ResourceUsage,
+}
+
+/// A failed attempt of a check that was run again, with
+/// ```Checker::retries```.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attempt {
+    /// The failure, as ```CheckError::kind``` reports it.
+    pub kind: &'static str,
+    pub message: String,
+    /// How long the attempt ran.
+    pub duration: Duration,
+    /// How long the check waited before the next attempt.
+    pub backoff: Duration,
+}
+
+/// Parts of the output of a failed cargo that mark the failure as transient,
+/// as it came from the network, a lock or a file that another process had
+/// open.
+const TRANSIENT_FAILURES: &[&str] = &[
+    "spurious network error",
+    "failed to download",
+    "failed to fetch",
+    "timed out",
+    "connection reset",
+    "could not resolve host",
+    "failed to acquire package cache lock",
+    // Windows, os error 32
+    "being used by another process",
+];
+
+/// Parts of the output that mark a failure as permanent, whatever else it
+/// says.
+const PERMANENT_FAILURES: &[&str] = &[
+    "could not compile",
+    "failed to parse manifest",
+    "could not find `cargo.toml`",
+];
+
+/// Whether a check that failed with the error and output may succeed when
+/// it is run again.
+fn is_transient(err: &CheckError, stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    if PERMANENT_FAILURES.iter().any(|pattern| stderr.contains(pattern)) {
+        return false;
+    }
+    match *err {
+        CheckError::CargoFailed { .. } =>
+            TRANSIENT_FAILURES.iter().any(|pattern| stderr.contains(pattern)),
+        ref err => err.is_retryable(),
+    }
+}
+
+/// How often a running check looks at its cancel token.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// A mapping of the paths under one directory to another directory, e.g.
+/// from where a project is mounted in a container to where it is on the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathMap {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+impl PathMap {
+    /// Maps a path using the mapping with the longest matching ```from``` 
+    /// path. Paths are compared by components, so trailing separators don't 
+    /// matter and ```/build/src``` doesn't match ```/build/srcs/lib.rs```.
+    pub fn apply(maps: &[PathMap], path: &Path) -> Option<PathBuf> {
+        maps.iter()
+            .filter_map(|map| path.strip_prefix(&map.from).ok().map(|rest| (map, rest)))
+            .max_by_key(|&(map, _)| map.from.components().count())
+            .map(|(map, rest)| if rest.as_os_str().is_empty() {
+                map.to.clone()
+            } else {
+                map.to.join(rest)
+            })
+    }
+}
+
+impl Checker {
+    /// Creates a checker running ```cargo check```.
+    pub fn new() -> Checker {
+        Checker::default()
+    }
+
+    /// Checks the project in the directory instead of the current one.
+    pub fn dir<P: Into<PathBuf>>(mut self, dir: P) -> Checker {
+        self.dir = Some(dir.into());
+        self
+    }
+
+    fn project_dir(&self) -> &Path {
+        self.dir.as_ref().map_or(Path::new("."), |dir| dir)
+    }
+
+    /// Sets the subcommand to run. With ```CommandKind::Auto```, a check
+    /// that has to use ```cargo build``` records
+    /// ```Fallback::CheckUnsupported``` on the report.
+    pub fn command(mut self, kind: CommandKind) -> Checker {
+        self.kind = kind;
+        self
+    }
+
+    /// Whether to run ```cargo clippy``` instead of ```cargo check```. The
+    /// output of clippy includes the rustc lints, which can be told apart 
+    /// with ```Check::rustc_only``` and ```Check::clippy_only```.
+    /// 
+    /// If clippy isn't installed, the check falls back to ```cargo check```
+    /// and records ```Fallback::ClippyNotInstalled``` on the report.
+    pub fn with_clippy(mut self, clippy: bool) -> Checker {
+        self.clippy = clippy;
+        self
+    }
+
+    /// Whether to keep checking the workspace members that don't depend on a
+    /// member that failed to compile, with ```--keep-going```. The report 
+    /// then lists the status of every member in ```CheckReport::members```,
+    /// telling the clean members from those that weren't checked.
+    /// 
+    /// If cargo doesn't support the flag, the check stops at the first 
+    /// failure as usual and records ```Fallback::KeepGoingUnsupported```.
+    pub fn keep_going(mut self, keep_going: bool) -> Checker {
+        self.keep_going = keep_going;
+        self
+    }
+
+    /// Maps the paths of the problems under ```from``` to be under ```to```
+    /// instead. This may be given several times, in which case the longest
+    /// matching ```from``` path wins.
+    pub fn path_map<F, T>(mut self, from: F, to: T) -> Checker
+            where F: Into<PathBuf>, T: Into<PathBuf> {
+        self.path_maps.push(PathMap { from: from.into(), to: to.into() });
+        self
+    }
+
+    /// Sets the ID that the report and the trace events of the check are
+    /// tagged with. By default, every run gets a short random ID.
+    pub fn correlation_id<T: Into<String>>(mut self, id: T) -> Checker {
+        self.correlation_id = Some(id.into());
+        self
+    }
+
+    /// Sends the trace events of the checks to the channel.
+    pub fn trace(mut self, sender: Sender<TraceEvent>) -> Checker {
+        self.trace = Some(sender);
+        self
+    }
+
+    /// Stops the check when it has run for the duration. The report then
+    /// has the problems that were reported until then, and is marked with
+    /// ```CheckReport::timed_out```.
+    pub fn timeout(mut self, timeout: Duration) -> Checker {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Stops the check when the token is cancelled. The report then has the
+    /// problems that were reported until then, and is marked with
+    /// ```CheckReport::cancelled```.
+    pub fn cancel_token(mut self, token: CancelToken) -> Checker {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Checks the project now, and again on another thread whenever its
+    /// files change, until the returned handle is stopped. The results that
+    /// the policy lets through are passed to the callback. The checks are
+    /// cancelled by stopping the watcher instead of by a ```CancelToken```
+    /// given to the checker.
+    pub fn watch<F>(self, policy: NotifyPolicy, callback: F) -> WatchHandle
+            where F: FnMut(&Result<CheckReport, CheckError>) + Send + 'static {
+        let dir = self.project_dir().to_path_buf();
+        let publish = ! self.skip_publish;
+        watch(self, dir, publish, policy, callback)
+    }
+
+    /// Whether ```watch``` publishes the report of each check of the
+    /// project, as ```CheckReport::publish``` does, which it does by default.
+    pub fn publish(mut self, publish: bool) -> Checker {
+        self.skip_publish = ! publish;
+        self
+    }
+
+    /// Whether a check that timed out or was cancelled returns
+    /// ```CheckError::TimedOut``` or ```CheckError::Cancelled``` instead of
+    /// its partial report.
+    pub fn strict_timeout(mut self, strict: bool) -> Checker {
+        self.strict_timeout = strict;
+        self
+    }
+
+    /// Whether to send ```TraceKind::Progress``` events to the trace sink as
+    /// cargo starts on the units of the check, with the total estimated
+    /// with ```cargo metadata``` before the check.
+    pub fn progress(mut self, progress: bool) -> Checker {
+        self.progress = progress;
+        self
+    }
+
+    /// Runs a check that fails transiently (e.g. from a network timeout or
+    /// a locked package cache) again, up to the number of times, first
+    /// waiting for the backoff and then twice as long each time. Failures
+    /// to compile and broken manifests are never retried, and with a
+    /// ```timeout``` the retries must fit in it.
+    ///
+    /// The failed attempts are recorded in ```CheckReport::attempts```.
+    pub fn retries(mut self, retries: usize, backoff: Duration) -> Checker {
+        self.retries = retries;
+        self.backoff = backoff;
+        self
+    }
+
+    /// Builds in the directory instead of the target directory of the
+    /// project, e.g. to not wait for the lock of a cargo that is running.
+    /// It overrides ```CARGO_TARGET_DIR``` and ```build.target-dir``` (see
+    /// ```target_dir_in_effect```).
+    pub fn target_dir<P: Into<PathBuf>>(mut self, dir: P) -> Checker {
+        self.target_dir = Some(dir.into());
+        self
+    }
+
+    /// Returns the target directory that cargo builds in: the one given to
+    /// ```target_dir```, or else ```CARGO_TARGET_DIR``` of the check or of
+    /// this process, or else the ```build.target-dir``` of the config files
+    /// of the project, or else the ```target``` of its workspace.
+    pub fn target_dir_in_effect(&self) -> PathBuf {
+        let dir = self.project_dir();
+        if let Some(ref target_dir) = self.target_dir {
+            return dir.join(target_dir);
+        }
+        if let Some(target_dir) = self.env_var("CARGO_TARGET_DIR") {
+            return dir.join(target_dir);
+        }
+        CargoConfig::discover(dir).target_dir.unwrap_or_else(|| match find_project_root(dir) {
+            Some(root) => root.dir().join("target"),
+            None => dir.join("target"),
+        })
+    }
+
+    /// Adds the flags to the flags that rustc gets, like
+    /// ```["-D", "warnings"]```. Unlike setting ```RUSTFLAGS``` with
+    /// ```env```, which replaces the ```build.rustflags``` of the config
+    /// files, they are added after the flags that rustc would get anyway:
+    /// those of ```CARGO_ENCODED_RUSTFLAGS``` or ```RUSTFLAGS``` of the
+    /// check or of this process, or else of the config files.
+    pub fn rustflags<I, T>(mut self, flags: I) -> Checker
+            where I: IntoIterator<Item=T>, T: AsRef<str> {
+        self.rustflags.extend(flags.into_iter().map(|flag| String::from(flag.as_ref())));
+        self
+    }
+
+    /// Returns the variable of the environment of cargo: as given to
+    /// ```env```, or else of this process.
+    fn env_var(&self, key: &str) -> Option<String> {
+        self.envs.iter().rev().find(|(name, _)| name == key).map(|(_, value)| value.clone())
+            .or_else(|| env::var(key).ok())
+    }
+
+    /// Returns the flags that rustc gets with those of ```rustflags```
+    /// added, or ```None``` if there are none to add.
+    fn rustflags_in_effect(&self) -> Option<Vec<String>> {
+        if self.rustflags.is_empty() {
+            return None;
+        }
+        let encoded = self.env_var("CARGO_ENCODED_RUSTFLAGS").map(|flags| {
+            flags.split('\u{1f}').filter(|flag| ! flag.is_empty()).map(String::from).collect()
+        });
+        let spaced = || self.env_var("RUSTFLAGS").map(|flags| {
+            flags.split_whitespace().map(String::from).collect()
+        });
+        let mut flags: Vec<String> = encoded.or_else(spaced)
+            .unwrap_or_else(|| CargoConfig::discover(self.project_dir()).rustflags);
+        flags.extend(self.rustflags.iter().cloned());
+        Some(flags)
+    }
+
+    /// Checks only the member of the workspace with the name, with
+    /// ```-p```.
+    pub fn package<T: Into<String>>(mut self, name: T) -> Checker {
+        self.package = Some(name.into());
+        self
+    }
+
+    /// Enables the features of the package, with ```--features```. This
+    /// may be given several times.
+    pub fn features<I, T>(mut self, features: I) -> Checker
+            where I: IntoIterator<Item=T>, T: AsRef<str> {
+        self.features.extend(features.into_iter().map(|feature| String::from(feature.as_ref())));
+        self
+    }
+
+    /// Whether to enable all the features, with ```--all-features```.
+    pub fn all_features(mut self, all_features: bool) -> Checker {
+        self.all_features = all_features;
+        self
+    }
+
+    /// Whether to disable the default features, with
+    /// ```--no-default-features```.
+    pub fn no_default_features(mut self, no_default_features: bool) -> Checker {
+        self.no_default_features = no_default_features;
+        self
+    }
+
+    /// Whether to check the tests, examples and benchmarks too, with
+    /// ```--all-targets```.
+    pub fn all_targets(mut self, all_targets: bool) -> Checker {
+        self.all_targets = all_targets;
+        self
+    }
+
+    /// Whether to check with the release profile, with ```--release```,
+    /// where code behind ```debug_assertions``` isn't compiled.
+    pub fn release(mut self, release: bool) -> Checker {
+        self.release = release;
+        self
+    }
+
+    /// Checks for the target triple instead of the host, with
+    /// ```--target```.
+    pub fn target<T: Into<String>>(mut self, triple: T) -> Checker {
+        self.target = Some(triple.into());
+        self
+    }
+
+    /// Sets an environment variable of cargo, like ```RUSTFLAGS```.
+    pub fn env<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Checker {
+        self.envs.push((key.into(), value.into()));
+        self
+    }
+
+    /// Passes the argument on to cargo after the others. An argument that
+    /// cargo doesn't know fails the check with ```CheckError::CargoFailed```.
+    pub fn arg<T: Into<String>>(mut self, arg: T) -> Checker {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Runs the check, and reads its problems from the JSON messages of
+    /// cargo, or from its text if it can't write them. The paths of the
+    /// problems are made absolute. The directory must contain a
+    /// ```Cargo.toml```, or the check fails with
+    /// ```CheckError::InvalidDirectory```.
+    pub fn run(&self) -> Result<CheckReport, CheckError> {
+        let root = project_root(self.project_dir())?;
+        let tracer = Tracer::new(self.correlation_id.clone().unwrap_or_else(generate_id),
+            self.trace.as_ref());
+        let mut report = self.run_attempts(&tracer, |timeout| self.attempt(&tracer, timeout))?;
+        report.check = absolute_paths(report.check, &root);
+        report.root = Some(root);
+        Ok(report)
+    }
+
+    /// Makes attempts at the check with the time left of the timeout until
+    /// one succeeds or shouldn't be retried.
+    fn run_attempts<F>(&self, tracer: &Tracer, mut attempt: F) -> Result<CheckReport, CheckError>
+            where F: FnMut(Option<Duration>) -> (Result<CheckReport, CheckError>, String) {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let mut attempts = Vec::new();
+        loop {
+            let started = Instant::now();
+            let timeout = deadline.map(|deadline| deadline.saturating_duration_since(started));
+            let err = match attempt(timeout) {
+                (Ok(mut report), _) => {
+                    report.attempts = attempts;
+                    return Ok(report);
+                },
+                (Err(err), stderr) => {
+                    if attempts.len() >= self.retries || ! is_transient(&err, &stderr) {
+                        return Err(err);
+                    }
+                    err
+                },
+            };
+            let backoff = self.backoff
+                .saturating_mul(1u32.checked_shl(attempts.len() as u32).unwrap_or(u32::MAX));
+            let fits = deadline.is_none_or(|deadline| Instant::now() + backoff < deadline);
+            if ! fits || self.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+                return Err(err);
+            }
+            tracer.emit(TraceKind::Retrying { attempt: attempts.len() + 2, backoff });
+            attempts.push(Attempt {
+                kind: err.kind(),
+                message: err.to_string(),
+                duration: started.elapsed(),
+                backoff,
+            });
+            thread::sleep(backoff);
+        }
+    }
+
+    /// Runs the check once, and returns the result with the output of cargo.
+    /// A cargo that can't write JSON messages is run again with the short
+    /// message format.
+    fn attempt(&self, tracer: &Tracer, timeout: Option<Duration>)
+            -> (Result<CheckReport, CheckError>, String) {
+        let started = Instant::now();
+        let (mut args, mut fallbacks) = self.arguments(&InstalledCargo);
+        let mut run = match self.run_cargo(tracer, &args, timeout) {
+            Ok(run) => run,
+            Err(err) => return (Err(err), String::new()),
+        };
+        if ! run.success && run.interruption.is_none() && run.stdout.trim().is_empty()
+                && run.stderr.contains("message-format") {
+            let format = args.iter().position(|&arg| arg == "--message-format")
+                .expect("JSON messages are asked for") + 1;
+            args[format] = "short";
+            fallbacks.push(Fallback::JsonUnsupported);
+            let timeout = timeout.map(|timeout| timeout.saturating_sub(started.elapsed()));
+            run = match self.run_cargo(tracer, &args, timeout) {
+                Ok(run) => run,
+                Err(err) => return (Err(err), String::new()),
+            };
+        }
+        let result = self.report(tracer, &args, fallbacks, &run);
+        (result, run.stderr)
+    }
+
+    /// Runs cargo with the arguments until it exits, times out or is
+    /// cancelled.
+    fn run_cargo(&self, tracer: &Tracer, args: &[&str], timeout: Option<Duration>)
+            -> Result<CargoRun, CheckError> {
+        tracer.emit(TraceKind::Started { args: args.iter().map(|&arg| String::from(arg)).collect() });
+        let child = self.cargo_command(args).spawn().map_err(CheckError::spawning_cargo);
+        let mut progress = Progress {
+            total_units: if self.progress { estimate_units(self.project_dir()) } else { None },
+            ..Progress::default()
+        };
+        let mut on_line = |line: &str| if self.progress && progress.advance(line) {
+            tracer.emit(TraceKind::Progress(progress.clone()));
+        };
+        let run = wait_for(child?, timeout, self.cancel.as_ref(), &mut on_line)?;
+        tracer.emit(match run.interruption {
+            None => TraceKind::CargoExited { success: run.success },
+            Some(interruption) => TraceKind::Killed {
+                timed_out: interruption == Interruption::TimedOut,
+            },
+        });
+        Ok(run)
+    }
+
+    /// Returns the command of cargo with the arguments, in the directory of
+    /// the project and with the environment of the check, and its output
+    /// piped.
+    fn cargo_command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new("cargo");
+        command.args(args)
+            .current_dir(self.project_dir())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some(ref dir) = self.target_dir {
+            command.env("CARGO_TARGET_DIR", dir);
+        }
+        command.envs(self.envs.iter().cloned());
+        // Cargo takes these over RUSTFLAGS, and they may have spaces
+        if let Some(flags) = self.rustflags_in_effect() {
+            command.env("CARGO_ENCODED_RUSTFLAGS", flags.join("\u{1f}"));
+        }
+        command
+    }
+
+    /// Builds the report of a run of cargo.
+    fn report(&self, tracer: &Tracer, args: &[&str], fallbacks: Vec<Fallback>, run: &CargoRun)
+            -> Result<CheckReport, CheckError> {
+        if self.strict_timeout {
+            match run.interruption {
+                Some(Interruption::TimedOut) => return Err(CheckError::TimedOut),
+                Some(Interruption::Cancelled) => return Err(CheckError::Cancelled),
+                None => {},
+            }
+        }
+        let mut report = parse_output_lenient(&run.stderr);
+        // The diagnostics of JSON messages are on stdout, and the units that
+        // failed and the failures of cargo are still on stderr
+        if args.windows(2).any(|pair| pair == ["--message-format", "json"]) {
+            report.check = parse_json_messages(&run.stdout);
+        }
+        tracer.emit(TraceKind::Parsed {
+            problems: report.check.problems().len(),
+            raw_blocks: report.raw_blocks.len(),
+        });
+        let failed = failed_packages(&report.raw_blocks);
+        let keep_going = args.contains(&"--keep-going");
+        // With --keep-going, members failing to compile is an expected result
+        let failures_expected = keep_going && ! failed.is_empty();
+        if ! run.success && run.interruption.is_none() && ! failures_expected {
+            // Errors of the project are a result, but cargo failing isn't
+            if let Some(err) = cargo_failure(self.project_dir(), run.code, &run.stderr,
+                    &report.check) {
+                return Err(err);
+            }
+        }
+        // The members of a check that was cut short can't be told apart
+        if keep_going && run.interruption.is_none() {
+            report.members = WorkspaceGraph::load(self.project_dir())?.classify(&failed);
+        }
+        for fallback in &fallbacks {
+            tracer.emit(TraceKind::Fallback(fallback.clone()));
+        }
+        report.fallbacks = fallbacks;
+        report.correlation_id = Some(tracer.correlation_id.clone());
+        report.timed_out = run.interruption == Some(Interruption::TimedOut);
+        report.cancelled = run.interruption == Some(Interruption::Cancelled);
+        report.resource = Some(run.usage);
+        self.map_paths(&mut report);
+        Ok(report)
+    }
+
+    /// Maps the paths of the problems of the report and of their spans.
+    fn map_paths(&self, report: &mut CheckReport) {
+        let map = |path: &Arc<str>| PathMap::apply(&self.path_maps, Path::new(&**path))
+            .map(|path| Arc::from(path.to_string_lossy()));
+        if let Check::Warning(ref mut problems) | Check::Error(ref mut problems) = report.check {
+            for problem in problems {
+                if let Some(path) = map(&problem.filepath) {
+                    problem.filepath = path;
+                }
+                for note in spans_mut(problem) {
+                    if let Some(path) = note.file.as_ref().and_then(map) {
+                        note.file = Some(path);
+                    }
+                }
+            }
+        }
+        report.path_maps = self.path_maps.clone();
+    }
+
+    /// Returns the arguments to run cargo with, and the fallbacks they 
+    /// imply.
+    fn arguments<P: Probes>(&self, probes: &P) -> (Vec<&str>, Vec<Fallback>) {
+        let mut fallbacks = Vec::new();
+        let mut args = if let Some(ref subcommand) = self.subcommand {
+            vec![&subcommand[..]]
+        } else if self.clippy && probes.clippy_installed() {
+            vec!["clippy"]
+        } else {
+            if self.clippy {
+                fallbacks.push(Fallback::ClippyNotInstalled);
+            }
+            match self.kind {
+                CommandKind::Check => vec!["check"],
+                CommandKind::Build => vec!["build"],
+                CommandKind::Auto if probes.check_supported() => vec!["check"],
+                CommandKind::Auto => {
+                    fallbacks.push(Fallback::CheckUnsupported);
+                    vec!["build"]
+                },
+            }
+        };
+        args.extend_from_slice(&["--message-format", "json"]);
+        if self.keep_going {
+            if probes.keep_going_supported(args[0]) {
+                args.push("--keep-going");
+            } else {
+                fallbacks.push(Fallback::KeepGoingUnsupported);
+            }
+        }
+        if let Some(ref package) = self.package {
+            args.extend_from_slice(&["-p", package]);
+        }
+        for feature in &self.features {
+            args.extend_from_slice(&["--features", feature]);
+        }
+        let flags = [
+            (self.all_features, "--all-features"),
+            (self.no_default_features, "--no-default-features"),
+            (self.all_targets, "--all-targets"),
+            (self.release, "--release"),
+        ];
+        args.extend(flags.iter().filter(|&&(enabled, _)| enabled).map(|&(_, flag)| flag));
+        if let Some(ref target) = self.target {
+            args.extend_from_slice(&["--target", target]);
+        }
+        args.extend(self.args.iter().map(|arg| &arg[..]));
+        (args, fallbacks)
+    }
+}
+
+/// What the arguments of a check depend on in the installed toolchain.
+trait Probes {
+    /// Whether the ```cargo clippy``` subcommand can be run.
+    fn clippy_installed(&self) -> bool;
+    /// Whether the ```cargo check``` subcommand exists.
+    fn check_supported(&self) -> bool;
+    /// Whether the subcommand supports ```--keep-going```.
+    fn keep_going_supported(&self, subcommand: &str) -> bool;
+}
+
+/// Probes the cargo on the path.
+struct InstalledCargo;
+
+/// Returns the help of ```cargo check```, if the subcommand exists. It is
+/// only asked for once.
+fn check_help() -> Option<&'static str> {
+    static HELP: OnceLock<Option<String>> = OnceLock::new();
+    HELP.get_or_init(|| subcommand_help("check")).as_deref()
+}
+
+fn subcommand_help(subcommand: &str) -> Option<String> {
+    Command::new("cargo").args([subcommand, "--help"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+impl Probes for InstalledCargo {
+    fn clippy_installed(&self) -> bool {
+        Command::new("cargo").args(["clippy", "--version"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false)
+    }
+
+    fn check_supported(&self) -> bool {
+        check_help().is_some()
+    }
+
+    fn keep_going_supported(&self, subcommand: &str) -> bool {
+        let help = match subcommand {
+            "check" | "clippy" => check_help().map(String::from),
+            subcommand => subcommand_help(subcommand),
+        };
+        help.is_some_and(|help| help.contains("--keep-going"))
+    }
+}
+
+/// Collects the output of the child until it exits, or kills it when the
+/// timeout or cancellation comes first. The output of a killed child ends
+/// before its last block of stderr and its last line of stdout, as those
+/// may have been cut off.
+///
+/// Both pipes are read on threads of their own, so that a child never
+/// blocks writing to one of them while the other is read.
+fn wait_for(mut child: Child, timeout: Option<Duration>, cancel: Option<&CancelToken>,
+        on_line: &mut dyn FnMut(&str)) -> io::Result<CargoRun> {
+    let started = Instant::now();
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let stdout_bytes = Arc::new(Mutex::new(Vec::new()));
+    let stdout = child.stdout.take().map(|mut stdout| {
+        let bytes = stdout_bytes.clone();
+        thread::spawn(move || {
+            let mut buffer = [0; 8192];
+            while let Ok(read @ 1..) = stdout.read(&mut buffer) {
+                bytes.lock().unwrap_or_else(|err| err.into_inner())
+                    .extend_from_slice(&buffer[..read]);
+            }
+        })
+    });
+    let (sender, receiver) = mpsc::channel();
+    if let Some(stderr) = child.stderr.take() {
+        // Read on another thread, so that waiting for a line can time out
+        thread::spawn(move || {
+            let mut stderr = BufReader::new(stderr);
+            let mut line = Vec::new();
+            while stderr.read_until(b'\n', &mut line).is_ok_and(|read| read > 0) {
+                let text = String::from_utf8_lossy(&line).trim_end_matches(['\r', '\n']).to_owned();
+                if sender.send(text).is_err() {
+                    break;
+                }
+                line.clear();
+            }
+        });
+    }
+
+    let mut lines = Vec::new();
+    let interruption = loop {
+        if cancel.is_some_and(CancelToken::is_cancelled) {
+            break Some(Interruption::Cancelled);
+        }
+        let wait = match deadline {
+            Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                Some(left) if left > Duration::from_secs(0) => left.min(POLL_INTERVAL),
+                _ => break Some(Interruption::TimedOut),
+            },
+            None => POLL_INTERVAL,
+        };
+        match receiver.recv_timeout(wait) {
+            Ok(line) => {
+                on_line(&line);
+                lines.push(line);
+            },
+            Err(RecvTimeoutError::Timeout) => {},
+            Err(RecvTimeoutError::Disconnected) => break None,
+        }
+    };
+
+    let (success, code, usage) = match interruption {
+        None => {
+            let (status, usage) = wait_with_usage(&mut child, started)?;
+            // Processes that cargo started may still hold the pipe open, so
+            // only an exited child waits for its stdout to close
+            if let Some(stdout) = stdout {
+                let _ = stdout.join();
+            }
+            (status.success(), status.code(), usage)
+        },
+        Some(_) => {
+            // Reap the killed child, so that it doesn't linger as a zombie
+            let _ = child.kill();
+            let (_, usage) = wait_with_usage(&mut child, started)?;
+            if let Some(last_block) = lines.iter().rposition(|line| line_starts_block(line)) {
+                lines.truncate(last_block);
+            }
+            (false, None, usage)
+        },
+    };
+    let mut stderr = lines.join("\n");
+    stderr.push('\n');
+    let bytes = stdout_bytes.lock().unwrap_or_else(|err| err.into_inner());
+    let mut stdout = String::from_utf8_lossy(&bytes).into_owned();
+    if interruption.is_some() {
+        stdout.truncate(stdout.rfind('\n').map_or(0, |end| end + 1));
+    }
+    Ok(CargoRun { success, code, stdout, stderr, interruption, usage })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {Applicability, BoundStep, Note, Suggestion};
+
+    fn finished(stderr: &str) -> CargoRun {
+        let usage = ResourceUsage {
+            wall_time: Duration::from_millis(1500),
+            user_time: None,
+            system_time: None,
+            max_rss: None,
+        };
+        CargoRun {
+            success: true,
+            code: Some(0),
+            stdout: String::new(),
+            stderr: String::from(stderr),
+            interruption: None,
+            usage,
+        }
+    }
+
+    /// A toolchain with the given features.
+    struct MockCargo {
+        clippy: bool,
+        check: bool,
+        keep_going: bool,
+    }
+
+    /// Returns the arguments of the subcommand with JSON messages and then
+    /// the rest.
+    fn json<'a>(subcommand: &'a str, rest: &[&'a str]) -> Vec<&'a str> {
+        let mut args = vec![subcommand, "--message-format", "json"];
+        args.extend_from_slice(rest);
+        args
+    }
+
+    const FULL: MockCargo = MockCargo { clippy: true, check: true, keep_going: true };
+
+    impl Probes for MockCargo {
+        fn clippy_installed(&self) -> bool {
+            self.clippy
+        }
+
+        fn check_supported(&self) -> bool {
+            self.check
+        }
+
+        fn keep_going_supported(&self, _: &str) -> bool {
+            self.keep_going
+        }
+    }
+
+    #[test]
+    fn clippy_falls_back_to_check_when_missing() {
+        assert_eq!(Checker::new().arguments(&FULL), (json("check", &[]), vec![]));
+        let clippy = Checker::new().with_clippy(true);
+        assert_eq!(clippy.arguments(&FULL), (json("clippy", &[]), vec![]));
+        assert_eq!(clippy.arguments(&MockCargo { clippy: false, ..FULL }),
+            (json("check", &[]), vec![Fallback::ClippyNotInstalled]));
+    }
+
+    #[test]
+    fn keep_going_degrades_when_unsupported() {
+        let checker = Checker::new().keep_going(true);
+        assert_eq!(checker.arguments(&FULL), (json("check", &["--keep-going"]), vec![]));
+        assert_eq!(checker.arguments(&MockCargo { keep_going: false, ..FULL }),
+            (json("check", &[]), vec![Fallback::KeepGoingUnsupported]));
+        assert_eq!(checker.package("core").arguments(&FULL),
+            (json("check", &["--keep-going", "-p", "core"]), vec![]));
+    }
+
+    #[test]
+    fn options_become_arguments_in_order() {
+        let checker = Checker::new()
+            .features(["serde"])
+            .features(vec![String::from("cli")])
+            .no_default_features(true)
+            .all_targets(true)
+            .release(true)
+            .target("wasm32-unknown-unknown")
+            .env("RUSTFLAGS", "-Dwarnings")
+            .arg("--locked");
+        assert_eq!(checker.arguments(&FULL), (json("check", &["--features", "serde",
+            "--features", "cli", "--no-default-features", "--all-targets", "--release", "--target",
+            "wasm32-unknown-unknown", "--locked"]), vec![]));
+        assert_eq!(checker.envs, vec![(String::from("RUSTFLAGS"), String::from("-Dwarnings"))]);
+    }
+
+    #[test]
+    fn auto_falls_back_to_build_without_check() {
+        let without_check = MockCargo { check: false, ..FULL };
+        let build = Checker::new().command(CommandKind::Build);
+        assert_eq!(build.arguments(&FULL), (json("build", &[]), vec![]));
+        let auto = Checker::new().command(CommandKind::Auto);
+        assert_eq!(auto.arguments(&FULL), (json("check", &[]), vec![]));
+        assert_eq!(auto.arguments(&without_check),
+            (json("build", &[]), vec![Fallback::CheckUnsupported]));
+        // Clippy doesn't need check
+        assert_eq!(auto.with_clippy(true).arguments(&without_check), (json("clippy", &[]), vec![]));
+    }
+
+    #[test]
+    fn checks_read_the_json_messages_of_stdout() {
+        let mut run = finished("   Compiling app v0.1.0 (/app)\n");
+        run.stdout = String::from(r#"{"reason":"compiler-artifact","package_id":"app 0.1.0 (path+file:///app)"}
+{"reason":"compiler-message","package_id":"app 0.1.0 (path+file:///app)","target":{"kind":["lib"],"name":"app"},"message":{"rendered":null,"children":[{"children":[],"code":null,"level":"help","message":"if this is intentional, prefix it with an underscore","rendered":null,"spans":[{"byte_end":26,"byte_start":25,"column_end":10,"column_start":9,"expansion":null,"file_name":"src/lib.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":"_x","suggestion_applicability":"MachineApplicable","text":[]}]}],"level":"warning","message":"unused variable: `x`","spans":[{"byte_end":26,"byte_start":25,"column_end":10,"column_start":9,"expansion":null,"file_name":"src/lib.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}],"code":{"code":"unused_variables","explanation":null}}}
+"#);
+        let tracer = Tracer::new(String::from("json"), None);
+        let report = Checker::new().report(&tracer, &json("check", &[]), vec![], &run).unwrap();
+        let problem = &report.check.problems()[0];
+        assert_eq!((&*problem.filepath, problem.message.byte_start), ("src/lib.rs", Some(25)));
+        assert_eq!((problem.during_package.as_deref(), problem.target.as_deref()),
+            (Some("app"), Some("lib")));
+        assert_eq!(problem.suggestions[0].applicability, Applicability::MachineApplicable);
+
+        // Without the flag, the diagnostics are the text of stderr
+        let text = Checker::new().report(&tracer, &["check"], vec![], &run).unwrap();
+        assert_eq!(text.check, Check::Perfect);
+    }
+
+    #[test]
+    fn interleaved_checks_have_separable_traces() {
+        let (sender, receiver) = ::std::sync::mpsc::channel();
+        let first = Checker::new().correlation_id("first").trace(sender.clone());
+        let second = Checker::new().with_clippy(true).trace(sender);
+        let first_tracer = Tracer::new(String::from("first"), first.trace.as_ref());
+        let second_tracer = Tracer::new(generate_id(), second.trace.as_ref());
+        let output = "src/lib.rs:2:9: 2:10 warning: unused variable: `x`\n";
+
+        first_tracer.emit(TraceKind::Started { args: vec![String::from("check")] });
+        second_tracer.emit(TraceKind::Started { args: vec![String::from("check")] });
+        let second_report = second.report(&second_tracer, &["check"],
+            vec![Fallback::ClippyNotInstalled], &finished(output)).unwrap();
+        let first_report = first.report(&first_tracer, &["check"], vec![], &finished(""))
+            .unwrap();
+        assert_eq!(first_report.correlation_id, Some(String::from("first")));
+        assert_eq!(first_report.resource.map(|usage| usage.wall_time),
+            Some(Duration::from_millis(1500)));
+        assert_eq!(second_report.correlation_id, Some(second_tracer.correlation_id.clone()));
+
+        let events: Vec<TraceEvent> = receiver.try_iter().collect();
+        let stream = |id: &str| -> Vec<TraceKind> {
+            events.iter()
+                .filter(|event| event.correlation_id == id)
+                .map(|event| event.kind.clone())
+                .collect()
+        };
+        assert_eq!(stream("first"), vec![
+            TraceKind::Started { args: vec![String::from("check")] },
+            TraceKind::Parsed { problems: 0, raw_blocks: 0 },
+        ]);
+        assert_eq!(stream(&second_tracer.correlation_id), vec![
+            TraceKind::Started { args: vec![String::from("check")] },
+            TraceKind::Parsed { problems: 1, raw_blocks: 0 },
+            TraceKind::Fallback(Fallback::ClippyNotInstalled),
+        ]);
+        assert_eq!(events.len(), 5);
+    }
+
+    #[test]
+    fn path_maps_use_the_longest_matching_prefix() {
+        let checker = Checker::new()
+            .path_map("/build", "/home/me")
+            .path_map("/build/src/", "/home/me/project")
+            .path_map("/build/src/vendor", "/opt/vendor/");
+        let apply = |path: &str| PathMap::apply(&checker.path_maps, Path::new(path));
+        assert_eq!(apply("/build/src/lib.rs"), Some(PathBuf::from("/home/me/project/lib.rs")));
+        assert_eq!(apply("/build/src"), Some(PathBuf::from("/home/me/project")));
+        assert_eq!(apply("/build/src/vendor/a.rs"), Some(PathBuf::from("/opt/vendor/a.rs")));
+        assert_eq!(apply("/build/srcs/lib.rs"), Some(PathBuf::from("/home/me/srcs/lib.rs")));
+        assert_eq!(apply("/elsewhere/lib.rs"), None);
+        assert_eq!(apply("src/lib.rs"), None);
+    }
+
+    #[test]
+    fn path_maps_are_applied_to_the_report() {
+        let checker = Checker::new().path_map("/build/src", "/home/me/project");
+        let mut report = ::parse_output_lenient("\
+/build/src/src/lib.rs:2:9: 2:10 warning: unused variable: `x`
+src/main.rs:2:9: 2:10 warning: unused variable: `y`
+");
+        // Spans of their own files, in every place that a problem has them
+        let span = |file: &str| Note::new(1, 1, 1, 2, "here").with_file(file);
+        let mut problems = report.check.problems().to_vec();
+        problems[0].help.push(span("/build/src/src/help.rs"));
+        problems[0].notes.push(span("/build/src/src/note.rs"));
+        problems[0].additional_primary_spans.push(span("/build/src/src/primary.rs"));
+        problems[0].suggestions.push(Suggestion::new(span("/build/src/src/fix.rs"), "_x",
+            Applicability::MachineApplicable));
+        problems[0].bound_chain.push(BoundStep::new("required by a bound in `f`",
+            Some(("/build/src/src/bound.rs", span("/build/src/src/bound.rs")))));
+        problems[1].notes.push(span("/elsewhere/note.rs"));
+        report.check = Check::from_problems(problems);
+
+        checker.map_paths(&mut report);
+        let paths: Vec<&str> = report.check.problems().iter()
+            .map(|problem| &problem.filepath[..])
+            .collect();
+        assert_eq!(paths, vec!["/home/me/project/src/lib.rs", "src/main.rs"]);
+        let mut problems = report.check.problems().to_vec();
+        let files: Vec<Vec<String>> = problems.iter_mut()
+            .map(|problem| spans_mut(problem).filter_map(|note| note.file.as_deref())
+                .map(String::from).collect())
+            .collect();
+        assert_eq!(files, vec![
+            vec!["/home/me/project/src/help.rs", "/home/me/project/src/note.rs",
+                "/home/me/project/src/primary.rs", "/home/me/project/src/fix.rs",
+                "/home/me/project/src/bound.rs"],
+            vec!["/elsewhere/note.rs"],
+        ]);
+        assert_eq!(report.path_maps, checker.path_maps);
+    }
+
+    #[test]
+    fn only_transient_failures_are_retried() {
+        let invalid = || CheckError::CargoFailed { status: Some(101), stderr: String::new() };
+        assert!(is_transient(&invalid(), "warning: spurious network error (2 tries remaining)"));
+        assert!(is_transient(&invalid(), "error: failed to acquire package cache lock"));
+        assert!(is_transient(&CheckError::TimedOut, ""));
+        assert!(! is_transient(&invalid(), "error: could not compile `a` due to 2 previous \
+            errors\nwarning: spurious network error"));
+        assert!(! is_transient(&invalid(), "error: failed to parse manifest at `Cargo.toml`"));
+        assert!(! is_transient(&CheckError::ComponentMissing {
+            component: String::from("clippy"),
+            install_hint: String::from("rustup component add clippy"),
+        }, "timed out"));
+
+        let (sender, receiver) = mpsc::channel();
+        let checker = Checker::new().retries(3, Duration::from_millis(1)).trace(sender);
+        let tracer = Tracer::new(String::from("retry"), checker.trace.as_ref());
+        let mut outputs = vec!["timed out", "failed to download", ""].into_iter();
+        let report = checker.run_attempts(&tracer, |_| match outputs.next() {
+            Some("") => (Ok(CheckReport::new(Check::Perfect)), String::new()),
+            Some(output) => (Err(invalid()), String::from(output)),
+            None => unreachable!(),
+        }).unwrap();
+        let backoffs: Vec<(&str, Duration)> = report.attempts.iter()
+            .map(|attempt| (attempt.kind, attempt.backoff))
+            .collect();
+        assert_eq!(backoffs, vec![("cargo_failed", Duration::from_millis(1)),
+            ("cargo_failed", Duration::from_millis(2))]);
+        assert_eq!(receiver.try_iter().map(|event| event.kind).collect::<Vec<_>>(), vec![
+            TraceKind::Retrying { attempt: 2, backoff: Duration::from_millis(1) },
+            TraceKind::Retrying { attempt: 3, backoff: Duration::from_millis(2) },
+        ]);
+
+        let mut runs = 0;
+        let compile_error = checker.run_attempts(&tracer, |_| {
+            runs += 1;
+            (Err(invalid()), String::from("error: could not compile `a`"))
+        });
+        assert!(compile_error.is_err() && runs == 1);
+
+        // The backoff doesn't fit in the timeout
+        let mut timeouts = Vec::new();
+        let slow = Checker::new().retries(3, Duration::from_secs(60))
+            .timeout(Duration::from_secs(30));
+        let result = slow.run_attempts(&tracer, |timeout| {
+            timeouts.push(timeout.unwrap());
+            (Err(invalid()), String::from("timed out"))
+        });
+        assert!(result.is_err() && timeouts.len() == 1);
+        assert!(timeouts[0] <= Duration::from_secs(30));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn timed_out_checks_keep_their_complete_problems_and_reap_cargo() {
+        let child = Command::new("sh")
+            .args(["-c", "echo 'src/lib.rs:2:9: 2:10 warning: unused variable: `x`' >&2; \
+                echo 'src/lib.rs:3:9: 3:10 warning: unused variable: `y`' >&2; \
+                echo '{\"reason\":\"compiler-artifact\"}'; printf '{\"rea'; exec sleep 10"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let pid = child.id();
+        let start = Instant::now();
+        let run = wait_for(child, Some(Duration::from_millis(300)), None, &mut |_| {}).unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(! Path::new(&format!("/proc/{}", pid)).exists());
+        assert_eq!(run.interruption, Some(Interruption::TimedOut));
+        assert_eq!(run.stdout, "{\"reason\":\"compiler-artifact\"}\n");
+
+        let tracer = Tracer::new(String::from("partial"), None);
+        let report = Checker::new().report(&tracer, &["check"], vec![], &run).unwrap();
+        assert!(report.timed_out && ! report.cancelled && report.is_partial());
+        // The second warning might have had more lines coming
+        assert_eq!(report.check.problems().len(), 1);
+
+        let strict = Checker::new().strict_timeout(true);
+        assert!(matches!(strict.report(&tracer, &["check"], vec![], &run),
+            Err(CheckError::TimedOut)));
+    }
+
+    #[test]
+    fn cancelled_checks_stop_early() {
+        let token = CancelToken::new();
+        token.clone().cancel();
+        let child = Command::new("cargo").args(["--version"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let run = wait_for(child, None, Some(&token), &mut |_| {}).unwrap();
+        assert_eq!(run.interruption, Some(Interruption::Cancelled));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn large_output_on_both_pipes_does_not_deadlock() {
+        // 100MB in alternating bursts of 1MB, of lines of 1000 bytes
+        let child = Command::new("sh")
+            .args(["-c", "line=$(head -c 999 /dev/zero | tr '\\0' x); i=0; \
+                while [ $i -lt 50 ]; do \
+                    yes \"$line\" | head -n 1000 >&2; yes \"$line\" | head -n 1000; \
+                    i=$((i + 1)); \
+                done"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let start = Instant::now();
+        let run = wait_for(child, Some(Duration::from_secs(60)), None, &mut |_| {}).unwrap();
+        assert_eq!(run.interruption, None);
+        assert!(run.success);
+        assert!(start.elapsed() < Duration::from_secs(60));
+        assert_eq!(run.stderr.len(), 50 * 1000 * 1000);
+    }
+}
@@ -0,0 +1,157 @@
+//! The settings of the ```.cargo/config.toml``` files of a project that a
+//! check depends on, found as cargo finds them: in the directory that cargo
+//! runs in and every directory above it, and then in the cargo home.
+//!
+//! Of the settings of a check, those given to the ```Checker``` come first,
+//! then the environment (```CARGO_TARGET_DIR```, ```RUSTFLAGS```), then the
+//! config files, and then the defaults of cargo.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use root::{strings, strip_comment, table, value};
+
+/// The settings of the config files that a check depends on.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CargoConfig {
+    /// The ```build.target-dir```, resolved against the directory that has
+    /// the ```.cargo``` directory of its file.
+    pub target_dir: Option<PathBuf>,
+    /// The ```build.rustflags``` of the files. Cargo joins arrays of flags,
+    /// with those of the files further up first, but a string of flags
+    /// replaces those further up.
+    pub rustflags: Vec<String>,
+    /// The arguments of ```alias.check```. Cargo ignores it with a warning,
+    /// as it shadows the subcommand, and so do checks.
+    pub check_alias: Option<Vec<String>>,
+    /// The files that were read, the nearest first.
+    pub files: Vec<PathBuf>,
+}
+
+/// Returns the config file of the directory, if it has one. Cargo takes
+/// the file without the extension when there are both.
+fn config_file(dir: &Path) -> Option<PathBuf> {
+    let dir = dir.join(".cargo");
+    [dir.join("config"), dir.join("config.toml")].iter().find(|path| path.is_file()).cloned()
+}
+
+/// Returns the directory of the config file of the cargo home.
+fn cargo_home() -> Option<PathBuf> {
+    env::var_os("CARGO_HOME").map(PathBuf::from).or_else(|| {
+        env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))
+            .map(|home| PathBuf::from(home).join(".cargo"))
+    })
+}
+
+/// Returns the text of the key of the table, written in the table or as a
+/// dotted key like ```build.target-dir``` before the first table.
+fn setting(config: &str, table_name: &str, key: &str) -> Option<String> {
+    let top_level: Vec<&str> = config.lines()
+        .map(|line| strip_comment(line).trim())
+        .take_while(|line| ! line.starts_with('['))
+        .collect();
+    table(config, table_name).and_then(|lines| value(&lines, key))
+        .or_else(|| value(&top_level, &format!("{}.{}", table_name, key)))
+}
+
+/// Returns the arguments of a value that is either an array of strings or
+/// a string of arguments separated by spaces, and whether it is an array.
+fn arguments(value: &str) -> (Vec<String>, bool) {
+    let strings = strings(value);
+    if value.starts_with('[') {
+        (strings, true)
+    } else {
+        (strings.iter().flat_map(|text| text.split_whitespace()).map(String::from).collect(), false)
+    }
+}
+
+impl CargoConfig {
+    /// Reads the config files that cargo reads when it runs in the
+    /// directory. Files that can't be read are left out, and a nearer file
+    /// overrides the settings of the ones further up.
+    pub fn discover<P: AsRef<Path>>(dir: P) -> CargoConfig {
+        let dir = dir.as_ref();
+        let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+        let mut dirs: Vec<PathBuf> = dir.ancestors().map(Path::to_path_buf).collect();
+        // The config of the cargo home is read last, if it isn't above
+        if let Some(home) = cargo_home().and_then(|home| home.parent().map(Path::to_path_buf)) {
+            let home = fs::canonicalize(&home).unwrap_or(home);
+            if ! dirs.contains(&home) {
+                dirs.push(home);
+            }
+        }
+
+        let mut config = CargoConfig::default();
+        let mut rustflags = Vec::new();
+        // Whether the flags of the files further up are joined to them
+        let mut joined = true;
+        for dir in dirs {
+            let path = match config_file(&dir) {
+                Some(path) => path,
+                None => continue,
+            };
+            let text = match fs::read_to_string(&path) {
+                Ok(text) => text,
+                Err(_) => continue,
+            };
+            if config.target_dir.is_none() {
+                config.target_dir = setting(&text, "build", "target-dir")
+                    .and_then(|value| strings(&value).into_iter().next())
+                    .map(|target_dir| dir.join(target_dir));
+            }
+            match setting(&text, "build", "rustflags").map(|flags| arguments(&flags)) {
+                Some((flags, is_array)) if joined => {
+                    rustflags.push(flags);
+                    joined = is_array;
+                },
+                _ => {},
+            }
+            if config.check_alias.is_none() {
+                config.check_alias = setting(&text, "alias", "check")
+                    .map(|alias| arguments(&alias).0);
+            }
+            config.files.push(path);
+        }
+        config.rustflags = rustflags.into_iter().rev().flatten().collect();
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    #[test]
+    fn nearer_files_override_the_ones_above() {
+        let root = env::temp_dir().join(format!("lintparser-config-{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let write = |path: &str, text: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        };
+        write(".cargo/config.toml", "build.target-dir = \"shared\" # for all\n\
+            build.rustflags = [\"--cfg\", \"outer\"]\n\n[alias]\ncheck = [\"check\", \"-q\"]\n");
+        write("app/.cargo/config.toml", "[build]\nrustflags = [\"--cfg\", \"inner\"]\n");
+        // Without the extension, it wins over the file with it
+        write("app/crate/.cargo/config", "[build]\ntarget-dir = 'out'\n");
+        write("app/crate/.cargo/config.toml", "[build]\ntarget-dir = 'ignored'\n");
+        let root = fs::canonicalize(&root).unwrap();
+
+        let app = CargoConfig::discover(root.join("app"));
+        assert_eq!(app.target_dir, Some(root.join("shared")));
+        assert_eq!(app.rustflags, vec!["--cfg", "outer", "--cfg", "inner"]);
+        assert_eq!(app.check_alias, Some(vec![String::from("check"), String::from("-q")]));
+        assert_eq!(&app.files[..2], &[root.join("app/.cargo/config.toml"),
+            root.join(".cargo/config.toml")]);
+        let nested = CargoConfig::discover(root.join("app/crate"));
+        assert_eq!(nested.target_dir, Some(root.join("app/crate/out")));
+        assert_eq!(nested.files[0], root.join("app/crate/.cargo/config"));
+        // A string replaces the flags above it
+        write("app/.cargo/config.toml", "build.rustflags = '-D warnings'\n");
+        assert_eq!(CargoConfig::discover(root.join("app")).rustflags, vec!["-D", "warnings"]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,110 @@
+//! The number of problems per thousand lines of each package, for finding
+//! the packages that need the most attention.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use scan::{rust_files, source_lines, LineCount};
+use {CheckReport, ProjectLayout};
+
+/// The problems of a package relative to its size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DensityReport {
+    pub package: String,
+    pub problems: usize,
+    /// The counted lines of the Rust files of the package.
+    pub lines: usize,
+    /// The problems per thousand lines, or 0 for a package without lines.
+    pub per_kloc: f64,
+}
+
+/// Returns the counted lines of the files under the root that belong to its
+/// package rather than to a package nested in it.
+fn package_lines(layout: &ProjectLayout, root: &Path, count: LineCount) -> io::Result<usize> {
+    let mut lines = 0;
+    for path in rust_files(root)? {
+        if layout.package_root(&path).map(PathBuf::as_path) == Some(root) {
+            lines += source_lines(&fs::read_to_string(&path)?, count);
+        }
+    }
+    Ok(lines)
+}
+
+impl CheckReport {
+    /// Returns the density of the problems of each package of the layout,
+    /// worst first, with the lines counted as given. Problems outside of
+    /// every package are left out.
+    pub fn density(&self, layout: &ProjectLayout, count: LineCount)
+            -> io::Result<Vec<DensityReport>> {
+        let mut problems: Vec<(&PathBuf, usize)> = layout.package_roots.iter()
+            .map(|root| (root, 0))
+            .collect();
+        for problem in self.check.problems() {
+            let path = layout.workspace_root.join(&*problem.filepath);
+            if let Some(root) = layout.package_root(&path) {
+                if let Some(entry) = problems.iter_mut().find(|entry| entry.0 == root) {
+                    entry.1 += 1;
+                }
+            }
+        }
+        let mut reports = Vec::new();
+        for (root, problems) in problems {
+            let lines = package_lines(layout, root, count)?;
+            let per_kloc = if lines == 0 { 0.0 } else { problems as f64 * 1000.0 / lines as f64 };
+            reports.push(DensityReport { package: layout.package_name(root), problems, lines,
+                per_kloc });
+        }
+        reports.sort_by(|a, b| b.per_kloc.total_cmp(&a.per_kloc)
+            .then_with(|| a.package.cmp(&b.package)));
+        Ok(reports)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+    use parse_output;
+
+    #[test]
+    fn packages_are_sorted_by_density() {
+        let root = env::temp_dir().join(format!("lintparser-density-{}", process::id()));
+        let write = |path: &str, lines: usize| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let source: String = (0..lines).map(|i| format!("// {}\nfn f{}() {{}}\n\n", i, i))
+                .collect();
+            fs::write(path, source).unwrap();
+        };
+        write("src/main.rs", 400);
+        write("core/src/lib.rs", 100);
+        write("docs/src/lib.rs", 10);
+        let mut layout = ProjectLayout::new(&root);
+        layout.add_named_package(&root, "app");
+        layout.add_named_package(root.join("core"), "app-core");
+        layout.add_package(root.join("docs"));
+        let report = CheckReport::new(parse_output("\
+src/main.rs:2:4: 2:6 warning: function is never used: `f0`, #[warn(dead_code)] on by default
+core/src/lib.rs:2:4: 2:6 warning: function is never used: `f0`, #[warn(dead_code)] on by default
+core/src/lib.rs:5:4: 5:6 warning: function is never used: `f1`, #[warn(dead_code)] on by default
+/elsewhere/lib.rs:1:1: 1:2 warning: unused import, #[warn(unused_imports)] on by default
+"));
+        let code = report.density(&layout, LineCount::Code);
+        let all = report.density(&layout, LineCount::All);
+        fs::remove_dir_all(&root).unwrap();
+
+        let summary = |reports: Vec<DensityReport>| -> Vec<(String, usize, usize, f64)> {
+            reports.into_iter()
+                .map(|report| (report.package, report.problems, report.lines, report.per_kloc))
+                .collect()
+        };
+        assert_eq!(summary(code.unwrap()), vec![
+            (String::from("app-core"), 2, 100, 20.0),
+            (String::from("app"), 1, 400, 2.5),
+            (String::from("docs"), 0, 10, 0.0),
+        ]);
+        assert_eq!(summary(all.unwrap())[1], (String::from("app"), 1, 1200, 1000.0 / 1200.0));
+    }
+}
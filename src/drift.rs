@@ -0,0 +1,147 @@
+//! Finding the problems whose files have changed since the diagnostics were
+//! produced, like those of a published report, so that no snippet shows
+//! code that the problem isn't about.
+//!
+//! The line that the diagnostic quoted is compared with the line on disk.
+//! Problems without a quoted line are compared by the hash of their file
+//! when the report was published, if it has one.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use {stable_hash, Check, CheckReport, ProblemDescription};
+
+/// Returns whether the line of the source is the one that a diagnostic
+/// quoted. Rustc shows tabs as four spaces and may cut long lines with
+/// ```...```, so only the text between cuts is compared, without
+/// whitespace.
+pub fn quoted_line_matches(quoted: &str, line: &str) -> bool {
+    let compact = |text: &str| text.split_whitespace().collect::<String>();
+    let line = compact(line);
+    if ! quoted.contains("...") {
+        return compact(quoted) == line;
+    }
+    let mut pieces = quoted.split("...").map(compact).filter(|piece| ! piece.is_empty());
+    let mut rest = &line[..];
+    pieces.all(|piece| match rest.find(&piece[..]) {
+        Some(start) => {
+            rest = &rest[start + piece.len()..];
+            true
+        },
+        None => false,
+    })
+}
+
+/// Returns whether the problem no longer fits the source: its lines are
+/// past the end, or its first line isn't the one it quoted.
+fn drifted(problem: &ProblemDescription, source: &str) -> bool {
+    let line = match problem.message.start_line.checked_sub(1)
+            .and_then(|index| source.lines().nth(index)) {
+        Some(line) => line,
+        None => return problem.message.start_line > 0,
+    };
+    problem.message.end_line > source.lines().count()
+        || problem.source_line.as_ref().is_some_and(|quoted| ! quoted_line_matches(quoted, line))
+}
+
+/// Marks the problems that drifted from their files under the root, with
+/// the hashes of the files for the problems that quote no line.
+fn mark_drift(check: Check, root: &Path, hashes: &BTreeMap<String, u64>) -> Check {
+    let mut sources: HashMap<String, Option<String>> = HashMap::new();
+    let problems = check.problems().iter().map(|problem| {
+        let mut problem = problem.clone();
+        let source = sources.entry(String::from(&*problem.filepath)).or_insert_with(|| {
+            fs::read_to_string(root.join(&*problem.filepath)).ok()
+        });
+        // Files that can't be read are stale rather than drifted
+        if let Some(ref source) = *source {
+            let changed = problem.source_line.is_none() && hashes.get(&*problem.filepath)
+                .is_some_and(|&hash| hash != stable_hash(source));
+            problem.drifted = changed || drifted(&problem, source);
+        }
+        problem
+    }).collect();
+    Check::from_problems(problems)
+}
+
+impl Check {
+    /// Sets whether the problems drifted from their files (relative to the
+    /// root): whether the line that each problem quoted has changed, or its
+    /// span is past the end of the file. The snippets of drifted problems
+    /// aren't rendered.
+    pub fn mark_drift<P: AsRef<Path>>(self, root: P) -> Check {
+        mark_drift(self, root.as_ref(), &BTreeMap::new())
+    }
+
+    /// Returns the number of problems that drifted from their files (see
+    /// ```mark_drift```).
+    pub fn drift_count(&self) -> usize {
+        self.problems().iter().filter(|problem| problem.drifted).count()
+    }
+}
+
+impl CheckReport {
+    /// Sets whether the problems of the check drifted from their files, as
+    /// ```Check::mark_drift``` does. A problem that quotes no line drifted
+    /// if its file has changed since the report was published.
+    pub fn mark_drift<P: AsRef<Path>>(mut self, root: P) -> CheckReport {
+        self.check = mark_drift(self.check, root.as_ref(), &self.source_hashes);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+    use publish::{parse_published, published_text};
+    use {parse_output, render_snippet};
+
+    const WARNINGS: &str = "\
+warning: unused variable: `x`
+ --> src/lib.rs:2:9
+  |
+2 |     let x = 1;
+  |         ^
+  |
+warning: unused variable: `y`
+ --> src/lib.rs:3:9
+  |
+3 |     let y = 2;
+  |         ^
+";
+
+    #[test]
+    fn edited_lines_are_drift_and_lose_their_snippets() {
+        let root = env::temp_dir().join(format!("lintparser-drift-{}", process::id()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/lib.rs"), "fn f() {\n    let x = 1;\n    let y = 2;\n}\n")
+            .unwrap();
+        let check = parse_output(WARNINGS);
+        assert_eq!(check.problems()[0].source_line.as_deref(), Some("    let x = 1;"));
+        // Published while the file was as it was checked
+        let mut report = CheckReport::new(parse_output("src/lib.rs:2:9: 2:10 warning: unused \
+            variable: `x`, #[warn(unused_variables)] on by default\n"));
+        report.source_hashes.insert(String::from("src/lib.rs"),
+            stable_hash(&fs::read_to_string(root.join("src/lib.rs")).unwrap()));
+        let published = published_text(&report, None);
+        let unchanged = Check::from_problems(check.problems().to_vec()).mark_drift(&root);
+        assert_eq!(unchanged.drift_count(), 0);
+
+        fs::write(root.join("src/lib.rs"), "fn f() {\n    let y = 2;\n}\n").unwrap();
+        let source = fs::read_to_string(root.join("src/lib.rs")).unwrap();
+        let marked = check.mark_drift(&root);
+        assert_eq!(marked.drift_count(), 2);
+        // The line of the second one is the end of the function now
+        let problem = &marked.problems()[0];
+        assert!(problem.drifted);
+        assert_eq!(render_snippet(&source, problem, 4), None);
+        let loaded = parse_published(&published).unwrap().mark_drift(&root);
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(loaded.check.drift_count(), 1);
+        assert!(quoted_line_matches("...let value = compute(first, ...", "    let value = \
+            compute(first, second, third);"));
+    }
+}
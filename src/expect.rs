@@ -0,0 +1,270 @@
+//! Checks as Rust string literals for golden tests in the style of
+//! ```expect-test```, for crates that test their own lint output.
+//!
+//! Problems are written as people read them (see the ```Display``` of
+//! ```ProblemDescription```), sorted by path and position. Parts that change
+//! between machines and runs are masked: absolute paths become
+//! ```$DIR/<file name>``` and durations in messages become ```$DURATION```.
+
+use std::path::Path;
+
+use {normalize_path, Check, Note, ProblemDescription};
+
+/// Whether the path is absolute on unix or windows.
+fn is_absolute(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    path.starts_with('/') || path.starts_with('\\')
+        || (bytes.len() > 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+            && (bytes[2] == b'\\' || bytes[2] == b'/'))
+}
+
+/// Returns the path normalized, with absolute paths masked.
+fn mask_path(path: &str) -> String {
+    let components = normalize_path(Path::new(""), path);
+    if is_absolute(path) {
+        format!("$DIR/{}", components.last().map(|c| &c[..]).unwrap_or(""))
+    } else {
+        components.join("/")
+    }
+}
+
+/// Whether the word is a duration like ```1.25s``` or ```300ms```.
+fn is_duration(word: &str) -> bool {
+    let number = ["ns", "µs", "us", "ms", "s"].iter()
+        .find_map(|unit| word.strip_suffix(unit));
+    match number {
+        Some(number) => {
+            let mut parts = number.splitn(2, '.');
+            let integer = parts.next().unwrap_or("");
+            let fraction = parts.next().unwrap_or("0");
+            [integer, fraction].iter()
+                .all(|part| ! part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+        },
+        None => false,
+    }
+}
+
+/// Masks the absolute paths and durations among the words of the text.
+fn mask_volatile(text: &str) -> String {
+    let mut masked = String::with_capacity(text.len());
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            masked.push('\n');
+        }
+        for (j, word) in line.split(' ').enumerate() {
+            if j > 0 {
+                masked.push(' ');
+            }
+            let core = word.trim_start_matches(['`', '\'', '"', '(', '[']);
+            let prefix = &word[..word.len() - core.len()];
+            let core = core.trim_end_matches(['`', '\'', '"', ')', ']', ',', ';', '.']);
+            let suffix = &word[prefix.len() + core.len()..];
+            if is_absolute(core) && core.len() > 1 {
+                masked.push_str(&format!("{}{}{}", prefix, mask_path(core), suffix));
+            } else if is_duration(core) {
+                masked.push_str(&format!("{}$DURATION{}", prefix, suffix));
+            } else {
+                masked.push_str(word);
+            }
+        }
+    }
+    masked
+}
+
+fn mask_note(note: &Note) -> Note {
+    Note::new(note.start_line, note.start_col, note.end_line, note.end_col,
+        mask_volatile(&note.message))
+}
+
+fn mask_problem(problem: &ProblemDescription) -> ProblemDescription {
+    let mut masked = problem.clone();
+    masked.filepath = mask_path(&problem.filepath).into();
+    masked.message = mask_note(&problem.message);
+    masked.help = problem.help.iter().map(mask_note).collect();
+    masked.notes = problem.notes.iter().map(mask_note).collect();
+    masked.emitted_at = None;
+    masked
+}
+
+impl Check {
+    /// Returns the text that ```to_expect_literal``` quotes.
+    fn to_expect_text(&self) -> String {
+        let mut problems: Vec<ProblemDescription> = self.problems().iter()
+            .map(mask_problem)
+            .collect();
+        problems.sort_by_cached_key(|problem| (problem.filepath.clone(),
+            problem.message.start_line, problem.message.start_col, problem.to_string()));
+        let mut text = String::new();
+        for problem in problems {
+            text.push_str(&problem.to_string());
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Returns the check as a raw string literal to paste into an
+    /// ```expect![[...]]``` golden test, with one problem (and its help and
+    /// notes) per line, in a fixed order and with volatile parts masked.
+    pub fn to_expect_literal(&self) -> String {
+        let text = self.to_expect_text();
+        // Enough hashes that no quote in the text ends the literal
+        let mut hashes = 0;
+        for (i, _) in text.match_indices('"') {
+            let run = text[i + 1..].bytes().take_while(|&b| b == b'#').count();
+            hashes = hashes.max(run + 1);
+        }
+        let hashes = "#".repeat(hashes);
+        format!("r{}\"\n{}\"{}", hashes, text, hashes)
+    }
+}
+
+/// Panics with a unified diff if the check doesn't match the golden
+/// problems, which are the contents of a literal from
+/// ```Check::to_expect_literal``` (the leading newline is optional).
+pub fn assert_check_matches(check: &Check, expected: &str) {
+    let actual = check.to_expect_text();
+    let expected = expected.strip_prefix('\n').unwrap_or(expected);
+    if actual != expected {
+        panic!("the check doesn't match the expected problems:\n{}\nThe actual problems are:\n{}",
+            unified_diff(expected, &actual), check.to_expect_literal());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineChange {
+    Kept,
+    Removed,
+    Added,
+}
+
+/// The changes from the old lines to the new ones, by their longest common
+/// subsequence.
+fn line_changes<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(LineChange, &'a str)> {
+    // The length of the common subsequence of the suffixes
+    let mut common = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut changes = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            changes.push((LineChange::Kept, old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            changes.push((LineChange::Removed, old[i]));
+            i += 1;
+        } else {
+            changes.push((LineChange::Added, new[j]));
+            j += 1;
+        }
+    }
+    changes
+}
+
+/// A unified diff of the lines, with three lines of context.
+fn unified_diff(expected: &str, actual: &str) -> String {
+    const CONTEXT: usize = 3;
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+    let changes = line_changes(&old, &new);
+
+    let mut out = String::from("--- expected\n+++ actual\n");
+    let mut start = 0;
+    while let Some(first) = changes[start..].iter().position(|&(c, _)| c != LineChange::Kept) {
+        // A hunk ends where more than twice the context is kept
+        let first = start + first;
+        let mut last = first;
+        let mut i = first;
+        while i < changes.len() {
+            if changes[i].0 != LineChange::Kept {
+                last = i;
+            } else if i - last > 2 * CONTEXT {
+                break;
+            }
+            i += 1;
+        }
+        let from = first.saturating_sub(CONTEXT);
+        let to = (last + CONTEXT + 1).min(changes.len());
+
+        let count = |range: &[(LineChange, &str)], skip: LineChange| {
+            range.iter().filter(|&&(c, _)| c != skip).count()
+        };
+        let old_start = count(&changes[..from], LineChange::Added) + 1;
+        let new_start = count(&changes[..from], LineChange::Removed) + 1;
+        out.push_str(&format!("@@ -{},{} +{},{} @@\n", old_start,
+            count(&changes[from..to], LineChange::Added), new_start,
+            count(&changes[from..to], LineChange::Removed)));
+        for &(change, line) in &changes[from..to] {
+            out.push(match change {
+                LineChange::Kept => ' ',
+                LineChange::Removed => '-',
+                LineChange::Added => '+',
+            });
+            out.push_str(line);
+            out.push('\n');
+        }
+        start = to;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic;
+    use parse_output;
+
+    const OUTPUT: &str = "\
+/home/someone/project/src/lib.rs:10:5: 10:8 warning: unused import: `a`, #[warn(unused_imports)] on by default
+src/main.rs:4:5: 4:8 error: unresolved name `foo` [E0425]
+src/main.rs:4:5: 4:8 help: did you mean `for`?
+/home/someone/project/src/lib.rs:2:9: 2:10 warning: the \"#x\" at /home/someone/project/build.rs took 1.25s
+";
+
+    #[test]
+    fn expect_literals_are_sorted_masked_and_quoted() {
+        let check = parse_output(OUTPUT);
+        assert_eq!(check.to_expect_literal(), "r##\"
+$DIR/lib.rs:2:9: warning: the \"#x\" at $DIR/build.rs took $DURATION
+$DIR/lib.rs:10:5: warning: unused import: `a`, #[warn(unused_imports)] on by default
+src/main.rs:4:5: error: unresolved name `foo` [E0425]
+    help: 4:5: did you mean `for`?
+\"##");
+        assert_eq!(Check::Perfect.to_expect_literal(), "r\"\n\"");
+        assert_check_matches(&check, r##"
+$DIR/lib.rs:2:9: warning: the "#x" at $DIR/build.rs took $DURATION
+$DIR/lib.rs:10:5: warning: unused import: `a`, #[warn(unused_imports)] on by default
+src/main.rs:4:5: error: unresolved name `foo` [E0425]
+    help: 4:5: did you mean `for`?
+"##);
+    }
+
+    #[test]
+    fn mismatches_panic_with_a_unified_diff() {
+        let check = parse_output(OUTPUT);
+        let result = panic::catch_unwind(|| assert_check_matches(&check, "
+$DIR/lib.rs:2:9: warning: the \"#x\" at $DIR/build.rs took $DURATION
+$DIR/lib.rs:10:5: warning: unused import: `b`, #[warn(unused_imports)] on by default
+src/main.rs:4:5: error: unresolved name `foo` [E0425]
+"));
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains("\
+--- expected
++++ actual
+@@ -1,3 +1,4 @@
+ $DIR/lib.rs:2:9: warning: the \"#x\" at $DIR/build.rs took $DURATION
+-$DIR/lib.rs:10:5: warning: unused import: `b`, #[warn(unused_imports)] on by default
++$DIR/lib.rs:10:5: warning: unused import: `a`, #[warn(unused_imports)] on by default
+ src/main.rs:4:5: error: unresolved name `foo` [E0425]
++    help: 4:5: did you mean `for`?
+"), "{}", message);
+    }
+}
@@ -0,0 +1,248 @@
+//! A C interface to checks, with the ```ffi``` feature.
+//!
+//! A check is returned as an opaque handle, which the caller frees with
+//! ```lintparser_check_free```. The strings of its problems are UTF-8 and
+//! NUL-terminated, owned by the handle and valid until it is freed; a NUL
+//! byte in a message ends it there. Every function accepts a null handle
+//! (returning zero counts and no problems), but a handle that was freed or
+//! didn't come from this library is undefined behavior. A panic in the
+//! library doesn't unwind into C: the check and parse functions return null
+//! instead.
+//!
+//! The prototypes are in ```include/lintparser.h```. The library to link with
+//! is built as a ```cdylib``` (or a ```staticlib```) with
+//! ```cargo rustc --lib --release --features ffi --crate-type cdylib```.
+//!
+//! ```c
+//! LintCheck *check = lintparser_check("/path/to/project");
+//! for (size_t i = 0; i < lintparser_problem_count(check); i++) {
+//!     size_t line, col;
+//!     int severity;
+//!     const char *file, *message;
+//!     lintparser_problem(check, i, &line, &col, &severity, &file, &message);
+//! }
+//! lintparser_check_free(check);
+//! ```
+
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic;
+use std::ptr;
+
+use {parse_output, Check, Checker, Severity};
+
+/// The severity of errors, from ```lintparser_problem```.
+pub const LINTPARSER_ERROR: c_int = 0;
+/// The severity of warnings, from ```lintparser_problem```.
+pub const LINTPARSER_WARNING: c_int = 1;
+/// The severity of standalone notes, from ```lintparser_problem```.
+pub const LINTPARSER_NOTE: c_int = 2;
+/// The severity of standalone help, from ```lintparser_problem```.
+pub const LINTPARSER_HELP: c_int = 3;
+
+/// A check, with the C strings of its problems.
+pub struct LintCheck {
+    check: Check,
+    /// The path and message of each problem.
+    strings: Vec<(CString, CString)>,
+}
+
+/// Returns the text up to its first NUL byte as a C string.
+fn c_string(text: &str) -> CString {
+    let end = text.find('\0').unwrap_or(text.len());
+    CString::new(&text[..end]).unwrap_or_default()
+}
+
+fn into_handle(check: Check) -> *mut LintCheck {
+    let strings = check.problems().iter()
+        .map(|problem| (c_string(&problem.filepath), c_string(&problem.message.message)))
+        .collect();
+    Box::into_raw(Box::new(LintCheck { check, strings }))
+}
+
+/// Reads a UTF-8 C string, or returns ```None``` for null or invalid UTF-8.
+unsafe fn read_str<'a>(text: *const c_char) -> Option<&'a str> {
+    if text.is_null() {
+        return None;
+    }
+    CStr::from_ptr(text).to_str().ok()
+}
+
+/// Runs a check of the project in the directory (a UTF-8 path), and returns
+/// its handle, or null if the path is null or invalid or the check fails or
+/// panics.
+///
+/// # Safety
+///
+/// The path must be null or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn lintparser_check(dir: *const c_char) -> *mut LintCheck {
+    let check = panic::catch_unwind(|| match read_str(dir) {
+        Some(dir) => Checker::new().dir(dir).run().ok().map(|report| into_handle(report.check)),
+        None => None,
+    });
+    check.ok().and_then(|check| check).unwrap_or(ptr::null_mut())
+}
+
+/// Parses the stderr output of cargo (as UTF-8) into a check, and returns
+/// its handle, or null if the output is null or invalid or the parser
+/// panics.
+///
+/// # Safety
+///
+/// The output must be null or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn lintparser_parse(output: *const c_char) -> *mut LintCheck {
+    let check = panic::catch_unwind(|| read_str(output).map(|output| {
+        into_handle(parse_output(output))
+    }));
+    check.ok().and_then(|check| check).unwrap_or(ptr::null_mut())
+}
+
+/// Frees a check and its strings. Null is ignored.
+///
+/// # Safety
+///
+/// The handle must be null or a live handle from this library, and isn't
+/// valid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn lintparser_check_free(check: *mut LintCheck) {
+    if ! check.is_null() {
+        drop(Box::from_raw(check));
+    }
+}
+
+/// Returns the number of problems of the check.
+///
+/// # Safety
+///
+/// The handle must be null or a live handle from this library.
+#[no_mangle]
+pub unsafe extern "C" fn lintparser_problem_count(check: *const LintCheck) -> usize {
+    check.as_ref().map_or(0, |check| check.check.problems().len())
+}
+
+/// Returns the number of errors of the check.
+///
+/// # Safety
+///
+/// The handle must be null or a live handle from this library.
+#[no_mangle]
+pub unsafe extern "C" fn lintparser_error_count(check: *const LintCheck) -> usize {
+    check.as_ref().map_or(0, |check| check.check.stats().errors)
+}
+
+/// Returns the number of warnings of the check.
+///
+/// # Safety
+///
+/// The handle must be null or a live handle from this library.
+#[no_mangle]
+pub unsafe extern "C" fn lintparser_warning_count(check: *const LintCheck) -> usize {
+    check.as_ref().map_or(0, |check| check.check.stats().warnings)
+}
+
+/// Writes the start of the span, the severity (```LINTPARSER_ERROR```,
+/// ```LINTPARSER_WARNING```, ```LINTPARSER_NOTE``` or ```LINTPARSER_HELP```),
+/// the path and the message of a problem to the
+/// out-params that aren't null. Returns 0 without writing anything if the
+/// handle is null or the index is out of range, and 1 otherwise.
+///
+/// # Safety
+///
+/// The handle must be null or a live handle from this library, and the
+/// out-params null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn lintparser_problem(check: *const LintCheck, index: usize,
+        line: *mut usize, col: *mut usize, severity: *mut c_int,
+        file: *mut *const c_char, message: *mut *const c_char) -> c_int {
+    let check = match check.as_ref() {
+        Some(check) => check,
+        None => return 0,
+    };
+    let (problem, strings) = match (check.check.problems().get(index), check.strings.get(index)) {
+        (Some(problem), Some(strings)) => (problem, strings),
+        _ => return 0,
+    };
+    if let Some(line) = line.as_mut() {
+        *line = problem.message.start_line;
+    }
+    if let Some(col) = col.as_mut() {
+        *col = problem.message.start_col;
+    }
+    if let Some(severity) = severity.as_mut() {
+        *severity = match problem.severity {
+            Severity::Error => LINTPARSER_ERROR,
+            Severity::Warning => LINTPARSER_WARNING,
+            Severity::Note => LINTPARSER_NOTE,
+            Severity::Help => LINTPARSER_HELP,
+        };
+    }
+    if let Some(file) = file.as_mut() {
+        *file = strings.0.as_ptr();
+    }
+    if let Some(message) = message.as_mut() {
+        *message = strings.1.as_ptr();
+    }
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &[u8] = b"\
+src/lib.rs:2:9: 2:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+src/m\xc3\xa5l.rs:4:5: 4:8 error: unresolved name `f\xc3\xb8\xc3\xb8` [E0425]
+\0";
+
+    #[test]
+    fn problems_are_read_through_the_handle() {
+        unsafe {
+            let check = lintparser_parse(OUTPUT.as_ptr() as *const c_char);
+            assert!(! check.is_null());
+            assert_eq!(lintparser_problem_count(check), 2);
+            assert_eq!(lintparser_error_count(check), 1);
+            assert_eq!(lintparser_warning_count(check), 1);
+
+            let (mut line, mut col, mut severity) = (0, 0, -1);
+            let (mut file, mut message) = (ptr::null(), ptr::null());
+            assert_eq!(lintparser_problem(check, 1, &mut line, &mut col, &mut severity,
+                &mut file, &mut message), 1);
+            assert_eq!((line, col, severity), (4, 5, LINTPARSER_ERROR));
+            assert_eq!(CStr::from_ptr(file).to_str(), Ok("src/mål.rs"));
+            assert_eq!(CStr::from_ptr(message).to_str(), Ok("unresolved name `føø` [E0425]"));
+            // The strings stay valid while other problems are read
+            assert_eq!(lintparser_problem(check, 0, ptr::null_mut(), ptr::null_mut(),
+                &mut severity, ptr::null_mut(), ptr::null_mut()), 1);
+            assert_eq!(severity, LINTPARSER_WARNING);
+            assert_eq!(CStr::from_ptr(message).to_str(), Ok("unresolved name `føø` [E0425]"));
+
+            assert_eq!(lintparser_problem(check, 2, &mut line, ptr::null_mut(), ptr::null_mut(),
+                ptr::null_mut(), ptr::null_mut()), 0);
+            assert_eq!(line, 4);
+            lintparser_check_free(check);
+        }
+    }
+
+    #[test]
+    fn null_and_invalid_arguments_are_handled() {
+        unsafe {
+            assert!(lintparser_parse(ptr::null()).is_null());
+            assert!(lintparser_parse(b"\xff\xfe\0".as_ptr() as *const c_char).is_null());
+            assert!(lintparser_check(ptr::null()).is_null());
+            assert_eq!(lintparser_problem_count(ptr::null()), 0);
+            assert_eq!(lintparser_error_count(ptr::null()), 0);
+            assert_eq!(lintparser_warning_count(ptr::null()), 0);
+            let mut line = 7;
+            assert_eq!(lintparser_problem(ptr::null(), 0, &mut line, ptr::null_mut(),
+                ptr::null_mut(), ptr::null_mut(), ptr::null_mut()), 0);
+            assert_eq!(line, 7);
+            lintparser_check_free(ptr::null_mut());
+
+            let empty = lintparser_parse(b"\0".as_ptr() as *const c_char);
+            assert_eq!(lintparser_problem_count(empty), 0);
+            lintparser_check_free(empty);
+        }
+    }
+}
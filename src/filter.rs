@@ -0,0 +1,179 @@
+//! Keeping the problems of a check by their severity, lint and file, for
+//! consumers that only care about some of them.
+
+use std::path::{Path, PathBuf};
+
+use paths::glob_matches;
+use {Check, ProblemDescription, Severity};
+
+/// Which problems of a check to keep, by ```Check::filtered```. A problem is
+/// kept when it passes every part of the filter; an empty filter keeps all
+/// of them.
+#[derive(Debug, Clone, Default)]
+pub struct ProblemFilter {
+    min_severity: Option<Severity>,
+    allowed_lints: Option<Vec<String>>,
+    denied_lints: Vec<String>,
+    included_paths: Vec<String>,
+    excluded_paths: Vec<String>,
+    root: Option<PathBuf>,
+}
+
+impl ProblemFilter {
+    /// Creates a filter that keeps every problem.
+    pub fn new() -> ProblemFilter {
+        ProblemFilter::default()
+    }
+
+    /// Keeps the problems of the severity and the ones worse than it.
+    pub fn min_severity(mut self, severity: Severity) -> ProblemFilter {
+        self.min_severity = Some(severity);
+        self
+    }
+
+    /// Keeps only the problems of these lints (or of the lints of these
+    /// groups) among the problems of lints. Problems without a lint, like
+    /// compile errors, are kept.
+    pub fn allow_lints<T: AsRef<str>>(mut self, lints: &[T]) -> ProblemFilter {
+        self.allowed_lints.get_or_insert_with(Vec::new)
+            .extend(lints.iter().map(|lint| String::from(lint.as_ref())));
+        self
+    }
+
+    /// Drops the problems of these lints, or of the lints of these groups.
+    pub fn deny_lints<T: AsRef<str>>(mut self, lints: &[T]) -> ProblemFilter {
+        self.denied_lints.extend(lints.iter().map(|lint| String::from(lint.as_ref())));
+        self
+    }
+
+    /// Keeps only the problems in files that match one of the globs, like
+    /// ```src/**```, where ```**``` matches any number of directories.
+    pub fn include_paths<T: AsRef<str>>(mut self, globs: &[T]) -> ProblemFilter {
+        self.included_paths.extend(globs.iter().map(|glob| String::from(glob.as_ref())));
+        self
+    }
+
+    /// Drops the problems in files that match one of the globs.
+    pub fn exclude_paths<T: AsRef<str>>(mut self, globs: &[T]) -> ProblemFilter {
+        self.excluded_paths.extend(globs.iter().map(|glob| String::from(glob.as_ref())));
+        self
+    }
+
+    /// Matches the paths under the directory relative to it, like the
+    /// absolute paths of ```cargo_check```. Without a root, the paths are
+    /// matched as the problems have them.
+    pub fn root<P: Into<PathBuf>>(mut self, dir: P) -> ProblemFilter {
+        self.root = Some(dir.into());
+        self
+    }
+
+    /// Returns whether the filter keeps the problem.
+    pub fn keeps(&self, problem: &ProblemDescription) -> bool {
+        let named = |names: &[String]| [problem.lint(), problem.lint_group()].iter()
+            .any(|lint| lint.is_some_and(|lint| names.iter().any(|name| name == lint)));
+        let path = Path::new(&*problem.filepath);
+        let path = self.root.as_ref()
+            .and_then(|root| path.strip_prefix(root).ok())
+            .unwrap_or(path);
+        let matched = |globs: &[String]| globs.iter().any(|glob| glob_matches(glob, path));
+        self.min_severity.is_none_or(|min| problem.severity >= min)
+            && self.allowed_lints.as_ref()
+                .is_none_or(|allowed| problem.lint().is_none() || named(allowed))
+            && ! named(&self.denied_lints)
+            && (self.included_paths.is_empty() || matched(&self.included_paths))
+            && ! matched(&self.excluded_paths)
+    }
+}
+
+impl Check {
+    /// Returns the check with only the problems that the filter keeps, as
+    /// an error, a warning or perfect by the problems that are left.
+    pub fn filtered(&self, filter: &ProblemFilter) -> Check {
+        Check::from_problems(self.problems().iter()
+            .filter(|problem| filter.keeps(problem))
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parse_output;
+
+    fn check() -> Check {
+        parse_output("\
+src/lib.rs:2:5: 2:6 error: cannot find value `x` in this scope [E0425]
+src/lib.rs:4:9: 4:10 warning: unused variable: `y`, #[warn(unused_variables)] on by default
+src/util/mod.rs:1:1: 1:10 warning: function is never used: `f`, #[warn(dead_code)] on by default
+tests/it.rs:3:5: 3:11 warning: unneeded `return` statement, #[warn(clippy::needless_return)] on by default
+target/debug/build/out/gen.rs:1:1: 1:2 warning: unused import: `a`, #[warn(unused_imports)] on by default
+")
+    }
+
+    fn files(check: &Check) -> Vec<(&str, usize)> {
+        check.problems().iter().map(|problem| (&*problem.filepath, problem.message.start_line))
+            .collect()
+    }
+
+    #[test]
+    fn problems_are_kept_by_severity_and_lint() {
+        let check = check();
+        let errors = check.filtered(&ProblemFilter::new().min_severity(Severity::Error));
+        assert_eq!(files(&errors), [("src/lib.rs", 2)]);
+        assert_eq!(check.filtered(&ProblemFilter::new().min_severity(Severity::Note)).problems(),
+            check.problems());
+
+        // The error has no lint, and stays
+        let allowed = check.filtered(&ProblemFilter::new()
+            .allow_lints(&["unused_variables", "clippy::needless_return"]));
+        assert_eq!(files(&allowed), [("src/lib.rs", 2), ("src/lib.rs", 4), ("tests/it.rs", 3)]);
+        let denied = check.filtered(&ProblemFilter::new().deny_lints(&["dead_code"]));
+        assert_eq!(denied.problems().len(), 4);
+        assert!(denied.problems().iter().all(|problem| problem.lint() != Some("dead_code")));
+
+        // Without the error, the check is a warning, and without the
+        // warnings too, it is perfect
+        let warnings = check.filtered(&ProblemFilter::new().exclude_paths(&["src/lib.rs"]));
+        assert!(matches!(warnings, Check::Warning(ref problems) if problems.len() == 3));
+        let no_lints = ProblemFilter::new().allow_lints::<&str>(&[]);
+        assert_eq!(check.filtered(&no_lints.exclude_paths(&["src/lib.rs"])), Check::Perfect);
+    }
+
+    #[test]
+    fn problems_are_kept_by_the_globs_of_their_paths() {
+        let check = check();
+        let sources = check.filtered(&ProblemFilter::new().include_paths(&["src/**"]));
+        assert_eq!(files(&sources),
+            [("src/lib.rs", 2), ("src/lib.rs", 4), ("src/util/mod.rs", 1)]);
+        let top = check.filtered(&ProblemFilter::new().include_paths(&["src/*.rs"]));
+        assert_eq!(top.problems().len(), 2);
+        let excluded = check.filtered(&ProblemFilter::new()
+            .exclude_paths(&["target/**", "tests/**"]));
+        assert_eq!(excluded.problems(), sources.problems());
+
+        // Absolute paths are matched under the root
+        let absolute = Check::from_problems(check.problems().iter().map(|problem| {
+            let mut problem = problem.clone();
+            problem.filepath = format!("/app/{}", problem.filepath).into();
+            problem
+        }).collect());
+        let filter = ProblemFilter::new().include_paths(&["src/**"]);
+        assert_eq!(absolute.filtered(&filter), Check::Perfect);
+        assert_eq!(absolute.filtered(&filter.root("/app")).problems().len(), 3);
+        assert!(glob_matches("**/gen.rs", Path::new("/app/target/debug/build/out/gen.rs")));
+    }
+
+    #[test]
+    fn filters_combine() {
+        let filter = ProblemFilter::new()
+            .min_severity(Severity::Warning)
+            .allow_lints(&["unused_variables", "dead_code", "unused_imports"])
+            .deny_lints(&["dead_code"])
+            .include_paths(&["src/**", "target/**"])
+            .exclude_paths(&["target/**"]);
+        let filtered = check().filtered(&filter);
+        assert!(matches!(filtered, Check::Error(_)));
+        assert_eq!(files(&filtered), [("src/lib.rs", 2), ("src/lib.rs", 4)]);
+    }
+}
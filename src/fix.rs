@@ -0,0 +1,675 @@
+//! Turning the suggestions of a check into changes of the source files.
+//!
+//! Only machine-applicable suggestions are used. When suggestions of a file
+//! overlap, the one reported first wins and the others are left out, and
+//! identical suggestions (e.g. reported once for each target) are used once.
+//!
+//! Suggestions for the crate root are inserted into the nearest ```lib.rs```
+//! or ```main.rs``` of the directories containing the file of the problem
+//! (or the file itself, if it is one of those or a ```src/bin``` file), after
+//! its shebang, inner doc comments and inner attributes.
+
+use std::io;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use {normalize_path, Applicability, Check, PathCompare, ProblemDescription, Suggestion,
+    SuggestionTarget};
+
+/// The number of unchanged lines shown around the changes of a diff.
+const CONTEXT: usize = 3;
+
+/// A problem turning suggestions into changes.
+#[derive(Debug)]
+pub enum FixError {
+    IoError(io::Error),
+    /// A suggestion spans text that isn't in the file (anymore).
+    InvalidSpan { filepath: String, line: usize },
+}
+
+impl From<io::Error> for FixError {
+    fn from(err: io::Error) -> FixError {
+        FixError::IoError(err)
+    }
+}
+
+/// A replacement of a byte range of a source text.
+#[derive(Debug, Clone, PartialEq)]
+struct Edit<'a> {
+    range: Range<usize>,
+    replacement: &'a str,
+}
+
+/// Returns the byte offset at which attributes are inserted into a crate
+/// root: after a shebang line, and the leading inner doc comments and inner
+/// attributes (with the blank lines and comments between them).
+fn crate_root_insertion(source: &str) -> usize {
+    let mut insertion = 0;
+    let mut offset = 0;
+    let mut lines = source.split_inclusive('\n');
+    // A shebang, unlike an inner attribute, isn't followed by '['
+    if source.starts_with("#!") && ! source[2..].trim_start().starts_with('[') {
+        if let Some(line) = lines.next() {
+            offset += line.len();
+            insertion = offset;
+        }
+    }
+    let mut depth = 0;
+    for line in lines {
+        let trimmed = line.trim();
+        offset += line.len();
+        if depth > 0 || trimmed.starts_with("#![") {
+            // Attributes may span several lines
+            for ch in trimmed.chars() {
+                match ch {
+                    '[' => depth += 1,
+                    ']' => depth -= 1,
+                    _ => {},
+                }
+            }
+            insertion = offset;
+        } else if trimmed.starts_with("//!") {
+            insertion = offset;
+        } else if ! trimmed.is_empty() && ! trimmed.starts_with("//") {
+            break;
+        }
+    }
+    // Keep a file without a final line break intact
+    if insertion == source.len() && ! source.is_empty() && ! source.ends_with('\n') {
+        insertion = source.rfind('\n').map_or(0, |i| i + 1);
+    }
+    insertion
+}
+
+/// Returns the components of the path of a file relative to the root, as
+/// the paths of a diff are. Absolute paths under the root, like those of
+/// ```cargo_check_in```, have the root stripped, and the others start with
+/// an empty component, so that they are joined into absolute paths again.
+fn relative_components(root: &Path, filepath: &str) -> Vec<String> {
+    let components = normalize_path(Path::new(""), filepath);
+    if ! Path::new(filepath).is_absolute() && ! filepath.starts_with(['/', '\\']) {
+        return components;
+    }
+    // The root as given, or as the canonical path that checks have
+    let roots = [Some(root.to_path_buf()), fs::canonicalize(root).ok()];
+    for root in roots.iter().flatten().filter(|root| root.is_absolute()) {
+        let root = normalize_path(Path::new(""), &root.to_string_lossy());
+        if let Some(relative) = components.strip_prefix(&root[..]) {
+            return relative.to_vec();
+        }
+    }
+    Some(String::new()).into_iter().chain(components).collect()
+}
+
+/// Returns the crate root that the attributes of a file belong in.
+fn crate_root_file(root: &Path, filepath: &str) -> String {
+    let components = relative_components(root, filepath);
+    let is_root = match components.last().map(|name| &name[..]) {
+        Some("lib.rs") | Some("main.rs") => true,
+        _ => components.len() >= 2 && components[components.len() - 2] == "bin",
+    };
+    if ! is_root {
+        for dir in (0..components.len()).rev() {
+            for name in &["lib.rs", "main.rs"] {
+                let mut candidate = components[..dir].to_vec();
+                candidate.push(String::from(*name));
+                let candidate = candidate.join("/");
+                if root.join(&candidate).is_file() {
+                    return candidate;
+                }
+            }
+        }
+    }
+    components.join("/")
+}
+
+/// Extends the range of a deletion so that it doesn't leave behind the
+/// separator of a list item or a blank line. A comma right after the range
+/// goes with it, or else the one before it when the item is the last one
+/// before a closing bracket. Then, if only whitespace is left on the line (or
+/// lines), the whole line goes, with its line break.
+fn extend_deletion(source: &str, range: Range<usize>) -> Range<usize> {
+    let is_blank = |ch: char| ch == ' ' || ch == '\t';
+    let (mut start, mut end) = (range.start, range.end);
+    let after = &source[end..];
+    let after_trimmed = after.trim_start_matches(is_blank);
+    let before_trimmed = source[..start].trim_end_matches(char::is_whitespace);
+    if let Some(rest) = after_trimmed.strip_prefix(',') {
+        end = source.len() - rest.trim_start_matches(is_blank).len();
+    } else if before_trimmed.ends_with(',') && after_trimmed.starts_with(['}', ')', ']']) {
+        start = before_trimmed.len() - 1;
+        end = source.len() - after_trimmed.len();
+    }
+
+    let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+    let leading_blank = source[line_start..start].chars().all(is_blank);
+    if leading_blank && end > start && source[..end].ends_with('\n') {
+        // The range ends with its line break already
+        return line_start..end;
+    }
+    let line_end = source[end..].find('\n').map_or(source.len(), |i| end + i);
+    if leading_blank && source[end..line_end].chars().all(is_blank) {
+        if line_end < source.len() {
+            return line_start..line_end + 1;
+        }
+        // The last line of a file has no line break of its own
+        return line_start.saturating_sub(1)..line_end;
+    }
+    start..end
+}
+
+/// Resolves the suggestions for a file into edits that don't overlap,
+/// sorted by their position.
+fn resolve_edits<'a>(source: &str, filepath: &str, suggestions: &[&'a Suggestion])
+        -> Result<Vec<Edit<'a>>, FixError> {
+    let mut edits: Vec<Edit> = Vec::new();
+    for suggestion in suggestions {
+        let range = match suggestion.target {
+            SuggestionTarget::Span if suggestion.replacement.is_empty() => suggestion.span
+                .to_byte_range(source)
+                .map(|range| if range.is_empty() { range } else { extend_deletion(source, range) }),
+            SuggestionTarget::Span => suggestion.span.to_byte_range(source),
+            SuggestionTarget::CrateRoot | SuggestionTarget::FileTop => {
+                let insertion = crate_root_insertion(source);
+                Some(insertion..insertion)
+            },
+        };
+        let range = range.ok_or_else(|| FixError::InvalidSpan {
+            filepath: String::from(filepath),
+            line: suggestion.span.start_line,
+        })?;
+        let edit = Edit { range, replacement: &suggestion.replacement };
+        let overlaps = edits.iter().any(|other| *other == edit
+            || (edit.range.start < other.range.end && other.range.start < edit.range.end));
+        if ! overlaps {
+            edits.push(edit);
+        }
+    }
+    // Insertions at the same position stay in the order they were reported
+    edits.sort_by_key(|edit| (edit.range.start, edit.range.end));
+    Ok(edits)
+}
+
+/// Applies sorted, non-overlapping edits to the text.
+fn apply_edits(text: &str, offset: usize, edits: &[&Edit]) -> String {
+    let mut out = String::new();
+    let mut pos = 0;
+    for edit in edits {
+        out.push_str(&text[pos..edit.range.start - offset]);
+        out.push_str(edit.replacement);
+        pos = edit.range.end - offset;
+    }
+    out.push_str(&text[pos..]);
+    out
+}
+
+/// A replacement of consecutive lines of a file.
+struct Change<'a> {
+    /// The index of the first replaced line.
+    start: usize,
+    old: Vec<&'a str>,
+    new: Vec<String>,
+}
+
+/// Finds the lines changed by the edits, with the lines ending in their
+/// line breaks (except for a last line without one).
+fn line_changes<'a>(source: &'a str, edits: &[Edit]) -> Vec<Change<'a>> {
+    let lines: Vec<&str> = source.split_inclusive('\n').collect();
+    let mut starts = Vec::with_capacity(lines.len() + 1);
+    let mut offset = 0;
+    for line in &lines {
+        starts.push(offset);
+        offset += line.len();
+    }
+    starts.push(offset);
+    let line_of = |pos: usize| starts[..lines.len()].partition_point(|&start| start <= pos)
+        .saturating_sub(1);
+
+    // The ranges of whole lines that the edits touch, merged when they overlap
+    let mut regions: Vec<(Range<usize>, Vec<&Edit>)> = Vec::new();
+    for edit in edits {
+        let first = line_of(edit.range.start);
+        let last = if edit.range.end > edit.range.start { line_of(edit.range.end - 1) } else { first };
+        let end = (last + 1).min(lines.len());
+        match regions.last_mut() {
+            Some(&mut (ref mut lines, ref mut edits)) if first < lines.end => {
+                lines.end = lines.end.max(end);
+                edits.push(edit);
+            },
+            _ => regions.push((first..end, vec![edit])),
+        }
+    }
+
+    let mut changes: Vec<Change> = Vec::new();
+    for (range, edits) in regions {
+        let text = &source[starts[range.start]..starts[range.end]];
+        let new_text = apply_edits(text, starts[range.start], &edits);
+        let old: Vec<&str> = lines[range.clone()].to_vec();
+        let new: Vec<String> = new_text.split_inclusive('\n').map(String::from).collect();
+        let prefix = old.iter().zip(&new).take_while(|&(old, new)| old == new).count();
+        let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev())
+            .take_while(|&(old, new)| old == new)
+            .count();
+        if old.len() == prefix + suffix && new.len() == prefix + suffix {
+            continue;
+        }
+        let change = Change {
+            start: range.start + prefix,
+            old: old[prefix..old.len() - suffix].to_vec(),
+            new: new[prefix..new.len() - suffix].to_vec(),
+        };
+        // Changes of consecutive lines are shown as one
+        match changes.last_mut() {
+            Some(last) if last.start + last.old.len() == change.start => {
+                last.old.extend(change.old);
+                last.new.extend(change.new);
+            },
+            _ => changes.push(change),
+        }
+    }
+    changes
+}
+
+/// Writes a line of a diff, marking a missing line break at the end.
+fn push_line(out: &mut String, prefix: char, line: &str) {
+    out.push(prefix);
+    out.push_str(line);
+    if ! line.ends_with('\n') {
+        out.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+/// Formats the range of a hunk header, from a 0-based start line.
+fn hunk_range(start: usize, len: usize) -> String {
+    if len == 0 {
+        format!("{},0", start)
+    } else {
+        format!("{},{}", start + 1, len)
+    }
+}
+
+/// Writes the unified diff of the edits of a file.
+fn file_diff(path: &str, source: &str, edits: &[Edit]) -> String {
+    let changes = line_changes(source, edits);
+    if changes.is_empty() {
+        return String::new();
+    }
+    let line_count = source.split_inclusive('\n').count();
+    let lines: Vec<&str> = source.split_inclusive('\n').collect();
+    let mut out = format!("diff --git a/{0} b/{0}\n--- a/{0}\n+++ b/{0}\n", path);
+
+    // Changes close enough for their context to touch share a hunk
+    let mut hunks: Vec<Vec<&Change>> = Vec::new();
+    for change in &changes {
+        match hunks.last_mut() {
+            Some(hunk) if change.start - (hunk[hunk.len() - 1].start
+                    + hunk[hunk.len() - 1].old.len()) <= 2 * CONTEXT => hunk.push(change),
+            _ => hunks.push(vec![change]),
+        }
+    }
+    let mut delta: isize = 0;
+    for hunk in hunks {
+        let last = hunk[hunk.len() - 1];
+        let old_start = hunk[0].start.saturating_sub(CONTEXT);
+        let old_end = (last.start + last.old.len() + CONTEXT).min(line_count);
+        let hunk_delta: isize = hunk.iter()
+            .map(|change| change.new.len() as isize - change.old.len() as isize)
+            .sum();
+        let old_len = old_end - old_start;
+        let new_start = (old_start as isize + delta) as usize;
+        let new_len = (old_len as isize + hunk_delta) as usize;
+        out.push_str(&format!("@@ -{} +{} @@\n", hunk_range(old_start, old_len),
+            hunk_range(new_start, new_len)));
+        let mut pos = old_start;
+        for change in hunk {
+            for line in &lines[pos..change.start] {
+                push_line(&mut out, ' ', line);
+            }
+            for line in &change.old {
+                push_line(&mut out, '-', line);
+            }
+            for line in &change.new {
+                push_line(&mut out, '+', line);
+            }
+            pos = change.start + change.old.len();
+        }
+        for line in &lines[pos..old_end] {
+            push_line(&mut out, ' ', line);
+        }
+        delta += hunk_delta;
+    }
+    out
+}
+
+/// Returns the lines of the source that the machine-applicable suggestions
+/// of the problem replace, as their 1-based first and last line and their
+/// new text, if they change a single run of existing lines of its file.
+pub fn suggested_lines(source: &str, problem: &ProblemDescription)
+        -> Option<(usize, usize, String)> {
+    let suggestions: Vec<&Suggestion> = problem.suggestions.iter()
+        .filter(|suggestion| suggestion.applicability == Applicability::MachineApplicable
+            && suggestion.target == SuggestionTarget::Span)
+        .collect();
+    if suggestions.is_empty() {
+        return None;
+    }
+    let edits = resolve_edits(source, &problem.filepath, &suggestions).ok()?;
+    let changes = line_changes(source, &edits);
+    match changes[..] {
+        [ref change] if ! change.old.is_empty() => {
+            let new: String = change.new.concat();
+            let new = new.strip_suffix('\n').unwrap_or(&new);
+            Some((change.start + 1, change.start + change.old.len(), String::from(new)))
+        },
+        _ => None,
+    }
+}
+
+impl Check {
+    /// Returns a unified diff (as applied by ```git apply```) of the files
+    /// under ```root``` with every machine-applicable suggestion applied,
+    /// without changing the files.
+    pub fn suggestions_to_diff<P: AsRef<Path>>(&self, root: P) -> Result<String, FixError> {
+        let root = root.as_ref();
+        let compare = PathCompare::default();
+        // The suggestions by the file they change, in the order first seen
+        let mut files: Vec<(String, Vec<&Suggestion>)> = Vec::new();
+        for problem in self.problems() {
+            let suggestions = problem.suggestions.iter()
+                .filter(|suggestion| suggestion.applicability == Applicability::MachineApplicable);
+            for suggestion in suggestions {
+                let path = match suggestion.target {
+                    SuggestionTarget::Span | SuggestionTarget::FileTop => {
+                        relative_components(root, &problem.filepath).join("/")
+                    },
+                    SuggestionTarget::CrateRoot => crate_root_file(root, &problem.filepath),
+                };
+                match files.iter().position(|(file, _)| compare.key(file) == compare.key(&path)) {
+                    Some(index) => files[index].1.push(suggestion),
+                    None => files.push((path, vec![suggestion])),
+                }
+            }
+        }
+
+        let mut diff = String::new();
+        for (path, suggestions) in files {
+            let source = fs::read_to_string(root.join(&path))?;
+            let edits = resolve_edits(&source, &path, &suggestions)?;
+            diff.push_str(&file_diff(&path, &source, &edits));
+        }
+        Ok(diff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use {parse_output, Fixability, Note};
+
+    const SOURCE: &str = "\
+fn main() {
+    let x = 1;
+    let y = 2;
+    println!(\"{}\", x);
+}
+
+fn unused() {
+    let z = 3;
+    let w = 4;
+    let v = 5;
+    return;
+}";
+
+    fn suggestion(start: (usize, usize), end: (usize, usize), replacement: &str) -> Suggestion {
+        Suggestion {
+            span: Note::new(start.0, start.1, end.0, end.1, "help"),
+            replacement: String::from(replacement),
+            applicability: Applicability::MachineApplicable,
+            target: SuggestionTarget::Span,
+        }
+    }
+
+    fn diff(suggestions: Vec<Suggestion>) -> String {
+        let edits = {
+            let suggestions: Vec<&Suggestion> = suggestions.iter().collect();
+            resolve_edits(SOURCE, "src/main.rs", &suggestions).unwrap()
+        };
+        file_diff("src/main.rs", SOURCE, &edits)
+    }
+
+    #[test]
+    fn nearby_edits_share_a_hunk() {
+        assert_eq!(diff(vec![
+            suggestion((2, 9), (2, 10), "_x"),
+            suggestion((3, 9), (3, 10), "_y"),
+            suggestion((1, 1), (1, 1), "use std::io;\n\n"),
+        ]), "\
+diff --git a/src/main.rs b/src/main.rs
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,6 +1,8 @@
++use std::io;
++
+ fn main() {
+-    let x = 1;
+-    let y = 2;
++    let _x = 1;
++    let _y = 2;
+     println!(\"{}\", x);
+ }
+\x20
+");
+    }
+
+    #[test]
+    fn distant_edits_get_their_own_hunks() {
+        assert_eq!(diff(vec![
+            suggestion((2, 1), (3, 1), ""),
+            suggestion((11, 1), (12, 1), ""),
+            suggestion((12, 2), (12, 2), "\n"),
+        ]), "\
+diff --git a/src/main.rs b/src/main.rs
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,5 +1,4 @@
+ fn main() {
+-    let x = 1;
+     let y = 2;
+     println!(\"{}\", x);
+ }
+@@ -8,5 +7,4 @@
+     let z = 3;
+     let w = 4;
+     let v = 5;
+-    return;
+-}
+\\ No newline at end of file
++}
+");
+    }
+
+    #[test]
+    fn overlapping_suggestions_keep_the_first() {
+        assert_eq!(diff(vec![
+            suggestion((8, 9), (8, 10), "_z"),
+            suggestion((8, 5), (8, 15), "let z = 4;"),
+            suggestion((8, 9), (8, 10), "_z"),
+            suggestion((8, 10), (8, 10), ": u8"),
+        ]), "\
+diff --git a/src/main.rs b/src/main.rs
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -5,7 +5,7 @@
+ }
+\x20
+ fn unused() {
+-    let z = 3;
++    let _z: u8 = 3;
+     let w = 4;
+     let v = 5;
+     return;
+");
+    }
+
+    #[test]
+    fn suggestions_to_diff_reads_the_files_and_skips_unsure_suggestions() {
+        let root = env::temp_dir().join(format!("lintparser-fix-{}", ::std::process::id()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/main.rs"), SOURCE).unwrap();
+        let mut problem = ProblemDescription::new(String::from("./src/main.rs"), 2, 9, 2, 10,
+            String::from("unused variable: `x`"), vec![], vec![]);
+        problem.suggestions.push(suggestion((2, 9), (2, 10), "_x"));
+        let mut unsure = suggestion((3, 9), (3, 10), "_y");
+        unsure.applicability = Applicability::MaybeIncorrect;
+        problem.suggestions.push(unsure);
+        let mut stale = problem.clone();
+        stale.suggestions = vec![suggestion((40, 1), (40, 2), "")];
+
+        let check = Check::Warning(vec![problem]);
+        let diff = check.suggestions_to_diff(&root);
+        let stale = Check::Warning(vec![stale]).suggestions_to_diff(&root);
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(diff.unwrap(), "\
+diff --git a/src/main.rs b/src/main.rs
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,5 +1,5 @@
+ fn main() {
+-    let x = 1;
++    let _x = 1;
+     let y = 2;
+     println!(\"{}\", x);
+ }
+");
+        match stale {
+            Err(FixError::InvalidSpan { line: 40, .. }) => {},
+            other => panic!("Expected an invalid span, got {:?}", other),
+        }
+        assert_eq!(Check::Perfect.suggestions_to_diff("/nonexistent").unwrap(), "");
+    }
+
+    #[test]
+    fn deletions_take_their_separators_and_blank_lines() {
+        let source = "\
+use std::io;
+use std::{fs, mem, ptr};
+use std::fmt::{self, Write};
+use std::{
+    env,
+    process,
+};
+fn main() {}";
+        let delete = |start: (usize, usize), end: (usize, usize)| {
+            let deletion = suggestion(start, end, "");
+            let edits = resolve_edits(source, "src/main.rs", &[&deletion]).unwrap();
+            apply_edits(source, 0, &edits.iter().collect::<Vec<_>>())
+        };
+        // A lone use, with and without its line break
+        assert_eq!(delete((1, 1), (1, 13)), &source[13..]);
+        assert_eq!(delete((1, 1), (2, 1)), &source[13..]);
+        // One item of a group, the last one before the brace, and one per line
+        assert!(delete((2, 11), (2, 13)).starts_with("use std::io;\nuse std::{mem, ptr};\n"));
+        assert!(delete((3, 22), (3, 27)).contains("\nuse std::fmt::{self};\n"));
+        assert!(delete((5, 5), (5, 8)).contains("{\n    process,\n};"));
+        // The last line of a file has no line break to take
+        assert_eq!(delete((8, 1), (8, 13)), &source[..source.len() - 13]);
+    }
+
+    #[test]
+    fn unused_import_helps_suggest_removals() {
+        let check = parse_output("\
+src/main.rs:2:15: 2:18 warning: unused import: `mem`, #[warn(unused_imports)] on by default
+src/main.rs:2:15: 2:18 help: remove the unused import
+src/main.rs:1:5: 1:12 warning: unused import: `std::io`, #[warn(unused_imports)] on by default
+src/main.rs:1:1: 1:13 help: remove the whole `use` item
+");
+        let suggestions: Vec<&Suggestion> = check.problems().iter()
+            .flat_map(|problem| &problem.suggestions)
+            .collect();
+        assert_eq!(suggestions.len(), 2);
+        assert!(suggestions.iter().all(|s| s.replacement.is_empty()
+            && s.applicability == Applicability::MaybeIncorrect));
+        assert!(check.problems().iter()
+            .all(|problem| problem.fixability() == Fixability::Assisted));
+        let source = "use std::io;\nuse std::{fs, mem, ptr};\nfn main() {}\n";
+        let edits = resolve_edits(source, "src/main.rs", &suggestions).unwrap();
+        assert_eq!(file_diff("src/main.rs", source, &edits), "\
+diff --git a/src/main.rs b/src/main.rs
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,3 +1,2 @@
+-use std::io;
+-use std::{fs, mem, ptr};
++use std::{fs, ptr};
+ fn main() {}
+");
+    }
+
+    #[test]
+    fn crate_attributes_go_after_shebangs_docs_and_attributes() {
+        let insert = |source: &str| {
+            let at = crate_root_insertion(source);
+            format!("{}#![feature(x)]\n{}", &source[..at], &source[at..])
+        };
+        assert_eq!(insert("fn main() {}\n"), "#![feature(x)]\nfn main() {}\n");
+        assert_eq!(insert("//! The crate.\n//!\n//! More docs.\n\nuse std::io;\n"),
+            "//! The crate.\n//!\n//! More docs.\n#![feature(x)]\n\nuse std::io;\n");
+        assert_eq!(insert("#!/usr/bin/env run-cargo-script\nfn main() {}\n"),
+            "#!/usr/bin/env run-cargo-script\n#![feature(x)]\nfn main() {}\n");
+        assert_eq!(insert("// License\n//! Docs\n#![cfg_attr(\n    test,\n    allow(dead_code)\n)]\nmod a;"),
+            "// License\n//! Docs\n#![cfg_attr(\n    test,\n    allow(dead_code)\n)]\n#![feature(x)]\nmod a;");
+        assert_eq!(insert("#![no_std]"), "#![feature(x)]\n#![no_std]");
+        assert_eq!(insert(""), "#![feature(x)]\n");
+    }
+
+    #[test]
+    fn crate_attributes_go_into_the_crate_root() {
+        let root = env::temp_dir().join(format!("lintparser-fix-root-{}", ::std::process::id()));
+        fs::create_dir_all(root.join("src/net")).unwrap();
+        fs::create_dir_all(root.join("src/bin")).unwrap();
+        fs::write(root.join("src/lib.rs"), "//! Docs\nmod net;\n").unwrap();
+        fs::write(root.join("src/net/tcp.rs"), "//! TCP\n#![allow(dead_code)]\n\nfn f() {}\n")
+            .unwrap();
+        let found = |path: &str| crate_root_file(&root, path);
+        let (nested, bin, lib) = (found("./src/net/tcp.rs"), found("src/bin/tool.rs"),
+            found("src/lib.rs"));
+        let absolute = found(&root.join("src/net/tcp.rs").to_string_lossy());
+
+        // With the absolute path of a check
+        let filepath = fs::canonicalize(&root).unwrap().join("src/net/tcp.rs");
+        let mut problem = ProblemDescription::new(filepath.to_string_lossy().into_owned(), 4, 13, 4, 20,
+            String::from("box expression syntax is experimental"), vec![], vec![]);
+        let mut attribute = suggestion((4, 13), (4, 13), "#![feature(box_syntax)]\n");
+        attribute.target = SuggestionTarget::CrateRoot;
+        problem.suggestions.push(attribute);
+        // Imports go into the file of the problem instead
+        let mut import = suggestion((1, 1), (1, 1), "use std::io::Read;\n");
+        import.target = SuggestionTarget::FileTop;
+        problem.suggestions.push(import);
+        let diff = Check::Error(vec![problem]).suggestions_to_diff(&root);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!((&nested[..], &bin[..], &lib[..], &absolute[..]),
+            ("src/lib.rs", "src/bin/tool.rs", "src/lib.rs", "src/lib.rs"));
+        assert_eq!(diff.unwrap(), "\
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,2 +1,3 @@
+ //! Docs
++#![feature(box_syntax)]
+ mod net;
+diff --git a/src/net/tcp.rs b/src/net/tcp.rs
+--- a/src/net/tcp.rs
++++ b/src/net/tcp.rs
+@@ -1,4 +1,5 @@
+ //! TCP
+ #![allow(dead_code)]
++use std::io::Read;
+ 
+ fn f() {}
+");
+    }
+}
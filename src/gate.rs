@@ -0,0 +1,177 @@
+//! A lint gate for the test suite of a project: a test that fails when the
+//! check of the project has problems that its baseline doesn't.
+//!
+//! ```ignore
+//! #[test]
+//! fn no_new_lints() {
+//!     lintparser::assert_no_new_problems!(baseline = "lint-baseline.json");
+//! }
+//! ```
+//!
+//! Running the tests with ```LINTPARSER_UPDATE_BASELINE=1``` writes the
+//! problems of the check to the baseline instead.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use publish::{parse_published, published_text};
+use {Check, CheckError, CheckReport, Checker, ProblemDescription, SanitizeRules};
+
+/// The variable that makes ```assert_no_new_problems``` update the
+/// baseline rather than compare with it.
+pub const UPDATE_BASELINE_VAR: &str = "LINTPARSER_UPDATE_BASELINE";
+
+/// Returns the problems that aren't in the baseline, by their fingerprints.
+/// Of identical problems, the ones past the number in the baseline are new.
+pub fn new_problems<'a, I>(problems: I, baseline: &Check) -> Vec<&'a ProblemDescription>
+        where I: IntoIterator<Item = &'a ProblemDescription> {
+    let mut known: BTreeMap<String, usize> = BTreeMap::new();
+    for problem in baseline.problems() {
+        *known.entry(problem.fingerprint()).or_insert(0) += 1;
+    }
+    let mut new = Vec::new();
+    for problem in problems {
+        match known.get_mut(&problem.fingerprint()) {
+            Some(count) if *count > 0 => *count -= 1,
+            _ => new.push(problem),
+        }
+    }
+    new
+}
+
+/// Returns the target directory to check the project in, when the tests
+/// that run the check are of the project itself: as ```cargo test``` holds
+/// the lock of its target directory, a check there would wait forever.
+fn separate_target_dir(dir: &Path) -> Option<PathBuf> {
+    env::var_os("CARGO")?;
+    let package = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR")?);
+    if fs::canonicalize(package).ok()? != fs::canonicalize(dir).ok()? {
+        return None;
+    }
+    // The test binary is at target/<profile>/deps/<name>
+    let target = env::var_os("CARGO_TARGET_DIR").map(PathBuf::from).or_else(|| {
+        env::current_exe().ok()?.ancestors().nth(3).map(Path::to_path_buf)
+    });
+    Some(target.unwrap_or_else(|| dir.join("target")).join("lintparser-gate"))
+}
+
+/// Runs the check of the project, with its paths redacted as in the
+/// baseline.
+fn check_project(dir: &Path) -> Result<Check, CheckError> {
+    let mut checker = Checker::new().dir(dir);
+    if let Some(target_dir) = separate_target_dir(dir) {
+        checker = checker.target_dir(target_dir);
+    }
+    let report = checker.run()?;
+    Ok(report.check.sanitized(&SanitizeRules::for_report(&report)))
+}
+
+/// Reads the baseline, which is empty if the file doesn't exist yet.
+fn load_baseline(path: &Path) -> io::Result<Check> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(parse_published(&text)?.check),
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(Check::Perfect),
+        Err(err) => Err(err),
+    }
+}
+
+/// Returns the failure message for the problems of the check that aren't
+/// in the baseline at the path, or ```None``` if there are none.
+fn compare(check: &Check, baseline: &Path) -> io::Result<Option<String>> {
+    let known = load_baseline(baseline)?;
+    let new = new_problems(check.problems(), &known);
+    if new.is_empty() {
+        return Ok(None);
+    }
+    let mut message = format!("{} {} not in the baseline {}:\n", new.len(),
+        if new.len() == 1 { "problem is" } else { "problems are" }, baseline.display());
+    for problem in new {
+        message.push_str(&format!("\n{}\n", problem));
+    }
+    message.push_str(&format!("\nIf they are expected, update the baseline with \
+        {}=1 cargo test", UPDATE_BASELINE_VAR));
+    Ok(Some(message))
+}
+
+/// Writes the check to the baseline at the path.
+fn write_baseline(check: Check, baseline: &Path) -> io::Result<()> {
+    fs::write(baseline, published_text(&CheckReport::new(check), None))
+}
+
+/// Checks the project in the directory and writes its problems to the
+/// baseline (relative to the project), replacing it.
+pub fn update_baseline<P: AsRef<Path>, B: AsRef<Path>>(dir: P, baseline: B)
+        -> Result<(), CheckError> {
+    let dir = dir.as_ref();
+    let check = check_project(dir)?;
+    Ok(write_baseline(check, &dir.join(baseline))?)
+}
+
+/// Checks the project in the directory and panics with the problems that
+/// aren't in the baseline (relative to the project), if there are any. A
+/// baseline that doesn't exist is empty.
+///
+/// With ```LINTPARSER_UPDATE_BASELINE``` set, the baseline is updated with
+/// the problems instead, as with ```update_baseline```.
+pub fn assert_no_new_problems<P: AsRef<Path>, B: AsRef<Path>>(dir: P, baseline: B) {
+    let dir = dir.as_ref();
+    let baseline = dir.join(baseline);
+    let check = check_project(dir)
+        .unwrap_or_else(|err| panic!("the project couldn't be checked: {}", err));
+    if env::var_os(UPDATE_BASELINE_VAR).is_some_and(|value| value != "0") {
+        write_baseline(check, &baseline).unwrap_or_else(|err|
+            panic!("the baseline {} couldn't be written: {}", baseline.display(), err));
+        return;
+    }
+    let failure = compare(&check, &baseline).unwrap_or_else(|err|
+        panic!("the baseline {} couldn't be read: {}", baseline.display(), err));
+    if let Some(message) = failure {
+        panic!("{}", message);
+    }
+}
+
+/// Checks the package of the calling crate and panics with the problems
+/// that aren't in the baseline, with ```assert_no_new_problems```. The
+/// baseline is relative to the manifest of the package.
+#[macro_export]
+macro_rules! assert_no_new_problems {
+    (baseline = $baseline:expr) => {
+        $crate::assert_no_new_problems(env!("CARGO_MANIFEST_DIR"), $baseline)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+    use parse_output;
+
+    #[test]
+    fn only_problems_missing_from_the_baseline_fail() {
+        let path = env::temp_dir().join(format!("lintparser-gate-{}.json", process::id()));
+        let _ = fs::remove_file(&path);
+        let old = parse_output("\
+src/lib.rs:2:9: 2:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+");
+        assert!(compare(&Check::Perfect, &path).unwrap().is_none());
+        assert!(compare(&old, &path).unwrap().is_some());
+        write_baseline(old, &path).unwrap();
+
+        // Moved problems are known, but another of the same kind is new
+        let new = parse_output("\
+src/lib.rs:7:9: 7:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+src/lib.rs:8:9: 8:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+");
+        let failure = compare(&new, &path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(failure.unwrap().unwrap(), format!("1 problem is not in the baseline {}:
+
+src/lib.rs:8:9: warning: unused variable: `x`, #[warn(unused_variables)] on by default
+
+If they are expected, update the baseline with LINTPARSER_UPDATE_BASELINE=1 cargo test",
+            path.display()));
+    }
+}
@@ -0,0 +1,222 @@
+//! Reviews of pull requests on GitHub, with an inline comment for each
+//! problem that a change introduces.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use fix::suggested_lines;
+use gate::new_problems;
+use json::Value;
+use {Check, ProblemDescription, SanitizeRules};
+
+/// The number of inline comments of a review by default. The problems past
+/// it are listed in the body of the review instead.
+pub const MAX_REVIEW_COMMENTS: usize = 50;
+
+/// The problems that a change introduces and fixes, compared by their
+/// fingerprints (see ```Check::diff```).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckDiff<'a> {
+    /// The problems of the check that aren't in the baseline.
+    pub introduced: Vec<&'a ProblemDescription>,
+    /// The problems of the baseline that aren't in the check.
+    pub fixed: Vec<&'a ProblemDescription>,
+}
+
+/// Options for writing a review.
+#[derive(Debug, Clone)]
+pub struct ReviewOptions {
+    /// The number of problems that get an inline comment.
+    pub max_comments: usize,
+    /// The root of the repository, which the paths of the comments are
+    /// relative to. The suggestions are read from the files under it, and
+    /// without it no suggestions are made.
+    pub root: Option<PathBuf>,
+    /// Redact the problems with the rules before writing them.
+    pub sanitize: Option<SanitizeRules>,
+}
+
+impl Default for ReviewOptions {
+    fn default() -> ReviewOptions {
+        ReviewOptions { max_comments: MAX_REVIEW_COMMENTS, root: None, sanitize: None }
+    }
+}
+
+/// Returns the fence for a block of the text: one more backtick than the
+/// longest run of them in the text, and at least three.
+fn fence(text: &str) -> String {
+    let longest = text.split(|ch| ch != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat((longest + 1).max(3))
+}
+
+/// Returns the path of the problem relative to the root, if it is under it.
+fn relative_path<'a>(problem: &'a ProblemDescription, root: Option<&Path>) -> &'a str {
+    root.and_then(|root| Path::new(&*problem.filepath).strip_prefix(root).ok())
+        .and_then(Path::to_str)
+        .unwrap_or(&problem.filepath)
+}
+
+/// Returns the Markdown body of the comment of a problem.
+fn comment_body(problem: &ProblemDescription, suggestion: Option<&str>) -> String {
+    let mut body = format!("**{}**: {}", problem.severity.name(), problem.message.message);
+    let children = problem.help.iter().map(|note| ("help", note))
+        .chain(problem.notes.iter().map(|note| ("note", note)));
+    for (kind, note) in children {
+        body.push_str(&format!("\n- {}: {}", kind, note.message));
+    }
+    if let Some(lines) = suggestion {
+        let fence = fence(lines);
+        body.push_str(&format!("\n\n{}suggestion\n{}\n{}", fence, lines, fence));
+    }
+    body
+}
+
+/// Returns the text with the rules of the options applied, if any.
+fn sanitize(text: &str, options: &ReviewOptions) -> String {
+    match options.sanitize {
+        Some(ref rules) => rules.apply(text),
+        None => String::from(text),
+    }
+}
+
+/// Returns the inline comment of a problem on the lines it changes, if an
+/// applicable suggestion can be made, or else on the lines of its span.
+///
+/// The path and the lines are found with the problem as it was reported, and
+/// the text is written from the sanitized problem.
+fn comment(problem: &ProblemDescription, sanitized: &ProblemDescription,
+        options: &ReviewOptions) -> Value {
+    let root = options.root.as_deref();
+    let path = relative_path(problem, root);
+    let suggestion = root.filter(|_| ! sanitized.suggestions.is_empty())
+        .and_then(|root| fs::read_to_string(root.join(path)).ok())
+        .and_then(|source| suggested_lines(&source, problem));
+    let (start, end, lines) = match suggestion {
+        Some((start, end, lines)) => (start, end, Some(sanitize(&lines, options))),
+        None => (problem.message.start_line, problem.message.end_line, None),
+    };
+    let mut members = vec![
+        ("path", Value::from(sanitize(path, options))),
+        ("line", Value::from(end)),
+        ("side", Value::from("RIGHT")),
+    ];
+    if start < end {
+        members.push(("start_line", Value::from(start)));
+        members.push(("start_side", Value::from("RIGHT")));
+    }
+    members.push(("body", Value::from(comment_body(sanitized, lines.as_deref()))));
+    Value::object(members)
+}
+
+impl<'a> CheckDiff<'a> {
+    /// Returns the JSON body of a request to create a review of the commit,
+    /// with a comment for each introduced problem (see
+    /// ```to_github_review_with```).
+    pub fn to_github_review(&self, commit_sha: &str) -> String {
+        self.to_github_review_with(commit_sha, &ReviewOptions::default())
+    }
+
+    /// Returns the JSON body of a request to create a review of the commit,
+    /// with an inline comment for each introduced problem, up to the number
+    /// of the options. The others are listed in the body of the review. The
+    /// lines of the comments must be in the diff of the pull request.
+    ///
+    /// A problem whose machine-applicable suggestions change a single run of
+    /// lines is commented on those lines, with a suggestion of their new
+    /// text.
+    pub fn to_github_review_with(&self, commit_sha: &str, options: &ReviewOptions) -> String {
+        let introduced = Check::from_problems(self.introduced.iter()
+            .map(|&problem| problem.clone())
+            .collect());
+        let sanitized = introduced.sanitized(options.sanitize.as_ref()
+            .unwrap_or(&SanitizeRules::new()));
+        let problems: Vec<_> = introduced.problems().iter()
+            .zip(sanitized.problems())
+            .collect();
+        let count = |count: usize, what: &str| {
+            format!("{} {}{}", count, what, if count == 1 { "" } else { "s" })
+        };
+        let mut body = format!("lintparser: {}, {} fixed", count(problems.len(), "new problem"),
+            self.fixed.len());
+        let (commented, listed) = problems.split_at(problems.len().min(options.max_comments));
+        if ! listed.is_empty() {
+            body.push_str(&format!("\n\n{} without a comment:\n",
+                count(listed.len(), "more problem")));
+            for &(problem, sanitized) in listed {
+                body.push_str(&format!("\n- `{}:{}`: {}: {}",
+                    sanitize(relative_path(problem, options.root.as_deref()), options),
+                    problem.message.start_line, problem.severity.name(),
+                    sanitized.message.message));
+            }
+        }
+        Value::object(vec![
+            ("commit_id", Value::from(commit_sha)),
+            ("event", Value::from("COMMENT")),
+            ("body", Value::from(body)),
+            ("comments", Value::from(commented.iter()
+                .map(|&(problem, sanitized)| comment(problem, sanitized, options))
+                .collect::<Vec<_>>())),
+        ]).to_string()
+    }
+}
+
+impl Check {
+    /// Returns the problems that the check has and the baseline (e.g. the
+    /// check of the base of a pull request) doesn't, and the other way
+    /// around, by their fingerprints, so that moved code isn't new.
+    pub fn diff<'a>(&'a self, baseline: &'a Check) -> CheckDiff<'a> {
+        CheckDiff {
+            introduced: new_problems(self.problems(), baseline),
+            fixed: new_problems(baseline.problems(), self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+    use {parse_json_messages, parse_output};
+
+    #[test]
+    fn introduced_problems_become_comments_with_suggestions() {
+        let root = env::temp_dir().join(format!("lintparser-github-{}", process::id()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {\n    let unused = 5;\n}\n").unwrap();
+        // The suggestion of the warning of the JSON messages, in the file
+        let base = parse_output("\
+src/lib.rs:6:4: 6:10 warning: function is never used: `helper`, #[warn(dead_code)] on by default
+");
+        let mut head = parse_json_messages(r#"{"reason":"compiler-message","package_id":"app 0.1.0 (path+file:///app)","target":{"kind":["bin"],"name":"app"},"message":{"rendered":"...","$message_type":"diagnostic","children":[{"children":[],"code":null,"level":"help","message":"if this is intentional, prefix it with an underscore","rendered":null,"spans":[{"byte_end":26,"byte_start":20,"column_end":15,"column_start":9,"expansion":null,"file_name":"src/main.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":"_unused","suggestion_applicability":"MachineApplicable","text":[]}]}],"level":"warning","message":"unused variable: `unused`","spans":[{"byte_end":26,"byte_start":20,"column_end":15,"column_start":9,"expansion":null,"file_name":"src/main.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}],"code":{"code":"unused_variables","explanation":null}}}"#)
+            .problems().to_vec();
+        head[0].filepath = root.join("src/main.rs").to_string_lossy().into();
+        head.extend(parse_output("\
+src/lib.rs:9:4: 12:5 error: mismatched types: expected `u32`, found `()` [E0308]
+src/lib.rs:20:9: 20:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+").problems().iter().cloned());
+        let head = Check::from_problems(head);
+        let diff = head.diff(&base);
+        assert_eq!((diff.introduced.len(), diff.fixed.len()), (3, 1));
+
+        let options = ReviewOptions { max_comments: 2, root: Some(root.clone()), sanitize: None };
+        let review = Value::parse(&diff.to_github_review_with("1a2b3c", &options)).unwrap();
+        // The paths and suggestions are found before the problems are sanitized
+        let sanitize = SanitizeRules::new().replace_prefix(&root, "$WORKSPACE");
+        let options = ReviewOptions { sanitize: Some(sanitize), ..options };
+        let sanitized = Value::parse(&diff.to_github_review_with("1a2b3c", &options)).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(sanitized.get("comments"), review.get("comments"));
+        assert_eq!(review.get("commit_id").and_then(Value::as_str), Some("1a2b3c"));
+        assert_eq!(review.get("body").and_then(Value::as_str), Some("\
+lintparser: 3 new problems, 1 fixed
+
+1 more problem without a comment:
+
+- `src/lib.rs:20`: warning: unused variable: `x`, #[warn(unused_variables)] on by default"));
+        let comments = review.get("comments").and_then(Value::as_array).unwrap();
+        assert_eq!(comments[0].to_string(), r#"{"path":"src/main.rs","line":2,"side":"RIGHT","body":"**warning**: unused variable: `unused`\n- help: if this is intentional, prefix it with an underscore\n\n```suggestion\n    let _unused = 5;\n```"}"#);
+        assert_eq!(comments[1].to_string(), r#"{"path":"src/lib.rs","line":12,"side":"RIGHT","start_line":9,"start_side":"RIGHT","body":"**error**: mismatched types: expected `u32`, found `()` [E0308]"}"#);
+        assert_eq!(fence("a ```` b"), "`````");
+    }
+}
@@ -0,0 +1,271 @@
+//! Grouping and deduplication of problems by file or module.
+
+use std::path::Path;
+
+use {normalize_path, Check, ProblemDescription};
+
+/// How the paths of problems are compared to find out whether they refer to
+/// the same file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathCompare {
+    /// Paths that differ only in casing are different files.
+    Sensitive,
+    /// Paths that differ only in casing are the same file, as on the
+    /// default file systems of Windows and macOS.
+    Insensitive,
+}
+
+/// Compares paths like the file systems of the platform usually do.
+impl Default for PathCompare {
+    fn default() -> PathCompare {
+        if cfg!(any(windows, target_os = "macos")) {
+            PathCompare::Insensitive
+        } else {
+            PathCompare::Sensitive
+        }
+    }
+}
+
+impl PathCompare {
+    /// Returns a key of the path that is equal for paths that refer to the
+    /// same file, ignoring separators and ```.``` components.
+    pub fn key(&self, path: &str) -> String {
+        let key = normalize_path(Path::new(""), path).join("/");
+        match *self {
+            PathCompare::Sensitive => key,
+            PathCompare::Insensitive => key.to_lowercase(),
+        }
+    }
+}
+
+/// Returns the module of a file under a ```src``` directory, from its path:
+/// ```src/config/loader.rs``` and ```src/config/loader/mod.rs``` are
+/// ```config::loader```, and crate roots like ```src/lib.rs```, ```src/main.rs```
+/// and ```src/bin/tool.rs``` are ```crate```. Other files have no module.
+pub fn module_path(path: &str) -> Option<String> {
+    let components = normalize_path(Path::new(""), path);
+    let (file, dirs) = components.split_last()?;
+    let src = dirs.iter().rposition(|dir| dir == "src")?;
+    let mut module: Vec<&str> = dirs[src + 1..].iter().map(|dir| &dir[..]).collect();
+    module.push(file.strip_suffix(".rs")?);
+    if module[0] == "bin" {
+        // The name of a binary with several files is its directory
+        module.drain(..module.len().min(2));
+        if module == ["main"] {
+            module.clear();
+        }
+    } else if module == ["lib"] || module == ["main"] {
+        module.clear();
+    }
+    if module.last() == Some(&"mod") {
+        module.pop();
+    }
+    if module.is_empty() {
+        Some(String::from("crate"))
+    } else {
+        Some(module.join("::"))
+    }
+}
+
+impl Check {
+    /// Groups the problems by module (see ```ProblemDescription::module```),
+    /// in the order in which the modules were first reported. Problems
+    /// without a module are grouped by their file instead.
+    pub fn by_module(&self) -> Vec<(&str, Vec<&ProblemDescription>)> {
+        let mut modules: Vec<(&str, Vec<&ProblemDescription>)> = Vec::new();
+        for problem in self.problems() {
+            let key = problem.module.as_deref().unwrap_or(&problem.filepath);
+            match modules.iter().position(|&(module, _)| module == key) {
+                Some(index) => modules[index].1.push(problem),
+                None => modules.push((key, vec![problem])),
+            }
+        }
+        modules
+    }
+
+    /// Groups the problems by file, in the order in which the files were
+    /// first reported. Each file is named by the first form of its path that
+    /// was seen.
+    pub fn by_file(&self, compare: PathCompare) -> Vec<(&str, Vec<&ProblemDescription>)> {
+        let mut keys: Vec<String> = Vec::new();
+        let mut files: Vec<(&str, Vec<&ProblemDescription>)> = Vec::new();
+        for problem in self.problems() {
+            let key = compare.key(&problem.filepath);
+            match keys.iter().position(|k| *k == key) {
+                Some(index) => files[index].1.push(problem),
+                None => {
+                    keys.push(key);
+                    files.push((&problem.filepath, vec![problem]));
+                },
+            }
+        }
+        files
+    }
+
+    /// Returns the problems in the file, whose path is compared as by
+    /// ```by_file```, so that ```src\main.rs``` and ```./src/main.rs``` are
+    /// the same file.
+    pub fn for_file(&self, path: &str, compare: PathCompare) -> Vec<&ProblemDescription> {
+        let key = compare.key(path);
+        self.problems().iter()
+            .filter(|problem| compare.key(&problem.filepath) == key)
+            .collect()
+    }
+
+    /// Returns the problems that cargo reported while checking the package,
+    /// like a member of a workspace.
+    pub fn for_package(&self, name: &str) -> Vec<&ProblemDescription> {
+        self.problems().iter()
+            .filter(|problem| problem.during_package.as_deref() == Some(name))
+            .collect()
+    }
+
+    /// Returns the check without the problems that repeat an earlier one
+    /// (with the same file, span, severity, code and message), like those
+    /// that are reported once for each target of a package. The earlier
+    /// one counts the repeats in ```count```, and has the targets of all of
+    /// them in ```targets``` if they are known and differ.
+    pub fn dedup(&self, compare: PathCompare) -> Check {
+        let mut seen = Vec::new();
+        let mut problems: Vec<ProblemDescription> = Vec::new();
+        for problem in self.problems() {
+            let message = &problem.message;
+            let key = (compare.key(&problem.filepath), message.start_line, message.start_col,
+                message.end_line, message.end_col, problem.severity, &problem.code,
+                &message.message);
+            match seen.iter().position(|seen| *seen == key) {
+                Some(index) => merge_duplicate(&mut problems[index], problem),
+                None => {
+                    seen.push(key);
+                    problems.push(problem.clone());
+                },
+            }
+        }
+        Check::from_problems(problems)
+    }
+}
+
+/// Returns the targets that the problem was reported for.
+fn targets(problem: &ProblemDescription) -> Vec<String> {
+    if problem.targets.is_empty() {
+        problem.target.iter().cloned().collect()
+    } else {
+        problem.targets.clone()
+    }
+}
+
+/// Counts the duplicate in the problem that it repeats.
+fn merge_duplicate(problem: &mut ProblemDescription, duplicate: &ProblemDescription) {
+    problem.count += duplicate.count;
+    let mut merged = targets(problem);
+    for target in targets(duplicate) {
+        if ! merged.contains(&target) {
+            merged.push(target);
+        }
+    }
+    if merged.len() > 1 {
+        problem.targets = merged;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parse_output;
+
+    const MIXED_CASING_OUTPUT: &str = "\
+src/Lib.rs:2:9: 2:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+src/main.rs:1:1: 1:2 warning: unused import, #[warn(unused_imports)] on by default
+src/lib.rs:2:9: 2:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+./SRC/lib.rs:5:1: 5:2 warning: function is never used: `f`, #[warn(dead_code)] on by default
+";
+
+    #[test]
+    fn insensitive_paths_group_under_the_first_seen_form() {
+        let check = parse_output(MIXED_CASING_OUTPUT);
+        let sizes = |files: Vec<(&str, Vec<&ProblemDescription>)>| -> Vec<(String, usize)> {
+            files.into_iter().map(|(path, problems)| (String::from(path), problems.len())).collect()
+        };
+        assert_eq!(sizes(check.by_file(PathCompare::Insensitive)), vec![
+            (String::from("src/Lib.rs"), 3),
+            (String::from("src/main.rs"), 1),
+        ]);
+        assert_eq!(check.by_file(PathCompare::Sensitive).len(), 4);
+    }
+
+    #[test]
+    fn problems_are_found_by_file_with_either_separator() {
+        let check = parse_output("\
+src\\main.rs:2:9: 2:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+src/main.rs:3:5: 3:8 error: unresolved name `foo` [E0425]
+src/lib.rs:1:1: 1:2 warning: unused import, #[warn(unused_imports)] on by default
+");
+        let lines = |problems: Vec<&ProblemDescription>| -> Vec<usize> {
+            problems.iter().map(|problem| problem.message.start_line).collect()
+        };
+        assert_eq!(lines(check.for_file("./src/main.rs", PathCompare::Sensitive)), vec![2, 3]);
+        assert_eq!(lines(check.for_file("src\\lib.rs", PathCompare::Sensitive)), vec![1]);
+        assert!(check.for_file("src/other.rs", PathCompare::Sensitive).is_empty());
+        assert!(Check::Perfect.for_file("src/main.rs", PathCompare::Sensitive).is_empty());
+        assert_eq!((check.error_count(), check.warning_count()), (1, 2));
+        assert_eq!((Check::Perfect.error_count(), Check::Perfect.warning_count()), (0, 0));
+    }
+
+    #[test]
+    fn modules_follow_the_file_layout() {
+        let module = |path: &str| module_path(path);
+        assert_eq!(module("src/config/loader.rs").as_deref(), Some("config::loader"));
+        assert_eq!(module("./src\\config\\mod.rs").as_deref(), Some("config"));
+        assert_eq!(module("src/lib.rs").as_deref(), Some("crate"));
+        assert_eq!(module("crates/core/src/main.rs").as_deref(), Some("crate"));
+        assert_eq!(module("src/bin/tool.rs").as_deref(), Some("crate"));
+        assert_eq!(module("src/bin/tool/main.rs").as_deref(), Some("crate"));
+        assert_eq!(module("src/bin/tool/args.rs").as_deref(), Some("args"));
+        assert_eq!(module("src/lib/main.rs").as_deref(), Some("lib::main"));
+        assert_eq!(module("tests/it.rs"), None);
+        assert_eq!(module("src/build.in"), None);
+
+        let check = parse_output("\
+src/config/loader.rs:2:9: 2:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+src/lib.rs:1:1: 1:2 warning: unused import, #[warn(unused_imports)] on by default
+src/config/loader/mod.rs:5:1: 5:2 warning: function is never used: `f`, #[warn(dead_code)] on by default
+build.rs:1:1: 1:2 warning: unused import, #[warn(unused_imports)] on by default
+");
+        let modules: Vec<(&str, usize)> = check.by_module().into_iter()
+            .map(|(module, problems)| (module, problems.len()))
+            .collect();
+        assert_eq!(modules, vec![("config::loader", 2), ("crate", 1), ("build.rs", 1)]);
+    }
+
+    #[test]
+    fn dedup_follows_the_path_comparison() {
+        let check = parse_output(MIXED_CASING_OUTPUT);
+        assert_eq!(check.dedup(PathCompare::Insensitive).problems().len(), 3);
+        assert_eq!(check.dedup(PathCompare::Sensitive).problems().len(), 4);
+        assert_eq!(&*check.dedup(PathCompare::Insensitive).problems()[0].filepath, "src/Lib.rs");
+    }
+
+    #[test]
+    fn duplicates_are_counted_in_the_problem_they_repeat() {
+        let mut problems = parse_output("\
+src/lib.rs:2:9: 2:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+src/lib.rs:2:9: 2:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+src/lib.rs:2:9: 2:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+src/lib.rs:2:9: 2:10 warning: variable `x` is assigned to, but never used
+").problems().to_vec();
+        problems[0].target = Some(String::from("lib"));
+        problems[1].target = Some(String::from("test \"it\""));
+        problems[2].target = Some(String::from("lib"));
+        let check = Check::from_problems(problems).dedup(PathCompare::Sensitive);
+        let problems = check.problems();
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0].count, 3);
+        assert_eq!(problems[0].targets, ["lib", "test \"it\""]);
+        // A different problem at the same span is kept, and so is its code
+        assert_eq!((problems[1].count, problems[1].targets.len()), (1, 0));
+        let mut coded = problems[1].clone();
+        coded.code = Some(String::from("unused_assignments"));
+        let check = Check::from_problems(vec![problems[1].clone(), coded]);
+        assert_eq!(check.dedup(PathCompare::Sensitive).problems().len(), 2);
+    }
+}
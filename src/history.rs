@@ -0,0 +1,293 @@
+//! A date-stamped log of check results, for following trends over time.
+//!
+//! The log is an append-only file with one JSON object per line. Every
+//! record is written as a single complete line, so concurrent writers don't
+//! corrupt each other's entries, and lines that can't be read (e.g. torn
+//! by a crash mid-write) are skipped.
+
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use json::Value;
+use {CheckReport, Severity};
+
+/// A recorded check result.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub time: SystemTime,
+    pub commit: Option<String>,
+    pub errors: usize,
+    pub warnings: usize,
+    /// The number of problems reported for each lint.
+    pub lints: BTreeMap<String, usize>,
+    /// The digest of the check (see ```Check::digest```), if it was recorded.
+    pub digest: Option<u64>,
+}
+
+impl HistoryEntry {
+    /// Summarizes a check report.
+    pub fn from_report(report: &CheckReport) -> HistoryEntry {
+        let mut errors = 0;
+        let mut lints = BTreeMap::new();
+        let problems = report.check.problems();
+        for problem in problems {
+            if problem.severity == Severity::Error {
+                errors += 1;
+            }
+            if let Some(lint) = problem.lint() {
+                *lints.entry(String::from(lint)).or_insert(0) += 1;
+            }
+        }
+        HistoryEntry {
+            time: report.time,
+            commit: report.commit.clone(),
+            errors,
+            warnings: problems.len() - errors,
+            lints,
+            digest: Some(report.check.digest()),
+        }
+    }
+
+    /// Returns the number of problems reported for the lint, or all problems
+    /// if no lint is given.
+    pub fn count(&self, lint: Option<&str>) -> usize {
+        match lint {
+            Some(lint) => self.lints.get(lint).cloned().unwrap_or(0),
+            None => self.errors + self.warnings,
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let time = self.time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let lints = self.lints.iter()
+            .map(|(lint, &count)| (lint.clone(), Value::from(count)))
+            .collect();
+        Value::object(vec![
+            ("time", Value::from(time.as_secs())),
+            ("commit", Value::from(self.commit.clone())),
+            ("errors", Value::from(self.errors)),
+            ("warnings", Value::from(self.warnings)),
+            ("lints", Value::Object(lints)),
+            ("digest", Value::from(self.digest.map(|digest| format!("{:016x}", digest)))),
+        ])
+    }
+
+    fn from_json(value: &Value) -> Option<HistoryEntry> {
+        let time = UNIX_EPOCH + Duration::from_secs(value.get("time")?.as_u64()?);
+        let commit = match *value.get("commit")? {
+            Value::Null => None,
+            ref commit => Some(String::from(commit.as_str()?)),
+        };
+        let mut lints = BTreeMap::new();
+        for (lint, count) in value.get("lints")?.as_object()? {
+            lints.insert(lint.clone(), count.as_usize()?);
+        }
+        // Entries recorded before digests were have none
+        let digest = match value.get("digest") {
+            None | Some(&Value::Null) => None,
+            Some(digest) => Some(u64::from_str_radix(digest.as_str()?, 16).ok()?),
+        };
+        Some(HistoryEntry {
+            time,
+            commit,
+            errors: value.get("errors")?.as_usize()?,
+            warnings: value.get("warnings")?.as_usize()?,
+            lints,
+            digest,
+        })
+    }
+}
+
+/// The point in the history that regressions are measured from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Since {
+    /// The last entry recorded at or before the time.
+    Time(SystemTime),
+    /// The last entry recorded for the commit.
+    Commit(String),
+}
+
+/// A lint reported more often in the latest entry than at an earlier point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regression {
+    pub lint: String,
+    pub before: usize,
+    pub after: usize,
+}
+
+/// A history of check results, backed by a JSON lines file.
+#[derive(Debug)]
+pub struct History {
+    path: PathBuf,
+    entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    /// Opens the history at the given path, which is created when the first
+    /// entry is recorded if it doesn't exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<History> {
+        let path = path.as_ref().to_path_buf();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+        let entries = text.lines()
+            .filter_map(|line| Value::parse(line).ok())
+            .filter_map(|value| HistoryEntry::from_json(&value))
+            .collect();
+        Ok(History { path, entries })
+    }
+
+    /// Returns the recorded entries, oldest first.
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    /// Appends the result of a check to the history.
+    pub fn record(&mut self, report: &CheckReport) -> io::Result<()> {
+        let entry = HistoryEntry::from_report(report);
+        if let Some(dir) = self.path.parent() {
+            if ! dir.as_os_str().is_empty() {
+                fs::create_dir_all(dir)?;
+            }
+        }
+        let mut file = OpenOptions::new().read(true).append(true).create(true)
+            .open(&self.path)?;
+
+        // Don't continue a line left incomplete by an interrupted writer
+        let mut line = String::new();
+        if file.seek(SeekFrom::End(0))? > 0 {
+            let mut last = [0];
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                line.push('\n');
+            }
+        }
+        line.push_str(&entry.to_json().to_string());
+        line.push('\n');
+        file.write_all(line.as_bytes())?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Returns the time and number of problems (for a single lint, if given)
+    /// of every entry recorded since the given time.
+    pub fn trend(&self, lint: Option<&str>, since: SystemTime) -> Vec<(SystemTime, usize)> {
+        self.entries.iter()
+            .filter(|entry| entry.time >= since)
+            .map(|entry| (entry.time, entry.count(lint)))
+            .collect()
+    }
+
+    /// Returns the lints that the latest entry reports more often than the
+    /// entry at the given point.
+    pub fn regressions_since(&self, since: &Since) -> Vec<Regression> {
+        let baseline = match *since {
+            Since::Time(time) => self.entries.iter().rev()
+                .find(|entry| entry.time <= time),
+            Since::Commit(ref commit) => self.entries.iter().rev()
+                .find(|entry| entry.commit.as_ref() == Some(commit)),
+        };
+        let (baseline, latest) = match (baseline, self.entries.last()) {
+            (Some(baseline), Some(latest)) => (baseline, latest),
+            _ => return Vec::new(),
+        };
+        latest.lints.iter()
+            .map(|(lint, &after)| Regression {
+                lint: lint.clone(),
+                before: baseline.count(Some(lint)),
+                after,
+            })
+            .filter(|regression| regression.after > regression.before)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::thread;
+    use {Check, ProblemDescription};
+
+    fn temp_history(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("lintparser-history-{}-{}.jsonl",
+            name, ::std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn report(secs: u64, commit: &str, lints: &[&str]) -> CheckReport {
+        let problems = lints.iter()
+            .map(|lint| ProblemDescription::new(String::from("src/lib.rs"), 1, 1, 1, 2,
+                format!("unused, #[warn({})] on by default", lint), vec![], vec![]))
+            .collect();
+        let mut report = CheckReport::new(Check::Warning(problems));
+        report.time = UNIX_EPOCH + Duration::from_secs(secs);
+        report.commit = Some(String::from(commit));
+        report
+    }
+
+    #[test]
+    fn records_survive_reopening_and_torn_lines() {
+        let path = temp_history("reopen");
+        let mut history = History::open(&path).unwrap();
+        history.record(&report(100, "a", &["dead_code", "dead_code"])).unwrap();
+        // A writer that crashed halfway through a line
+        OpenOptions::new().append(true).open(&path).unwrap()
+            .write_all(b"{\"time\":150,\"com").unwrap();
+        history.record(&report(200, "b", &["dead_code", "unused_imports"])).unwrap();
+
+        let reopened = History::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(reopened.entries(), history.entries());
+        assert_eq!(reopened.entries().len(), 2);
+        assert_eq!(reopened.entries()[1].lints.get("unused_imports"), Some(&1));
+    }
+
+    #[test]
+    fn trend_and_regressions() {
+        let path = temp_history("trend");
+        let mut history = History::open(&path).unwrap();
+        history.record(&report(100, "a", &["dead_code"])).unwrap();
+        history.record(&report(200, "b", &["dead_code", "dead_code"])).unwrap();
+        history.record(&report(300, "c", &["unused_imports"])).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        assert_eq!(history.trend(Some("dead_code"), at(150)), vec![(at(200), 2), (at(300), 0)]);
+        assert_eq!(history.trend(None, at(0)).len(), 3);
+
+        let regressions = history.regressions_since(&Since::Commit(String::from("b")));
+        assert_eq!(regressions, vec![Regression {
+            lint: String::from("unused_imports"), before: 0, after: 1
+        }]);
+        assert_eq!(history.regressions_since(&Since::Time(at(300))), vec![]);
+        assert_eq!(history.regressions_since(&Since::Commit(String::from("x"))), vec![]);
+    }
+
+    #[test]
+    fn concurrent_writers_write_complete_lines() {
+        let path = temp_history("concurrent");
+        let writers: Vec<_> = (0..4).map(|writer| {
+            let path = path.clone();
+            thread::spawn(move || {
+                let mut history = History::open(&path).unwrap();
+                for i in 0..25 {
+                    history.record(&report(writer * 100 + i, "a", &["dead_code"])).unwrap();
+                }
+            })
+        }).collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        let history = History::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(history.entries().len(), 100);
+    }
+}
@@ -0,0 +1,423 @@
+//! The human format of the diagnostics of rustc 1.20 and later, where the
+//! span follows the message on an arrow line:
+//!
+//! ```text
+//! error[E0425]: cannot find value `x` in this scope
+//!  --> src/lib.rs:2:5
+//!   |
+//! 2 |     x
+//!   |     ^ not found in this scope
+//!   |
+//!   = help: consider importing this function
+//! ```
+
+use std::ops::Range;
+
+use bounds::is_bound_step;
+use {BoundStep, Heuristics, Level, Note, ProblemDescription, Suggestion};
+
+/// Returns the span of an arrow line like ```--> src/lib.rs:2:5```. Lines
+/// and columns are counted from 1, so an arrow at 0 isn't one.
+fn arrow_span(line: &str) -> Option<(&str, usize, usize)> {
+    let location = line.trim_start().strip_prefix("--> ")?;
+    // The path may contain colons, but the position can't
+    let mut parts = location.trim_end().rsplitn(3, ':');
+    let col = parts.next()?.parse().ok().filter(|&col| col > 0)?;
+    let line = parts.next()?.parse().ok().filter(|&line| line > 0)?;
+    let path = parts.next().filter(|path| ! path.is_empty())?;
+    Some((path, line, col))
+}
+
+/// Splits a line of the snippet, like ```2 |     x``` or ```  |     ^```, into
+/// its line number (if any) and the text after the gutter.
+fn snippet_line(line: &str) -> Option<(Option<usize>, &str)> {
+    let bar = line.find('|')?;
+    let number = line[..bar].trim();
+    let number = if number.is_empty() {
+        None
+    } else {
+        Some(number.parse().ok()?)
+    };
+    let text = &line[bar + 1..];
+    Some((number, text.strip_prefix(' ').unwrap_or(text)))
+}
+
+/// Returns the path of a line of a suggested snippet that adds an import,
+/// like ```4 + use std::io::Read;```, which rustc prints under the help
+/// that suggests it.
+fn inserted_import(line: &str) -> Option<&str> {
+    let (number, text) = line.trim_start().split_once(" + ")?;
+    number.parse::<usize>().ok()?;
+    text.trim_end().strip_prefix("use ")?.strip_suffix(';')
+}
+
+/// Returns the number of columns that rustc gives a character in a snippet:
+/// two for the wide characters of East Asian scripts and for emoji, none for
+/// combining marks and zero-width characters, and one otherwise.
+pub fn display_width(ch: char) -> usize {
+    match ch as u32 {
+        0x0300..=0x036F | 0x200B..=0x200F | 0xFE00..=0xFE0F => 0,
+        0x1100..=0x115F | 0x2E80..=0x303E | 0x3041..=0x33FF | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF | 0xA000..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F | 0xFF00..=0xFF60 | 0xFFE0..=0xFFE6 | 0x1F300..=0x1F64F
+            | 0x1F900..=0x1F9FF | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+/// Returns the number of characters of the line of a snippet that are in
+/// the columns, as the underline is in columns rather than characters.
+fn characters_in_columns(code: &str, columns: Range<usize>) -> usize {
+    let mut column = 0;
+    code.chars().filter(|&ch| {
+        let at = column;
+        column += display_width(ch);
+        // A combining mark goes with the character before it
+        columns.contains(&at) || (at == columns.end && at == column && at > columns.start)
+    }).count()
+}
+
+/// Returns the end of the span from the underline of its lines in the
+/// snippet: the width of the ```^^^``` under a line in characters, or the
+/// ```|___^``` under the last line of a span of several lines.
+fn underlined_end(lines: &[&str], start_line: usize, start_col: usize)
+        -> Option<(usize, usize)> {
+    let source = lines.iter().position(|line| {
+        snippet_line(line).is_some_and(|(number, _)| number == Some(start_line))
+    })?;
+    let code = snippet_line(lines[source])?.1;
+    let (number, underline) = snippet_line(lines.get(source + 1)?)?;
+    if number.is_some() {
+        return None;
+    }
+    if underline.trim_start().starts_with('_') {
+        return multi_line_end(&lines[source + 2..], start_line);
+    }
+    let chars: Vec<char> = underline.chars().collect();
+    // The underline of the span starts under its column, or rustc
+    // replaced tabs and the first run is taken
+    let start = if start_col.checked_sub(1).and_then(|i| chars.get(i)) == Some(&'^') {
+        start_col - 1
+    } else {
+        chars.iter().position(|&ch| ch == '^')?
+    };
+    let width = chars[start..].iter().take_while(|&&ch| ch == '^').count();
+    let covered = characters_in_columns(code, start..start + width);
+    Some((start_line, start_col + if covered > 0 { covered } else { width }))
+}
+
+/// Returns the end of a span of several lines, from the lines of the snippet
+/// after its first one, which are prefixed with ```| ```.
+fn multi_line_end(lines: &[&str], start_line: usize) -> Option<(usize, usize)> {
+    let mut end_line = start_line;
+    for line in lines {
+        let (number, text) = snippet_line(line)?;
+        match number {
+            Some(number) => end_line = number,
+            None => if let Some(rest) = text.strip_prefix("|_") {
+                // The ^ is under the last character, after the prefix
+                let caret = rest.find(|ch: char| ch != '_')?;
+                return Some((end_line, caret + 2)).filter(|_| rest[caret..].starts_with('^'));
+            },
+        }
+    }
+    None
+}
+
+/// Parses a ```= help: ...``` or ```= note: ...``` line of a snippet.
+fn child_line(line: &str) -> Option<(Level, &str)> {
+    let rest = line.trim_start().strip_prefix("= ")?;
+    if let Some(message) = rest.strip_prefix("help: ") {
+        Some((Level::Help, message))
+    } else {
+        rest.strip_prefix("note: ").map(|message| (Level::Note, message))
+    }
+}
+
+/// Parses a top-level ```help: ...``` or ```note: ...``` line, which may have
+/// a span of its own on the next arrow line.
+fn subdiagnostic_line(line: &str) -> Option<(Level, &str)> {
+    if let Some(message) = line.strip_prefix("help: ") {
+        Some((Level::Help, message))
+    } else {
+        line.strip_prefix("note: ").map(|message| (Level::Note, message))
+    }
+}
+
+/// Parses a diagnostic of the human format, starting with its ```error: ```
+/// or ```warning[E0000]: ``` line. Returns ```None``` for diagnostics
+/// without a span, like the warnings of cargo, or in another format.
+pub fn parse_human_block(block: &[&str]) -> Option<(Level, ProblemDescription, Heuristics)> {
+    let first = block[0];
+    let (level, rest) = if let Some(rest) = first.strip_prefix("error") {
+        (Level::Error, rest)
+    } else {
+        (Level::Warning, first.strip_prefix("warning")?)
+    };
+    let (code, rest) = match rest.strip_prefix('[') {
+        Some(code) => {
+            let end = code.find(']')?;
+            (Some(&code[..end]), &code[end + 1..])
+        },
+        None => (None, rest),
+    };
+    let mut message = String::from(rest.strip_prefix(": ")?);
+    let mut heuristics = Heuristics::default();
+
+    // Lines up to the arrow continue the message
+    let arrow = block.iter().position(|line| arrow_span(line).is_some())?;
+    for line in &block[1..arrow] {
+        if subdiagnostic_line(line).is_some() || snippet_line(line).is_some() {
+            return None;
+        }
+        heuristics.insert(Heuristics::MESSAGE_CONTINUATION);
+        message.push('\n');
+        message.push_str(line.trim_end());
+    }
+    if let Some(code) = code {
+        message.push_str(&format!(" [{}]", code));
+    }
+    let (path, start_line, start_col) = arrow_span(block[arrow])?;
+    let (end_line, end_col) = match underlined_end(&block[arrow + 1..], start_line, start_col) {
+        Some(end) => {
+            heuristics.insert(Heuristics::UNDERLINE_COLUMNS);
+            end
+        },
+        None => (start_line, start_col),
+    };
+    let mut problem = ProblemDescription::new(path, start_line, start_col, end_line, end_col,
+        &message[..], vec![], vec![]);
+    problem.code = code.map(String::from);
+    problem.source_line = block[arrow + 1..].iter()
+        .filter_map(|line| snippet_line(line))
+        .find(|&(number, _)| number == Some(start_line))
+        .map(|(_, text)| String::from(text.trim_end()));
+
+    // The help and notes, whose lines are indented to continue them
+    let mut child: Option<(Level, Note)> = None;
+    let mut lines = block[arrow + 1..].iter().peekable();
+    while let Some(line) = lines.next() {
+        if let Some(import) = inserted_import(line) {
+            if let Some((level, note)) = child.take() {
+                push_child(&mut problem, level, note);
+            }
+            if let Some(help) = problem.help.last() {
+                let suggestion = Suggestion::import(import, help.message.clone());
+                problem.suggestions.push(suggestion);
+                heuristics.insert(Heuristics::SUGGESTION_FROM_HELP);
+            }
+            continue;
+        }
+        let next = child_line(line).or_else(|| subdiagnostic_line(line));
+        if let Some((level, text)) = next {
+            // Like the children of JSON messages, those without an arrow
+            // of their own have no span
+            let mut note = Note::new(0, 0, 0, 0, text);
+            if subdiagnostic_line(line).is_some() {
+                if let Some((at, line, col)) = lines.peek().and_then(|line| arrow_span(line)) {
+                    lines.next();
+                    let rest: Vec<&str> = lines.clone().cloned().collect();
+                    let (end_line, end_col) = underlined_end(&rest, line, col)
+                        .unwrap_or((line, col));
+                    note = Note::new(line, col, end_line, end_col, text).with_file(at);
+                }
+            }
+            if let Some((level, note)) = child.replace((level, note)) {
+                push_child(&mut problem, level, note);
+            }
+            continue;
+        }
+        let continues = line.starts_with(' ') && snippet_line(line).is_none()
+            && arrow_span(line).is_none() && ! line.trim().is_empty();
+        match child {
+            Some((_, ref mut note)) if continues => {
+                note.message.push('\n');
+                note.message.push_str(line.trim());
+            },
+            _ => if let Some((level, note)) = child.take() {
+                push_child(&mut problem, level, note);
+            },
+        }
+    }
+    if let Some((level, note)) = child {
+        push_child(&mut problem, level, note);
+    }
+    Some((level, problem, heuristics))
+}
+
+fn push_child(problem: &mut ProblemDescription, level: Level, note: Note) {
+    match level {
+        Level::Help => problem.help.push(note),
+        _ => {
+            if is_bound_step(&note.message) {
+                problem.bound_chain.push(BoundStep::new(&note.message,
+                    note.file.as_ref().map(|file| (&**file, note.clone()))));
+            }
+            problem.notes.push(note)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::characters_in_columns;
+    use {parse_output, parse_output_lenient, Applicability, Severity, SuggestionTarget};
+
+    /// The output of rustc 1.20.
+    const RUSTC_1_20: &str = "\
+warning: unused variable: `x`
+ --> src/main.rs:2:9
+  |
+2 |     let x = 5;
+  |         ^
+  |
+  = note: #[warn(unused_variables)] on by default
+  = note: to avoid this warning, consider using `_x` instead
+
+error[E0308]: mismatched types
+ --> src/main.rs:7:18
+  |
+7 |     let y: u32 = \"text\";
+  |                  ^^^^^^ expected u32, found reference
+  |
+  = note: expected type `u32`
+             found type `&'static str`
+
+error: aborting due to previous error
+";
+
+    /// The output of a current rustc, with a message of two lines.
+    const CURRENT: &str = "\
+error[E0425]: cannot find value `z` in this scope
+  --> src/lib.rs:12:5
+   |
+12 |     z + 1
+   |     ^ not found in this scope
+   |
+help: a local variable with a similar name exists
+   --> src/lib.rs:12:5
+   |
+12 |     y + 1
+   |     ~
+   = help: consider importing this function
+warning: this `if` has identical blocks
+in both of its branches
+ --> C:\\work\\src\\app.rs:3:10
+  |
+3 |     if a { 1 } else { 1 }
+  |          ^^^^^
+  |
+  = note: `#[warn(clippy::if_same_then_else)]` on by default
+warning: unused manifest key: package.colour
+";
+
+    #[test]
+    fn arrow_diagnostics_are_parsed() {
+        let check = parse_output(RUSTC_1_20);
+        let problems = check.problems();
+        assert_eq!(problems.len(), 2);
+        let spans: Vec<(usize, usize, usize, usize)> = problems.iter()
+            .map(|problem| (problem.message.start_line, problem.message.start_col,
+                problem.message.end_line, problem.message.end_col))
+            .collect();
+        assert_eq!(spans, vec![(2, 9, 2, 10), (7, 18, 7, 24)]);
+        assert_eq!(problems[0].lint(), Some("unused_variables"));
+        assert_eq!(problems[0].notes[1].message, "to avoid this warning, consider using `_x` \
+            instead");
+        assert_eq!(problems[1].severity, Severity::Error);
+        assert_eq!(problems[1].message.message, "mismatched types [E0308]");
+        assert_eq!(problems[1].error_code(), Some("E0308"));
+        assert_eq!(problems[1].notes[0].message, "expected type `u32`\nfound type `&'static str`");
+    }
+
+    #[test]
+    fn help_and_multi_line_messages_are_kept() {
+        let report = parse_output_lenient(CURRENT);
+        let problems = report.check.problems();
+        assert_eq!(problems.len(), 2);
+        let help: Vec<(&str, usize)> = problems[0].help.iter()
+            .map(|help| (&help.message[..], help.start_col))
+            .collect();
+        assert_eq!(help, vec![("a local variable with a similar name exists", 5),
+            ("consider importing this function", 0)]);
+        assert_eq!(&*problems[1].filepath, "C:\\work\\src\\app.rs");
+        assert_eq!(problems[1].message.message,
+            "this `if` has identical blocks\nin both of its branches");
+        assert_eq!((problems[1].message.start_col, problems[1].message.end_col), (10, 15));
+        assert_eq!(problems[1].lint(), Some("clippy::if_same_then_else"));
+        // Without a span, the warning of cargo is still skipped
+        assert_eq!(report.raw_blocks.last().unwrap().lines,
+            "warning: unused manifest key: package.colour");
+    }
+
+    #[test]
+    fn underlines_of_wide_characters_end_after_them() {
+        let check = parse_output("\
+warning: unused variable: `日本`
+  --> src/main.rs:10:24
+   |
+10 |     let    _wide = \"日本\"; let 日本 = 4;
+   |                                ^^^^ help: if this is intentional, prefix it with an \
+underscore: `_日本`
+");
+        let note = &check.problems()[0].message;
+        assert_eq!((note.start_col, note.end_col), (24, 26));
+        assert_eq!(characters_in_columns("e\u{301}x", 0..1), 2);
+    }
+
+    #[test]
+    fn imports_are_suggested_by_the_snippets_of_help() {
+        // From rustc 1.95
+        let check = parse_output("\
+error[E0425]: cannot find value `MAX` in this scope
+  --> src/lib.rs:17:5
+   |
+17 |     MAX
+   |     ^^^ not found in this scope
+   |
+help: consider importing one of these constants
+   |
+ 4 + use std::char::MAX;
+   |
+ 4 + use std::f32::MAX;
+   |
+   = and 11 other candidates
+
+error[E0599]: no method named `read_to_string` found for struct `File` in the current scope
+  --> src/lib.rs:12:10
+   |
+12 |     file.read_to_string(&mut text).unwrap();
+   |          ^^^^^^^^^^^^^^
+   |
+   = help: items from traits can only be used if the trait is in scope
+help: trait `Read` which provides `read_to_string` is implemented but not in scope; perhaps \
+you want to import it
+   |
+ 4 + use std::io::Read;
+   |
+help: there is a method `read_to_end` with a similar name
+   |
+12 -     file.read_to_string(&mut text).unwrap();
+12 +     file.read_to_end(&mut text).unwrap();
+   |
+");
+        let imports = |index: usize| -> Vec<(String, &str)> {
+            check.problems()[index].suggestions.iter()
+                .inspect(|suggestion| {
+                    assert_eq!(suggestion.target, SuggestionTarget::FileTop);
+                    assert_eq!(suggestion.applicability, Applicability::MaybeIncorrect);
+                })
+                .map(|suggestion| (suggestion.replacement.clone(), &suggestion.span.message[..]))
+                .collect()
+        };
+        assert_eq!(imports(0), [
+            (String::from("use std::char::MAX;\n"), "consider importing one of these constants"),
+            (String::from("use std::f32::MAX;\n"), "consider importing one of these constants"),
+        ]);
+        let read = imports(1);
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].0, "use std::io::Read;\n");
+        assert!(read[0].1.starts_with("trait `Read` which provides"));
+        assert_eq!(check.problems()[1].help.len(), 3);
+    }
+}
@@ -0,0 +1,388 @@
+//! A minimal JSON value, used for the file formats of the crate so that it
+//! doesn't need any dependencies.
+
+use std::fmt;
+
+/// A JSON value. Numbers keep their original text, so that large integers
+/// don't lose precision.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+    /// The members in their original order.
+    Object(Vec<(String, Value)>),
+}
+
+/// The position at which a JSON text could not be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonError {
+    pub offset: usize,
+}
+
+/// How deeply values may be nested before the parser gives up.
+const MAX_DEPTH: usize = 128;
+
+impl Value {
+    /// Creates an object from its members.
+    pub fn object<K: Into<String>>(members: Vec<(K, Value)>) -> Value {
+        Value::Object(members.into_iter().map(|(key, value)| (key.into(), value)).collect())
+    }
+
+    /// Parses a JSON text, which must contain nothing but a single value.
+    pub fn parse(text: &str) -> Result<Value, JsonError> {
+        let mut parser = Parser { text, offset: 0 };
+        let value = parser.value(0)?;
+        parser.skip_whitespace();
+        if parser.offset == text.len() {
+            Ok(value)
+        } else {
+            Err(parser.error())
+        }
+    }
+
+    /// Returns the member of an object with the given key.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match *self {
+            Value::Object(ref members) => members.iter()
+                .find(|&(k, _)| k == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Value::Number(ref n) => n.parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        match *self {
+            Value::Number(ref n) => n.parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match *self {
+            Value::Array(ref values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, Value)]> {
+        match *self {
+            Value::Object(ref members) => Some(members),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Value {
+    fn from(s: &'a str) -> Value {
+        Value::String(String::from(s))
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Value {
+        Value::String(s)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Bool(b)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(n: usize) -> Value {
+        Value::Number(n.to_string())
+    }
+}
+
+impl From<u64> for Value {
+    fn from(n: u64) -> Value {
+        Value::Number(n.to_string())
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(values: Vec<Value>) -> Value {
+        Value::Array(values)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Value {
+        match value {
+            Some(value) => value.into(),
+            None => Value::Null,
+        }
+    }
+}
+
+/// Writes a string literal with the necessary escapes.
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for ch in s.chars() {
+        match ch {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// Writes the value as compact JSON on a single line.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(ref n) => write!(f, "{}", n),
+            Value::String(ref s) => write_string(f, s),
+            Value::Array(ref values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            },
+            Value::Object(ref members) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    offset: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self) -> JsonError {
+        JsonError { offset: self.offset }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.offset).cloned()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = self.peek() {
+            self.offset += 1;
+        }
+    }
+
+    fn expect(&mut self, literal: &str) -> Result<(), JsonError> {
+        if self.text[self.offset..].starts_with(literal) {
+            self.offset += literal.len();
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, JsonError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error());
+        }
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'n') => self.expect("null").map(|_| Value::Null),
+            Some(b't') => self.expect("true").map(|_| Value::Bool(true)),
+            Some(b'f') => self.expect("false").map(|_| Value::Bool(false)),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b'[') => {
+                self.offset += 1;
+                let mut values = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(b']') {
+                    self.offset += 1;
+                    return Ok(Value::Array(values));
+                }
+                loop {
+                    values.push(self.value(depth + 1)?);
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(b',') => self.offset += 1,
+                        Some(b']') => {
+                            self.offset += 1;
+                            return Ok(Value::Array(values));
+                        },
+                        _ => return Err(self.error()),
+                    }
+                }
+            },
+            Some(b'{') => {
+                self.offset += 1;
+                let mut members = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(b'}') {
+                    self.offset += 1;
+                    return Ok(Value::Object(members));
+                }
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() != Some(b'"') {
+                        return Err(self.error());
+                    }
+                    let key = self.string()?;
+                    self.skip_whitespace();
+                    self.expect(":")?;
+                    let value = self.value(depth + 1)?;
+                    members.push((key, value));
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(b',') => self.offset += 1,
+                        Some(b'}') => {
+                            self.offset += 1;
+                            return Ok(Value::Object(members));
+                        },
+                        _ => return Err(self.error()),
+                    }
+                }
+            },
+            Some(b'-') | Some(b'0'..=b'9') => self.number(),
+            _ => Err(self.error()),
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, JsonError> {
+        let start = self.offset;
+        while let Some(b'0'..=b'9') | Some(b'-') | Some(b'+') | Some(b'.')
+                | Some(b'e') | Some(b'E') = self.peek() {
+            self.offset += 1;
+        }
+        let text = &self.text[start..self.offset];
+        if text.parse::<f64>().is_ok() {
+            Ok(Value::Number(String::from(text)))
+        } else {
+            self.offset = start;
+            Err(self.error())
+        }
+    }
+
+    fn hex_escape(&mut self) -> Result<u32, JsonError> {
+        let digits = self.text.get(self.offset..self.offset + 4).ok_or_else(|| self.error())?;
+        let code = u32::from_str_radix(digits, 16).map_err(|_| self.error())?;
+        self.offset += 4;
+        Ok(code)
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        // Skip the opening quote
+        self.offset += 1;
+        let mut s = String::new();
+        loop {
+            let rest = &self.text[self.offset..];
+            let ch = rest.chars().next().ok_or_else(|| self.error())?;
+            self.offset += ch.len_utf8();
+            match ch {
+                '"' => return Ok(s),
+                '\\' => {
+                    let escape = self.peek().ok_or_else(|| self.error())?;
+                    self.offset += 1;
+                    match escape {
+                        b'"' => s.push('"'),
+                        b'\\' => s.push('\\'),
+                        b'/' => s.push('/'),
+                        b'b' => s.push('\u{8}'),
+                        b'f' => s.push('\u{c}'),
+                        b'n' => s.push('\n'),
+                        b'r' => s.push('\r'),
+                        b't' => s.push('\t'),
+                        b'u' => {
+                            let mut code = self.hex_escape()?;
+                            if (0xd800..0xdc00).contains(&code)
+                                    && self.text[self.offset..].starts_with("\\u") {
+                                self.offset += 2;
+                                let low = self.hex_escape()?;
+                                code = if (0xdc00..0xe000).contains(&low) {
+                                    0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00)
+                                } else {
+                                    0xfffd
+                                };
+                            }
+                            s.push(::std::char::from_u32(code).unwrap_or('\u{fffd}'));
+                        },
+                        _ => return Err(self.error()),
+                    }
+                },
+                c if (c as u32) < 0x20 => return Err(self.error()),
+                c => s.push(c),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_write_round_trip() {
+        let text = r#"{"a":[1,-2.5e3,true,false,null],"b":"x\"y\\z\n\u00e9","c":{}}"#;
+        let value = Value::parse(text).unwrap();
+        assert_eq!(value.get("b").and_then(Value::as_str), Some("x\"y\\z\né"));
+        assert_eq!(value.get("c"), Some(&Value::Object(vec![])));
+        assert_eq!(Value::parse(&value.to_string()), Ok(value));
+    }
+
+    #[test]
+    fn parse_keeps_large_integers_exact() {
+        let value = Value::parse("18446744073709551615").unwrap();
+        assert_eq!(value.as_u64(), Some(u64::MAX));
+    }
+
+    #[test]
+    fn parse_surrogate_pairs() {
+        assert_eq!(Value::parse(r#""\ud83e\udd80""#), Ok(Value::from("🦀")));
+    }
+
+    #[test]
+    fn parse_rejects_invalid_text() {
+        for text in &["", "{", "[1,]", "{\"a\" 1}", "\"abc", "01x", "[1] 2", "\"\\u12\""] {
+            assert!(Value::parse(text).is_err(), "parsed {:?}", text);
+        }
+        let deep = "[".repeat(1000);
+        assert!(Value::parse(&deep).is_err());
+    }
+}
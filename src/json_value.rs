@@ -0,0 +1,196 @@
+//! Problems as ```serde_json::Value``` trees, for consumers that merge them
+//! into JSON of their own, with the ```json``` feature.
+//!
+//! The trees have the shape that the ```serde``` feature serializes to:
+//! structs are objects with a member for every field, missing values are
+//! ```null```, and enums are the names of their variants, with the data of
+//! a variant (like the heuristics of ```Confidence::Heuristic```) as the
+//! one member of an object.
+
+use serde_json::Value;
+
+use bounds::BoundStep;
+use {Check, Confidence, MacroOrigin, Note, ProblemDescription, SanitizeRules, Suggestion};
+
+fn object(members: Vec<(&str, Value)>) -> Value {
+    Value::Object(members.into_iter().map(|(key, value)| (String::from(key), value)).collect())
+}
+
+/// Returns the value of the variant of an enum, with the data of it if any.
+fn variant(name: &str, data: Option<Value>) -> Value {
+    match data {
+        Some(data) => object(vec![(name, data)]),
+        None => Value::from(name),
+    }
+}
+
+fn notes(notes: &[Note]) -> Value {
+    Value::Array(notes.iter().map(Note::to_json_value).collect())
+}
+
+fn suggestion(suggestion: &Suggestion) -> Value {
+    object(vec![
+        ("span", suggestion.span.to_json_value()),
+        ("replacement", Value::from(&suggestion.replacement[..])),
+        ("applicability", variant(&format!("{:?}", suggestion.applicability), None)),
+        ("target", variant(&format!("{:?}", suggestion.target), None)),
+    ])
+}
+
+fn confidence(confidence: &Confidence) -> Value {
+    match *confidence {
+        Confidence::Exact => variant("Exact", None),
+        Confidence::Heuristic(ref names) => variant("Heuristic", Some(Value::from(names.clone()))),
+    }
+}
+
+fn macro_origin(origin: &MacroOrigin) -> Value {
+    object(vec![
+        ("kind", variant(&format!("{:?}", origin.kind), None)),
+        ("name", Value::from(&origin.name[..])),
+    ])
+}
+
+fn bound_step(step: &BoundStep) -> Value {
+    object(vec![
+        ("description", Value::from(&step.description[..])),
+        ("span", step.span.as_ref().map_or(Value::Null, Note::to_json_value)),
+        ("in_dependency", Value::from(step.in_dependency)),
+    ])
+}
+
+impl Note {
+    /// Returns the note as a JSON object with a member for each field.
+    pub fn to_json_value(&self) -> Value {
+        object(vec![
+            ("start_line", Value::from(self.start_line)),
+            ("start_col", Value::from(self.start_col)),
+            ("end_line", Value::from(self.end_line)),
+            ("end_col", Value::from(self.end_col)),
+            ("message", Value::from(&self.message[..])),
+            ("byte_start", Value::from(self.byte_start)),
+            ("byte_end", Value::from(self.byte_end)),
+            ("file", Value::from(self.file.as_deref())),
+        ])
+    }
+}
+
+impl ProblemDescription {
+    /// Returns the problem as a JSON object with a member for each field,
+    /// as the ```serde``` feature serializes it. A duration is an object
+    /// of its ```secs``` and ```nanos```.
+    pub fn to_json_value(&self) -> Value {
+        object(vec![
+            ("filepath", Value::from(&*self.filepath)),
+            ("severity", variant(&format!("{:?}", self.severity), None)),
+            ("message", self.message.to_json_value()),
+            ("help", notes(&self.help)),
+            ("notes", notes(&self.notes)),
+            ("suggestions", Value::Array(self.suggestions.iter().map(suggestion).collect())),
+            ("emitted_at", self.emitted_at.map_or(Value::Null, |time| object(vec![
+                ("secs", Value::from(time.as_secs())),
+                ("nanos", Value::from(time.subsec_nanos())),
+            ]))),
+            ("during_package", Value::from(self.during_package.clone())),
+            ("target", Value::from(self.target.clone())),
+            ("in_test_code", Value::from(self.in_test_code)),
+            ("parse_confidence", confidence(&self.parse_confidence)),
+            ("additional_primary_spans", notes(&self.additional_primary_spans)),
+            ("module", Value::from(self.module.clone())),
+            ("code", Value::from(self.code.clone())),
+            ("from_macro", self.from_macro.as_ref().map_or(Value::Null, macro_origin)),
+            ("bound_chain", Value::Array(self.bound_chain.iter().map(bound_step).collect())),
+            ("source_line", Value::from(self.source_line.clone())),
+            ("drifted", Value::from(self.drifted)),
+            ("rendered", Value::from(self.rendered.clone())),
+            ("count", Value::from(self.count)),
+            ("targets", Value::from(self.targets.clone())),
+        ])
+    }
+}
+
+impl Check {
+    /// Returns the check as JSON, as the ```serde``` feature serializes it:
+    /// ```"Perfect"```, or an object with the problems as its
+    /// ```"Warning"``` or ```"Error"``` member. The problems are sanitized
+    /// by the rules if any.
+    pub fn to_json_value(&self, sanitize: Option<&SanitizeRules>) -> Value {
+        if let Some(rules) = sanitize {
+            return self.sanitized(rules).to_json_value(None);
+        }
+        let problems = || Some(Value::Array(self.problems().iter()
+            .map(ProblemDescription::to_json_value)
+            .collect()));
+        match *self {
+            Check::Perfect => variant("Perfect", None),
+            Check::Warning(_) => variant("Warning", problems()),
+            Check::Error(_) => variant("Error", problems()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use {parse_json_messages, parse_output, Applicability, MacroKind, SuggestionTarget,
+        HEURISTIC_NAMES};
+
+    /// A problem with every field set.
+    fn problem() -> ProblemDescription {
+        let mut problem = parse_json_messages(r#"{"reason":"compiler-message","package_id":"app 0.1.0 (path+file:///app)","target":{"kind":["bin"],"name":"app"},"message":{"rendered":"warning: unused variable: `x`\n","$message_type":"diagnostic","children":[],"level":"warning","message":"unused variable: `x`","spans":[{"byte_end":26,"byte_start":25,"column_end":10,"column_start":9,"expansion":null,"file_name":"src/main.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":null,"suggestion_applicability":null,"text":[{"text":"    let x = 1;","highlight_start":9,"highlight_end":10}]}],"code":{"code":"unused_variables","explanation":null}}}"#)
+            .problems()[0].clone();
+        problem.notes.push(Note::new(0, 0, 0, 0, "`#[warn(unused_variables)]` on by default"));
+        problem.suggestions.push(Suggestion {
+            span: Note::new(2, 9, 2, 10, "if this is intentional, prefix it with an underscore"),
+            replacement: String::from("_x"),
+            applicability: Applicability::MachineApplicable,
+            target: SuggestionTarget::Span,
+        });
+        problem.emitted_at = Some(Duration::from_millis(1500));
+        problem.in_test_code = Some(false);
+        problem.parse_confidence = Confidence::Heuristic(vec![HEURISTIC_NAMES[0]]);
+        problem.module = Some(String::from("main"));
+        problem.from_macro = Some(MacroOrigin { kind: MacroKind::Bang, name: String::from("m") });
+        problem.bound_chain.push(BoundStep::new("required for `T` to implement `U`", None));
+        problem
+    }
+
+    #[test]
+    fn problems_become_trees_of_their_fields() {
+        let value = Check::from_problems(vec![problem()]).to_json_value(None);
+        let problem = &value["Warning"][0];
+        assert_eq!(problem["message"]["byte_start"], 25);
+        assert_eq!(problem["suggestions"][0]["applicability"], "MachineApplicable");
+        assert_eq!(problem["emitted_at"], serde_json::json!({"secs": 1, "nanos": 500_000_000}));
+        assert_eq!(problem["parse_confidence"], serde_json::json!({"Heuristic": ["visual-aid"]}));
+        assert_eq!(problem["bound_chain"][0]["span"], Value::Null);
+        assert_eq!(problem["target"], "bin \"app\"");
+        assert_eq!(Check::Perfect.to_json_value(None), "Perfect");
+        let error = parse_output("src/lib.rs:1:1: 1:2 error: oops\n").to_json_value(None);
+        assert_eq!(error["Error"][0]["severity"], "Error");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn trees_agree_with_the_serialization_of_serde() {
+        let mut bare = ProblemDescription::new("src/lib.rs", 1, 1, 1, 2, "oops", vec![], vec![]);
+        bare.severity = ::Severity::Error;
+        for check in [Check::Perfect, Check::from_problems(vec![problem()]),
+                Check::from_problems(vec![bare])] {
+            let typed = serde_json::to_value(&check).unwrap();
+            let tree = check.to_json_value(None);
+            for problem in check.problems() {
+                let (typed, tree) = (serde_json::to_value(problem).unwrap(),
+                    problem.to_json_value());
+                let keys = |value: &Value| value.as_object().unwrap().keys().cloned()
+                    .collect::<Vec<_>>();
+                assert_eq!(keys(&typed), keys(&tree));
+                for key in keys(&typed) {
+                    assert_eq!(typed[&key], tree[&key], "{}", key);
+                }
+            }
+            assert_eq!(typed, tree);
+        }
+    }
+}
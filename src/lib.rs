@@ -1,10 +1,137 @@
-use std::process::Command;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(any(feature = "json", all(test, feature = "serde")))]
+extern crate serde_json;
+#[cfg(all(unix, feature = "resource-usage"))]
+extern crate libc;
+#[cfg(feature = "tokio")]
+extern crate futures_core;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+
+use std::cmp::Ordering;
+use std::error;
 use std::io;
-use std::str::FromStr;
 use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::ops::Range;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{Duration, SystemTime};
+
+use checker::subcommand_checker;
+
+mod allows;
+mod annotate;
+#[cfg(feature = "tokio")]
+mod asynchronous;
+mod azure;
+mod bounds;
+mod budget;
+mod cascade;
+mod category;
+mod density;
+mod drift;
+mod expect;
+mod filter;
+mod checker;
+mod config;
+mod fix;
+mod gate;
+mod github;
+mod grouping;
+mod human;
+mod json;
+#[cfg(feature = "json")]
+mod json_value;
+mod lints;
+#[cfg(feature = "lsp")]
+mod lsp;
+mod messages;
+mod paste;
+mod paths;
+mod policy;
+mod pool;
+mod publish;
+mod render;
+mod resource;
+mod root;
+mod sanitize;
+#[cfg(feature = "sarif")]
+mod sarif;
+mod scan;
+mod snapshot;
+mod stats;
+mod stream;
+mod suppress;
+mod testcode;
+mod trace;
+mod units;
+mod verify;
+mod viewport;
+mod watch;
+mod workspace;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod history;
+pub mod triage;
+
+pub use allows::{count_allows, AllowScope, AllowSite};
+#[cfg(feature = "tokio")]
+pub use asynchronous::{cargo_check_async, cargo_check_async_in, cargo_check_stream_async,
+    cargo_check_stream_async_in, AsyncCheckStream, CheckFuture, FinishCheck};
+pub use annotate::{annotate_source, annotate_source_with, render_snippet, AnnotateOptions,
+    AnnotationStyle};
+pub use bounds::BoundStep;
+pub use budget::{Budget, BudgetResult, Overrun};
+pub use cascade::{CascadeRules, ErrorCluster};
+pub use category::{Category, CategoryTable};
+pub use checker::{Attempt, CancelToken, Checker, CommandKind, PathMap};
+pub use config::CargoConfig;
+pub use density::DensityReport;
+pub use expect::assert_check_matches;
+pub use filter::ProblemFilter;
+pub use fix::FixError;
+pub use gate::{assert_no_new_problems, update_baseline, UPDATE_BASELINE_VAR};
+pub use github::{CheckDiff, ReviewOptions, MAX_REVIEW_COMMENTS};
+pub use grouping::PathCompare;
+pub use lints::{list_lints, parse_lint_table, LintGroup, LintInfo, LintLevel, LintTable};
+#[cfg(feature = "lsp")]
+pub use lsp::{check_to_lsp, Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location,
+    LspRange, Position};
+pub use messages::parse_json_messages;
+pub use paste::{explain_paste, explain_paste_with, LinePrefix, PasteAnalysis, PasteOptions};
+pub use paths::{shorten_path, PathBase, PathView, ProjectLayout};
+pub use policy::{ConfigWarning, SeverityPolicy};
+pub use pool::{CheckResult, CheckTicket, CheckerPool};
+pub use publish::{published_path, PUBLISHED_FILE};
+pub use render::RenderOptions;
+pub use resource::ResourceUsage;
+pub use root::{cargo_check_for_file, find_project_root, ProjectRoot};
+pub use sanitize::SanitizeRules;
+#[cfg(feature = "sarif")]
+pub use sarif::SarifOptions;
+pub use scan::{count_lines, LineCount};
+pub use snapshot::SnapshotOptions;
+pub use stats::CheckStats;
+pub use stream::{cargo_check_stream, cargo_check_stream_in, CheckStream};
+pub use testcode::test_module_lines;
+pub use trace::{Progress, TraceEvent, TraceKind};
+pub use units::{UnitLine, Verb};
+pub use verify::RenderedMismatch;
+pub use viewport::SortedProblems;
+pub use watch::{NotifyPolicy, WatchHandle, WATCH_INTERVAL};
+pub use workspace::{FailedUnit, MemberStatus, WorkspaceGraph};
 
 /// The result of a lint check.
-#[derive(Debug)]
+///
+/// With the ```serde``` feature, it serializes externally tagged, e.g. as
+/// ```"Perfect"``` or ```{"Warning": [...]}``` in JSON, and the enums of its
+/// problems as the names of their variants. This shape is kept stable.
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
 pub enum Check {
     /// Not problems were found.
     Perfect,
@@ -13,13 +140,305 @@ pub enum Check {
     /// These errors were found.
     Error(Vec<ProblemDescription>),
 }
-unsafe impl Send for Check {}
+
+impl Check {
+    /// Returns the problems found by the check.
+    pub fn problems(&self) -> &[ProblemDescription] {
+        match *self {
+            Check::Perfect => &[],
+            Check::Warning(ref problems) | Check::Error(ref problems) => problems,
+        }
+    }
+
+    /// Returns the number of errors.
+    pub fn error_count(&self) -> usize {
+        self.problems().iter().filter(|problem| problem.is_error()).count()
+    }
+
+    /// Returns the number of problems that aren't errors, as notes and help
+    /// count as warnings.
+    pub fn warning_count(&self) -> usize {
+        self.problems().len() - self.error_count()
+    }
+
+    /// Returns the severity of the most severe problem, if there are any.
+    pub fn worst_severity(&self) -> Option<Severity> {
+        worst_severity(self.problems())
+    }
+
+    /// Classifies the problems by the most severe one, where notes and help
+    /// count as warnings.
+    pub fn from_problems(problems: Vec<ProblemDescription>) -> Check {
+        match worst_severity(&problems) {
+            None => Check::Perfect,
+            Some(Severity::Error) => Check::Error(problems),
+            Some(_) => Check::Warning(problems),
+        }
+    }
+
+    /// Returns the problems that weren't reported by clippy lints (including
+    /// the problems without a lint, like compile errors).
+    pub fn rustc_only(&self) -> Check {
+        Check::from_problems(self.problems().iter()
+            .filter(|problem| problem.lint_tool() != Some(LintTool::Clippy))
+            .cloned()
+            .collect())
+    }
+
+    /// Returns the problems reported by clippy lints.
+    pub fn clippy_only(&self) -> Check {
+        Check::from_problems(self.problems().iter()
+            .filter(|problem| problem.lint_tool() == Some(LintTool::Clippy))
+            .cloned()
+            .collect())
+    }
+
+    /// Returns a one-line summary of the check for logs, in the stable format
+    /// ```lintparser: 2 errors, 14 warnings, 9 files```, or 
+    /// ```lintparser: clean``` when no problems were found.
+    /// 
+    /// The counts are always followed by the plural nouns, so the line can be
+    /// matched with a fixed pattern.
+    pub fn summary_line(&self) -> String {
+        if self.problems().is_empty() {
+            return String::from("lintparser: clean");
+        }
+        let stats = self.stats();
+        format!("lintparser: {} errors, {} warnings, {} files",
+            stats.errors, stats.warnings, stats.files)
+    }
+
+    /// Returns a hash of the problems that changes whenever the problems,
+    /// their positions or their help and notes do, but not with their order
+    /// or how their paths are spelled. It is stable across runs and releases,
+    /// so it can be stored to tell cheaply whether anything changed.
+    pub fn digest(&self) -> u64 {
+        let mut keys: Vec<String> = self.problems().iter().map(|problem| {
+            let mut key = format!("{}\0{}:{}", problem.fingerprint(),
+                problem.message.start_line, problem.message.start_col);
+            for note in problem.help.iter().chain(problem.notes.iter()) {
+                key.push_str(&format!("\0{}", note));
+            }
+            key
+        }).collect();
+        keys.sort();
+        stable_hash(&keys.join("\n"))
+    }
+
+    /// Compares the problems against the current contents of their files
+    /// (relative to ```root```), and returns the problems whose files have
+    /// been deleted or no longer contain the lines of their spans.
+    pub fn verify_against_disk<P: AsRef<Path>>(&self, root: P) -> Vec<StaleProblem> {
+        let root = root.as_ref();
+        let mut line_counts: HashMap<&str, Option<usize>> = HashMap::new();
+        let mut stale = Vec::new();
+        for (index, problem) in self.problems().iter().enumerate() {
+            let line_count = *line_counts.entry(&problem.filepath).or_insert_with(|| {
+                fs::read_to_string(root.join(&*problem.filepath)).ok()
+                    .map(|source| source.lines().count())
+            });
+            if let Some(reason) = verify_problem(problem, line_count) {
+                stale.push(StaleProblem {
+                    index,
+                    filepath: problem.filepath.clone(),
+                    reason,
+                });
+            }
+        }
+        stale
+    }
+}
+
+/// Why a problem no longer matches the file it was reported in.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StaleReason {
+    /// The file could not be read (it was deleted, renamed or is unreadable).
+    Missing,
+    /// The file has fewer lines than the spans of the problem reach.
+    TooShort { line_count: usize },
+}
+
+/// A problem whose spans are no longer valid for the file on disk.
+#[derive(Debug, Clone)]
+pub struct StaleProblem {
+    /// The index of the problem in ```Check::problems```.
+    pub index: usize,
+    pub filepath: Arc<str>,
+    pub reason: StaleReason,
+}
+
+/// Checks that a file with the given number of lines (if it could be read)
+/// still contains every span of the problem.
+fn verify_problem(problem: &ProblemDescription, line_count: Option<usize>) 
+        -> Option<StaleReason> {
+    let line_count = match line_count {
+        Some(count) => count,
+        None => return Some(StaleReason::Missing),
+    };
+    let last_line = problem.help.iter().chain(problem.notes.iter())
+        .map(|note| note.end_line)
+        .fold(problem.message.end_line, std::cmp::max);
+    if last_line > line_count {
+        Some(StaleReason::TooShort { line_count })
+    } else {
+        None
+    }
+}
+
+/// The result of a check, along with information about the run.
+#[derive(Debug)]
+pub struct CheckReport {
+    pub check: Check,
+    /// When the check was run.
+    pub time: SystemTime,
+    /// The commit that was checked, if known.
+    pub commit: Option<String>,
+    /// The blocks of the output that were not parsed into problems.
+    pub raw_blocks: Vec<RawBlock>,
+    /// The ways in which the check deviated from what was requested.
+    pub fallbacks: Vec<Fallback>,
+    /// The mappings that were applied to the paths of the problems.
+    pub path_maps: Vec<PathMap>,
+    /// The ID of the check in its trace events, when it was run by a
+    /// ```Checker```.
+    pub correlation_id: Option<String>,
+    /// The status of each workspace member, when the check was run with
+    /// ```Checker::keep_going```.
+    pub members: BTreeMap<String, MemberStatus>,
+    /// Whether the check was stopped by ```Checker::timeout```.
+    pub timed_out: bool,
+    /// Whether the check was stopped by a ```CancelToken```.
+    pub cancelled: bool,
+    /// The units that cargo says could not be compiled.
+    pub failed_units: Vec<FailedUnit>,
+    /// The units that cargo compiled, checked or found fresh, in the order
+    /// of the output.
+    pub units: Vec<UnitLine>,
+    /// The failed attempts before the one of the report, with
+    /// ```Checker::retries```.
+    pub attempts: Vec<Attempt>,
+    /// The time and memory that cargo took, when the check was run by a
+    /// ```Checker```.
+    pub resource: Option<ResourceUsage>,
+    /// The hashes of the files of the problems when the report was
+    /// published, by their paths (see ```CheckReport::mark_drift```).
+    pub source_hashes: BTreeMap<String, u64>,
+    /// The canonical directory of the project, when the check was run by a
+    /// ```Checker```.
+    pub root: Option<PathBuf>,
+}
+
+/// A way in which a check had to deviate from what was requested.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fallback {
+    /// Clippy isn't installed, so ```cargo check``` was run instead.
+    ClippyNotInstalled,
+    /// The toolchain doesn't support ```--keep-going```, so the check stopped
+    /// at the first member that failed to compile.
+    KeepGoingUnsupported,
+    /// The toolchain doesn't have ```cargo check```, so ```cargo build``` was
+    /// run instead.
+    CheckUnsupported,
+    /// Cargo can't write JSON messages, so the text of the short message
+    /// format was read instead.
+    JsonUnsupported,
+}
+
+impl CheckReport {
+    /// Creates a report of a check that was just run.
+    pub fn new(check: Check) -> CheckReport {
+        CheckReport {
+            check,
+            time: SystemTime::now(),
+            commit: None,
+            raw_blocks: Vec::new(),
+            fallbacks: Vec::new(),
+            path_maps: Vec::new(),
+            correlation_id: None,
+            members: BTreeMap::new(),
+            timed_out: false,
+            cancelled: false,
+            failed_units: Vec::new(),
+            units: Vec::new(),
+            attempts: Vec::new(),
+            resource: None,
+            source_hashes: BTreeMap::new(),
+            root: None,
+        }
+    }
+
+    /// Returns the summary line of the check (see ```Check::summary_line```),
+    /// followed by the units that failed to compile, if any, and the digest
+    /// of the check, like ```lintparser: 3 errors, 0 warnings, 2 files;
+    /// failed: mypkg (lib); digest 5e0c7a9d2f14b3c8```.
+    pub fn summary_line(&self) -> String {
+        let mut line = self.check.summary_line();
+        if ! self.failed_units.is_empty() {
+            let units: Vec<String> = self.failed_units.iter().map(FailedUnit::to_string).collect();
+            line.push_str(&format!("; failed: {}", units.join(", ")));
+        }
+        line.push_str(&format!("; digest {:016x}", self.check.digest()));
+        line
+    }
+
+    /// Whether the check has the digest of an earlier one (see
+    /// ```Check::digest```), so nothing changed since.
+    pub fn unchanged_since(&self, previous_digest: u64) -> bool {
+        self.check.digest() == previous_digest
+    }
+
+    /// Returns the lines of the output that weren't understood, in order.
+    /// Blank lines, progress lines and the summaries of compilations aren't
+    /// included.
+    pub fn ignored_lines(&self) -> Vec<&str> {
+        self.raw_blocks.iter()
+            .filter(|block| block.reason == SkipReason::UnrecognizedFormat)
+            .flat_map(|block| block.lines.lines())
+            .filter(|line| ! line.trim().is_empty())
+            .collect()
+    }
+
+    /// Whether the check was stopped before cargo finished, in which case
+    /// the problems (and their counts) are only those reported until then.
+    pub fn is_partial(&self) -> bool {
+        self.timed_out || self.cancelled
+    }
+}
+
+/// Hashes the text with 64-bit FNV-1a, which unlike the std hashers is
+/// stable across releases.
+fn stable_hash(text: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in text.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
 
 /// A problem found when using the cargo check linter.
+///
+/// Kinds of errors may be added in minor versions, so matches need a
+/// wildcard arm, or ```CheckError::kind``` to branch on.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum CheckError {
-    InvalidDirectory,
+    /// The directory doesn't exist or has no ```Cargo.toml```.
+    InvalidDirectory(PathBuf),
+    /// Cargo could not be started, as it isn't installed or on the
+    /// ```PATH```.
+    CargoNotFound(io::Error),
     IoError(io::Error),
+    /// The check timed out, with ```Checker::strict_timeout```.
+    TimedOut,
+    /// The check was cancelled, with ```Checker::strict_timeout```.
+    Cancelled,
+    /// The toolchain lacks a component that the check needs, e.g. ```clippy```
+    /// or ```rust-src```. The hint is the command that installs it.
+    ComponentMissing { component: String, install_hint: String },
+    /// Cargo failed without any diagnostics that could be parsed, e.g. as
+    /// its manifest is invalid. The status is ```None``` if it was killed.
+    CargoFailed { status: Option<i32>, stderr: String },
 }
 impl From<io::Error> for CheckError {
     fn from(err: io::Error) -> CheckError {
@@ -27,8 +446,117 @@ impl From<io::Error> for CheckError {
     }
 }
 
+impl CheckError {
+    /// Returns the error of starting cargo, which is ```CargoNotFound``` if
+    /// there is no cargo to start.
+    fn spawning_cargo(err: io::Error) -> CheckError {
+        match err.kind() {
+            io::ErrorKind::NotFound => CheckError::CargoNotFound(err),
+            _ => CheckError::IoError(err),
+        }
+    }
+
+    /// Returns a stable snake_case code for the kind of error, for tools to
+    /// branch on. Codes are never changed or reused once released.
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            CheckError::InvalidDirectory(_) => "invalid_directory",
+            CheckError::CargoNotFound(_) => "cargo_not_found",
+            CheckError::IoError(_) => "io_error",
+            CheckError::TimedOut => "timed_out",
+            CheckError::Cancelled => "cancelled",
+            CheckError::ComponentMissing { .. } => "component_missing",
+            CheckError::CargoFailed { .. } => "cargo_failed",
+        }
+    }
+
+    /// Whether running the check again may succeed without any change to
+    /// the project or the toolchain.
+    pub fn is_retryable(&self) -> bool {
+        match *self {
+            CheckError::TimedOut | CheckError::Cancelled => true,
+            CheckError::IoError(ref err) => matches!(err.kind(), io::ErrorKind::Interrupted
+                | io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut),
+            CheckError::InvalidDirectory(_) | CheckError::CargoNotFound(_)
+                | CheckError::ComponentMissing { .. }
+                | CheckError::CargoFailed { .. } => false,
+        }
+    }
+
+    /// What went wrong, without the kind.
+    fn description(&self) -> String {
+        match *self {
+            CheckError::InvalidDirectory(ref dir) => format!(
+                "the directory '{}' isn't a cargo project that can be checked", dir.display()),
+            CheckError::CargoNotFound(ref err) => format!("cargo could not be run: {}", err),
+            CheckError::IoError(ref err) => err.to_string(),
+            CheckError::TimedOut => String::from("the check timed out"),
+            CheckError::Cancelled => String::from("the check was cancelled"),
+            CheckError::ComponentMissing { ref component, ref install_hint } => format!(
+                "the toolchain lacks the '{}' component, install it with: {}",
+                component, install_hint),
+            CheckError::CargoFailed { status, ref stderr } => {
+                let status = status.map_or(String::from("was killed"),
+                    |status| format!("exited with status {}", status));
+                match stderr.lines().find(|line| line.starts_with("error")) {
+                    Some(line) => format!("cargo {}: {}", status, line),
+                    None => format!("cargo {}", status),
+                }
+            },
+        }
+    }
+
+    /// Returns the error as a JSON object with its ```kind```, its
+    /// ```message```, whether it is ```retryable``` and, for a missing
+    /// component, the ```component``` and its ```install_hint```, or for a
+    /// failed cargo, its ```status``` and ```stderr```.
+    pub fn to_json(&self) -> String {
+        let mut members = vec![
+            ("kind", json::Value::from(self.kind())),
+            ("message", json::Value::from(self.description())),
+            ("retryable", json::Value::from(self.is_retryable())),
+        ];
+        if let CheckError::ComponentMissing { ref component, ref install_hint } = *self {
+            members.push(("component", json::Value::from(&component[..])));
+            members.push(("install_hint", json::Value::from(&install_hint[..])));
+        }
+        if let CheckError::CargoFailed { status, ref stderr } = *self {
+            members.push(("status", json::Value::from(status.map(|status|
+                json::Value::Number(status.to_string())))));
+            members.push(("stderr", json::Value::from(&stderr[..])));
+        }
+        json::Value::object(members).to_string()
+    }
+}
+
+/// Formats the error as ```[kind] message```.
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "[{}] {}", self.kind(), self.description())
+    }
+}
+
+impl error::Error for CheckError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            CheckError::CargoNotFound(ref err) | CheckError::IoError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
 /// A note about a span in the source file.
-#[derive(Debug, Clone)]
+/// 
+/// Lines and columns are 1-based, and the end position is exclusive.
+/// Columns count characters (Unicode scalar values) as rustc does in both
+/// its text and its JSON output: a tab or an ```α``` is a single column,
+/// whatever its width or its length in bytes.
+///
+/// Fields may be added in minor versions, so notes are created with
+/// ```Note::new``` and its ```with_``` methods rather than literally.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[non_exhaustive]
 pub struct Note {
     pub start_line: usize,
     /// In characters.
@@ -37,6 +565,15 @@ pub struct Note {
     /// In characters.
     pub end_col: usize,
     pub message: String,
+    /// The 0-based byte offset of the start in the file, when the output
+    /// gives it (only the JSON messages do).
+    pub byte_start: Option<usize>,
+    /// The byte offset of the end in the file, exclusive.
+    pub byte_end: Option<usize>,
+    /// The file of the span of a help or note, which may be another than
+    /// the file of its problem. ```None``` for the spans of the problem
+    /// itself, for children without a span, and when the output doesn't say.
+    pub file: Option<Arc<str>>,
 }
 
 impl Note {
@@ -45,12 +582,107 @@ impl Note {
             -> Note 
             where T: Into<String> {
         Note {
-            start_line: start_line, start_col: start_col, end_line: end_line,
-            end_col: end_col, message: message.into()
+            start_line, start_col, end_line, end_col, message: message.into(),
+            byte_start: None, byte_end: None, file: None,
+        }
+    }
+
+    /// Sets the 0-based byte offsets of the start and the (exclusive) end
+    /// of the span in its file.
+    pub fn with_byte_range(mut self, start: usize, end: usize) -> Note {
+        self.byte_start = Some(start);
+        self.byte_end = Some(end);
+        self
+    }
+
+    /// Sets the file of the span.
+    pub fn with_file<T: Into<Arc<str>>>(mut self, file: T) -> Note {
+        self.file = Some(file.into());
+        self
+    }
+
+    /// Returns the 0-based range of the characters of the line of text
+    /// (the first line of the span) that the note spans. A span over
+    /// several lines runs to the end of the line, and the range is clamped
+    /// to the line.
+    pub fn char_range_in(&self, line_text: &str) -> Range<usize> {
+        let length = line_text.trim_end_matches(['\r', '\n']).chars().count();
+        let start = self.start_col.saturating_sub(1).min(length);
+        let end = if self.end_line == self.start_line {
+            self.end_col.saturating_sub(1).clamp(start, length)
+        } else {
+            length
+        };
+        start..end
+    }
+
+    /// Creates a note spanning the given byte range of the source text.
+    /// 
+    /// Panics if the range is out of bounds or not on character boundaries.
+    pub fn from_byte_range<T>(source: &str, range: Range<usize>, message: T) 
+            -> Note 
+            where T: Into<String> {
+        let _ = &source[range.clone()];
+        let index = LineIndex::new(source);
+        let (start_line, start_col) = index.position(source, range.start);
+        let (end_line, end_col) = index.position(source, range.end);
+        Note {
+            byte_start: Some(range.start),
+            byte_end: Some(range.end),
+            ..Note::new(start_line, start_col, end_line, end_col, message)
+        }
+    }
+
+    /// Returns the byte range of the source text that this note spans, or 
+    /// ```None``` if the span does not fit in the text.
+    pub fn to_byte_range(&self, source: &str) -> Option<Range<usize>> {
+        let index = LineIndex::new(source);
+        let start = index.offset(source, self.start_line, self.start_col)?;
+        let end = index.offset(source, self.end_line, self.end_col)?;
+        if start <= end {
+            Some(start..end)
+        } else {
+            None
+        }
+    }
+}
+
+/// The byte offsets at which the lines of a source text start.
+struct LineIndex {
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    fn new(source: &str) -> LineIndex {
+        let mut starts = vec![0];
+        starts.extend(source.match_indices('\n').map(|(i, _)| i + 1));
+        LineIndex { starts }
+    }
+
+    /// Converts a byte offset to a 1-based line and character column.
+    fn position(&self, source: &str, offset: usize) -> (usize, usize) {
+        let line = self.starts.partition_point(|&start| start <= offset);
+        let line_start = self.starts[line - 1];
+        (line, source[line_start..offset].chars().count() + 1)
+    }
+
+    /// Converts a 1-based line and character column to a byte offset.
+    /// The column may point just past the last character of the line.
+    fn offset(&self, source: &str, line: usize, col: usize) -> Option<usize> {
+        if line == 0 || col == 0 {
+            return None;
         }
+        let line_start = *self.starts.get(line - 1)?;
+        let line_end = self.starts.get(line).map(|&next| next - 1)
+            .unwrap_or(source.len());
+        let text = &source[line_start..line_end];
+        let mut chars = text.char_indices().map(|(i, _)| i).chain(Some(text.len()));
+        chars.nth(col - 1).map(|i| line_start + i)
     }
 }
 
+/// Formats the note as ```start_line:start_col: end_line:end_col: message```,
+/// which ```Note::from_str``` reads back.
 impl fmt::Display for Note {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         write!(f, "{}:{}: {}:{}: {}", self.start_line, self.start_col, 
@@ -59,79 +691,801 @@ impl fmt::Display for Note {
     }
 }
 
+/// The text wasn't a note as formatted by its ```Display``` implementation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseNoteError;
+
+impl fmt::Display for ParseNoteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "expected a note like '1:5: 1:9: message'")
+    }
+}
+
+/// Parses a note as formatted by its ```Display``` implementation. The four
+/// positions are read first, so the message may contain anything.
+impl FromStr for Note {
+    type Err = ParseNoteError;
+
+    fn from_str(text: &str) -> Result<Note, ParseNoteError> {
+        let number = |field: &str| -> Result<usize, ParseNoteError> {
+            if field.is_empty() || ! field.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(ParseNoteError);
+            }
+            field.parse().map_err(|_| ParseNoteError)
+        };
+        let position = |field: &str| -> Result<(usize, usize), ParseNoteError> {
+            let (line, col) = field.split_once(':').ok_or(ParseNoteError)?;
+            Ok((number(line)?, number(col)?))
+        };
+        let (start, rest) = text.split_once(": ").ok_or(ParseNoteError)?;
+        let (end, message) = rest.split_once(": ").ok_or(ParseNoteError)?;
+        let (start_line, start_col) = position(start)?;
+        let (end_line, end_col) = position(end)?;
+        Ok(Note::new(start_line, start_col, end_line, end_col, message))
+    }
+}
+
+/// How severe a problem is. Standalone notes and help only come from the
+/// JSON messages of cargo (see ```parse_json_messages```), as in the text
+/// output they can't be told apart from the children of another problem.
+///
+/// Severities are ordered by how severe they are: help is the least severe,
+/// then notes, warnings and errors. Severities may be added in minor
+/// versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[non_exhaustive]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+impl Severity {
+    /// Returns the name of the severity, as rustc writes it.
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+            Severity::Help => "help",
+        }
+    }
+
+    /// Returns how severe the severity is, the higher the more.
+    fn rank(self) -> u8 {
+        match self {
+            Severity::Help => 0,
+            Severity::Note => 1,
+            Severity::Warning => 2,
+            Severity::Error => 3,
+        }
+    }
+
+    /// Returns the severity with the name.
+    fn from_name(name: &str) -> Option<Severity> {
+        match name {
+            "error" => Some(Severity::Error),
+            "warning" => Some(Severity::Warning),
+            "note" => Some(Severity::Note),
+            "help" => Some(Severity::Help),
+            _ => None,
+        }
+    }
+}
+
+impl PartialOrd for Severity {
+    fn partial_cmp(&self, other: &Severity) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Severity {
+    fn cmp(&self, other: &Severity) -> Ordering {
+        self.rank().cmp(&other.rank())
+    }
+}
+
+/// Returns the severity of the most severe of the problems.
+fn worst_severity(problems: &[ProblemDescription]) -> Option<Severity> {
+    problems.iter().map(|problem| problem.severity).max()
+}
+
+/// The beginnings of the notes that rustc points at the enclosing item with.
+const ENCLOSING_ITEM_NOTES: &[&str] = &[
+    "field in this struct",
+    "fields in this struct",
+    "field in this variant",
+    "fields in this variant",
+    "variant in this enum",
+    "variants in this enum",
+    "associated function in this implementation",
+    "associated functions in this implementation",
+    "associated items in this implementation",
+];
+
+/// Returns the lint named by the first ```#[warn(...)]``` (or deny/forbid)
+/// attribute in the text.
+fn lint_attribute(text: &str) -> Option<&str> {
+    ["#[warn(", "#[deny(", "#[forbid("].iter()
+        .filter_map(|attribute| text.find(attribute).map(|start| start + attribute.len()))
+        .min()
+        .and_then(|start| {
+            let rest = &text[start..];
+            rest.find(')').map(|end| &rest[..end])
+        })
+}
+
+/// A change to the source code suggested by a diagnostic.
+///
+/// Fields may be added in minor versions, so suggestions are created with
+/// ```Suggestion::new``` and its ```with_``` methods rather than literally.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[non_exhaustive]
+pub struct Suggestion {
+    /// The span to replace, with the message that suggested the change. An
+    /// empty span is an insertion, and an empty replacement a deletion.
+    pub span: Note,
+    /// The replacement text, verbatim.
+    pub replacement: String,
+    pub applicability: Applicability,
+    pub target: SuggestionTarget,
+}
+
+/// Where a suggestion changes the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub enum SuggestionTarget {
+    /// The span of the suggestion, in the file of its problem.
+    Span,
+    /// An insertion among the crate attributes at the top of the crate root
+    /// (after a shebang, the inner doc comments and the existing inner 
+    /// attributes). Only the message of the span is used.
+    CrateRoot,
+    /// An insertion at the top of the file of its problem, after the same
+    /// shebang, inner doc comments and inner attributes, like an import.
+    /// Only the message of the span is used.
+    FileTop,
+}
+
+/// How confident the compiler is that a suggestion is what the user wants.
+/// Rustc may add levels, and so may minor versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[non_exhaustive]
+pub enum Applicability {
+    /// The suggestion is definitely correct, and can be applied without
+    /// a review.
+    MachineApplicable,
+    /// The suggestion may be what the user wants, but needs a review.
+    MaybeIncorrect,
+    /// The suggestion contains placeholders like ```(...)``` to fill in.
+    HasPlaceholders,
+    Unspecified,
+}
+
+/// How much of the work of fixing a problem its suggestions do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Fixability {
+    /// A suggestion can be applied without a review.
+    Automatic,
+    /// A suggestion needs a review, or placeholders filled in.
+    Assisted,
+    /// There are no suggestions.
+    Manual,
+}
+
+/// How sure the parser is that it read a problem correctly.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub enum Confidence {
+    /// Only the fixed format of the output was used.
+    #[default]
+    Exact,
+    /// The problem was assembled with heuristics that can guess wrong, by
+    /// name (see ```HEURISTIC_NAMES```).
+    Heuristic(
+        #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_heuristics"))]
+        Vec<&'static str>
+    ),
+}
+
+/// Reads the names of heuristics as the ones of ```HEURISTIC_NAMES```.
+#[cfg(feature = "serde")]
+fn deserialize_heuristics<'de, D>(deserializer: D) -> Result<Vec<&'static str>, D::Error>
+        where D: ::serde::Deserializer<'de> {
+    use serde::de::{Deserialize, Error};
+    Vec::<String>::deserialize(deserializer)?.iter()
+        .map(|name| HEURISTIC_NAMES.iter().find(|&&known| known == name).cloned()
+            .ok_or_else(|| D::Error::custom(format!("unknown heuristic '{}'", name))))
+        .collect()
+}
+
+impl Confidence {
+    /// Whether no heuristics were used.
+    pub fn is_exact(&self) -> bool {
+        *self == Confidence::Exact
+    }
+}
+
+/// The names of the heuristics of the parser, in the order of their flags.
+pub const HEURISTIC_NAMES: &[&str] = &[
+    // A line of a block was taken as the start of the visual aids
+    "visual-aid",
+    // Lines of a block were taken as the rest of the message
+    "message-continuation",
+    // A position was too large and saturated
+    "saturated-position",
+    // A help or note in another file was added to the problem before it
+    "child-in-other-file",
+    // A suggestion was read from the text of a help message
+    "suggestion-from-help",
+    // Spans were read from the columns of the underlines of the visual aids
+    "underline-columns",
+];
+
+/// The heuristics used for a problem, as flags by their index in
+/// ```HEURISTIC_NAMES```.
+#[derive(Debug, Clone, Copy, Default)]
+struct Heuristics(u8);
+
+impl Heuristics {
+    const VISUAL_AID: u8 = 1;
+    const MESSAGE_CONTINUATION: u8 = 1 << 1;
+    const SATURATED_POSITION: u8 = 1 << 2;
+    const CHILD_IN_OTHER_FILE: u8 = 1 << 3;
+    const SUGGESTION_FROM_HELP: u8 = 1 << 4;
+    const UNDERLINE_COLUMNS: u8 = 1 << 5;
+
+    fn insert(&mut self, flags: u8) {
+        self.0 |= flags;
+    }
+
+    fn confidence(self) -> Confidence {
+        if self.0 == 0 {
+            return Confidence::Exact;
+        }
+        Confidence::Heuristic(HEURISTIC_NAMES.iter().enumerate()
+            .filter(|&(i, _)| self.0 & (1 << i) != 0)
+            .map(|(_, &name)| name)
+            .collect())
+    }
+}
+
+impl Suggestion {
+    /// Creates a suggestion to replace the span, in the file of its problem,
+    /// with the text.
+    pub fn new<T: Into<String>>(span: Note, replacement: T, applicability: Applicability)
+            -> Suggestion {
+        Suggestion {
+            span,
+            replacement: replacement.into(),
+            applicability,
+            target: SuggestionTarget::Span,
+        }
+    }
+
+    /// Sets where the suggestion changes the source.
+    pub fn with_target(mut self, target: SuggestionTarget) -> Suggestion {
+        self.target = target;
+        self
+    }
+
+    /// Creates a suggestion to insert an import at the top of the file. It
+    /// may be incorrect, as it is one of several candidates.
+    fn import<T: Into<String>>(path: &str, message: T) -> Suggestion {
+        Suggestion {
+            span: Note::new(1, 1, 1, 1, message),
+            replacement: format!("use {};\n", path),
+            applicability: Applicability::MaybeIncorrect,
+            target: SuggestionTarget::FileTop,
+        }
+    }
+
+    /// Creates a suggestion to add an attribute like ```#![feature(...)]```
+    /// to the crate root. It may be incorrect, as e.g. enabling a feature
+    /// is a decision to make.
+    fn crate_attribute<T: Into<String>>(attribute: &str, message: T) -> Suggestion {
+        Suggestion {
+            span: Note::new(1, 1, 1, 1, message),
+            replacement: format!("{}\n", attribute),
+            applicability: Applicability::MaybeIncorrect,
+            target: SuggestionTarget::CrateRoot,
+        }
+    }
+
+    /// Creates a suggestion to delete the span of a help message, like the
+    /// ```remove the unused import``` help. It may be incorrect, as the text
+    /// doesn't say how applicable it is.
+    fn removal(span: Note) -> Suggestion {
+        Suggestion {
+            span,
+            replacement: String::new(),
+            applicability: Applicability::MaybeIncorrect,
+            target: SuggestionTarget::Span,
+        }
+    }
+}
+
+/// Whether a help message suggests deleting its span.
+fn suggests_removal(message: &str) -> bool {
+    message.starts_with("remove the unused import") || message == "remove the whole `use` item"
+}
+
+/// Returns the attribute that a help message suggests adding to the crate,
+/// like ```add `#![feature(box_syntax)]` to the crate attributes to enable```.
+fn suggested_crate_attribute(message: &str) -> Option<&str> {
+    let rest = &message[message.find("add `#![")? + "add `".len()..];
+    let end = rest.find("` to the crate attributes")?;
+    Some(&rest[..end])
+}
+
+/// Returns the path that a help message suggests importing, like the 
+/// ```candidate #1: use `std::io::Read` ``` help of a missing trait method.
+fn suggested_import(message: &str) -> Option<&str> {
+    let candidate = message.strip_prefix("candidate #")?;
+    let rest = &candidate[candidate.find(": use `")? + ": use `".len()..];
+    rest.find('`').map(|end| &rest[..end])
+}
+
+/// The tool that a lint belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintTool {
+    Rustc,
+    Clippy,
+}
+
 /// A problem found in the code of a file during linting.
-#[derive(Debug, Clone)]
+///
+/// Fields may be added in minor versions, so problems are created with
+/// ```ProblemDescription::new``` and its ```with_``` methods, and the
+/// other fields set afterwards, rather than literally.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[non_exhaustive]
 pub struct ProblemDescription {
-    pub filepath: String,
+    pub filepath: Arc<str>,
+    pub severity: Severity,
     pub message: Note,
     pub help: Vec<Note>,
     pub notes: Vec<Note>,
+    /// The changes to the code suggested by the help messages.
+    pub suggestions: Vec<Suggestion>,
+    /// How long after the start of the check the problem was emitted, when
+    /// the output was read while cargo was running.
+    pub emitted_at: Option<Duration>,
+    /// The package cargo was checking when the problem was emitted.
+    pub during_package: Option<String>,
+    /// The target of the package, like ```lib``` or ```bin "cli"```, as
+    /// cargo names them. Only the JSON messages say it.
+    pub target: Option<String>,
+    /// Whether the problem is in code that only tests use, if known (see
+    /// ```Check::mark_test_code```).
+    pub in_test_code: Option<bool>,
+    /// Whether heuristics were used to read the problem from the output.
+    pub parse_confidence: Confidence,
+    /// Spans that are as much the subject of the problem as the one of its
+    /// message, like the other names of ```unused imports: `a`, `b` ```.
+    /// They share the message of the problem.
+    pub additional_primary_spans: Vec<Note>,
+    /// The module of the file, like ```config::loader```, if it is under a
+    /// ```src``` directory (see ```Check::by_module```).
+    pub module: Option<String>,
+    /// The code of the diagnostic: an error code like ```E0308```, or in the
+    /// JSON messages of rustc also the name of a lint. Error codes stay at
+    /// the end of the message too, as rustc used to write them, so that
+    /// fingerprints don't change with the format of the output.
+    pub code: Option<String>,
+    /// The macro that the problem originates in, as its notes say.
+    pub from_macro: Option<MacroOrigin>,
+    /// The requirements that lead to the unsatisfied bound of a trait-bound
+    /// error, in the order of its notes, which are kept as well.
+    pub bound_chain: Vec<BoundStep>,
+    /// The first line of the span as the diagnostic quoted it, for telling
+    /// whether the file has changed since (see ```Check::mark_drift```).
+    pub source_line: Option<String>,
+    /// Whether the file no longer has the code of the problem where its
+    /// span is, as found by ```Check::mark_drift```.
+    pub drifted: bool,
+    /// The diagnostic as rustc printed it, with its snippet and underlines,
+    /// without its final line break. It has the text of the help and notes
+    /// that were printed as diagnostics of their own too.
+    pub rendered: Option<String>,
+    /// How many times the problem was reported, which is more than once
+    /// when ```Check::dedup``` merged its duplicates into it.
+    pub count: usize,
+    /// The targets that the problem and its duplicates were reported for,
+    /// when ```Check::dedup``` merged duplicates of other targets into it.
+    pub targets: Vec<String>,
+}
+
+/// The kinds of macros that problems can originate in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub enum MacroKind {
+    /// ```#[derive(Debug)]```.
+    Derive,
+    /// ```#[tokio::main]```.
+    Attribute,
+    /// ```vec![]```.
+    Bang,
+}
+
+/// The macro of a problem, from a note like ```this error originates in the
+/// attribute macro `tokio::main` ```.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct MacroOrigin {
+    pub kind: MacroKind,
+    pub name: String,
+}
+
+/// Returns the macro that the note says a problem originates in.
+fn macro_origin(note: &str) -> Option<MacroOrigin> {
+    let rest = &note[note.find(" originates in the ")? + " originates in the ".len()..];
+    let (kind, rest) = if let Some(rest) = rest.strip_prefix("derive macro `") {
+        (MacroKind::Derive, rest)
+    } else if let Some(rest) = rest.strip_prefix("attribute macro `") {
+        (MacroKind::Attribute, rest)
+    } else {
+        (MacroKind::Bang, rest.strip_prefix("macro `")?)
+    };
+    let name = &rest[..rest.find('`')?];
+    Some(MacroOrigin { kind, name: String::from(name) })
 }
 
 impl ProblemDescription {
-    /// Creates a new problem description.
+    /// Creates a new problem description with the ```Warning``` severity.
+    #[allow(clippy::too_many_arguments)]
     pub fn new<T, N>(filepath: T, start_line: usize, start_col: usize, end_line: usize, 
             end_col: usize, message: T, help: N, notes: N) 
             -> ProblemDescription 
             where T: Into<String>, N: Into<Vec<Note>> {
         let message = Note::new(start_line, start_col, end_line, end_col, message);
+        let filepath: Arc<str> = Arc::from(filepath.into());
+        let in_test_code = if testcode::in_test_directory(&filepath) { Some(true) } else { None };
+        let module = grouping::module_path(&filepath);
         ProblemDescription {
-            filepath: filepath.into(),
-            message: message,
+            filepath,
+            severity: Severity::Warning,
+            message,
             help: help.into(),
             notes: notes.into(),
+            suggestions: Vec::new(),
+            emitted_at: None,
+            during_package: None,
+            target: None,
+            in_test_code,
+            parse_confidence: Confidence::Exact,
+            additional_primary_spans: Vec::new(),
+            module,
+            code: None,
+            from_macro: None,
+            bound_chain: Vec::new(),
+            source_line: None,
+            drifted: false,
+            rendered: None,
+            count: 1,
+            targets: Vec::new(),
         }
     }
-}
 
-impl fmt::Display for ProblemDescription {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        try!(write!(f, "{}:", self.filepath));
-        try!(fmt::Display::fmt(&self.message, f));
-        for help in &self.help {
-            try!(write!(f, " (help: "));
-            try!(fmt::Display::fmt(help, f));
-            try!(write!(f, ")"));
-        }
-        for note in &self.notes {
-            try!(write!(f, " (note: "));
-            try!(fmt::Display::fmt(note, f));
-            try!(write!(f, ")"));
+    pub fn with_severity(mut self, severity: Severity) -> ProblemDescription {
+        self.severity = severity;
+        self
+    }
+
+    /// Sets the code of the diagnostic, like ```E0308``` or the name of a
+    /// lint.
+    pub fn with_code<T: Into<String>>(mut self, code: T) -> ProblemDescription {
+        self.code = Some(code.into());
+        self
+    }
+
+    /// Adds a suggested change to the code.
+    pub fn with_suggestion(mut self, suggestion: Suggestion) -> ProblemDescription {
+        self.suggestions.push(suggestion);
+        self
+    }
+
+    /// Adds a span that shares the message of the problem.
+    pub fn with_primary_span(mut self, span: Note) -> ProblemDescription {
+        self.additional_primary_spans.push(span);
+        self
+    }
+
+    /// Sets the package and the target, as cargo names them, that the
+    /// problem was reported for.
+    pub fn with_target<P, T>(mut self, package: P, target: T) -> ProblemDescription
+            where P: Into<String>, T: Into<String> {
+        self.during_package = Some(package.into());
+        self.target = Some(target.into());
+        self
+    }
+
+    /// Sets the macro of the problem from its notes.
+    fn find_macro_origin(&mut self) {
+        self.from_macro = self.notes.iter().find_map(|note| macro_origin(&note.message));
+    }
+
+    /// Returns the span in the code of the user to show for the problem.
+    /// The span of a problem from a derive or attribute macro may cover the
+    /// whole item, and then the narrowest of its other spans inside it is
+    /// taken, like the span of a note that points at the cause.
+    pub fn primary_user_span(&self) -> &Note {
+        let span = &self.message;
+        let expanded = self.from_macro.as_ref()
+            .is_some_and(|origin| origin.kind != MacroKind::Bang);
+        if ! expanded || span.start_line == span.end_line {
+            return span;
         }
-        Ok(())
+        let inside = |note: &&Note| (note.start_line, note.start_col) >= (span.start_line,
+            span.start_col) && (note.end_line, note.end_col) <= (span.end_line, span.end_col)
+            && note.start_line > 0;
+        self.additional_primary_spans.iter().chain(&self.notes).chain(&self.help)
+            .filter(inside)
+            .min_by_key(|note| (note.end_line - note.start_line,
+                note.end_col.saturating_sub(note.start_col)))
+            .unwrap_or(span)
     }
-}
 
-const COMPILE_ERROR_LINE: &'static str = "error: aborting due to previous error";
+    /// Returns the name of the lint that reported the problem, as mentioned
+    /// by the ```#[warn(...)]``` (or deny/forbid) level in its message or 
+    /// notes, or else as the code of a problem read from JSON messages.
+    pub fn lint(&self) -> Option<&str> {
+        self.lint_texts().filter_map(lint_attribute).next()
+            .or_else(|| self.code.as_deref().filter(|code| ! messages::is_error_code(code)))
+    }
 
-#[derive(Debug)]
-enum LineParseState {
-    File,
-    StartLine,
-    StartCol,
-    EndLine,
-    EndCol,
-    Level,
-    FirstMessageLine,
-}
+    /// Returns the lint group that enabled the lint of the problem, as in
+    /// ```#[warn(clippy::xyz)]` implied by `#[warn(clippy::pedantic)]```.
+    pub fn lint_group(&self) -> Option<&str> {
+        self.lint_texts()
+            .filter_map(|text| text.find("implied by ").map(|i| &text[i..]))
+            .filter_map(lint_attribute)
+            .next()
+    }
 
-#[derive(Debug)]
-enum Level {
-    Warning,
-    Error,
-    Help,
-    Note,
-}
+    fn lint_texts(&self) -> impl Iterator<Item = &str> {
+        Some(&self.message).into_iter().chain(self.notes.iter())
+            .map(|note| &note.message[..])
+    }
 
-fn parse_check_line(line: &str) -> (Level, ProblemDescription) {
-    use self::LineParseState::*;
-    let mut state = File;
-    let mut filepath = String::new();
-    let mut start_line = 0;
-    let mut start_col = 0;
-    let mut end_line = 0;
-    let mut end_col = 0;
-    let mut level = self::Level::Warning;
+    /// Returns the error code of the problem, like ```E0425```, from its
+    /// code or as given in brackets in its message.
+    pub fn error_code(&self) -> Option<&str> {
+        if let Some(code) = self.code.as_deref().filter(|&code| messages::is_error_code(code)) {
+            return Some(code);
+        }
+        let message = &self.message.message;
+        message.match_indices("[E").find_map(|(start, _)| {
+            let code = message[start + 1..].split(']').next()?;
+            if code.len() == 5 && code[1..].bytes().all(|b| b.is_ascii_digit()) {
+                Some(code)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Returns the span of the message followed by the additional primary
+    /// spans.
+    pub fn primary_spans(&self) -> Vec<&Note> {
+        let mut spans = vec![&self.message];
+        spans.extend(&self.additional_primary_spans);
+        spans
+    }
+
+    /// Returns how much of the fix the suggestions of the problem do. The
+    /// human-readable output doesn't say how applicable its suggestions are,
+    /// so problems parsed from it are ```Assisted``` at best.
+    pub fn fixability(&self) -> Fixability {
+        let applicable = |applicability| self.suggestions.iter()
+            .any(|suggestion| suggestion.applicability == applicability);
+        if applicable(Applicability::MachineApplicable) {
+            Fixability::Automatic
+        } else if self.suggestions.is_empty() {
+            Fixability::Manual
+        } else {
+            Fixability::Assisted
+        }
+    }
+
+    /// Returns the note pointing at the item that encloses the problem, like
+    /// the struct of a field that is never read.
+    pub fn enclosing_item(&self) -> Option<&Note> {
+        self.notes.iter().find(|note| note.start_line > 0
+            && ENCLOSING_ITEM_NOTES.iter().any(|text| note.message.starts_with(text)))
+    }
+
+    /// Returns the help and notes that point at a location in the source, 
+    /// like the earlier match arm that makes a pattern unreachable, for 
+    /// showing as related locations of the problem.
+    pub fn related_spans(&self) -> Vec<&Note> {
+        self.help.iter().chain(self.notes.iter())
+            .filter(|note| note.start_line > 0)
+            .collect()
+    }
+
+    /// Returns a fingerprint that identifies the problem across runs: a hash
+    /// of its file, severity, lint and message, but not its position, so
+    /// that it survives edits elsewhere in the file. Identical problems in
+    /// the same file share a fingerprint.
+    pub fn fingerprint(&self) -> String {
+        let severity = self.severity.name();
+        let message: Vec<&str> = self.message.message.split_whitespace().collect();
+        let key = format!("{}\0{}\0{}\0{}", normalize_path(Path::new(""), &self.filepath).join("/"),
+            severity, self.lint().unwrap_or(""), message.join(" "));
+        format!("{:016x}", stable_hash(&key))
+    }
+
+    /// Returns which tool the lint of the problem belongs to, based on the 
+    /// ```clippy::``` prefix of its name.
+    pub fn lint_tool(&self) -> Option<LintTool> {
+        self.lint().map(|lint| if lint.starts_with("clippy::") {
+            LintTool::Clippy
+        } else {
+            LintTool::Rustc
+        })
+    }
+
+    /// Whether the problem is an error.
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Whether the problem is a warning. Notes and help are neither
+    /// warnings nor errors.
+    pub fn is_warning(&self) -> bool {
+        self.severity == Severity::Warning
+    }
+
+    /// Whether the problem is an error of the linker, which a build reports
+    /// but a check doesn't. It has neither a file nor a span: its path is
+    /// empty and its lines are 0, and the notes have the output of the
+    /// linker.
+    pub fn is_link_error(&self) -> bool {
+        self.filepath.is_empty() && messages::is_link_error(&self.message.message)
+    }
+}
+
+/// Formats the problem for people to read, like rustc does, with its help
+/// and notes on the following lines. This isn't meant to be read back: for
+/// that, see ```Check::to_snapshot``` or ```CheckReport::publish```.
+///
+/// The alternate form (```{:#}```) is the text that rustc printed, if the
+/// problem has it.
+impl fmt::Display for ProblemDescription {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        if let Some(rendered) = self.rendered.as_ref().filter(|_| f.alternate()) {
+            return f.write_str(rendered);
+        }
+        let severity = self.severity.name();
+        // Errors of the linker have no position, as rustc writes them
+        if self.filepath.is_empty() {
+            write!(f, "{}: {}", severity, self.message.message)?;
+        } else {
+            write!(f, "{}:{}:{}: {}: {}", self.filepath, self.message.start_line,
+                self.message.start_col, severity, self.message.message)?;
+        }
+        let children = self.help.iter().map(|note| ("help", note))
+            .chain(self.notes.iter().map(|note| ("note", note)));
+        for (kind, note) in children {
+            write!(f, "\n    {}: {}:{}: {}", kind, note.start_line, note.start_col, note.message)?;
+        }
+        Ok(())
+    }
+}
+
+const COMPILE_ERROR_LINE: &str = "error: aborting due to previous error";
+
+#[derive(Debug)]
+enum LineParseState {
+    File,
+    StartLine,
+    StartCol,
+    EndLine,
+    EndCol,
+    Level,
+    FirstMessageLine,
+}
+
+#[derive(Debug)]
+enum Level {
+    Warning,
+    Error,
+    Help,
+    Note,
+}
+
+/// The thousands separators that log pipelines put into numbers.
+const THOUSANDS_SEPARATORS: &[char] = &[',', '.', '\'', '_', '\u{a0}', '\u{202f}'];
+
+/// Parses a span number, saturating at ```usize::MAX``` instead of overflowing.
+/// Thousands separators are skipped, as in ```2,600,000```, if every group
+/// after the first one has three digits.
+fn parse_number(text: &str) -> Option<usize> {
+    let separator = text.chars().find(|ch| THOUSANDS_SEPARATORS.contains(ch));
+    let digits: String = match separator {
+        Some(separator) => {
+            let groups: Vec<&str> = text.split(separator).collect();
+            let first = groups[0].len();
+            if first == 0 || first > 3 || groups[1..].iter().any(|group| group.len() != 3) {
+                return None;
+            }
+            groups.concat()
+        },
+        None => String::from(text),
+    };
+    if digits.is_empty() || ! digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(digits.bytes().fold(0usize, |number, digit| {
+        number.saturating_mul(10).saturating_add((digit - b'0') as usize)
+    }))
+}
+
+/// Parses the first line of a diagnostic block, or returns ```None``` if it 
+/// isn't in the ```file:line:col: line:col level: message``` format, or the
+/// short format of cargo (see ```parse_short_line```). The error code at
+/// the end of the message is also set as the code of the problem.
+fn parse_check_line(line: &str) -> Option<(Level, ProblemDescription)> {
+    let (level, mut problem) = parse_legacy_line(line).or_else(|| parse_short_line(line))?;
+    problem.code = problem.error_code().map(String::from);
+    Some((level, problem))
+}
+
+/// Parses a line of the ```file:line:col: level: message``` format of
+/// ```cargo check --message-format short```, which has no end of the span.
+/// An error code, as in ```error[E0308]: ...```, goes at the end of the
+/// message, as in the other format.
+fn parse_short_line(line: &str) -> Option<(Level, ProblemDescription)> {
+    // The path may contain colons, so the position is the first pair of
+    // numbers between colons that is followed by a space
+    let (filepath, position) = line.match_indices(':').find_map(|(i, _)| {
+        let mut fields = line[i + 1..].splitn(3, ':');
+        let start_line = parse_number(fields.next()?)?;
+        let start_col = parse_number(fields.next()?)?;
+        let rest = fields.next()?.strip_prefix(' ')?;
+        Some((&line[..i], (start_line, start_col, rest)))
+    })?;
+    let (start_line, start_col, rest) = position;
+    let end = rest.find(':')?;
+    let (level_text, code) = match rest[..end].find('[') {
+        Some(open) => {
+            let code = rest[open + 1..end].strip_suffix(']')?;
+            (&rest[..open], Some(code))
+        },
+        None => (&rest[..end], None),
+    };
+    let level = match level_text {
+        "warning" => Level::Warning,
+        "error" => Level::Error,
+        "help" => Level::Help,
+        "note" => Level::Note,
+        _ => return None,
+    };
+    let mut message = String::from(rest[end + 1..].trim_start());
+    if let Some(code) = code {
+        message.push_str(&format!(" [{}]", code));
+    }
+    let problem = ProblemDescription::new(filepath, start_line, start_col, start_line,
+        start_col, &message[..], vec![], vec![]);
+    Some((level, problem))
+}
+
+/// Parses a line of the ```file:line:col: line:col level: message``` format.
+fn parse_legacy_line(line: &str) -> Option<(Level, ProblemDescription)> {
+    use self::LineParseState::*;
+    let mut state = File;
+    let mut filepath = String::new();
+    let mut start_line = 0;
+    let mut start_col = 0;
+    let mut end_line = 0;
+    let mut end_col = 0;
+    let mut level = self::Level::Warning;
     
     let mut start = 0;
     for (i, ch) in line.char_indices() {
@@ -145,16 +1499,14 @@ fn parse_check_line(line: &str) -> (Level, ProblemDescription) {
             },
             StartLine => {
                 if ch == ':' {
-                    start_line = usize::from_str(&line[start..i])
-                        .expect("Invalid start line from cargo check");
+                    start_line = parse_number(&line[start..i])?;
                     state = StartCol;
                     start = i + ch.len_utf8();
                 }
             },
             StartCol => {
                 if ch == ':' {
-                    start_col = usize::from_str(&line[start..i])
-                        .expect("Invalid start col from cargo check");
+                    start_col = parse_number(&line[start..i])?;
                     state = EndLine;
                     start = i + ch.len_utf8();
                 }
@@ -163,16 +1515,14 @@ fn parse_check_line(line: &str) -> (Level, ProblemDescription) {
                 if ch.is_whitespace() {
                     start = i + ch.len_utf8();
                 } else if ch == ':' {
-                    end_line = usize::from_str(&line[start..i])
-                        .expect("Invalid end line from cargo check");
+                    end_line = parse_number(&line[start..i])?;
                     state = EndCol;
                     start = i + ch.len_utf8();
                 }
             },
             EndCol => {
                 if ch.is_whitespace() {
-                    end_col = usize::from_str(&line[start..i])
-                        .expect("Invalid end col from cargo check");
+                    end_col = parse_number(&line[start..i])?;
                     state = Level;
                     start = i + ch.len_utf8();
                 }
@@ -193,7 +1543,7 @@ fn parse_check_line(line: &str) -> (Level, ProblemDescription) {
                         "note" => {
                             level = self::Level::Note;
                         },
-                        l => panic!("Unknown error warning level: {}", l),
+                        _ => return None,
                     }
                     state = FirstMessageLine;
                 }
@@ -206,12 +1556,20 @@ fn parse_check_line(line: &str) -> (Level, ProblemDescription) {
                         start_col, end_line, end_col, 
                         String::from(&line[start..]), vec![], vec![],
                     );
-                    return (level, problem);
+                    return Some((level, problem));
                 }
             }
         }
     }
-    panic!("The line could not be parsed! '{}'", line);
+    // Diagnostics may have an empty message
+    match state {
+        FirstMessageLine => {
+            let problem = ProblemDescription::new(filepath, start_line, start_col, 
+                end_line, end_col, String::new(), vec![], vec![]);
+            Some((level, problem))
+        },
+        _ => None,
+    }
 }
 
 fn line_is_visual_aid(line: &str) -> bool {
@@ -234,99 +1592,1715 @@ fn line_is_visual_aid(line: &str) -> bool {
             }
         }
     }
-    return false
+    false
 }
 
 /// Runs the ```cargo check``` linter on the current directory and returns
-/// descriptions of the found problems.
+/// descriptions of the found problems (see ```cargo_check_in```).
 pub fn cargo_check() -> Result<Check, CheckError> {
-    use self::Check::*;
-    
-    let mut problems = Vec::new();
-    let output = try!(Command::new("cargo").arg("check").output());
-    if ! output.status.success() {
-        return Err(CheckError::InvalidDirectory);
+    Checker::new().run().map(|report| report.check)
+}
+
+/// Runs the ```cargo check``` linter on the project in the directory, which
+/// must contain a ```Cargo.toml```, and returns descriptions of the found
+/// problems, with absolute paths.
+///
+/// This waits for cargo for as long as it runs. A check that should stop
+/// after a while, or when an editor calls it off, is run by a ```Checker```
+/// with ```Checker::timeout``` or ```Checker::cancel_token```.
+pub fn cargo_check_in<P: AsRef<Path>>(dir: P) -> Result<Check, CheckError> {
+    Checker::new().dir(dir.as_ref()).run().map(|report| report.check)
+}
+
+/// Runs ```cargo build``` on the current directory and returns descriptions
+/// of the found problems (see ```cargo_build_in```).
+pub fn cargo_build() -> Result<Check, CheckError> {
+    cargo_build_in(".")
+}
+
+/// Runs ```cargo build``` on the project in the directory and returns
+/// descriptions of the found problems, with absolute paths. Unlike a check,
+/// a build links, so its problems include the errors of the linker (see
+/// ```ProblemDescription::is_link_error```).
+pub fn cargo_build_in<P: AsRef<Path>>(dir: P) -> Result<Check, CheckError> {
+    Checker::new().dir(dir.as_ref()).command(CommandKind::Build).run().map(|report| report.check)
+}
+
+/// Compiles the tests of the current directory without running them, and
+/// returns descriptions of the found problems (see
+/// ```cargo_test_compile_in```).
+pub fn cargo_test_compile() -> Result<Check, CheckError> {
+    cargo_test_compile_in(".")
+}
+
+/// Runs ```cargo test --no-run``` on the project in the directory, which
+/// compiles and links its tests without running them, and returns
+/// descriptions of the found problems, with absolute paths. It finds the
+/// problems of code that only tests compile, like ```#[cfg(test)]```
+/// modules and dev-dependencies.
+pub fn cargo_test_compile_in<P: AsRef<Path>>(dir: P) -> Result<Check, CheckError> {
+    subcommand_checker(dir.as_ref(), "test", &["--no-run"]).run().map(|report| report.check)
+}
+
+/// Runs ```cargo clippy``` on the current directory and returns descriptions
+/// of the found problems (see ```run_lint```). The problems of rustc lints
+/// and of clippy lints can be told apart with
+/// ```ProblemDescription::lint_tool```.
+pub fn cargo_clippy() -> Result<Check, CheckError> {
+    run_lint("clippy", &[])
+}
+
+/// Runs a cargo subcommand that reports the diagnostics of rustc, like
+/// ```check```, ```clippy``` or ```build```, with the extra arguments (e.g.
+/// ```["--all-targets", "--", "-W", "clippy::pedantic"]```) on the project
+/// in the current directory, and returns descriptions of the found
+/// problems, with absolute paths. A subcommand that isn't installed gives
+/// ```CheckError::ComponentMissing``` if rustup knows its component.
+pub fn run_lint(subcommand: &str, args: &[&str]) -> Result<Check, CheckError> {
+    subcommand_checker(".", subcommand, args).run().map(|report| report.check)
+}
+
+/// Returns the canonical path of the directory of a project, or the error
+/// if it doesn't exist or has no manifest.
+fn project_root(dir: &Path) -> Result<PathBuf, CheckError> {
+    match fs::canonicalize(dir) {
+        Ok(root) if root.join("Cargo.toml").is_file() => Ok(root),
+        _ => Err(CheckError::InvalidDirectory(dir.to_path_buf())),
     }
-    let mut is_warning = true;
-    
-    //println!("Stderr:");
-    let stderr_text = String::from_utf8(output.stderr)
-        .expect("Invalid UTF-8 returned by cargo check");
-    let mut lines = stderr_text.lines();
-    let mut cur_line = lines.next();
-    
-    while let Some(line) = cur_line {
-        if line == COMPILE_ERROR_LINE {
+}
+
+/// Joins the relative paths of the problems onto the directory, or onto the
+/// closest directory above it that has the file, like the root of the
+/// workspace that cargo reports the paths of members relative to.
+fn absolute_paths(check: Check, dir: &Path) -> Check {
+    let mut paths = HashSet::new();
+    Check::from_problems(check.problems().iter()
+        .map(|problem| absolute_path(problem.clone(), dir, &mut paths))
+        .collect())
+}
+
+/// Joins the path onto the directory, or the ancestor of it that has the
+/// file, if it is relative.
+fn absolute_file(file: &Arc<str>, dir: &Path) -> Arc<str> {
+    let path = PathBuf::from(&**file);
+    // Errors of the linker have no file
+    if path.is_absolute() || file.is_empty() {
+        return file.clone();
+    }
+    let root = dir.ancestors().find(|root| root.join(&path).exists()).unwrap_or(dir);
+    root.join(path).to_string_lossy().into()
+}
+
+/// Joins the paths of the problem and its children onto the directory, as
+/// ```absolute_paths``` does, and interns them among the paths.
+fn absolute_path(mut problem: ProblemDescription, dir: &Path, paths: &mut HashSet<Arc<str>>)
+        -> ProblemDescription {
+    problem.filepath = absolute_file(&problem.filepath, dir);
+    for note in spans_mut(&mut problem) {
+        note.file = note.file.as_ref().map(|file| absolute_file(file, dir));
+    }
+    intern_paths(&mut problem, paths);
+    problem
+}
+
+/// The problems found in a single file by ```check_file```.
+#[derive(Debug)]
+pub struct FileCheck {
+    /// The problems reported in the file.
+    pub problems: Vec<ProblemDescription>,
+    /// The result for the whole project, so that problems in other files
+    /// (e.g. errors preventing a build) are not hidden.
+    pub overall: Check,
+}
+
+/// Runs the check of the options in the given directory and returns the
+/// problems reported in the given file, along with the overall result. The
+/// directory of the options is replaced with the given one.
+///
+/// Cargo cannot check a single file, so this runs the normal check and
+/// filters its problems by their normalized path. A relative file is
+/// relative to the directory.
+pub fn check_file<D, F>(dir: D, file: F, options: &Checker) -> Result<FileCheck, CheckError>
+        where D: AsRef<Path>, F: AsRef<Path> {
+    let root = project_root(dir.as_ref())?;
+    let overall = options.clone().dir(&root).run()?.check;
+    // The paths of the problems are under the canonical directory
+    let file = fs::canonicalize(root.join(file.as_ref())).unwrap_or_else(|_| root.join(file));
+    let problems = problems_in_file(&overall, &root, &file);
+    Ok(FileCheck { problems, overall })
+}
+
+/// Returns the problems of the check that were reported in the given file.
+/// Relative paths are interpreted relative to ```dir```.
+fn problems_in_file(check: &Check, dir: &Path, file: &Path) -> Vec<ProblemDescription> {
+    let file = normalize_path(dir, &file.to_string_lossy());
+    check.problems().iter()
+        .filter(|problem| normalize_path(dir, &problem.filepath) == file)
+        .cloned()
+        .collect()
+}
+
+/// Lexically normalizes a path (joined onto ```dir``` if relative), treating
+/// both '/' and '\\' as separators and resolving '.' and '..' components.
+fn normalize_path(dir: &Path, path: &str) -> Vec<String> {
+    let path = path.replace('\\', "/");
+    let joined = if Path::new(&path).is_absolute() || dir.as_os_str().is_empty() {
+        path
+    } else {
+        format!("{}/{}", dir.to_string_lossy().replace('\\', "/"), path)
+    };
+    let mut components: Vec<String> = Vec::new();
+    for component in joined.split('/') {
+        match component {
+            "" | "." => {},
+            ".." => {
+                components.pop();
+            },
+            c => components.push(String::from(c)),
+        }
+    }
+    components
+}
+
+/// Returns the error for a failed run of cargo in the directory, which
+/// exited with the code, or ```None``` if it only failed as the project has
+/// errors: the check found some, or cargo named units it couldn't compile.
+fn cargo_failure(dir: &Path, code: Option<i32>, stderr: &str, check: &Check)
+        -> Option<CheckError> {
+    if matches!(*check, Check::Error(_)) {
+        return None;
+    }
+    let report = parse_output_lenient(stderr);
+    if matches!(report.check, Check::Error(_)) || ! report.failed_units.is_empty() {
+        return None;
+    }
+    if let Some(err) = missing_component(stderr) {
+        return Some(err);
+    }
+    if stderr.contains("could not find `Cargo.toml`") {
+        return Some(CheckError::InvalidDirectory(dir.to_path_buf()));
+    }
+    Some(CheckError::CargoFailed { status: code, stderr: String::from(stderr) })
+}
+
+/// The binaries that rustup reports as not installed, with their component.
+const COMPONENT_BINARIES: &[(&str, &str)] = &[
+    ("cargo-clippy", "clippy"),
+    ("clippy-driver", "clippy"),
+    ("cargo-fmt", "rustfmt"),
+    ("rustfmt", "rustfmt"),
+];
+
+/// Returns the error for a run of cargo that failed because the toolchain
+/// lacks a component, if the output says so.
+fn missing_component(stderr: &str) -> Option<CheckError> {
+    const INSTALL: &str = "rustup component add ";
+    // Cargo and newer versions of rustup give the command themselves
+    if let Some(start) = stderr.find(INSTALL) {
+        let install_hint = stderr[start..].split(['`', '\n']).next()?.trim();
+        let component = install_hint[INSTALL.len()..].split_whitespace().next()?;
+        return Some(CheckError::ComponentMissing {
+            component: String::from(component),
+            install_hint: String::from(install_hint),
+        });
+    }
+    // error: 'clippy-driver' is not installed for the toolchain 'stable-x86_64-unknown-linux-gnu'
+    COMPONENT_BINARIES.iter().find_map(|&(binary, component)| {
+        let message = format!("'{}' is not installed", binary);
+        let rest = &stderr[stderr.find(&message)? + message.len()..];
+        let toolchain = rest.strip_prefix(" for the toolchain '")
+            .and_then(|rest| rest.split('\'').next());
+        let install_hint = match toolchain {
+            Some(toolchain) => format!("{}{} --toolchain {}", INSTALL, component, toolchain),
+            None => format!("{}{}", INSTALL, component),
+        };
+        Some(CheckError::ComponentMissing { component: String::from(component), install_hint })
+    })
+}
+
+/// Parses arbitrary bytes as the output of ```cargo check``` and discards
+/// the result. This is an entry point for fuzzers: the parser must never 
+/// panic, whatever the input.
+pub fn fuzz_parse(data: &[u8]) {
+    if let Ok(text) = std::str::from_utf8(data) {
+        let _ = parse_output(text);
+        let _ = parse_output_lenient(text);
+        let _ = explain_paste(text);
+    }
+}
+
+/// Returns whether the line starts with a ```file:line:col:``` span.
+fn has_span_prefix(line: &str) -> bool {
+    for (i, ch) in line.char_indices() {
+        if ch.is_whitespace() {
+            return false;
+        }
+        if ch == ':' && i > 0 {
+            let mut fields = line[i + 1..].splitn(3, ':');
+            // Fields that start like a number but aren't one start a block
+            // too, so that the block is reported rather than merged into
+            // the previous one
+            let is_number = |field: Option<&str>| match field {
+                Some(field) => field.starts_with(|ch: char| ch.is_ascii_digit())
+                    && field.chars().all(|ch| ch.is_alphanumeric()
+                        || THOUSANDS_SEPARATORS.contains(&ch)),
+                None => false,
+            };
+            if is_number(fields.next()) && is_number(fields.next()) && fields.next().is_some() {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Returns whether the line starts a new diagnostic block. 
+/// 
+/// This must not depend on the previous block, since the output of rustc
+/// invocations running in parallel may alternate between blocks.
+fn line_starts_block(line: &str) -> bool {
+    line.starts_with("warning:") || line.starts_with("error:") 
+        || line.starts_with("error[") || has_span_prefix(line)
+        || is_progress_line(line)
+}
+
+/// Returns whether the line starts a top-level diagnostic of the current
+/// format, like ```error[E0425]: ...``` or ```warning: ...```.
+fn starts_top_level_diagnostic(line: &str) -> bool {
+    let rest = match line.strip_prefix("error").or_else(|| line.strip_prefix("warning")) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let rest = match rest.strip_prefix("[E") {
+        Some(code) => match code.find(']') {
+            Some(end) if end > 0 && code[..end].bytes().all(|b| b.is_ascii_digit()) => {
+                &code[end + 1..]
+            },
+            _ => return false,
+        },
+        None => rest,
+    };
+    rest.starts_with(": ")
+}
+
+/// Returns whether parsing can resume at the line after a block that
+/// couldn't be parsed: at a top-level diagnostic (not a help or note) of
+/// either format, or at a line of cargo.
+fn is_resync_point(line: &str) -> bool {
+    is_progress_line(line) || is_terminator(line) || starts_top_level_diagnostic(line)
+        || matches!(parse_check_line(line), Some((Level::Warning, _)) | Some((Level::Error, _)))
+}
+
+/// The verbs that cargo starts its progress lines with.
+const PROGRESS_VERBS: &[&str] = &[
+    "Adding", "Blocking", "Checking", "Compiling", "Documenting", "Downloaded",
+    "Downloading", "Finished", "Fresh", "Locking", "Running", "Updating",
+];
+
+/// Returns whether the line is a cargo progress line like ```Compiling ...```.
+fn is_progress_line(line: &str) -> bool {
+    match line.split_whitespace().next() {
+        Some(verb) => PROGRESS_VERBS.contains(&verb),
+        None => false,
+    }
+}
+
+/// Returns the package of a ```Checking```/```Compiling``` progress line.
+fn progress_package(line: &str) -> Option<&str> {
+    let mut words = line.split_whitespace();
+    match words.next() {
+        Some("Checking") | Some("Compiling") => words.next(),
+        _ => None,
+    }
+}
+
+/// Returns whether the line summarizes the diagnostics of a compilation, 
+/// like ```error: aborting due to previous error```.
+fn is_terminator(line: &str) -> bool {
+    line == COMPILE_ERROR_LINE || line.starts_with("error: aborting due to")
+        || line.starts_with("error: Could not compile")
+        || line.starts_with("error: could not compile")
+        || (line.starts_with("warning: `") && line.contains(" generated "))
+}
+
+/// Why a block of the output was not parsed into a problem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The block isn't in a format the parser understands.
+    UnrecognizedFormat,
+    /// The block is known, but carries no diagnostic (e.g. progress lines).
+    Ignored,
+    /// The block terminates or summarizes the output of a compilation.
+    Terminator,
+}
+
+/// A block of the output that was not parsed into a problem.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawBlock {
+    /// The lines of the block, without their final line break.
+    pub lines: String,
+    /// The 0-based indices of the lines in the output.
+    pub line_range: Range<usize>,
+    pub reason: SkipReason,
+}
+
+/// Parses the first line and the following lines of a diagnostic block,
+/// with the heuristics used for it.
+fn parse_block(block: &[&str]) -> Option<(Level, ProblemDescription, Heuristics)> {
+    // The format is detected for each diagnostic, as both can be mixed
+    if starts_top_level_diagnostic(block[0]) {
+        return human::parse_human_block(block);
+    }
+    let (level, mut problem) = parse_check_line(block[0])?;
+    let mut heuristics = Heuristics::default();
+    let note = &problem.message;
+    if [note.start_line, note.start_col, note.end_line, note.end_col].contains(&usize::MAX) {
+        heuristics.insert(Heuristics::SATURATED_POSITION);
+    }
+    // Message lines may follow, until the visual aids start
+    for line in &block[1..] {
+        if line_is_visual_aid(line) {
+            heuristics.insert(Heuristics::VISUAL_AID);
             break;
         }
-        println!("Current line: '{}'", line);
-        // Parse the problem on the current line
-        let (level, mut problem) = parse_check_line(line);
-        
-        // Check for more info in the following lines
-        let mut was_visual = false;
-        cur_line = lines.next();
-        while let Some(line) = cur_line {
-            if ! line_is_visual_aid(line) {
-                // This is a new message
-                if line.starts_with(&problem.filepath) {
-                    break;
-                } else if was_visual {
-                    continue;
-                } else {
-                    problem.message.message.push('\n');
-                    problem.message.message.push_str(line);
+        heuristics.insert(Heuristics::MESSAGE_CONTINUATION);
+        problem.message.message.push('\n');
+        problem.message.message.push_str(line);
+    }
+    problem.additional_primary_spans = underlined_spans(block, &problem);
+    if ! problem.additional_primary_spans.is_empty() {
+        heuristics.insert(Heuristics::UNDERLINE_COLUMNS);
+    }
+    Some((level, problem, heuristics))
+}
+
+/// Returns the other spans that are underlined on the line of the span of
+/// the problem in the visual aids of its block, like the ```^~  ^~~``` of
+/// several unused imports. The underlines start under the code that follows
+/// the ```file:line ``` prefix of the line above them.
+fn underlined_spans(block: &[&str], problem: &ProblemDescription) -> Vec<Note> {
+    let note = &problem.message;
+    let prefix = format!("{}:{} ", problem.filepath, note.start_line);
+    let prefix_len = prefix.chars().count();
+    let underline = block.windows(2)
+        .find(|lines| lines[0].starts_with(&prefix))
+        .map(|lines| lines[1])
+        .filter(|line| line.contains('^') && line.chars().all(|ch| " ^~".contains(ch)));
+    let underline = match underline {
+        Some(underline) => underline,
+        None => return Vec::new(),
+    };
+
+    // The 1-based columns of the groups of a '^' and the '~' after it
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for (i, ch) in underline.chars().enumerate() {
+        match ch {
+            '^' if i >= prefix_len => groups.push((i - prefix_len + 1, i - prefix_len + 2)),
+            '~' => if let Some(group) = groups.last_mut() {
+                if group.1 + prefix_len == i + 1 {
+                    group.1 += 1;
                 }
-            } else {
-                was_visual = true;
+            },
+            _ => {},
+        }
+    }
+    // Without the span of the message among them, the columns are off
+    if groups.len() < 2 || ! groups.iter().any(|&(start, _)| start == note.start_col) {
+        return Vec::new();
+    }
+    groups.into_iter()
+        .filter(|&(start, _)| start != note.start_col)
+        .map(|(start, end)| Note::new(note.start_line, start, note.start_line, end,
+            &note.message[..]))
+        .collect()
+}
+
+/// Parses the stderr output of ```cargo check```, skipping the blocks that
+/// can't be parsed.
+fn parse_output(text: &str) -> Check {
+    parse_blocks(text).0
+}
+
+/// Parses the stderr output of ```cargo check``` in lenient mode: the 
+/// blocks that can't be parsed are skipped, and returned as the 
+/// ```raw_blocks``` of the report so that no output is lost.
+/// 
+/// This is the recommended way of parsing output for interactive tools.
+pub fn parse_output_lenient(text: &str) -> CheckReport {
+    let (check, raw_blocks) = parse_blocks(text);
+    let mut report = CheckReport::new(check);
+    report.failed_units = workspace::failed_units(&raw_blocks);
+    report.units = units::unit_lines(&raw_blocks);
+    report.raw_blocks = raw_blocks;
+    report
+}
+
+/// Output that ```parse_check_output``` couldn't read all of.
+#[derive(Debug)]
+pub struct ParseError {
+    /// The problems that were parsed.
+    pub check: Check,
+    /// The blocks in a format that the parser doesn't understand.
+    pub unrecognized: Vec<RawBlock>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{} blocks of the output couldn't be parsed", self.unrecognized.len())?;
+        if let Some(block) = self.unrecognized.first() {
+            write!(f, ", the first at line {}: '{}'", block.line_range.start + 1,
+                block.lines.lines().next().unwrap_or(""))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses the stderr output of ```cargo check``` or ```rustc``` strictly:
+/// the output must only have diagnostics, progress lines and summaries
+/// like ```error: aborting due to previous error```. Blank lines are fine
+/// too. This has no side effects, and never runs cargo.
+pub fn parse_check_output(stderr: &str) -> Result<Check, ParseError> {
+    let (check, raw_blocks) = parse_blocks(stderr);
+    let unrecognized: Vec<RawBlock> = raw_blocks.into_iter()
+        .filter(|block| block.reason == SkipReason::UnrecognizedFormat
+            && ! block.lines.trim().is_empty())
+        .collect();
+    if unrecognized.is_empty() {
+        Ok(check)
+    } else {
+        Err(ParseError { check, unrecognized })
+    }
+}
+
+/// Returns the shared copy of the path, so that the problems in a file
+/// don't each hold a copy of its path.
+fn intern(paths: &mut HashSet<Arc<str>>, path: &Arc<str>) -> Arc<str> {
+    match paths.get(&**path) {
+        Some(shared) => shared.clone(),
+        None => {
+            paths.insert(path.clone());
+            path.clone()
+        },
+    }
+}
+
+/// Interns the paths of the problem and of its help and notes.
+fn intern_paths(problem: &mut ProblemDescription, paths: &mut HashSet<Arc<str>>) {
+    problem.filepath = intern(paths, &problem.filepath);
+    for note in spans_mut(problem) {
+        note.file = note.file.as_ref().map(|file| intern(paths, file));
+    }
+}
+
+/// Returns the spans of the problem that may be in a file of their own: its
+/// help and notes, its other primary spans, and the spans of its
+/// suggestions and of its chain of bounds.
+fn spans_mut(problem: &mut ProblemDescription) -> impl Iterator<Item = &mut Note> {
+    problem.help.iter_mut()
+        .chain(problem.notes.iter_mut())
+        .chain(problem.additional_primary_spans.iter_mut())
+        .chain(problem.suggestions.iter_mut().map(|suggestion| &mut suggestion.span))
+        .chain(problem.bound_chain.iter_mut().filter_map(|step| step.span.as_mut()))
+}
+
+fn parse_blocks(text: &str) -> (Check, Vec<RawBlock>) {
+    let lines: Vec<&str> = text.lines().collect();
+    // The byte offsets of the lines, for the text of the problems as it was
+    let mut offsets = Vec::with_capacity(lines.len() + 1);
+    offsets.push(0);
+    offsets.extend(text.split_inclusive('\n').scan(0, |end, line| {
+        *end += line.len();
+        Some(*end)
+    }));
+    let rendered = |range: Range<usize>| {
+        let end = lines[range.clone()].iter().rposition(|line| ! line.trim().is_empty())
+            .map_or(range.start, |last| range.start + last);
+        text[offsets[range.start]..offsets[end + 1]].trim_end_matches(['\r', '\n'])
+    };
+    let mut problems: Vec<ProblemDescription> = Vec::new();
+    let mut raw_blocks = Vec::new();
+    let mut current_package = None;
+    let mut paths = HashSet::new();
+    // The heuristics used for each of the problems, and their first lines
+    let mut heuristics: Vec<Heuristics> = Vec::new();
+    let mut first_lines: Vec<usize> = Vec::new();
+    
+    let mut start = 0;
+    while start < lines.len() {
+        let line = lines[start];
+        // Find the end of the block
+        let mut end = start + 1;
+        if ! is_progress_line(line) && ! is_terminator(line) {
+            while end < lines.len() && ! line_starts_block(lines[end]) {
+                end += 1;
             }
-            cur_line = lines.next();
         }
+        let block = &lines[start..end];
+        let line_range = start..end;
+        let skipped = |reason| RawBlock {
+            lines: block.join("\n"),
+            line_range: line_range.clone(),
+            reason,
+        };
+        start = end;
+        
+        if is_progress_line(line) {
+            if let Some(package) = progress_package(line) {
+                current_package = Some(package);
+            }
+            raw_blocks.push(skipped(SkipReason::Ignored));
+            continue;
+        }
+        if is_terminator(line) {
+            raw_blocks.push(skipped(SkipReason::Terminator));
+            continue;
+        }
+        let (level, mut problem, mut block_heuristics) = match parse_block(block) {
+            Some(parsed) => parsed,
+            None => {
+                // The help and notes of a mangled problem mustn't go to the
+                // one before it, so they are skipped along with it
+                while start < lines.len() && ! is_resync_point(lines[start]) {
+                    start += 1;
+                }
+                raw_blocks.push(RawBlock {
+                    lines: lines[line_range.start..start].join("\n"),
+                    line_range: line_range.start..start,
+                    reason: SkipReason::UnrecognizedFormat,
+                });
+                continue;
+            },
+        };
+        problem.during_package = current_package.map(String::from);
+        intern_paths(&mut problem, &mut paths);
+        problem.rendered = Some(String::from(rendered(line_range.clone())));
         
         // Find out how to use the found problem
         match level {
             Level::Error => {
-                is_warning = false;
+                problem.severity = Severity::Error;
                 problems.push(problem);
-            },
-            // Add this help message to the previous problem
-            Level::Help => {
-                let last = problems.len() - 1;
-                let mut last_problem = &mut problems[last];
-                last_problem.help.push(problem.message);
+                heuristics.push(block_heuristics);
+                first_lines.push(line_range.start);
             },
             Level::Warning => {
                 problems.push(problem);
-                // Ignore the visual error location lines
-                lines.next();
-                lines.next();
+                heuristics.push(block_heuristics);
+                first_lines.push(line_range.start);
             },
-            // Add this help note to the previous problem
-            Level::Note => {
-                let last = problems.len() - 1;
-                let mut last_problem = &mut problems[last];
-                last_problem.notes.push(problem.message);
+            // Add this help message or note to the previous problem
+            Level::Help | Level::Note => {
+                match problems.last_mut() {
+                    Some(last_problem) => {
+                        if problem.filepath != last_problem.filepath {
+                            block_heuristics.insert(Heuristics::CHILD_IN_OTHER_FILE);
+                        }
+                        match level {
+                            Level::Help => {
+                                let message = &problem.message.message;
+                                let count = last_problem.suggestions.len();
+                                if let Some(path) = suggested_import(message) {
+                                    last_problem.suggestions.push(
+                                        Suggestion::import(path, message.clone()));
+                                }
+                                if let Some(attribute) = suggested_crate_attribute(message) {
+                                    last_problem.suggestions.push(
+                                        Suggestion::crate_attribute(attribute, message.clone()));
+                                }
+                                if suggests_removal(message) {
+                                    last_problem.suggestions.push(
+                                        Suggestion::removal(problem.message.clone()));
+                                }
+                                if last_problem.suggestions.len() > count {
+                                    block_heuristics.insert(Heuristics::SUGGESTION_FROM_HELP);
+                                }
+                                last_problem.help.push(
+                                    problem.message.with_file(problem.filepath));
+                            },
+                            _ => {
+                                if bounds::is_bound_step(&problem.message.message) {
+                                    last_problem.bound_chain.push(BoundStep::new(
+                                        &problem.message.message,
+                                        Some((&problem.filepath, problem.message.clone()))));
+                                }
+                                last_problem.notes.push(
+                                    problem.message.with_file(problem.filepath))
+                            },
+                        }
+                        if let Some(last) = heuristics.last_mut() {
+                            last.insert(block_heuristics.0);
+                        }
+                        if let Some(&first) = first_lines.last() {
+                            last_problem.rendered = Some(String::from(rendered(first..end)));
+                        }
+                    },
+                    None => raw_blocks.push(skipped(SkipReason::UnrecognizedFormat)),
+                }
             },
         }
     }
-    /*for (num, line) in stderr_text.lines().enumerate() {
-        println!("{}: {}", num, line);
-    }*/
-    Ok(if is_warning {
-        if problems.is_empty() {
-            Perfect
-        } else {
-            Warning(problems)
-        }
-    } else {
-        Error(problems)
-    })
+    for (problem, heuristics) in problems.iter_mut().zip(heuristics) {
+        problem.parse_confidence = heuristics.confidence();
+        problem.find_macro_origin();
+    }
+    (Check::from_problems(problems), raw_blocks)
 }
 
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use std::env;
+    use std::path::PathBuf;
+
+    fn temp_project(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("lintparser-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("src")).unwrap();
+        dir
+    }
+
+    fn warning(filepath: &str, line: usize) -> ProblemDescription {
+        ProblemDescription::new(filepath, line, 5, line, 6, "unused variable: `x`", 
+            vec![], vec![])
+    }
+
     #[test]
     fn it_works() {
     }
+
+    const MULTIBYTE_SOURCE: &str = "fn main() {\n    let αβγ = \"日本語\";\r\n\n}\n🦀 ok";
+
+    #[test]
+    fn note_from_byte_range_counts_characters() {
+        let start = MULTIBYTE_SOURCE.find('α').unwrap();
+        let end = MULTIBYTE_SOURCE.find(" =").unwrap();
+        let note = Note::from_byte_range(MULTIBYTE_SOURCE, start..end, "unused");
+        assert_eq!((note.start_line, note.start_col), (2, 9));
+        assert_eq!((note.end_line, note.end_col), (2, 12));
+        assert_eq!(note.message, "unused");
+
+        let crab = MULTIBYTE_SOURCE.find('🦀').unwrap();
+        let note = Note::from_byte_range(MULTIBYTE_SOURCE, crab..MULTIBYTE_SOURCE.len(), "");
+        assert_eq!((note.start_line, note.start_col), (5, 1));
+        assert_eq!((note.end_line, note.end_col), (5, 5));
+    }
+
+    #[test]
+    fn note_byte_range_conversions_agree() {
+        // Every range between two character boundaries must survive the round trip.
+        let boundaries: Vec<usize> = MULTIBYTE_SOURCE.char_indices().map(|(i, _)| i)
+            .chain(Some(MULTIBYTE_SOURCE.len())).collect();
+        for (i, &start) in boundaries.iter().enumerate() {
+            for &end in &boundaries[i..] {
+                let note = Note::from_byte_range(MULTIBYTE_SOURCE, start..end, "");
+                assert_eq!(note.to_byte_range(MULTIBYTE_SOURCE), Some(start..end),
+                    "round trip of {:?} via {}", start..end, note);
+            }
+        }
+    }
+
+    #[test]
+    fn note_to_byte_range_rejects_spans_outside_the_source() {
+        assert_eq!(Note::new(0, 1, 1, 1, "").to_byte_range(MULTIBYTE_SOURCE), None);
+        assert_eq!(Note::new(1, 1, 1, 12, "").to_byte_range(MULTIBYTE_SOURCE), Some(0..11));
+        assert_eq!(Note::new(1, 1, 1, 13, "").to_byte_range(MULTIBYTE_SOURCE), None);
+        assert_eq!(Note::new(6, 1, 6, 1, "").to_byte_range(MULTIBYTE_SOURCE), None);
+        assert_eq!(Note::new(2, 5, 1, 5, "").to_byte_range(MULTIBYTE_SOURCE), None);
+    }
+
+    #[test]
+    fn problems_in_file_matches_normalized_paths() {
+        let check = Check::Warning(vec![
+            warning("src/main.rs", 1),
+            warning("src\\main.rs", 2),
+            warning("./src/lib.rs", 3),
+            warning("/project/src/main.rs", 4),
+        ]);
+        let dir = Path::new("/project");
+        let lines = |file: &str| -> Vec<usize> {
+            problems_in_file(&check, dir, Path::new(file)).iter()
+                .map(|problem| problem.message.start_line).collect()
+        };
+        assert_eq!(lines("src/main.rs"), vec![1, 2, 4]);
+        assert_eq!(lines("/project/src/../src/main.rs"), vec![1, 2, 4]);
+        assert_eq!(lines("src/lib.rs"), vec![3]);
+        assert!(lines("src/other.rs").is_empty());
+    }
+
+    const PACKAGE_A_OUTPUT: &str = "\
+a/src/lib.rs:2:9: 2:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+a/src/lib.rs:2     let x = 5;
+                       ^
+a/src/lib.rs:6:1: 8:2 warning: function is never used: `helper`, #[warn(dead_code)] on by default
+a/src/lib.rs:6 fn helper() {
+a/src/lib.rs:7     println!(\"hi\");
+a/src/lib.rs:8 }
+";
+
+    const PACKAGE_B_OUTPUT: &str = "\
+b/src/main.rs:4:5: 4:8 error: unresolved name `foo` [E0425]
+b/src/main.rs:4     foo();
+                    ^~~
+b/src/main.rs:4:5: 4:8 help: run `rustc --explain E0425` to see a detailed explanation
+error: aborting due to previous error
+";
+
+    /// The blocks of both outputs, alternating between the packages.
+    const INTERLEAVED_OUTPUT: &str = "\
+   Compiling a v0.1.0 (file:///work/a)
+   Compiling b v0.1.0 (file:///work/b)
+a/src/lib.rs:2:9: 2:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+a/src/lib.rs:2     let x = 5;
+                       ^
+b/src/main.rs:4:5: 4:8 error: unresolved name `foo` [E0425]
+b/src/main.rs:4     foo();
+                    ^~~
+b/src/main.rs:4:5: 4:8 help: run `rustc --explain E0425` to see a detailed explanation
+a/src/lib.rs:6:1: 8:2 warning: function is never used: `helper`, #[warn(dead_code)] on by default
+a/src/lib.rs:6 fn helper() {
+a/src/lib.rs:7     println!(\"hi\");
+a/src/lib.rs:8 }
+error: aborting due to previous error
+";
+
+    #[test]
+    fn parse_output_separates_interleaved_blocks() {
+        let check = parse_output(INTERLEAVED_OUTPUT);
+        match check {
+            Check::Error(_) => {},
+            ref other => panic!("Expected errors, got {:?}", other),
+        }
+        let problems = check.problems();
+        assert_eq!(problems.len(), 3);
+
+        assert_eq!(&*problems[0].filepath, "a/src/lib.rs");
+        assert_eq!(problems[0].message.message, 
+            "unused variable: `x`, #[warn(unused_variables)] on by default");
+        assert!(problems[0].help.is_empty());
+
+        assert_eq!(&*problems[1].filepath, "b/src/main.rs");
+        assert_eq!(problems[1].message.message, "unresolved name `foo` [E0425]");
+        assert_eq!(problems[1].help.len(), 1);
+        assert_eq!(problems[1].help[0].message, 
+            "run `rustc --explain E0425` to see a detailed explanation");
+
+        assert_eq!(&*problems[2].filepath, "a/src/lib.rs");
+        assert_eq!((problems[2].message.start_line, problems[2].message.end_line), (6, 8));
+        assert!(problems[2].help.is_empty());
+    }
+
+    #[test]
+    fn parse_output_of_interleaved_blocks_matches_separate_outputs() {
+        let mut separate: Vec<String> = parse_output(PACKAGE_A_OUTPUT).problems().iter()
+            .chain(parse_output(PACKAGE_B_OUTPUT).problems())
+            .map(|problem| problem.to_string())
+            .collect();
+        let mut interleaved: Vec<String> = parse_output(INTERLEAVED_OUTPUT).problems().iter()
+            .map(|problem| problem.to_string())
+            .collect();
+        interleaved.sort();
+        separate.sort();
+        assert_eq!(interleaved, separate);
+    }
+
+    #[test]
+    fn parse_output_associates_problems_with_the_package_being_checked() {
+        let check = parse_output("\
+src/build.rs:1:1: 1:2 warning: before any package
+    Checking a v0.1.0 (file:///work/a)
+a/src/lib.rs:2:9: 2:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+a/src/lib.rs:2     let x = 5;
+                       ^
+   Compiling b v0.1.0 (file:///work/b)
+b/src/main.rs:4:5: 4:8 error: unresolved name `foo` [E0425]
+b/src/main.rs:4:5: 4:8 help: run `rustc --explain E0425` to see a detailed explanation
+");
+        let packages: Vec<Option<&str>> = check.problems().iter()
+            .map(|problem| problem.during_package.as_ref().map(|p| &p[..]))
+            .collect();
+        assert_eq!(packages, vec![None, Some("a"), Some("b")]);
+        assert_eq!(check.problems()[1].message.message, 
+            "unused variable: `x`, #[warn(unused_variables)] on by default");
+        assert_eq!(check.problems()[2].help.len(), 1);
+        assert!(check.problems().iter().all(|problem| problem.emitted_at.is_none()));
+    }
+
+    #[test]
+    fn parse_output_lenient_keeps_skipped_blocks() {
+        let report = parse_output_lenient("\
+warning: unused manifest key: package.colour
+   Compiling a v0.1.0 (file:///work/a)
+a/src/lib.rs:1:1: 1:2 note: a note without a problem
+a/src/lib.rs:2:9: 2:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+a/src/lib.rs:2     let x = 5;
+                       ^
+error: aborting due to previous error
+b/src/main.rs:4:5: 4:8 error: unresolved name `foo` [E0425]
+error: Could not compile `b`.
+");
+        assert_eq!(report.check.problems().len(), 2);
+        let blocks: Vec<(SkipReason, Range<usize>)> = report.raw_blocks.iter()
+            .map(|block| (block.reason, block.line_range.clone()))
+            .collect();
+        assert_eq!(blocks, vec![
+            (SkipReason::UnrecognizedFormat, 0..1),
+            (SkipReason::Ignored, 1..2),
+            (SkipReason::UnrecognizedFormat, 2..3),
+            (SkipReason::Terminator, 6..7),
+            (SkipReason::Terminator, 8..9),
+        ]);
+        assert_eq!(report.raw_blocks[0].lines, "warning: unused manifest key: package.colour");
+        assert_eq!(report.raw_blocks[1].lines, "   Compiling a v0.1.0 (file:///work/a)");
+    }
+
+    /// A misuse of ```#[tokio::main]``` without the runtime feature, and an
+    /// ```async_trait``` method whose future isn't ```Send```.
+    const ATTRIBUTE_MACRO_ERRORS: &str = "\
+error: The default runtime flavor is `multi_thread`, but the `rt-multi-thread` feature is disabled.
+ --> src/main.rs:1:1
+  |
+1 | #[tokio::main]
+  | ^^^^^^^^^^^^^^
+  |
+  = note: this error originates in the attribute macro `tokio::main` (in Nightly builds, run with -Z macro-backtrace for more info)
+
+error: future cannot be sent between threads safely
+  --> src/lib.rs:10:47
+   |
+10 |       async fn run(&self) -> Result<(), Error> {
+   |  _______________________________________________^
+11 | |         let rc = Rc::new(1);
+12 | |         self.step().await?;
+13 | |         Ok(())
+14 | |     }
+   | |_____^ future created by async block is not `Send`
+   |
+note: future is not `Send` as this value is used across an await
+  --> src/lib.rs:12:21
+   |
+11 |         let rc = Rc::new(1);
+   |             -- has type `Rc<i32>` which is not `Send`
+12 |         self.step().await?;
+   |                     ^^^^^ await occurs here, with `rc` maybe used later
+   = note: required for the cast from `Pin<Box<{async block@src/lib.rs:10:47: 14:6}>>` to `Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>`
+   = note: this error originates in the attribute macro `async_trait` (in Nightly builds, run with -Z macro-backtrace for more info)
+
+error[E0277]: `Rc<u8>` doesn't implement `Debug`
+ --> src/model.rs:3:5
+  |
+1 | #[derive(Debug)]
+  |          ----- in this derive macro expansion
+2 | struct Model {
+3 |     data: Rc<u8>,
+  |     ^^^^^^^^^^^^ `Rc<u8>` cannot be formatted using `{:?}`
+  |
+  = note: this error originates in the derive macro `Debug` (in Nightly builds, run with -Z macro-backtrace for more info)
+";
+
+    #[test]
+    fn problems_from_attribute_macros_point_into_the_item() {
+        let check = parse_output(ATTRIBUTE_MACRO_ERRORS);
+        let problems = check.problems();
+        let origins: Vec<Option<(MacroKind, &str)>> = problems.iter()
+            .map(|problem| problem.from_macro.as_ref()
+                .map(|origin| (origin.kind, &origin.name[..])))
+            .collect();
+        assert_eq!(origins, vec![Some((MacroKind::Attribute, "tokio::main")),
+            Some((MacroKind::Attribute, "async_trait")), Some((MacroKind::Derive, "Debug"))]);
+        assert_eq!(macro_origin("this error originates in the macro `vec` (in Nightly builds)"),
+            Some(MacroOrigin { kind: MacroKind::Bang, name: String::from("vec") }));
+
+        let span = |note: &Note| (note.start_line, note.start_col, note.end_line, note.end_col);
+        assert_eq!(span(problems[0].primary_user_span()), (1, 1, 1, 15));
+        // The span of the future covers the whole method
+        assert_eq!(span(&problems[1].message), (10, 47, 14, 6));
+        assert_eq!(span(problems[1].primary_user_span()), (12, 21, 12, 26));
+        assert_eq!(span(problems[2].primary_user_span()), (3, 5, 3, 17));
+    }
+
+    /// A warning, as rustc writes it.
+    const SINGLE_WARNING: &str = "\
+src/main.rs:2:9: 2:15 warning: unused variable: `unused`, #[warn(unused_variables)] on by default
+src/main.rs:2     let unused = 5;
+                      ^~~~~~
+";
+
+    /// Two errors and the line that aborts the compilation.
+    const ERRORS_AND_ABORT: &str = "\
+   Compiling errors v0.1.0 (file:///work/errors)
+src/lib.rs:2:18: 2:25 error: mismatched types: expected `u32`, found `&'static str` [E0308]
+src/lib.rs:2 let x: u32 = \"hello\";
+                          ^~~~~~~
+src/lib.rs:5:5: 5:8 error: unresolved name `foo` [E0425]
+src/lib.rs:5     foo();
+                 ^~~
+error: aborting due to 2 previous errors
+error: Could not compile `errors`.
+";
+
+    /// An error with help and a note.
+    const ERROR_WITH_HELP_AND_NOTE: &str = "\
+src/lib.rs:3:5: 3:12 error: use of unstable library feature 'rustc_private'
+src/lib.rs:3:5: 3:12 help: add #![feature(rustc_private)] to the crate attributes to enable
+src/lib.rs:1:1: 1:2 note: the crate starts here
+";
+
+    #[test]
+    fn fixtures_parse_strictly() {
+        let warning = parse_check_output(SINGLE_WARNING).unwrap();
+        assert!(matches!(warning, Check::Warning(_)));
+        assert_eq!(warning.problems()[0].to_string(), "src/main.rs:2:9: warning: unused \
+            variable: `unused`, #[warn(unused_variables)] on by default");
+
+        let errors = parse_check_output(ERRORS_AND_ABORT).unwrap();
+        let messages: Vec<(usize, &str, Option<&str>)> = errors.problems().iter()
+            .map(|problem| (problem.message.start_line, &problem.message.message[..],
+                problem.error_code()))
+            .collect();
+        assert_eq!(messages, vec![
+            (2, "mismatched types: expected `u32`, found `&'static str` [E0308]", Some("E0308")),
+            (5, "unresolved name `foo` [E0425]", Some("E0425")),
+        ]);
+
+        let error = parse_check_output(ERROR_WITH_HELP_AND_NOTE).unwrap();
+        let problem = &error.problems()[0];
+        assert_eq!(problem.severity, Severity::Error);
+        assert_eq!(problem.help[0].message,
+            "add #![feature(rustc_private)] to the crate attributes to enable");
+        assert_eq!((problem.notes[0].start_line, &problem.notes[0].message[..]),
+            (1, "the crate starts here"));
+
+        let err = parse_check_output("error: linking failed\n").unwrap_err();
+        assert!(matches!(err.check, Check::Perfect));
+        assert_eq!(err.to_string(),
+            "1 blocks of the output couldn't be parsed, the first at line 1: 'error: linking failed'");
+    }
+
+    #[test]
+    fn malformed_lines_are_ignored_rather_than_fatal() {
+        let report = parse_output_lenient("\
+error: linking failed
+
+src/lib.rs:3: warning: a line without columns
+src/lib.rs:3:1: 3:2 info: an unknown level
+src/lib.rs:4:9: 4:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+");
+        assert_eq!(report.check.problems().len(), 1);
+        assert_eq!(report.ignored_lines(), vec![
+            "error: linking failed",
+            "src/lib.rs:3: warning: a line without columns",
+            "src/lib.rs:3:1: 3:2 info: an unknown level",
+        ]);
+        assert!(parse_output_lenient("").ignored_lines().is_empty());
+    }
+
+    #[test]
+    fn parse_output_sets_severities() {
+        let check = parse_output(INTERLEAVED_OUTPUT);
+        let severities: Vec<Severity> = check.problems().iter()
+            .map(|problem| problem.severity)
+            .collect();
+        assert_eq!(severities, vec![Severity::Warning, Severity::Error, Severity::Warning]);
+    }
+
+    #[test]
+    fn summary_line_format() {
+        assert_eq!(Check::Perfect.summary_line(), "lintparser: clean");
+        assert_eq!(parse_output(INTERLEAVED_OUTPUT).summary_line(), 
+            "lintparser: 1 errors, 2 warnings, 2 files");
+        let check = Check::Warning(vec![warning("src/main.rs", 1), 
+            warning("src\\main.rs", 2)]);
+        assert_eq!(check.summary_line(), "lintparser: 0 errors, 2 warnings, 1 files");
+    }
+
+    /// A small deterministic xorshift generator for the adversarial tests.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+    }
+
+    #[test]
+    fn the_variant_of_a_check_follows_its_worst_severity() {
+        let severities = [Severity::Error, Severity::Warning, Severity::Note, Severity::Help];
+        let mut sorted = severities;
+        sorted.sort();
+        assert_eq!(sorted, [Severity::Help, Severity::Note, Severity::Warning, Severity::Error]);
+        let agrees = |check: &Check| match *check {
+            Check::Perfect => check.worst_severity().is_none() && check.problems().is_empty(),
+            Check::Error(_) => check.worst_severity() == Some(Severity::Error),
+            Check::Warning(ref problems) => ! problems.is_empty()
+                && check.worst_severity() < Some(Severity::Error),
+        };
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        let random_problems = |rng: &mut Rng| -> Vec<ProblemDescription> {
+            (0..rng.below(4)).map(|_| {
+                let line = rng.below(3) + 1;
+                let lint = ["unused_variables", "clippy::needless_return"][rng.below(2)];
+                let mut problem = ProblemDescription::new("src/lib.rs", line, 1, line, 2,
+                    &format!("problem, #[warn({})] on by default", lint)[..], vec![], vec![]);
+                problem.severity = severities[rng.below(severities.len())];
+                problem
+            }).collect()
+        };
+        let mut policy = SeverityPolicy::new();
+        policy.set("unused_variables", Severity::Error);
+        for _ in 0..200 {
+            let mut check = Check::from_problems(random_problems(&mut rng));
+            for _ in 0..8 {
+                check = match rng.below(6) {
+                    // Keep some of the problems
+                    0 => Check::from_problems(check.problems().iter()
+                        .filter(|_| rng.below(2) == 0)
+                        .cloned()
+                        .collect()),
+                    // Merge it with another check
+                    1 => {
+                        let mut problems = check.problems().to_vec();
+                        problems.extend(random_problems(&mut rng));
+                        Check::from_problems(problems)
+                    },
+                    2 => check.rustc_only(),
+                    3 => check.clippy_only(),
+                    4 => check.dedup(PathCompare::Sensitive),
+                    _ => policy.apply(&check),
+                };
+                assert!(agrees(&check), "{:?}", check);
+                assert_eq!(check.error_count(),
+                    check.problems().iter().filter(|problem| problem.is_error()).count());
+            }
+        }
+    }
+
+    #[test]
+    fn parse_output_survives_near_miss_lines() {
+        let paths = ["", "src/lib.rs", "ü/ß.rs", ":", " "];
+        let numbers = ["", "0", "7", "-1", "1,234", "x", "ä", " ", "99999999999999999999999"];
+        let levels = ["warning", "error", "help", "note", "info", "", "wärning", "error[E0308]"];
+        let messages = ["", " ", " msg", ":", "é: ü"];
+        let mut lines = Vec::new();
+        for path in &paths {
+            for first in &numbers {
+                for second in &numbers {
+                    for level in &levels {
+                        for message in &messages {
+                            lines.push(format!("{}:{}:{}: {}:{} {}:{}", 
+                                path, first, second, first, second, level, message));
+                        }
+                    }
+                }
+            }
+        }
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        let fragments = [":", " ", "\n", "é", "0", "help:", "error:", "^~~"];
+        for _ in 0..5000 {
+            let mut line: Vec<char> = lines[rng.below(lines.len())].chars().collect();
+            for _ in 0..rng.below(4) {
+                let at = rng.below(line.len() + 1);
+                if rng.below(2) == 0 && at < line.len() {
+                    line.remove(at);
+                } else {
+                    let fragment = fragments[rng.below(fragments.len())];
+                    for (offset, ch) in fragment.chars().enumerate() {
+                        line.insert(at + offset, ch);
+                    }
+                }
+            }
+            lines.push(line.into_iter().collect());
+        }
+        for line in &lines {
+            parse_output(line);
+        }
+        parse_output(&lines.join("\n"));
+    }
+
+    #[test]
+    fn parse_output_saturates_huge_numbers() {
+        let check = parse_output("src/lib.rs:99999999999999999999999:1: \
+            99999999999999999999999:2 warning: unused variable: `x`");
+        assert_eq!(check.problems().len(), 1);
+        assert_eq!(check.problems()[0].message.start_line, usize::MAX);
+        assert_eq!(check.problems()[0].message.end_col, 2);
+    }
+
+    #[test]
+    fn span_numbers_may_have_thousands_separators() {
+        let report = parse_output_lenient("\
+gen/bindings.rs:2,600,000:5: 2,600,000:9 warning: unused import: `c_int`
+gen/bindings.rs:2_600_001:1: warning: unused import: `c_long`
+gen/bindings.rs:26,00:1: 26,00:2 warning: misplaced separators
+gen/bindings.rs:12x:5: 12x:9 warning: not a number
+");
+        let positions: Vec<(usize, usize, usize)> = report.check.problems().iter()
+            .map(|problem| (problem.message.start_line, problem.message.end_line,
+                problem.message.end_col))
+            .collect();
+        assert_eq!(positions, vec![(2_600_000, 2_600_000, 9), (2_600_001, 2_600_001, 1)]);
+        let skipped: Vec<Range<usize>> = report.raw_blocks.iter()
+            .map(|block| block.line_range.clone())
+            .collect();
+        assert_eq!(skipped, vec![2..4]);
+
+        // The source of generated bindings is long, but not too long to show
+        let source: String = (0..2_600_000).map(|_| "use c::c_int;\n").collect();
+        let problem = &report.check.problems()[0];
+        assert_eq!(render_snippet(&source, problem, 4).unwrap(), "\
+2600000 | use c::c_int;
+        |     ^^^^ warning: unused import: `c_int`
+");
+        let mut saturated = problem.clone();
+        saturated.message.start_line = usize::MAX;
+        saturated.message.start_col = usize::MAX;
+        assert_eq!(render_snippet(&source, &saturated, 4), None);
+        assert_eq!(annotate_source(&source[..28], &[&saturated], AnnotationStyle::Html),
+            &source[..28]);
+    }
+
+    #[test]
+    fn parse_output_drops_orphaned_children_and_unknown_levels() {
+        let check = parse_output("\
+src/lib.rs:1:1: 1:2 help: a help without a problem
+src/lib.rs:1:1: 1:2 info: an unknown level
+src/lib.rs:2:1: 2:2 note: a note without a problem
+src/lib.rs:3:1: 3:2 warning:");
+        assert_eq!(check.problems().len(), 1);
+        assert_eq!(check.problems()[0].message.message, "");
+        assert!(check.problems()[0].notes.is_empty());
+    }
+
+    #[test]
+    fn fuzz_parse_accepts_arbitrary_bytes() {
+        fuzz_parse(&[0xff, 0xfe, b':', b'1']);
+        fuzz_parse(b"src/lib.rs:1:1: 1:2 warning: x");
+        fuzz_parse(b"");
+        // The positions of an arrow start at 1
+        fuzz_parse(b"warning: x\n --> src/lib.rs:1:0\n  |\n1 | abc\n  | ^\n");
+        fuzz_parse(b"warning: x\n --> src/lib.rs:0:1\n  |\n1 | abc\n  | ^\n");
+        fuzz_parse(b"warning: x\n --> src/lib.rs:1:1\n  |\n1 | abc\n  | ^\n\
+            help: y\n --> src/lib.rs:1:0\n");
+    }
+
+    #[test]
+    fn lint_names_are_found_in_messages_and_notes() {
+        let problems = parse_output(INTERLEAVED_OUTPUT);
+        let lints: Vec<Option<&str>> = problems.problems().iter()
+            .map(|problem| problem.lint())
+            .collect();
+        assert_eq!(lints, vec![Some("unused_variables"), None, Some("dead_code")]);
+
+        let mut problem = warning("src/lib.rs", 1);
+        problem.notes.push(Note::new(1, 1, 1, 1, "`#[deny(unused_must_use)]` on by default"));
+        assert_eq!(problem.lint(), Some("unused_must_use"));
+    }
+
+    #[test]
+    fn error_codes_are_read_from_messages() {
+        let mut problem = warning("src/lib.rs", 1);
+        problem.message.message = String::from("unresolved name `foo` [E0425]");
+        assert_eq!(problem.error_code(), Some("E0425"));
+        problem.message.message = String::from("index [E] out of [Eabcd] range [E12]");
+        assert_eq!(problem.error_code(), None);
+        problem.code = Some(String::from("E0308"));
+        assert_eq!(problem.error_code(), Some("E0308"));
+
+        let check = parse_output("\
+src/main.rs:2:22: error[E0308]: mismatched types
+src/main.rs:3:22: error: linking failed
+src/main.rs:4:5: 4:8 error: unresolved name `foo` [E0425]
+src/main.rs:5:9: warning: unused variable: `x`
+src/main.rs:6:9: 6:10 warning: unused variable: `y`, #[warn(unused_variables)] on by default
+");
+        let codes: Vec<Option<&str>> = check.problems().iter()
+            .map(|problem| problem.code.as_deref())
+            .collect();
+        assert_eq!(codes, vec![Some("E0308"), None, Some("E0425"), None, None]);
+        assert_eq!(check.problems()[4].lint(), Some("unused_variables"));
+    }
+
+    #[test]
+    fn notes_pointing_at_other_code_are_related_spans() {
+        let check = parse_output("\
+src/main.rs:5:9: 5:10 warning: unreachable pattern, #[warn(unreachable_patterns)] on by default
+src/main.rs:4:9: 4:10 note: matches any value
+src/lib.rs:7:1: 7:25 error: conflicting implementations of trait `Foo` for type `Bar`: [E0119]
+src/lib.rs:7:1: 7:25 note: conflicting implementation for `Bar`
+src/lib.rs:3:1: 3:25 note: first implementation here
+");
+        let locations: Vec<Vec<(usize, usize)>> = check.problems().iter()
+            .map(|problem| problem.related_spans().iter()
+                .map(|note| (note.start_line, note.start_col))
+                .collect())
+            .collect();
+        assert_eq!(locations, vec![vec![(4, 9)], vec![(7, 1), (3, 1)]]);
+        assert_eq!(check.problems()[1].related_spans()[1].message, "first implementation here");
+
+        let mut problem = warning("src/lib.rs", 1);
+        problem.notes.push(Note::new(0, 0, 0, 0, "spanless"));
+        assert!(problem.related_spans().is_empty());
+    }
+
+    #[test]
+    fn fingerprints_ignore_positions_and_path_spelling() {
+        let fingerprint = warning("src/lib.rs", 1).fingerprint();
+        assert_eq!(fingerprint.len(), 16);
+        assert_eq!(warning("./src\\lib.rs", 40).fingerprint(), fingerprint);
+        assert!(warning("src/main.rs", 1).fingerprint() != fingerprint);
+        let mut error = warning("src/lib.rs", 1);
+        error.severity = Severity::Error;
+        assert!(error.fingerprint() != fingerprint);
+    }
+
+    #[test]
+    fn digests_ignore_order_and_path_spelling() {
+        let check = |problems: Vec<ProblemDescription>| Check::from_problems(problems);
+        let digest = check(vec![warning("src/lib.rs", 1), warning("src/main.rs", 2)]).digest();
+        assert_eq!(check(vec![warning("src/main.rs", 2), warning("./src\\lib.rs", 1)]).digest(),
+            digest);
+        assert!(check(vec![warning("src/lib.rs", 3), warning("src/main.rs", 2)]).digest() != digest);
+        let mut noted = warning("src/lib.rs", 1);
+        noted.notes.push(Note::new(1, 5, 1, 6, "the variable is declared here"));
+        assert!(check(vec![noted, warning("src/main.rs", 2)]).digest() != digest);
+        assert!(Check::Perfect.digest() != digest);
+
+        let report = CheckReport::new(check(vec![warning("src/lib.rs", 1)]));
+        assert!(! report.unchanged_since(check(vec![warning("lib.rs", 1)]).digest()));
+        assert!(report.unchanged_since(report.check.digest()));
+    }
+
+    #[test]
+    fn clippy_and_rustc_views() {
+        let check = parse_output("\
+src/lib.rs:2:5: 2:14 warning: unneeded return statement, #[warn(clippy::needless_return)] on by default
+src/lib.rs:3:9: 3:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+src/lib.rs:4:5: 4:8 error: unresolved name `foo` [E0425]
+");
+        let tools: Vec<Option<LintTool>> = check.problems().iter()
+            .map(|problem| problem.lint_tool())
+            .collect();
+        assert_eq!(tools, vec![Some(LintTool::Clippy), Some(LintTool::Rustc), None]);
+
+        match check.clippy_only() {
+            Check::Warning(ref problems) => assert_eq!(problems.len(), 1),
+            ref other => panic!("Expected a warning, got {:?}", other),
+        }
+        match check.rustc_only() {
+            Check::Error(ref problems) => assert_eq!(problems.len(), 2),
+            ref other => panic!("Expected errors, got {:?}", other),
+        }
+        match parse_output(PACKAGE_A_OUTPUT).clippy_only() {
+            Check::Perfect => {},
+            ref other => panic!("Expected no problems, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_output_suggests_imports_for_traits_not_in_scope() {
+        let check = parse_output("\
+src/main.rs:5:7: 5:21 error: no method named `read_to_string` found for type `std::fs::File` in the current scope
+src/main.rs:5     f.read_to_string(&mut s);
+                    ^~~~~~~~~~~~~~
+src/main.rs:5:7: 5:21 help: items from traits can only be used if the trait is in scope; the following trait is implemented but not in scope, perhaps add a `use` for it:
+src/main.rs:5:7: 5:21 help: candidate #1: use `std::io::Read`
+error: aborting due to previous error
+");
+        let problems = check.problems();
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].help.len(), 2);
+        assert_eq!(problems[0].suggestions.len(), 1);
+        let suggestion = &problems[0].suggestions[0];
+        assert_eq!(suggestion.replacement, "use std::io::Read;\n");
+        assert_eq!((suggestion.span.start_line, suggestion.span.start_col), (1, 1));
+        assert_eq!((suggestion.span.end_line, suggestion.span.end_col), (1, 1));
+        assert_eq!(suggestion.span.message, "candidate #1: use `std::io::Read`");
+    }
+
+    #[test]
+    fn missing_components_are_recognized() {
+        let component = |stderr: &str| match missing_component(stderr) {
+            Some(CheckError::ComponentMissing { component, install_hint }) =>
+                Some((component, install_hint)),
+            _ => None,
+        };
+        assert_eq!(component("\
+error: 'clippy-driver' is not installed for the toolchain 'stable-x86_64-unknown-linux-gnu'
+"), Some((String::from("clippy"),
+            String::from("rustup component add clippy --toolchain stable-x86_64-unknown-linux-gnu"))));
+        assert_eq!(component("\
+error: 'cargo-clippy' is not installed for the toolchain '1.70.0-x86_64-unknown-linux-gnu'.
+To install, run `rustup component add clippy --toolchain 1.70.0-x86_64-unknown-linux-gnu`
+"), Some((String::from("clippy"),
+            String::from("rustup component add clippy --toolchain 1.70.0-x86_64-unknown-linux-gnu"))));
+        assert_eq!(component("\
+error: \"/home/me/.rustup/toolchains/nightly/lib/rustlib/src/rust/library/Cargo.lock\" does not exist, unable to build with the standard library, try:
+        rustup component add rust-src --toolchain nightly
+"), Some((String::from("rust-src"), String::from("rustup component add rust-src --toolchain nightly"))));
+        assert_eq!(component("error: could not compile `a` due to previous error\n"), None);
+    }
+
+    #[test]
+    fn failed_checks_are_only_errors_without_diagnostics() {
+        let dir = Path::new("/tmp/broken");
+        // From cargo check --message-format short, with status 101
+        let compile_error = "    Checking broken v0.1.0 (/tmp/broken)
+src/lib.rs:2:5: error[E0425]: cannot find value `x` in this scope: not found in this scope
+error: could not compile `broken` (lib) due to 1 previous error
+";
+        let check = parse_output(compile_error);
+        assert!(cargo_failure(dir, Some(101), compile_error, &check).is_none());
+        assert!(cargo_failure(dir, Some(101), compile_error, &Check::Perfect).is_none());
+
+        let no_manifest = "error: could not find `Cargo.toml` in `/tmp/broken` or any parent \
+            directory\n";
+        assert!(matches!(cargo_failure(dir, Some(101), no_manifest, &Check::Perfect),
+            Some(CheckError::InvalidDirectory(ref path)) if path == dir));
+
+        let invalid_manifest = "error: failed to parse manifest at `/tmp/broken/Cargo.toml`\n";
+        let err = cargo_failure(dir, Some(101), invalid_manifest, &Check::Perfect).unwrap();
+        assert_eq!(err.to_string(), "[cargo_failed] cargo exited with status 101: error: \
+            failed to parse manifest at `/tmp/broken/Cargo.toml`");
+        assert!(matches!(err, CheckError::CargoFailed { status: Some(101), ref stderr }
+            if stderr == invalid_manifest));
+    }
+
+    #[test]
+    fn error_kinds_are_stable() {
+        let component = CheckError::ComponentMissing {
+            component: String::from("clippy"),
+            install_hint: String::from("rustup component add clippy"),
+        };
+        let errors = [
+            (CheckError::InvalidDirectory(PathBuf::from("a")), "invalid_directory", false),
+            (CheckError::spawning_cargo(io::Error::from(io::ErrorKind::NotFound)),
+                "cargo_not_found", false),
+            // Other files that aren't found aren't cargo
+            (CheckError::IoError(io::Error::from(io::ErrorKind::NotFound)), "io_error", false),
+            (CheckError::IoError(io::Error::from(io::ErrorKind::Interrupted)), "io_error", true),
+            (CheckError::IoError(io::Error::other("broken pipe")), "io_error", false),
+            (CheckError::TimedOut, "timed_out", true),
+            (CheckError::Cancelled, "cancelled", true),
+            (component, "component_missing", false),
+            (CheckError::CargoFailed { status: None, stderr: String::new() }, "cargo_failed", false),
+        ];
+        for &(ref err, kind, retryable) in &errors {
+            assert_eq!((err.kind(), err.is_retryable()), (kind, retryable), "{:?}", err);
+            assert!(err.to_string().starts_with(&format!("[{}] ", kind)), "{}", err);
+            let json = json::Value::parse(&err.to_json()).unwrap();
+            assert_eq!(json.get("kind").and_then(json::Value::as_str), Some(kind));
+            assert_eq!(json.get("retryable").and_then(json::Value::as_bool), Some(retryable));
+        }
+        assert_eq!(errors[7].0.to_string(), "[component_missing] the toolchain lacks the \
+            'clippy' component, install it with: rustup component add clippy");
+        assert_eq!(errors[7].0.to_json(), "{\"kind\":\"component_missing\",\"message\":\
+            \"the toolchain lacks the 'clippy' component, install it with: rustup component add \
+            clippy\",\"retryable\":false,\"component\":\"clippy\",\
+            \"install_hint\":\"rustup component add clippy\"}");
+    }
+
+    #[test]
+    fn results_can_be_sent_to_other_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Check>();
+        assert_send_sync::<ProblemDescription>();
+        assert_send_sync::<Note>();
+        assert_send_sync::<CheckError>();
+        assert_send_sync::<CheckReport>();
+
+        let err = CheckError::from(io::Error::other("broken pipe"));
+        let boxed: Box<dyn error::Error + Send + Sync> = Box::new(err);
+        let sent = ::std::thread::spawn(move || boxed).join().unwrap();
+        assert_eq!(sent.to_string(), "[io_error] broken pipe");
+        assert_eq!(sent.source().map(|source| source.to_string()),
+            Some(String::from("broken pipe")));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn checks_round_trip_through_serde() {
+        let mut problem = ProblemDescription::new("src/lib.rs", 2, 9, 2, 10,
+            "unused variable: `x`, #[warn(unused_variables)] on by default",
+            vec![Note::new(2, 9, 2, 10, "consider using `_x` instead")], vec![]);
+        problem.suggestions.push(Suggestion {
+            span: Note::new(2, 9, 2, 10, "consider using `_x` instead"),
+            replacement: String::from("_x"),
+            applicability: Applicability::MaybeIncorrect,
+            target: SuggestionTarget::Span,
+        });
+        problem.emitted_at = Some(Duration::from_millis(1500));
+        problem.parse_confidence = Confidence::Heuristic(vec![HEURISTIC_NAMES[1]]);
+        let mut error = problem.clone();
+        error.severity = Severity::Error;
+        for check in [Check::Perfect, Check::Warning(vec![problem]), Check::Error(vec![error])] {
+            let json = serde_json::to_string(&check).unwrap();
+            assert_eq!(serde_json::from_str::<Check>(&json).unwrap(), check, "{}", json);
+        }
+        assert_eq!(serde_json::to_string(&Check::Perfect).unwrap(), "\"Perfect\"");
+        let json = serde_json::to_value(Check::from_problems(vec![
+            ProblemDescription::new("a.rs", 1, 1, 1, 2, "b", vec![], vec![])])).unwrap();
+        assert_eq!(json["Warning"][0]["severity"], "Warning");
+        assert!(serde_json::from_str::<Confidence>("{\"Heuristic\":[\"guess\"]}").is_err());
+    }
+
+    #[test]
+    fn notes_round_trip_through_display() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        let alphabet: Vec<char> = "ab :1\n\t`#[]()é0".chars().collect();
+        for _ in 0..500 {
+            let (start_line, start_col) = (rng.below(1000), rng.below(1000));
+            let (end_line, end_col) = (rng.below(1000), rng.below(1000));
+            let message: String = (0..rng.below(20))
+                .map(|_| alphabet[rng.below(alphabet.len())])
+                .collect();
+            let note = Note::new(start_line, start_col, end_line, end_col, &message[..]);
+            let parsed: Note = note.to_string().parse().unwrap();
+            assert_eq!((parsed.start_line, parsed.start_col, parsed.end_line, parsed.end_col),
+                (start_line, start_col, end_line, end_col));
+            assert_eq!(parsed.message, message);
+        }
+        assert_eq!("1:2: 3:4: 5:6: message".parse::<Note>().unwrap().message, "5:6: message");
+        assert_eq!("1:2 3:4: message".parse::<Note>().err(), Some(ParseNoteError));
+        assert_eq!("1:+2: 3:4: message".parse::<Note>().err(), Some(ParseNoteError));
+    }
+
+    #[test]
+    fn problems_display_for_people() {
+        let mut problem = ProblemDescription::new("src/lib.rs", 3, 5, 3, 12, 
+            "box expression syntax is experimental", 
+            vec![Note::new(3, 5, 3, 12, "add `#![feature(box_syntax)]` to the crate attributes")],
+            vec![]);
+        problem.severity = Severity::Error;
+        assert_eq!(problem.to_string(), "\
+src/lib.rs:3:5: error: box expression syntax is experimental
+    help: 3:5: add `#![feature(box_syntax)]` to the crate attributes");
+    }
+
+    #[test]
+    fn problems_keep_the_text_rustc_printed() {
+        let block = "warning: unused variable: `x`\r\n --> src/lib.rs:2:9\r\n  |\r\n\
+            2 |     let x = 1;\r\n  |         ^ \t\r\n  |\r\n  = note: on by default";
+        let check = parse_output(&format!("{}\r\n\r\n{}", block, PACKAGE_B_OUTPUT));
+        let problems = check.problems();
+        assert_eq!(problems[0].rendered.as_deref(), Some(block));
+        assert_eq!(format!("{:#}", problems[0]), block);
+        assert_eq!(format!("{}", problems[0]), "src/lib.rs:2:9: warning: unused variable: `x`\n    \
+            note: 0:0: on by default");
+        // With the help that was printed after it
+        assert_eq!(problems[1].rendered.as_deref(),
+            Some(&PACKAGE_B_OUTPUT[..PACKAGE_B_OUTPUT.find("\nerror: aborting").unwrap()]));
+    }
+
+    #[test]
+    fn dead_fields_keep_the_span_of_their_struct() {
+        let check = parse_output("\
+src/config.rs:3:5: 3:9 warning: field `port` is never read, #[warn(dead_code)] on by default
+src/config.rs:1:8: 1:14 note: field in this struct
+");
+        let problem = &check.problems()[0];
+        assert_eq!((problem.message.start_line, problem.message.start_col), (3, 5));
+        let item = problem.enclosing_item().unwrap();
+        assert_eq!((item.start_line, item.start_col, item.end_col), (1, 8, 14));
+        assert_eq!(problem.lint(), Some("dead_code"));
+    }
+
+    #[test]
+    fn problems_in_a_file_share_its_path() {
+        let check = parse_output(INTERLEAVED_OUTPUT);
+        let problems = check.problems();
+        assert!(Arc::ptr_eq(&problems[0].filepath, &problems[2].filepath));
+        assert!(! Arc::ptr_eq(&problems[0].filepath, &problems[1].filepath));
+    }
+
+    #[test]
+    fn build_output_with_linker_errors_is_parsed() {
+        let report = parse_output_lenient("\
+   Compiling libc v0.2.150
+   Compiling app v0.1.0 (/work/app)
+src/main.rs:2:9: 2:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+error: linking with `cc` failed: exit status: 1
+  |
+  = note: LC_ALL=\"C\" \"cc\" \"-m64\" \"/work/app/target/debug/deps/app.o\"
+  = note: /usr/bin/ld: cannot find -lssl
+warning: `app` (bin \"app\") generated 1 warning
+error: could not compile `app` (bin \"app\") due to 1 previous error; 1 warning emitted
+");
+        assert_eq!(report.check.problems().len(), 1);
+        let reasons: Vec<SkipReason> = report.raw_blocks.iter().map(|block| block.reason).collect();
+        assert_eq!(reasons, vec![SkipReason::Ignored, SkipReason::Ignored,
+            SkipReason::UnrecognizedFormat, SkipReason::Terminator, SkipReason::Terminator]);
+        assert_eq!(report.raw_blocks[2].line_range, 3..7);
+    }
+
+    #[test]
+    fn parse_output_resynchronizes_after_corrupted_blocks() {
+        let chunks = [
+            "    Checking a v0.1.0 (file:///work/a)\n",
+            "src/lib.rs:2:9: 2:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default\n\
+src/lib.rs:2     let x = 5;\n\
+                     ^\n",
+            "src/lib.rs:3:5: 3:12 error: box expression syntax is experimental\n\
+src/lib.rs:3:5: 3:12 help: add `#![feature(box_syntax)]` to the crate attributes to enable\n",
+            "src/main.rs:4:5: 4:8 error: unresolved name `foo` [E0425]\n\
+src/main.rs:4     foo();\n\
+                  ^~~\n\
+src/main.rs:4:5: 4:8 help: run `rustc --explain E0425` to see a detailed explanation\n\
+src/main.rs:1:1: 1:9 note: defined here\n",
+            "error: aborting due to previous error\n",
+            "src/a.rs:10:1: 12:2 warning: function is never used: `f`, #[warn(dead_code)] on by default\n\
+src/a.rs:10:1: 10:5 note: in this module\n",
+            "src/b.rs:1:1: 1:2 warning: crate `B` should have a snake case name\n\
+src/b.rs:1:1: 1:2 help: convert the identifier to snake case: `b`\n",
+            "   Compiling b v0.1.0 (file:///work/b)\n",
+            "b/src/lib.rs:7:13: 7:14 error: mismatched types [E0308]\n\
+b/src/lib.rs:7:13: 7:14 note: expected `u8`, found `char`\n",
+        ];
+        let text: String = chunks.concat();
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..50 {
+            // Cut out a part of the output, like a cropped line or lost lines
+            let cut_start = rng.below(text.len());
+            let cut_end = cut_start + 1 + rng.below(120).min(text.len() - cut_start - 1);
+            let corrupted = format!("{}{}", &text[..cut_start], &text[cut_end..]);
+
+            // Everything from the first chunk whose line break before it
+            // is left
+            let mut offset = 0;
+            let mut after = Vec::new();
+            for chunk in &chunks {
+                if offset > cut_end {
+                    after.push(*chunk);
+                }
+                offset += chunk.len();
+            }
+            let expected: Vec<String> = parse_output(&after.concat()).problems().iter()
+                .map(ProblemDescription::to_string)
+                .collect();
+            let recovered: Vec<String> = parse_output(&corrupted).problems().iter()
+                .map(ProblemDescription::to_string)
+                .collect();
+            assert!(recovered.ends_with(&expected), "cut {}..{}: {:?} doesn't end with {:?}",
+                cut_start, cut_end, recovered, expected);
+        }
+    }
+
+    #[test]
+    fn mangled_problems_take_their_children_with_them() {
+        let report = parse_output_lenient("\
+src/lib.rs:2:9: 2:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+src/main.rs:4:5: 4:8 erro
+src/main.rs:4:5: 4:8 help: run `rustc --explain E0425` to see a detailed explanation
+src/main.rs:1:1: 1:9 note: defined here
+error[E0308]: mismatched types
+src/b.rs:1:1: 1:2 warning: crate `B` should have a snake case name
+");
+        let problems = report.check.problems();
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].help.is_empty() && problems[0].notes.is_empty());
+        let ranges: Vec<Range<usize>> = report.raw_blocks.iter()
+            .map(|block| block.line_range.clone())
+            .collect();
+        assert_eq!(ranges, vec![1..4, 4..5]);
+    }
+
+    #[test]
+    fn underlined_names_are_additional_primary_spans() {
+        let check = parse_output("\
+src/lib.rs:1:11: 1:13 warning: unused imports: `fs`, `io`, and `mem`, #[warn(unused_imports)] on by default
+src/lib.rs:1 use std::{fs, io, mem};
+                       ^~  ^~  ^~~
+src/lib.rs:2:5: 2:7 warning: unused import: `io`, #[warn(unused_imports)] on by default
+src/lib.rs:2 use io;
+                 ^~
+");
+        let problems = check.problems();
+        let spans: Vec<(usize, usize, usize)> = problems[0].primary_spans().iter()
+            .map(|note| (note.start_line, note.start_col, note.end_col))
+            .collect();
+        assert_eq!(spans, vec![(1, 11, 13), (1, 15, 17), (1, 19, 22)]);
+        assert_eq!(problems[0].additional_primary_spans[1].message, problems[0].message.message);
+        assert_eq!(problems[0].parse_confidence,
+            Confidence::Heuristic(vec!["visual-aid", "underline-columns"]));
+        assert!(problems[1].additional_primary_spans.is_empty());
+    }
+
+    #[test]
+    fn short_format_lines_are_parsed() {
+        let check = parse_output("\
+    Checking errors v0.1.0 (/work/errors)
+src/main.rs:2:22: error[E0308]: mismatched types: expected `u8`, found `&str`
+C:\\work\\src\\lib.rs:3:5: warning: unused variable: `x`: help: prefix it with an underscore: `_x`
+src/main.rs:4:1: info: an unknown level
+src/main.rs:5:1: warning:
+error: could not compile `errors` (bin \"errors\") due to 1 previous error
+");
+        let problems = check.problems();
+        assert_eq!(problems.len(), 3);
+        assert_eq!(problems[0].to_string(),
+            "src/main.rs:2:22: error: mismatched types: expected `u8`, found `&str` [E0308]");
+        assert_eq!(problems[0].error_code(), Some("E0308"));
+        assert_eq!((problems[0].message.end_line, problems[0].message.end_col), (2, 22));
+        assert_eq!(&*problems[1].filepath, "C:\\work\\src\\lib.rs");
+        assert_eq!(problems[2].message.message, "");
+    }
+
+    #[test]
+    fn problems_record_the_heuristics_used_for_them() {
+        let check = parse_output("\
+src/lib.rs:2:9: 2:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+src/lib.rs:3:5: 3:12 error: box expression syntax is experimental
+src/lib.rs:3 let b = box 5;
+                     ^~~~~~~
+src/lib.rs:3:5: 3:12 help: add `#![feature(box_syntax)]` to the crate attributes to enable
+src/lib.rs:4:1: 99999999999999999999999:2 error: expected one of:
+`u8`,`u16`
+src/other.rs:1:1: 1:2 note: defined here
+");
+        let confidences: Vec<&Confidence> = check.problems().iter()
+            .map(|problem| &problem.parse_confidence)
+            .collect();
+        assert_eq!(confidences, vec![
+            &Confidence::Exact,
+            &Confidence::Heuristic(vec!["visual-aid", "suggestion-from-help"]),
+            &Confidence::Heuristic(vec!["message-continuation", "saturated-position",
+                "child-in-other-file"]),
+        ]);
+        assert!(confidences[0].is_exact());
+    }
+
+    #[test]
+    fn parse_output_suggests_crate_attributes() {
+        let check = parse_output("\
+src/net.rs:4:13: 4:20 error: box expression syntax is experimental; you can call `Box::new` instead. (see issue #27779)
+src/net.rs:4:13: 4:20 help: add `#![feature(box_syntax)]` to the crate attributes to enable
+");
+        let suggestions = &check.problems()[0].suggestions;
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].target, SuggestionTarget::CrateRoot);
+        assert_eq!(suggestions[0].replacement, "#![feature(box_syntax)]\n");
+        assert_eq!(suggestions[0].applicability, Applicability::MaybeIncorrect);
+    }
+
+    #[test]
+    fn verify_against_disk_flags_missing_and_short_files() {
+        let dir = temp_project("verify");
+        fs::write(dir.join("src/lib.rs"), "fn main() {\n    let x = 5;\n}\n").unwrap();
+        let check = Check::Warning(vec![
+            warning("src/lib.rs", 2),
+            warning("src/lib.rs", 7),
+            warning("src/gone.rs", 1),
+        ]);
+        let stale = check.verify_against_disk(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(stale.len(), 2);
+        assert_eq!(stale[0].index, 1);
+        assert_eq!(stale[0].reason, StaleReason::TooShort { line_count: 3 });
+        assert_eq!(stale[1].index, 2);
+        assert_eq!(&*stale[1].filepath, "src/gone.rs");
+        assert_eq!(stale[1].reason, StaleReason::Missing);
+    }
 }
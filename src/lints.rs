@@ -0,0 +1,259 @@
+//! The lints known to the toolchain, from the tables of ```rustc -W help```.
+
+use std::io;
+use std::process::Command;
+use std::sync::Mutex;
+
+use CheckError;
+
+/// The level of a lint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+    Forbid,
+}
+
+impl LintLevel {
+    /// Parses a level as written in the lint table.
+    pub fn parse(text: &str) -> Option<LintLevel> {
+        match text {
+            "allow" => Some(LintLevel::Allow),
+            "warn" => Some(LintLevel::Warn),
+            "deny" => Some(LintLevel::Deny),
+            "forbid" => Some(LintLevel::Forbid),
+            _ => None,
+        }
+    }
+}
+
+/// A lint known to the toolchain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintInfo {
+    /// The name as used in attributes, e.g. ```dead_code``` or
+    /// ```clippy::needless_return```.
+    pub name: String,
+    pub default_level: LintLevel,
+    /// The first group that contains the lint, other than the groups of all
+    /// lints (```warnings``` and ```clippy::all```).
+    pub group: Option<String>,
+}
+
+/// A group of lints, like ```unused``` or ```clippy::style```.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintGroup {
+    pub name: String,
+    /// The names of the lints of the group. The table doesn't list the
+    /// lints of ```warnings```, which is every lint that warns.
+    pub members: Vec<String>,
+}
+
+/// The lints and lint groups known to the toolchain.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LintTable {
+    pub lints: Vec<LintInfo>,
+    /// Every group, including the ones of all lints.
+    pub groups: Vec<LintGroup>,
+}
+
+impl LintTable {
+    /// Returns the names of the lints and then of the groups.
+    pub fn names(&self) -> Vec<&str> {
+        self.lints.iter().map(|lint| &lint.name[..])
+            .chain(self.groups.iter().map(|group| &group.name[..]))
+            .collect()
+    }
+}
+
+/// The tables of lints by toolchain, as they don't change between calls.
+static CACHE: Mutex<Vec<(Option<String>, LintTable)>> = Mutex::new(Vec::new());
+
+/// Returns the lints and lint groups of rustc and clippy (when it is
+/// installed) for the toolchain, or the default toolchain if it is
+/// ```None```.
+pub fn list_lints(toolchain: Option<&str>) -> Result<LintTable, CheckError> {
+    let key = toolchain.map(String::from);
+    if let Ok(cache) = CACHE.lock() {
+        if let Some((_, table)) = cache.iter().find(|(toolchain, _)| *toolchain == key) {
+            return Ok(table.clone());
+        }
+    }
+
+    let mut rustc = Command::new("rustc");
+    if let Some(toolchain) = toolchain {
+        rustc.arg(format!("+{}", toolchain));
+    }
+    let output = rustc.args(["-W", "help"]).output()?;
+    if ! output.status.success() {
+        return Err(CheckError::IoError(io::Error::other("rustc -W help failed")));
+    }
+    let mut table = parse_lint_table(&String::from_utf8_lossy(&output.stdout));
+
+    // Clippy prints the rustc lints too; only its own are added, once,
+    // though it prints them for each crate it checks
+    let mut cargo = Command::new("cargo");
+    if let Some(toolchain) = toolchain {
+        cargo.arg(format!("+{}", toolchain));
+    }
+    if let Ok(output) = cargo.args(["clippy", "--", "-W", "help"]).output() {
+        let clippy = parse_lint_table(&String::from_utf8_lossy(&output.stdout));
+        for lint in clippy.lints {
+            if lint.name.starts_with("clippy::") && ! table.names().contains(&&lint.name[..]) {
+                table.lints.push(lint);
+            }
+        }
+        for group in clippy.groups {
+            if group.name.starts_with("clippy::") && ! table.names().contains(&&group.name[..]) {
+                table.groups.push(group);
+            }
+        }
+    }
+
+    if let Ok(mut cache) = CACHE.lock() {
+        cache.push((key, table.clone()));
+    }
+    Ok(table)
+}
+
+/// Returns whether the word of a table is the name of a lint or group,
+/// like ```clippy::style``` or ```rust_2018_idioms```.
+fn is_lint_name(word: &str) -> bool {
+    word.starts_with(|ch: char| ch.is_ascii_lowercase())
+        && word.chars().all(|ch| ch.is_ascii_lowercase() || ch.is_ascii_digit()
+            || ch == '_' || ch == ':')
+}
+
+/// Parses the lint and lint group tables printed by ```rustc -W help```.
+/// Names are given with underscores, as in attributes.
+pub fn parse_lint_table(text: &str) -> LintTable {
+    let mut in_groups = false;
+    let mut lints: Vec<LintInfo> = Vec::new();
+    let mut groups: Vec<LintGroup> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with("Lint checks") && line.ends_with(':') {
+            in_groups = false;
+            continue;
+        } else if line.starts_with("Lint groups") && line.ends_with(':') {
+            in_groups = true;
+            continue;
+        }
+        let mut columns = line.split_whitespace();
+        let name = match columns.next() {
+            Some(name) => name.replace('-', "_"),
+            None => continue,
+        };
+        if in_groups {
+            // The header, the dashes under it and the text after the table
+            if name == "name" || ! is_lint_name(&name) {
+                continue;
+            }
+            // The lints of warnings are described rather than listed
+            let members = match &name[..] {
+                "warnings" => Vec::new(),
+                _ => columns.collect::<Vec<_>>().join(" ").split(',')
+                    .map(|member| member.trim().replace('-', "_"))
+                    .filter(|member| ! member.is_empty())
+                    .collect(),
+            };
+            groups.push(LintGroup { name, members });
+        } else if let Some(level) = columns.next().and_then(LintLevel::parse) {
+            lints.push(LintInfo { name, default_level: level, group: None });
+        }
+    }
+
+    for lint in &mut lints {
+        lint.group = groups.iter()
+            .filter(|group| group.name != "clippy::all")
+            .find(|group| group.members.contains(&lint.name))
+            .map(|group| group.name.clone());
+    }
+    LintTable { lints, groups }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUSTC_HELP: &str = "
+Available lint options:
+    -W <foo>           Warn about <foo>
+    -A <foo>           Allow <foo>
+
+Lint checks provided by rustc:
+
+                                                   name  default  meaning
+                                                   ----  -------  -------
+             absolute-paths-not-starting-with-crate  allow    fully qualified paths that start with a module name
+                                          dead-code  warn     detect unused, unexported items
+                                 arithmetic-overflow  deny     arithmetic operation overflows
+                                      unused-imports  warn     imports that are never used
+
+
+Lint groups provided by rustc:
+
+                                name  sub-lints
+                                ----  ---------
+                            warnings  all lints that are set to issue warnings
+                           rust-2018-idioms  absolute-paths-not-starting-with-crate
+                              unused  dead-code, unused-imports
+
+
+Lint tools like Clippy can load additional lints and lint groups.
+";
+
+    const CLIPPY_HELP: &str = "
+Lint checks loaded by this crate:
+
+                                                   name  default  meaning
+                                                   ----  -------  -------
+                                 clippy::needless-return  warn     using a return statement like `return expr;` where an expression would suffice
+                                    clippy::eq-op  deny     equal operands on both sides of a comparison or bitwise combination
+
+Lint groups loaded by this crate:
+
+                                name  sub-lints
+                                ----  ---------
+                         clippy::all  clippy::needless-return, clippy::eq-op
+                       clippy::style  clippy::needless-return
+                 clippy::correctness  clippy::eq-op
+";
+
+    fn info(name: &str, level: LintLevel, group: Option<&str>) -> LintInfo {
+        LintInfo { name: String::from(name), default_level: level, group: group.map(String::from) }
+    }
+
+    fn group(name: &str, members: &[&str]) -> LintGroup {
+        LintGroup { name: String::from(name),
+            members: members.iter().map(|&member| String::from(member)).collect() }
+    }
+
+    #[test]
+    fn rustc_lint_table_is_parsed_with_groups() {
+        let table = parse_lint_table(RUSTC_HELP);
+        assert_eq!(table.groups, vec![
+            group("warnings", &[]),
+            group("rust_2018_idioms", &["absolute_paths_not_starting_with_crate"]),
+            group("unused", &["dead_code", "unused_imports"]),
+        ]);
+        assert_eq!(table.lints, vec![
+            info("absolute_paths_not_starting_with_crate", LintLevel::Allow,
+                Some("rust_2018_idioms")),
+            info("dead_code", LintLevel::Warn, Some("unused")),
+            info("arithmetic_overflow", LintLevel::Deny, None),
+            info("unused_imports", LintLevel::Warn, Some("unused")),
+        ]);
+    }
+
+    #[test]
+    fn clippy_lint_table_skips_the_all_group() {
+        let table = parse_lint_table(CLIPPY_HELP);
+        assert_eq!(table.groups.len(), 3);
+        assert!(table.names().contains(&"clippy::all"));
+        assert_eq!(table.lints, vec![
+            info("clippy::needless_return", LintLevel::Warn, Some("clippy::style")),
+            info("clippy::eq_op", LintLevel::Deny, Some("clippy::correctness")),
+        ]);
+    }
+}
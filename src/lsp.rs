@@ -0,0 +1,214 @@
+//! The diagnostics of the Language Server Protocol, for editors and plugins
+//! that publish the problems of a check.
+//!
+//! The characters of positions are counted in characters, as the columns of
+//! the problems are, which is the ```utf-32``` position encoding of LSP
+//! 3.17. Clients that only know ```utf-16``` differ on the characters
+//! outside of the Basic Multilingual Plane.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use paths::uri_reference;
+use {Check, LintTool, Note, ProblemDescription, SanitizeRules, Severity};
+
+/// A 0-based position in a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+/// The positions of a span, the end exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LspRange {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// The severity of a diagnostic, with the numbers that LSP gives them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticSeverity {
+    Error = 1,
+    Warning = 2,
+    Information = 3,
+    Hint = 4,
+}
+
+/// A span in a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    /// The ```file://``` URI of the file.
+    pub uri: String,
+    pub range: LspRange,
+}
+
+/// A help or note of a diagnostic, at its own span.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagnosticRelatedInformation {
+    pub location: Location,
+    pub message: String,
+}
+
+/// A diagnostic, as given to ```textDocument/publishDiagnostics```.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub range: LspRange,
+    pub severity: Option<DiagnosticSeverity>,
+    /// The error code or lint of the problem.
+    pub code: Option<String>,
+    pub source: Option<String>,
+    pub message: String,
+    pub related_information: Vec<DiagnosticRelatedInformation>,
+}
+
+/// Returns the ```file://``` URI of the path. Relative paths are resolved
+/// against the root of the project.
+fn file_uri(path: &str, root: &Path) -> String {
+    let absolute = if Path::new(path).is_absolute() {
+        String::from(path)
+    } else {
+        root.join(path).to_string_lossy().into_owned()
+    };
+    let path = uri_reference(&absolute);
+    // The drive of a Windows path comes after a third slash
+    if path.starts_with('/') {
+        format!("file://{}", path)
+    } else {
+        format!("file:///{}", path)
+    }
+}
+
+/// Returns the 0-based range of the span. A span without a line is at the
+/// start of the file, a span without an end column ends where it starts,
+/// and an end before the start is moved to the start.
+fn range(note: &Note) -> LspRange {
+    let start = Position {
+        line: note.start_line.saturating_sub(1),
+        character: note.start_col.saturating_sub(1),
+    };
+    let end = if note.end_col == 0 {
+        start
+    } else {
+        Position { line: note.end_line.saturating_sub(1), character: note.end_col - 1 }
+    };
+    LspRange { start, end: end.max(start) }
+}
+
+impl ProblemDescription {
+    /// Returns the problem as an LSP diagnostic, with its help and notes as
+    /// related information in their own files. The children without a span
+    /// are placed at the span of the problem, and the ones with a span in
+    /// an unknown file are left out. Relative paths are relative to the root
+    /// of the project.
+    pub fn to_lsp_diagnostic(&self, root: &Path) -> Diagnostic {
+        let range = range(&self.message);
+        let uri = file_uri(&self.filepath, root);
+        let severity = match self.severity {
+            Severity::Error => DiagnosticSeverity::Error,
+            Severity::Warning => DiagnosticSeverity::Warning,
+            Severity::Note => DiagnosticSeverity::Information,
+            Severity::Help => DiagnosticSeverity::Hint,
+        };
+        let related_information = self.help.iter().map(|note| ("help", note))
+            .chain(self.notes.iter().map(|note| ("note", note)))
+            .filter_map(|(kind, note)| {
+                let location = match note.file {
+                    _ if note.start_line == 0 => Location { uri: uri.clone(), range },
+                    Some(ref file) => Location {
+                        uri: file_uri(file, root),
+                        range: self::range(note),
+                    },
+                    None => return None,
+                };
+                Some(DiagnosticRelatedInformation {
+                    location,
+                    message: format!("{}: {}", kind, note.message),
+                })
+            })
+            .collect();
+        Diagnostic {
+            range,
+            severity: Some(severity),
+            code: self.error_code().or_else(|| self.lint()).map(String::from),
+            source: Some(String::from(match self.lint_tool() {
+                Some(LintTool::Clippy) => "clippy",
+                _ => "rustc",
+            })),
+            message: self.message.message.clone(),
+            related_information,
+        }
+    }
+}
+
+/// Returns the diagnostics of the problems of the check by the URI of
+/// their file, in the order of the check, with the problems sanitized by the
+/// rules if any. Relative paths are relative to the root of the project.
+pub fn check_to_lsp(check: &Check, root: &Path, sanitize: Option<&SanitizeRules>)
+        -> HashMap<String, Vec<Diagnostic>> {
+    if let Some(rules) = sanitize {
+        return check_to_lsp(&check.sanitized(rules), root, None);
+    }
+    let mut files: HashMap<String, Vec<Diagnostic>> = HashMap::new();
+    for problem in check.problems() {
+        let diagnostic = problem.to_lsp_diagnostic(root);
+        files.entry(file_uri(&problem.filepath, root)).or_default().push(diagnostic);
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parse_output;
+
+    #[test]
+    fn problems_become_diagnostics_of_their_files() {
+        let mut problems = parse_output("\
+/app/src/lib.rs:2:5: 2:6 error: cannot find value `x` in this scope [E0425]
+/app/src/lib.rs:4:9: 4:10 warning: unused variable: `y`, #[warn(unused_variables)] on by default
+src/main.rs:1:1: 1:1 warning: function is never used: `main`
+src/main.rs:3:5: 3:11 warning: unneeded `return` statement, #[warn(clippy::needless_return)] on by default
+").problems().to_vec();
+        problems[0].help.push(Note::new(1, 5, 1, 6,
+            "a local variable with a similar name exists").with_file("/app/src/lib.rs"));
+        problems[0].notes.push(Note::new(0, 0, 0, 0, "see the docs"));
+        problems[0].notes.push(Note::new(7, 1, 7, 4, "the trait is defined here")
+            .with_file("/app/src/traits.rs"));
+        problems[0].notes.push(Note::new(3, 1, 3, 2, "somewhere"));
+        // The end is before the start, and the column is missing
+        problems[1].message.end_line = 3;
+        problems[2].message.end_col = 0;
+        problems[2].severity = Severity::Help;
+        let diagnostics = check_to_lsp(&Check::from_problems(problems), Path::new("/app"), None);
+        assert_eq!(diagnostics.len(), 2);
+
+        let lib = &diagnostics["file:///app/src/lib.rs"];
+        assert_eq!(lib.len(), 2);
+        let error = &lib[0];
+        let at = |line, character| Position { line, character };
+        assert_eq!(error.range, LspRange { start: at(1, 4), end: at(1, 5) });
+        assert_eq!((error.severity, error.code.as_deref()),
+            (Some(DiagnosticSeverity::Error), Some("E0425")));
+        let related: Vec<(&str, LspRange, &str)> = error.related_information.iter()
+            .map(|info| (&info.location.uri[..], info.location.range, &info.message[..]))
+            .collect();
+        // The note in an unknown file is left out
+        assert_eq!(related, [
+            ("file:///app/src/lib.rs", LspRange { start: at(0, 4), end: at(0, 5) },
+                "help: a local variable with a similar name exists"),
+            ("file:///app/src/lib.rs", error.range, "note: see the docs"),
+            ("file:///app/src/traits.rs", LspRange { start: at(6, 0), end: at(6, 3) },
+                "note: the trait is defined here"),
+        ]);
+        assert_eq!(lib[1].range, LspRange { start: at(3, 8), end: at(3, 8) });
+        assert_eq!(lib[1].code.as_deref(), Some("unused_variables"));
+        assert_eq!(error.source.as_deref(), Some("rustc"));
+        // Relative paths are under the root
+        let main = &diagnostics["file:///app/src/main.rs"];
+        assert_eq!((main[0].range, main[0].severity), (LspRange::default(),
+            Some(DiagnosticSeverity::Hint)));
+        assert_eq!(main[1].source.as_deref(), Some("clippy"));
+        assert_eq!(file_uri("/my app/lib.rs", Path::new("/")), "file:///my%20app/lib.rs");
+    }
+}
@@ -0,0 +1,251 @@
+extern crate lintparser;
+
+use std::env;
+use std::process;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::sync::mpsc;
+use std::thread;
+use lintparser::{Check, CheckError, CheckReport, Checker};
+use lintparser::history::History;
+use lintparser::triage::{Decision, TriageDb};
+
+const USAGE: &str = "\
+Usage: lintparser [--summary-only] [--hide-triaged] [--no-publish] [-v|--verbose]
+                  [--correlation-id ID] [--json-errors]
+       lintparser history record [--file PATH] [--commit SHA]
+       lintparser history trend [--file PATH] [--lint NAME] [--days N]
+       lintparser triage list [--file PATH]
+       lintparser triage set FINGERPRINT wontfix|false-positive|fix-by:VERSION [-m NOTE] [--file PATH]
+       lintparser triage stale [--file PATH]";
+
+const DEFAULT_HISTORY: &str = "target/lintparser/history.jsonl";
+
+const DEFAULT_TRIAGE: &str = "lintparser-triage.json";
+
+fn fail(message: &str) -> ! {
+    eprintln!("lintparser: {}\n{}", message, USAGE);
+    process::exit(2);
+}
+
+/// Reports the error on stderr, as JSON with ```--json-errors```, and exits.
+fn check_failed(err: CheckError, json: bool) -> ! {
+    if json {
+        eprintln!("{}", err.to_json());
+    } else {
+        eprintln!("lintparser: could not check the project: {}", err);
+    }
+    process::exit(2);
+}
+
+/// Checks the current directory, with the paths relative to it as in the
+/// fingerprints of the triage database.
+fn run_check() -> Check {
+    match Checker::new().run() {
+        Ok(report) => report.check,
+        Err(err) => check_failed(err, false),
+    }
+}
+
+/// Formats a time as a UTC date (YYYY-MM-DD).
+fn format_date(time: SystemTime) -> String {
+    let days = (time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / 86400) as i64;
+    // Converts days since the epoch to a civil date (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn history(args: &[String]) {
+    let mut file = String::from(DEFAULT_HISTORY);
+    let mut commit = None;
+    let mut lint = None;
+    let mut days = None;
+    let command = args.first().map(|arg| &arg[..]);
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || match iter.next() {
+            Some(value) => value.clone(),
+            None => fail(&format!("missing value for '{}'", arg)),
+        };
+        match &arg[..] {
+            "--file" => file = value(),
+            "--commit" => commit = Some(value()),
+            "--lint" => lint = Some(value()),
+            "--days" => match value().parse::<u64>() {
+                Ok(n) => days = Some(n),
+                Err(_) => fail("'--days' must be a number"),
+            },
+            other => fail(&format!("unknown argument '{}'", other)),
+        }
+    }
+
+    let mut history = match History::open(&file) {
+        Ok(history) => history,
+        Err(err) => fail(&format!("could not read the history '{}': {}", file, err)),
+    };
+    match command {
+        Some("record") => {
+            let mut report = CheckReport::new(run_check());
+            report.commit = commit;
+            if let Err(err) = history.record(&report) {
+                fail(&format!("could not write the history '{}': {}", file, err));
+            }
+            println!("{}", report.check.summary_line());
+        },
+        Some("trend") => {
+            let since = match days {
+                Some(days) => SystemTime::now() - Duration::from_secs(days * 86400),
+                None => UNIX_EPOCH,
+            };
+            for (time, count) in history.trend(lint.as_ref().map(|lint| &lint[..]), since) {
+                println!("{}\t{}", format_date(time), count);
+            }
+        },
+        _ => fail("expected 'record' or 'trend'"),
+    }
+}
+
+fn open_triage(file: &str) -> TriageDb {
+    match TriageDb::open(file) {
+        Ok(db) => db,
+        Err(err) => fail(&format!("could not read the triage database '{}': {}", file, err)),
+    }
+}
+
+fn triage(args: &[String]) {
+    let mut file = String::from(DEFAULT_TRIAGE);
+    let mut note = String::new();
+    let mut positional = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || match iter.next() {
+            Some(value) => value.clone(),
+            None => fail(&format!("missing value for '{}'", arg)),
+        };
+        match &arg[..] {
+            "--file" => file = value(),
+            "-m" => note = value(),
+            other if other.starts_with('-') => fail(&format!("unknown argument '{}'", other)),
+            other => positional.push(other),
+        }
+    }
+
+    let mut db = open_triage(&file);
+    match positional[..] {
+        ["list"] => {
+            for (problem, decision) in run_check().annotate_triage(&db) {
+                let decision = decision.map(|decision| decision.to_string());
+                println!("{}\t{}\t{}", problem.fingerprint(),
+                    decision.as_ref().map(|decision| &decision[..]).unwrap_or("-"), problem);
+            }
+        },
+        ["set", fingerprint, decision] => {
+            let decision = match Decision::parse(decision) {
+                Some(decision) => decision,
+                None => fail(&format!("unknown decision '{}'", decision)),
+            };
+            db.set(fingerprint, decision, note);
+            if let Err(err) = db.save() {
+                fail(&format!("could not write the triage database '{}': {}", file, err));
+            }
+        },
+        ["stale"] => {
+            for fingerprint in db.stale(&run_check()) {
+                println!("{}", fingerprint);
+            }
+        },
+        _ => fail("expected 'list', 'set FINGERPRINT DECISION' or 'stale'"),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(|arg| &arg[..]) == Some("history") {
+        history(&args[1..]);
+        return;
+    }
+    if args.first().map(|arg| &arg[..]) == Some("triage") {
+        triage(&args[1..]);
+        return;
+    }
+
+    let mut summary_only = false;
+    let mut hide_triaged = false;
+    let mut verbose = false;
+    let mut publish = true;
+    let mut json_errors = false;
+    let mut checker = Checker::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match &arg[..] {
+            "--summary-only" => summary_only = true,
+            "--hide-triaged" => hide_triaged = true,
+            "--no-publish" => publish = false,
+            "--json-errors" => json_errors = true,
+            "-v" | "--verbose" => verbose = true,
+            "--correlation-id" => match iter.next() {
+                Some(id) => checker = checker.correlation_id(&id[..]),
+                None => fail("missing value for '--correlation-id'"),
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            },
+            other => fail(&format!("unknown argument '{}'", other)),
+        }
+    }
+
+    let printer = if verbose {
+        let (sender, receiver) = mpsc::channel();
+        checker = checker.trace(sender);
+        Some(thread::spawn(move || {
+            for event in receiver {
+                eprintln!("lintparser: {}", event);
+            }
+        }))
+    } else {
+        None
+    };
+    let report = checker.run();
+    // Dropping the checker hangs up the trace channel, so the printer ends
+    drop(checker);
+    if let Some(printer) = printer {
+        let _ = printer.join();
+    }
+    let mut report = match report {
+        Ok(report) => {
+            if verbose {
+                if let Some(ref id) = report.correlation_id {
+                    eprintln!("lintparser: correlation id {}", id);
+                }
+            }
+            // Other tools can reuse the results instead of checking again
+            if publish {
+                if let Err(err) = report.publish(".", None) {
+                    eprintln!("lintparser: could not publish the diagnostics: {}", err);
+                }
+            }
+            report
+        },
+        Err(err) => check_failed(err, json_errors),
+    };
+    if hide_triaged {
+        report.check = report.check.without_triaged(&open_triage(DEFAULT_TRIAGE));
+    }
+    if ! summary_only {
+        for problem in report.check.problems() {
+            println!("{}", problem);
+        }
+    }
+    println!("{}", report.summary_line());
+    if let Check::Error(_) = report.check {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,378 @@
+//! Reading the JSON messages of ```cargo check --message-format json```,
+//! which unlike the human-readable output is a stable interface.
+//!
+//! Each ```compiler-message``` line becomes a problem like the text parser
+//! reads it: the primary span and message of the diagnostic (with its error
+//! code in brackets), its help and notes, and the suggested replacements of
+//! its spans. Diagnostics without a span, like ```aborting due to 2
+//! previous errors```, are summaries and left out, as are the lines that
+//! aren't compiler messages or aren't JSON.
+
+use std::collections::HashSet;
+
+use bounds::is_bound_step;
+use json::Value;
+use {intern_paths, Applicability, BoundStep, Check, Note, ProblemDescription, Severity,
+    Suggestion, SuggestionTarget};
+
+/// Parses the stdout output of cargo with ```--message-format json``` into
+/// a check.
+pub fn parse_json_messages(stdout: &str) -> Check {
+    Check::from_problems(json_problems(stdout))
+}
+
+/// Returns the problems of the compiler messages among the lines.
+pub fn json_problems(stdout: &str) -> Vec<ProblemDescription> {
+    let mut paths = HashSet::new();
+    stdout.lines()
+        .filter_map(|line| Value::parse(line).ok())
+        .filter_map(|value| message_problem(&value))
+        .map(|mut problem| {
+            intern_paths(&mut problem, &mut paths);
+            problem
+        })
+        .collect()
+}
+
+/// Returns the problem of a message, if it is a compiler message with a
+/// span or an error of the linker.
+pub fn message_problem(value: &Value) -> Option<ProblemDescription> {
+    if value.get("reason").and_then(Value::as_str) != Some("compiler-message") {
+        return None;
+    }
+    let mut problem = diagnostic_problem(value.get("message")?)?;
+    problem.during_package = value.get("package_id").and_then(Value::as_str)
+        .map(package_name);
+    problem.target = value.get("target").and_then(target_name);
+    Some(problem)
+}
+
+/// Returns the name of a target of a message as cargo writes it, like
+/// ```lib```, ```bin "cli"``` or ```build script```.
+fn target_name(target: &Value) -> Option<String> {
+    let kind = target.get("kind")?.as_array()?.first()?.as_str()?;
+    let name = target.get("name")?.as_str()?;
+    Some(match kind {
+        "lib" | "rlib" | "dylib" | "cdylib" | "staticlib" | "proc-macro" => String::from("lib"),
+        "custom-build" => String::from("build script"),
+        kind => format!("{} \"{}\"", kind, name),
+    })
+}
+
+/// Returns the name of the package from its id, which is either like
+/// ```foo 0.1.0 (path+file:///...)``` or a URL like
+/// ```path+file:///.../foo#0.1.0``` or ```...#foo@0.1.0```.
+fn package_name(id: &str) -> String {
+    if let Some(space) = id.find(' ') {
+        return String::from(&id[..space]);
+    }
+    let (url, fragment) = match id.rfind('#') {
+        Some(hash) => (&id[..hash], &id[hash + 1..]),
+        None => (id, ""),
+    };
+    match fragment.find('@') {
+        Some(at) => String::from(&fragment[..at]),
+        None => String::from(url.rsplit('/').next().unwrap_or(url)),
+    }
+}
+
+/// The position of a span in the source.
+struct Span<'a> {
+    file: &'a str,
+    note: Note,
+    replacement: Option<&'a str>,
+    applicability: Applicability,
+    /// The first line of the code of the span.
+    text: Option<&'a str>,
+}
+
+/// Reads a span, following macro expansions out of code that has no file.
+fn read_span<'a>(value: &'a Value, message: &str) -> Option<Span<'a>> {
+    let mut value = value;
+    while value.get("file_name")?.as_str()?.starts_with('<') {
+        value = value.get("expansion")?.get("span")?;
+    }
+    let applicability = match value.get("suggestion_applicability").and_then(Value::as_str) {
+        Some("MachineApplicable") => Applicability::MachineApplicable,
+        Some("MaybeIncorrect") => Applicability::MaybeIncorrect,
+        Some("HasPlaceholders") => Applicability::HasPlaceholders,
+        _ => Applicability::Unspecified,
+    };
+    Some(Span {
+        file: value.get("file_name")?.as_str()?,
+        note: Note {
+            byte_start: value.get("byte_start").and_then(Value::as_usize),
+            byte_end: value.get("byte_end").and_then(Value::as_usize),
+            ..Note::new(
+                value.get("line_start")?.as_usize()?,
+                value.get("column_start")?.as_usize()?,
+                value.get("line_end")?.as_usize()?,
+                value.get("column_end")?.as_usize()?,
+                message,
+            )
+        },
+        replacement: value.get("suggested_replacement").and_then(Value::as_str),
+        applicability,
+        text: value.get("text").and_then(Value::as_array).and_then(|lines| lines.first())
+            .and_then(|line| line.get("text")).and_then(Value::as_str),
+    })
+}
+
+/// Returns the spans of the diagnostic, with the primary ones first.
+fn read_spans<'a>(diagnostic: &'a Value, message: &str) -> Vec<(bool, Span<'a>)> {
+    let mut spans: Vec<(bool, Span)> = diagnostic.get("spans").and_then(Value::as_array)
+        .unwrap_or(&[])
+        .iter()
+        .filter_map(|span| {
+            let primary = span.get("is_primary").and_then(Value::as_bool) == Some(true);
+            read_span(span, message).map(|span| (primary, span))
+        })
+        .collect();
+    spans.sort_by_key(|&(primary, _)| ! primary);
+    spans
+}
+
+fn suggestions(spans: &[(bool, Span)]) -> Vec<Suggestion> {
+    spans.iter()
+        .filter_map(|(_, span)| span.replacement.map(|replacement| Suggestion {
+            span: span.note.clone(),
+            replacement: String::from(replacement),
+            applicability: span.applicability,
+            target: SuggestionTarget::Span,
+        }))
+        .collect()
+}
+
+/// Returns whether the code is an error code like ```E0308``` rather than
+/// the name of a lint.
+pub fn is_error_code(code: &str) -> bool {
+    code.len() == 5 && code.starts_with('E') && code[1..].bytes().all(|b| b.is_ascii_digit())
+}
+
+/// The beginnings of the messages of the errors of the linker.
+const LINK_ERRORS: &[&str] = &[
+    "linking with `",
+    "linker `",
+    "could not find native static library",
+];
+
+/// Returns whether the message is one of an error of the linker, like
+/// ```linking with `cc` failed: exit status: 1```.
+pub fn is_link_error(message: &str) -> bool {
+    LINK_ERRORS.iter().any(|start| message.starts_with(start))
+}
+
+/// Maps a diagnostic onto a problem, if it has a span or is an error of
+/// the linker.
+fn diagnostic_problem(diagnostic: &Value) -> Option<ProblemDescription> {
+    let severity = match diagnostic.get("level")?.as_str()? {
+        "error" | "error: internal compiler error" => Severity::Error,
+        "warning" => Severity::Warning,
+        "note" => Severity::Note,
+        "help" => Severity::Help,
+        _ => return None,
+    };
+    let code = diagnostic.get("code").and_then(|code| code.get("code")).and_then(Value::as_str);
+    let mut message = String::from(diagnostic.get("message")?.as_str()?);
+    if let Some(code) = code.filter(|&code| is_error_code(code)) {
+        message.push_str(&format!(" [{}]", code));
+    }
+    let spans = read_spans(diagnostic, &message);
+    let (file, note, text) = match spans.first() {
+        Some((_, span)) => (span.file, span.note.clone(), span.text),
+        // The errors of the linker are problems of the build without a span
+        None if severity == Severity::Error && is_link_error(&message) => {
+            ("", Note::new(0, 0, 0, 0, ""), None)
+        },
+        None => return None,
+    };
+    let others = spans.get(1..).unwrap_or(&[]);
+
+    let mut problem = ProblemDescription::new(file, note.start_line, note.start_col,
+        note.end_line, note.end_col, &message[..], vec![], vec![]);
+    problem.message.byte_start = note.byte_start;
+    problem.message.byte_end = note.byte_end;
+    problem.severity = severity;
+    problem.code = code.map(String::from);
+    problem.source_line = text.map(String::from);
+    problem.rendered = diagnostic.get("rendered").and_then(Value::as_str)
+        .map(|text| String::from(text.strip_suffix('\n').unwrap_or(text)));
+    problem.additional_primary_spans = others.iter()
+        .filter(|&&(primary, ref span)| primary && span.file == &*problem.filepath)
+        .map(|(_, span)| span.note.clone())
+        .collect();
+    problem.suggestions = suggestions(&spans);
+    for child in diagnostic.get("children").and_then(Value::as_array).unwrap_or(&[]) {
+        let message = match child.get("message").and_then(Value::as_str) {
+            Some(message) => message,
+            None => continue,
+        };
+        let child_spans = read_spans(child, message);
+        let note = child_spans.first().map_or_else(|| Note::new(0, 0, 0, 0, message),
+            |(_, span)| span.note.clone().with_file(span.file));
+        match child.get("level").and_then(Value::as_str) {
+            Some("help") => problem.help.push(note),
+            _ => {
+                if is_bound_step(message) {
+                    problem.bound_chain.push(BoundStep::new(message, child_spans.first()
+                        .map(|(_, span)| (span.file, note.clone()))));
+                }
+                problem.notes.push(note)
+            },
+        }
+        problem.suggestions.extend(suggestions(&child_spans));
+    }
+    problem.find_macro_origin();
+    Some(problem)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use publish::{parse_published, published_text};
+    use {parse_output, CheckReport};
+
+    /// The messages of two warnings and an error, with the artifact and the
+    /// summaries around them.
+    const MESSAGES: &str = r#"{"reason":"compiler-message","package_id":"path+file:///home/me/warnings#0.1.0","target":{"kind":["bin"],"name":"warnings"},"message":{"rendered":"...","$message_type":"diagnostic","children":[{"children":[],"code":null,"level":"note","message":"`#[warn(unused_variables)]` on by default","rendered":null,"spans":[]},{"children":[],"code":null,"level":"help","message":"if this is intentional, prefix it with an underscore","rendered":null,"spans":[{"byte_end":26,"byte_start":20,"column_end":15,"column_start":9,"expansion":null,"file_name":"src/main.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":"_unused","suggestion_applicability":"MachineApplicable","text":[]}]}],"level":"warning","message":"unused variable: `unused`","spans":[{"byte_end":26,"byte_start":20,"column_end":15,"column_start":9,"expansion":null,"file_name":"src/main.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}],"code":{"code":"unused_variables","explanation":null}}}
+{"reason":"compiler-message","package_id":"warnings 0.1.0 (path+file:///home/me/warnings)","target":{"kind":["bin"],"name":"warnings"},"message":{"rendered":"...","$message_type":"diagnostic","children":[],"level":"warning","message":"function `helper` is never used","spans":[{"byte_end":75,"byte_start":69,"column_end":10,"column_start":4,"expansion":null,"file_name":"src/main.rs","is_primary":true,"label":null,"line_end":6,"line_start":6,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}],"code":{"code":"dead_code","explanation":null}}}
+{"reason":"compiler-message","package_id":"path+file:///home/me/errors#errors@0.1.0","target":{"kind":["bin"],"name":"errors"},"message":{"rendered":"...","$message_type":"diagnostic","children":[{"children":[],"code":null,"level":"note","message":"expected due to this","rendered":null,"spans":[{"byte_end":27,"byte_start":24,"column_end":15,"column_start":12,"expansion":null,"file_name":"src/lib.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}]}],"level":"error","message":"mismatched types","spans":[{"byte_end":22,"byte_start":19,"column_end":15,"column_start":12,"expansion":null,"file_name":"src/lib.rs","is_primary":false,"label":"expected due to this","line_end":2,"line_start":2,"suggested_replacement":null,"suggestion_applicability":null,"text":[]},{"byte_end":37,"byte_start":30,"column_end":29,"column_start":22,"expansion":null,"file_name":"src/lib.rs","is_primary":true,"label":"expected `u32`, found `&str`","line_end":2,"line_start":2,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}],"code":{"code":"E0308","explanation":"..."}}}
+{"reason":"compiler-message","package_id":"path+file:///home/me/errors#errors@0.1.0","target":{"kind":["bin"],"name":"errors"},"message":{"rendered":"...","$message_type":"diagnostic","children":[],"level":"error","message":"aborting due to 1 previous error","spans":[],"code":null}}
+{"reason":"compiler-artifact","package_id":"path+file:///home/me/warnings#0.1.0","fresh":true}
+not json at all
+{"reason":"build-finished","success":false}
+"#;
+
+    /// The same problems as the messages, in the short format.
+    const SHORT_OUTPUT: &str = "\
+src/main.rs:2:9: warning: unused variable: `unused`
+src/main.rs:6:4: warning: function `helper` is never used
+src/lib.rs:2:22: error[E0308]: mismatched types
+error: aborting due to 1 previous error
+";
+
+    fn shape(check: &Check) -> Vec<(String, usize, usize, Severity, String)> {
+        check.problems().iter().map(|problem| (String::from(&*problem.filepath),
+            problem.message.start_line, problem.message.start_col, problem.severity,
+            problem.message.message.clone())).collect()
+    }
+
+    #[test]
+    fn messages_have_the_shape_of_the_text_output() {
+        let check = parse_json_messages(MESSAGES);
+        assert!(matches!(check, Check::Error(_)));
+        assert_eq!(shape(&check), shape(&parse_output(SHORT_OUTPUT)));
+
+        let problems = check.problems();
+        assert_eq!(problems[0].message.end_col, 15);
+        assert_eq!(problems[0].lint(), Some("unused_variables"));
+        assert_eq!(problems[0].help[0].message, "if this is intentional, prefix it with an underscore");
+        assert_eq!(problems[0].suggestions[0].replacement, "_unused");
+        assert_eq!(problems[0].suggestions[0].applicability, Applicability::MachineApplicable);
+        // The lint is known from the code without the note
+        assert_eq!(problems[1].lint(), Some("dead_code"));
+        assert_eq!(problems[2].error_code(), Some("E0308"));
+        assert_eq!(problems[2].lint(), None);
+        assert_eq!((problems[2].notes[0].start_line, problems[2].notes[0].start_col), (2, 12));
+        let packages: Vec<Option<&str>> = problems.iter()
+            .map(|problem| problem.during_package.as_deref())
+            .collect();
+        assert_eq!(packages, vec![Some("warnings"), Some("warnings"), Some("errors")]);
+    }
+
+    #[test]
+    fn the_rendered_text_of_rustc_is_kept() {
+        let check = parse_json_messages(r#"{"reason":"compiler-message","message":{"rendered":"warning: unused variable: `x`\n --> src/lib.rs:2:9\n  |\n2 |\tlet x = 1;\n  |         ^ \n\n","level":"warning","message":"unused variable: `x`","spans":[{"file_name":"src/lib.rs","line_start":2,"column_start":9,"line_end":2,"column_end":10,"is_primary":true}]}}
+"#);
+        let problem = &check.problems()[0];
+        let rendered = "warning: unused variable: `x`\n --> src/lib.rs:2:9\n  |\n2 |\tlet x = 1;\n  \
+            |         ^ \n";
+        assert_eq!(problem.rendered.as_deref(), Some(rendered));
+        assert_eq!(format!("{:#}", problem), rendered);
+    }
+
+    #[test]
+    fn unexpected_messages_are_skipped() {
+        let check = parse_json_messages(r#"{"reason":"compiler-message","message":{"level":"warning"}}
+{"reason":"compiler-message","message":{"level":"warning","message":"m","spans":[{"file_name":"src/a.rs"}]}}
+{"reason":"compiler-message","message":{"level":"warning","message":"in a macro","spans":[{"file_name":"<std macros>","line_start":1,"column_start":1,"line_end":1,"column_end":2,"is_primary":true,"expansion":{"span":{"file_name":"src/b.rs","line_start":4,"column_start":5,"line_end":4,"column_end":9,"is_primary":true}}}]}}
+[1, 2
+"#);
+        assert_eq!(check.problems().len(), 1);
+        assert_eq!(&*check.problems()[0].filepath, "src/b.rs");
+        assert_eq!(check.problems()[0].message.start_line, 4);
+    }
+
+    #[test]
+    fn standalone_notes_keep_their_severity() {
+        let check = parse_json_messages(r#"{"reason":"compiler-message","message":{"level":"note","message":"the lint level is defined here","spans":[{"file_name":"src/lib.rs","line_start":1,"column_start":9,"line_end":1,"column_end":17,"is_primary":true}]}}
+"#);
+        assert!(matches!(check, Check::Warning(_)));
+        assert_eq!(check.problems()[0].severity, Severity::Note);
+        assert_eq!(check.problems()[0].to_string(),
+            "src/lib.rs:1:9: note: the lint level is defined here");
+        assert_eq!(check.problems()[0].rendered, None);
+        assert_eq!(check.stats().warnings, 1);
+
+        let mut problems = check.problems().to_vec();
+        problems[0].severity = Severity::Error;
+        assert!(matches!(Check::from_problems(problems), Check::Error(_)));
+    }
+
+    /// A clippy warning with a multi-span suggestion, as ```cargo clippy
+    /// --message-format json``` prints it.
+    const CLIPPY_MESSAGE: &str = r#"{"reason":"compiler-message","package_id":"path+file:///home/me/lints#0.1.0","target":{"kind":["lib"],"name":"lints"},"message":{"rendered":"...","$message_type":"diagnostic","children":[{"children":[],"code":null,"level":"note","message":"`#[warn(clippy::needless_range_loop)]` on by default","rendered":null,"spans":[]},{"children":[],"code":null,"level":"help","message":"consider using an iterator","rendered":null,"spans":[{"byte_end":40,"byte_start":39,"column_end":10,"column_start":9,"expansion":null,"file_name":"src/lib.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":"<item>","suggestion_applicability":"HasPlaceholders","text":[]},{"byte_end":54,"byte_start":44,"column_end":24,"column_start":14,"expansion":null,"file_name":"src/lib.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":"&items","suggestion_applicability":"HasPlaceholders","text":[]}]}],"level":"warning","message":"the loop variable `i` is only used to index `items`","spans":[{"byte_end":54,"byte_start":44,"column_end":24,"column_start":14,"expansion":null,"file_name":"src/lib.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}],"code":{"code":"clippy::needless_range_loop","explanation":null}}}
+"#;
+
+    #[test]
+    fn clippy_messages_keep_their_notes_and_suggestions() {
+        let check = parse_json_messages(CLIPPY_MESSAGE);
+        let problem = &check.problems()[0];
+        assert_eq!(problem.lint(), Some("clippy::needless_range_loop"));
+        assert_eq!(problem.lint_tool(), Some(::LintTool::Clippy));
+        assert_eq!(problem.notes[0].message,
+            "`#[warn(clippy::needless_range_loop)]` on by default");
+        assert_eq!(problem.help[0].message, "consider using an iterator");
+        let replacements: Vec<(usize, &str)> = problem.suggestions.iter()
+            .map(|suggestion| (suggestion.span.start_col, &suggestion.replacement[..]))
+            .collect();
+        assert_eq!(replacements, vec![(9, "<item>"), (14, "&items")]);
+        assert_eq!(check.clippy_only().problems().len(), 1);
+        assert!(check.rustc_only().problems().is_empty());
+    }
+
+    /// Errors with suggestions to borrow and to remove a semicolon, and a
+    /// clippy suggestion that spans lines.
+    const FIX_MESSAGES: &str = r#"{"reason":"compiler-message","package_id":"path+file:///home/me/app#0.1.0","target":{"kind":["lib"],"name":"app"},"message":{"rendered":"...","$message_type":"diagnostic","children":[{"children":[],"code":null,"level":"note","message":"expected reference `&String`\n   found struct `String`","rendered":null,"spans":[]},{"children":[],"code":null,"level":"help","message":"consider borrowing here","rendered":null,"spans":[{"byte_end":61,"byte_start":61,"column_end":12,"column_start":12,"expansion":null,"file_name":"src/lib.rs","is_primary":true,"label":null,"line_end":4,"line_start":4,"suggested_replacement":"&","suggestion_applicability":"MachineApplicable","text":[]}]}],"level":"error","message":"mismatched types","spans":[{"byte_end":65,"byte_start":61,"column_end":16,"column_start":12,"expansion":null,"file_name":"src/lib.rs","is_primary":true,"label":"expected `&String`, found `String`","line_end":4,"line_start":4,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}],"code":{"code":"E0308","explanation":"..."}}}
+{"reason":"compiler-message","package_id":"path+file:///home/me/app#0.1.0","target":{"kind":["lib"],"name":"app"},"message":{"rendered":"...","$message_type":"diagnostic","children":[{"children":[],"code":null,"level":"help","message":"remove this semicolon to return this value","rendered":null,"spans":[{"byte_end":112,"byte_start":111,"column_end":7,"column_start":6,"expansion":null,"file_name":"src/lib.rs","is_primary":true,"label":null,"line_end":9,"line_start":9,"suggested_replacement":"","suggestion_applicability":"MachineApplicable","text":[]}]}],"level":"error","message":"mismatched types","spans":[{"byte_end":98,"byte_start":95,"column_end":21,"column_start":18,"expansion":null,"file_name":"src/lib.rs","is_primary":true,"label":"expected `u32`, found `()`","line_end":8,"line_start":8,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}],"code":{"code":"E0308","explanation":"..."}}}
+{"reason":"compiler-message","package_id":"path+file:///home/me/app#0.1.0","target":{"kind":["lib"],"name":"app"},"message":{"rendered":"...","$message_type":"diagnostic","children":[{"children":[],"code":null,"level":"help","message":"collapse nested if block","rendered":null,"spans":[{"byte_end":190,"byte_start":150,"column_end":6,"column_start":12,"expansion":null,"file_name":"src/lib.rs","is_primary":true,"label":null,"line_end":16,"line_start":12,"suggested_replacement":"if b {\n        c();\n    }","suggestion_applicability":"MachineApplicable","text":[]}]}],"level":"warning","message":"this `else { if .. }` block can be collapsed","spans":[{"byte_end":190,"byte_start":150,"column_end":6,"column_start":12,"expansion":null,"file_name":"src/lib.rs","is_primary":true,"label":null,"line_end":16,"line_start":12,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}],"code":{"code":"clippy::collapsible_else_if","explanation":null}}}
+"#;
+
+    #[test]
+    fn suggestions_keep_their_replacements_verbatim() {
+        let check = parse_json_messages(FIX_MESSAGES);
+        let suggestion = |span: Note, (start, end): (usize, usize), replacement: &str| Suggestion {
+            span: Note { byte_start: Some(start), byte_end: Some(end), ..span },
+            replacement: String::from(replacement),
+            applicability: Applicability::MachineApplicable,
+            target: SuggestionTarget::Span,
+        };
+        let suggestions: Vec<&[Suggestion]> = check.problems().iter()
+            .map(|problem| &problem.suggestions[..])
+            .collect();
+        assert_eq!(suggestions, vec![
+            &[suggestion(Note::new(4, 12, 4, 12, "consider borrowing here"), (61, 61), "&")][..],
+            &[suggestion(Note::new(9, 6, 9, 7, "remove this semicolon to return this value"),
+                (111, 112), "")][..],
+            &[suggestion(Note::new(12, 12, 16, 6, "collapse nested if block"), (150, 190),
+                "if b {\n        c();\n    }")][..],
+        ]);
+
+        let published = published_text(&CheckReport::new(parse_json_messages(FIX_MESSAGES)), None);
+        let loaded = parse_published(&published).unwrap();
+        let loaded: Vec<&[Suggestion]> = loaded.check.problems().iter()
+            .map(|problem| &problem.suggestions[..])
+            .collect();
+        assert_eq!(loaded, suggestions);
+    }
+}
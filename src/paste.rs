@@ -0,0 +1,185 @@
+//! Reading diagnostics out of text that was pasted from a terminal or a CI
+//! log, with shell prompts, timestamps and other chatter around them.
+
+use {parse_output_lenient, Check, RawBlock, SkipReason};
+
+/// A prefix that is stripped from the lines of pasted text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinePrefix {
+    /// The text, like the ```$ ``` of a shell prompt.
+    Literal(String),
+    /// A timestamp like ```2024-05-01T12:00:00Z ```, as CI logs have, with
+    /// fractional seconds and an offset or not.
+    Timestamp,
+}
+
+/// Options for reading pasted text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasteOptions {
+    /// The prefixes that are stripped from the start of each line, as long
+    /// as one of them matches.
+    pub prefixes: Vec<LinePrefix>,
+}
+
+impl Default for PasteOptions {
+    /// Strips timestamps, shell prompts, and the group markers and levels of
+    /// GitHub Actions and Azure Pipelines.
+    fn default() -> PasteOptions {
+        let literals = ["$ ", "##[group]", "##[endgroup]", "##[error]", "##[warning]",
+            "##[section]", "::group::", "::endgroup::"];
+        let mut prefixes = vec![LinePrefix::Timestamp];
+        prefixes.extend(literals.iter().map(|&literal| LinePrefix::Literal(String::from(literal))));
+        PasteOptions { prefixes }
+    }
+}
+
+/// The diagnostics found in pasted text.
+#[derive(Debug)]
+pub struct PasteAnalysis {
+    /// The problems, each with the confidence of its parse in
+    /// ```ProblemDescription::parse_confidence```.
+    pub check: Check,
+    /// The parts of the text that no diagnostic was read from, with their
+    /// prefixes stripped. Blank parts and the lines that cargo prints
+    /// around diagnostics are left out.
+    pub unrecognized: Vec<RawBlock>,
+}
+
+impl PasteAnalysis {
+    /// Whether no diagnostics were found in the text.
+    pub fn is_empty(&self) -> bool {
+        self.check.problems().is_empty()
+    }
+}
+
+/// Returns the length of the timestamp and the space after it at the start
+/// of the line, like ```2024-05-01T12:00:00.123+02:00 ```. A timestamp may
+/// also be all of the line.
+fn timestamp_len(line: &str) -> Option<usize> {
+    let bytes = line.as_bytes();
+    // The digits and separators of 2024-05-01T12:00:00
+    let pattern = b"dddd-dd-ddTdd:dd:dd";
+    if bytes.len() < pattern.len() {
+        return None;
+    }
+    for (&byte, &expected) in bytes.iter().zip(pattern.iter()) {
+        let matches = match expected {
+            b'd' => byte.is_ascii_digit(),
+            b'T' => byte == b'T' || byte == b' ',
+            expected => byte == expected,
+        };
+        if ! matches {
+            return None;
+        }
+    }
+    let mut end = pattern.len();
+    let digits = |from: usize| bytes[from..].iter().take_while(|byte| byte.is_ascii_digit())
+        .count();
+    if bytes.get(end) == Some(&b'.') {
+        end += 1 + digits(end + 1);
+    }
+    match bytes.get(end) {
+        Some(&b'Z') => end += 1,
+        Some(&b'+') | Some(&b'-') if bytes.get(end + 3) == Some(&b':') => end += 6,
+        _ => {},
+    }
+    match bytes.get(end) {
+        None => Some(end),
+        Some(&b' ') => Some(end + 1),
+        _ => None,
+    }
+}
+
+/// Strips the prefixes from the start of the line, as long as one of them
+/// matches, so that a prompt after a timestamp goes too.
+fn strip_prefixes<'a>(mut line: &'a str, prefixes: &[LinePrefix]) -> &'a str {
+    loop {
+        let stripped = prefixes.iter().find_map(|prefix| match *prefix {
+            LinePrefix::Literal(ref literal) => line.strip_prefix(&literal[..])
+                .filter(|_| ! literal.is_empty()),
+            LinePrefix::Timestamp => timestamp_len(line).and_then(|len| line.get(len..)),
+        });
+        match stripped {
+            Some(rest) => line = rest,
+            None => return line,
+        }
+    }
+}
+
+/// Reads the diagnostics out of pasted text, like ```explain_paste_with```
+/// with the default options.
+pub fn explain_paste(text: &str) -> PasteAnalysis {
+    explain_paste_with(text, &PasteOptions::default())
+}
+
+/// Reads whatever diagnostics there are in pasted text, of which the lines
+/// may have prefixes like prompts and timestamps, diagnostics may be cut
+/// off, and anything else may be around them. This never fails: text
+/// without diagnostics gives an empty check.
+///
+/// As a diagnostic has no blank lines, the text is read a paragraph at a
+/// time, so that what follows a diagnostic isn't taken for a part of it.
+pub fn explain_paste_with(text: &str, options: &PasteOptions) -> PasteAnalysis {
+    let lines: Vec<&str> = text.lines()
+        .map(|line| strip_prefixes(line.trim_end_matches('\r'), &options.prefixes))
+        .collect();
+    let mut problems = Vec::new();
+    let mut unrecognized = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let end = lines[start..].iter().position(|line| line.trim().is_empty())
+            .map_or(lines.len(), |blank| start + blank);
+        let report = parse_output_lenient(&lines[start..end].join("\n"));
+        problems.extend(report.check.problems().iter().cloned());
+        for mut block in report.raw_blocks {
+            if block.reason == SkipReason::UnrecognizedFormat && ! block.lines.trim().is_empty() {
+                block.line_range = block.line_range.start + start..block.line_range.end + start;
+                unrecognized.push(block);
+            }
+        }
+        start = end + 1;
+    }
+    PasteAnalysis { check: Check::from_problems(problems), unrecognized }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Confidence;
+
+    #[test]
+    fn diagnostics_are_read_out_of_chatter() {
+        let analysis = explain_paste("\
+hey, any idea why this fails?
+$ cargo check
+2024-05-01T12:00:00.1234567Z ##[group]Run cargo check
+2024-05-01T12:00:01Z error[E0425]: cannot find value `x` in this scope
+2024-05-01T12:00:01Z  --> src/lib.rs:2:5
+2024-05-01T12:00:01Z   |
+2024-05-01T12:00:01Z 2 |     x
+2024-05-01T12:00:01Z   |     ^ not found in this scope
+2024-05-01T12:00:01Z
+thanks!
+");
+        let problems = analysis.check.problems();
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].message.message, "cannot find value `x` in this scope [E0425]");
+        assert_eq!((problems[0].message.start_line, problems[0].message.end_col), (2, 6));
+        assert!(matches!(problems[0].parse_confidence, Confidence::Heuristic(_)));
+        let chatter: Vec<&str> = analysis.unrecognized.iter()
+            .flat_map(|block| block.lines.lines())
+            .collect();
+        assert!(chatter.contains(&"hey, any idea why this fails?"));
+        assert_eq!(analysis.unrecognized.last().map(|block| block.line_range.clone()),
+            Some(9..10));
+        assert!(chatter.contains(&"thanks!"));
+
+        for text in ["", "\u{1b}[31m", "error", "--> :", "1 |", "2024-05-01T12:00:00+0", "$ $ "] {
+            assert!(explain_paste(text).is_empty(), "{:?}", text);
+        }
+        let options = PasteOptions { prefixes: vec![LinePrefix::Literal(String::from("> "))] };
+        let quoted = explain_paste_with("> src/lib.rs:2:9: 2:10 warning: unused variable: `x`",
+            &options);
+        assert_eq!(quoted.check.problems().len(), 1);
+    }
+}
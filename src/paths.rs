@@ -0,0 +1,293 @@
+//! Views of the paths of problems relative to the workspace, relative to
+//! their package, or absolute, computed when they are needed so that the
+//! check itself is never rewritten for one consumer.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::slice;
+
+use human::display_width;
+use json::Value;
+use workspace::cargo_metadata;
+use {Check, CheckError, ProblemDescription};
+
+/// Returns the path as a URI reference, with the separators of Windows
+/// made slashes and the characters that a URI can't have escaped.
+#[cfg(any(feature = "sarif", feature = "lsp"))]
+pub fn uri_reference(path: &str) -> String {
+    let mut uri = String::with_capacity(path.len());
+    for byte in path.replace('\\', "/").bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'.' | b'_' | b'~' | b':' => {
+                uri.push(byte as char)
+            },
+            byte => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// Returns whether a component of a path matches a component of a glob,
+/// with ```*``` and ```?```.
+fn component_matches(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((&'*', rest)) => (0..=name.len()).any(|skip| component_matches(rest, &name[skip..])),
+        Some((&'?', rest)) => ! name.is_empty() && component_matches(rest, &name[1..]),
+        Some((ch, rest)) => name.first() == Some(ch) && component_matches(rest, &name[1..]),
+    }
+}
+
+/// Returns whether the components of a path match those of a glob, where
+/// ```**``` matches any number of components.
+fn components_match(patterns: &[Vec<char>], names: &[Vec<char>]) -> bool {
+    match patterns.split_first() {
+        None => names.is_empty(),
+        Some((pattern, rest)) if pattern[..] == ['*', '*'] => {
+            (0..=names.len()).any(|skip| components_match(rest, &names[skip..]))
+        },
+        Some((pattern, rest)) => names.split_first().is_some_and(|(name, names)| {
+            component_matches(pattern, name) && components_match(rest, names)
+        }),
+    }
+}
+
+/// Returns whether the path matches a glob like the ```crates/*``` of
+/// workspace members or ```src/**/*.rs```, with either separator.
+pub fn glob_matches(glob: &str, path: &Path) -> bool {
+    let names: Vec<Vec<char>> = path.components()
+        .map(|component| component.as_os_str().to_string_lossy().chars().collect())
+        .collect();
+    let patterns: Vec<Vec<char>> = glob.split(['/', '\\'])
+        .filter(|pattern| ! pattern.is_empty() && *pattern != ".")
+        .map(|pattern| pattern.chars().collect())
+        .collect();
+    components_match(&patterns, &names)
+}
+
+/// What the paths of a ```PathView``` are relative to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathBase {
+    /// The root of the workspace, e.g. for CI annotations.
+    Workspace,
+    /// The root of the package containing the file, e.g. for reports per
+    /// crate. Files outside of every package are relative to the workspace.
+    Package,
+    /// Absolute paths, e.g. for editors.
+    Absolute,
+}
+
+/// The roots of a workspace and of its packages.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProjectLayout {
+    pub workspace_root: PathBuf,
+    pub package_roots: Vec<PathBuf>,
+    /// The names of the packages by their root, where they are known.
+    pub package_names: BTreeMap<PathBuf, String>,
+}
+
+impl ProjectLayout {
+    /// Creates a layout of a workspace without packages.
+    pub fn new<P: Into<PathBuf>>(workspace_root: P) -> ProjectLayout {
+        ProjectLayout { workspace_root: workspace_root.into(), ..ProjectLayout::default() }
+    }
+
+    /// Adds the root directory of a package.
+    pub fn add_package<P: Into<PathBuf>>(&mut self, root: P) {
+        self.package_roots.push(root.into());
+    }
+
+    /// Adds the root directory of a package with its name.
+    pub fn add_named_package<P: Into<PathBuf>, N: Into<String>>(&mut self, root: P, name: N) {
+        let root = root.into();
+        self.package_names.insert(root.clone(), name.into());
+        self.package_roots.push(root);
+    }
+
+    /// Returns the name of the package at the root, or else the name of its
+    /// directory.
+    pub fn package_name(&self, root: &Path) -> String {
+        match self.package_names.get(root) {
+            Some(name) => name.clone(),
+            None => root.file_name().map_or_else(String::new,
+                |name| name.to_string_lossy().into_owned()),
+        }
+    }
+
+    /// Returns the root of the innermost package containing the absolute
+    /// path, if any.
+    pub fn package_root(&self, path: &Path) -> Option<&PathBuf> {
+        self.package_roots.iter()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count())
+    }
+
+    /// Reads the layout of the workspace in the directory, with
+    /// ```cargo metadata```.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<ProjectLayout, CheckError> {
+        ProjectLayout::from_metadata(&cargo_metadata(dir.as_ref())?)
+            .ok_or_else(|| CheckError::InvalidDirectory(dir.as_ref().to_path_buf()))
+    }
+
+    /// Builds the layout from the output of ```cargo metadata --no-deps```.
+    fn from_metadata(metadata: &str) -> Option<ProjectLayout> {
+        let metadata = Value::parse(metadata).ok()?;
+        let mut layout = ProjectLayout::new(metadata.get("workspace_root")?.as_str()?);
+        for package in metadata.get("packages")?.as_array()? {
+            let manifest = Path::new(package.get("manifest_path")?.as_str()?);
+            layout.add_named_package(manifest.parent()?, package.get("name")?.as_str()?);
+        }
+        Some(layout)
+    }
+
+    /// Returns the path of a problem (either absolute, or relative to the
+    /// workspace root as cargo reports it) relative to the base. Paths
+    /// outside of the workspace stay absolute.
+    pub fn resolve<'a>(&self, path: &'a Path, base: PathBase) -> Cow<'a, Path> {
+        let absolute = if path.is_absolute() {
+            Cow::Borrowed(path)
+        } else {
+            Cow::Owned(self.workspace_root.join(path))
+        };
+        let root = match base {
+            PathBase::Absolute => return absolute,
+            PathBase::Workspace => &self.workspace_root,
+            PathBase::Package => self.package_root(&absolute).unwrap_or(&self.workspace_root),
+        };
+        match absolute.strip_prefix(root) {
+            Ok(relative) if relative == path => Cow::Borrowed(path),
+            Ok(relative) => Cow::Owned(relative.to_path_buf()),
+            Err(_) => absolute,
+        }
+    }
+}
+
+/// The problems of a check, with their paths relative to a base.
+pub struct PathView<'a> {
+    problems: slice::Iter<'a, ProblemDescription>,
+    layout: &'a ProjectLayout,
+    base: PathBase,
+}
+
+impl<'a> Iterator for PathView<'a> {
+    type Item = (Cow<'a, Path>, &'a ProblemDescription);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let problem = self.problems.next()?;
+        Some((self.layout.resolve(Path::new(&*problem.filepath), self.base), problem))
+    }
+}
+
+impl Check {
+    /// Returns the problems with their paths relative to the base, in the
+    /// layout of the project.
+    pub fn paths<'a>(&'a self, base: PathBase, layout: &'a ProjectLayout) -> PathView<'a> {
+        PathView { problems: self.problems().iter(), layout, base }
+    }
+}
+
+/// Returns the number of columns that the text takes in a terminal.
+fn text_width(text: &str) -> usize {
+    text.chars().map(display_width).sum()
+}
+
+/// Returns the path for a report in at most the number of columns:
+/// relative to the base if it is under it, and otherwise with directories
+/// in its middle elided as ```…```, keeping as many of the directories at
+/// the end as fit, and the first one too if there is room. Components are
+/// elided whole, and the file name never is, so a name that is too wide is
+/// returned alone.
+pub fn shorten_path(path: &Path, base: Option<&Path>, max_width: usize) -> String {
+    let path = base.and_then(|base| path.strip_prefix(base).ok())
+        .filter(|relative| ! relative.as_os_str().is_empty())
+        .unwrap_or(path);
+    let text = path.to_string_lossy();
+    if text_width(&text) <= max_width {
+        return text.into_owned();
+    }
+    let separator = text.chars().find(|&ch| ch == '/' || ch == '\\').unwrap_or('/');
+    let parts: Vec<&str> = text.split(separator).collect();
+    // The root of an absolute path goes with its first directory
+    let head_len = if parts[0].is_empty() { 2 } else { 1 };
+    let join = |parts: &[&str]| parts.join(&separator.to_string());
+    for tail_len in (1..parts.len()).rev() {
+        let tail = join(&parts[parts.len() - tail_len..]);
+        let mut candidates = vec![format!("…{}{}", separator, tail)];
+        if parts.len() - tail_len > head_len {
+            let head = join(&parts[..head_len]);
+            candidates.insert(0, format!("{}{}…{}{}", head, separator, separator, tail));
+        }
+        if let Some(fitting) = candidates.into_iter().find(|shown| text_width(shown) <= max_width) {
+            return fitting;
+        }
+    }
+    String::from(parts[parts.len() - 1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parse_output;
+
+    #[test]
+    fn paths_are_shortened_at_directories() {
+        let path = Path::new("/home/user/work/project/src/foo/bar.rs");
+        let shorten = |width| shorten_path(path, None, width);
+        assert_eq!(shorten(80), "/home/user/work/project/src/foo/bar.rs");
+        assert_eq!(shorten(37), "…/user/work/project/src/foo/bar.rs");
+        assert_eq!(shorten(29), "…/work/project/src/foo/bar.rs");
+        assert_eq!(shorten(23), "/home/…/src/foo/bar.rs");
+        assert_eq!(shorten(16), "…/src/foo/bar.rs");
+        assert_eq!(shorten(14), "…/foo/bar.rs");
+        assert_eq!(shorten(10), "…/bar.rs");
+        assert_eq!(shorten(3), "bar.rs");
+
+        // The base comes first, and the paths outside of it are kept
+        let base = Path::new("/home/user/work/project");
+        assert_eq!(shorten_path(path, Some(base), 80), "src/foo/bar.rs");
+        assert_eq!(shorten_path(path, Some(base), 13), "…/foo/bar.rs");
+        assert_eq!(shorten_path(path, Some(Path::new("/elsewhere")), 14), "…/foo/bar.rs");
+
+        // The wide characters take two columns each
+        let wide = Path::new("データ/ファイル/lib.rs");
+        assert_eq!(shorten_path(wide, None, 22), "データ/ファイル/lib.rs");
+        assert_eq!(shorten_path(wide, None, 20), "…/ファイル/lib.rs");
+        assert_eq!(shorten_path(Path::new("src\\foo\\bar.rs"), None, 10), "…\\bar.rs");
+    }
+
+    #[test]
+    fn paths_are_viewed_from_each_base() {
+        let metadata = r#"{"packages": [
+            {"name": "core", "manifest_path": "/work/ws/core/Cargo.toml"},
+            {"name": "ws", "manifest_path": "/work/ws/Cargo.toml"}
+        ], "workspace_root": "/work/ws"}"#;
+        let layout = ProjectLayout::from_metadata(metadata).unwrap();
+        let check = parse_output("\
+core/src/lib.rs:2:9: 2:10 warning: unused variable: `x`
+/work/ws/src/main.rs:3:9: 3:10 warning: unused variable: `y`
+/home/me/.cargo/registry/src/dep/lib.rs:4:9: 4:10 warning: unused variable: `z`
+");
+        let view = |base| -> Vec<(PathBuf, bool)> {
+            check.paths(base, &layout)
+                .map(|(path, _)| (path.to_path_buf(), matches!(path, Cow::Borrowed(_))))
+                .collect()
+        };
+        let dep = PathBuf::from("/home/me/.cargo/registry/src/dep/lib.rs");
+        assert_eq!(view(PathBase::Workspace), vec![
+            (PathBuf::from("core/src/lib.rs"), true),
+            (PathBuf::from("src/main.rs"), false),
+            (dep.clone(), true),
+        ]);
+        assert_eq!(view(PathBase::Package), vec![
+            (PathBuf::from("src/lib.rs"), false),
+            (PathBuf::from("src/main.rs"), false),
+            (dep.clone(), true),
+        ]);
+        assert_eq!(view(PathBase::Absolute), vec![
+            (PathBuf::from("/work/ws/core/src/lib.rs"), false),
+            (PathBuf::from("/work/ws/src/main.rs"), true),
+            (dep, true),
+        ]);
+    }
+}
@@ -0,0 +1,186 @@
+//! Overrides of the severity of problems by lint.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use {Check, LintTable, Severity};
+
+/// A problem with a severity policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigWarning {
+    /// The policy names a lint (or group) that the toolchain doesn't know,
+    /// with the most similar known name, if one is close enough.
+    UnknownLint { name: String, suggestion: Option<String> },
+    /// A line of the ```[budget]``` section of a config isn't a quota like
+    /// ```dead_code = 25```.
+    InvalidQuota { line: usize, text: String },
+}
+
+/// Formats the warning, e.g. ```unknown lint `dead-code`, did you mean `dead_code`?```.
+impl fmt::Display for ConfigWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigWarning::UnknownLint { ref name, ref suggestion } => {
+                write!(f, "unknown lint `{}`", name)?;
+                match *suggestion {
+                    Some(ref suggestion) => write!(f, ", did you mean `{}`?", suggestion),
+                    None => Ok(()),
+                }
+            },
+            ConfigWarning::InvalidQuota { line, ref text } =>
+                write!(f, "line {}: `{}` isn't a quota like `dead_code = 25`", line, text),
+        }
+    }
+}
+
+/// The severities that problems of lints are reported with, overriding the
+/// ones of the compiler.
+#[derive(Debug, Clone, Default)]
+pub struct SeverityPolicy {
+    overrides: BTreeMap<String, Severity>,
+    test_code: Option<Severity>,
+}
+
+impl SeverityPolicy {
+    /// Creates a policy without overrides.
+    pub fn new() -> SeverityPolicy {
+        SeverityPolicy::default()
+    }
+
+    /// Sets the severity of the problems of a lint, or of the lints of a
+    /// group.
+    pub fn set<T: Into<String>>(&mut self, lint: T, severity: Severity) {
+        self.overrides.insert(lint.into(), severity);
+    }
+
+    /// Sets the severity of the problems of lints in test code (see
+    /// ```ProblemDescription::in_test_code```), over the one set for their
+    /// lint. Problems without a lint, like compile errors, keep theirs.
+    pub fn set_test_code(&mut self, severity: Severity) {
+        self.test_code = Some(severity);
+    }
+
+    /// Returns the check with the severities of the policy applied. A lint
+    /// overrides the group that enabled it.
+    pub fn apply(&self, check: &Check) -> Check {
+        let problems = check.problems().iter().map(|problem| {
+            let mut problem = problem.clone();
+            let severity = problem.lint().and_then(|lint| self.overrides.get(lint))
+                .or_else(|| problem.lint_group().and_then(|group| self.overrides.get(group)))
+                .cloned();
+            let in_tests = problem.lint().is_some() && problem.in_test_code == Some(true);
+            if let Some(severity) = self.test_code.filter(|_| in_tests).or(severity) {
+                problem.severity = severity;
+            }
+            problem
+        }).collect();
+        Check::from_problems(problems)
+    }
+
+    /// Returns warnings about the lints of the policy that are neither lints
+    /// nor groups of the table, e.g. from ```list_lints```.
+    pub fn validate(&self, table: &LintTable) -> Vec<ConfigWarning> {
+        let known = table.names();
+        self.overrides.keys()
+            .filter(|name| ! known.contains(&&name[..]))
+            .map(|name| ConfigWarning::UnknownLint {
+                name: name.clone(),
+                suggestion: closest(name, &known).map(String::from),
+            })
+            .collect()
+    }
+}
+
+/// Returns the known name closest to the name, if the edit distance is at
+/// most a third of its length (but at least one edit).
+fn closest<'a>(name: &str, known: &[&'a str]) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(1);
+    known.iter()
+        .map(|&candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= limit)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// The Levenshtein distance between the texts, in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..b.len() + 1).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == cb { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {parse_output, LintGroup, LintInfo, LintLevel};
+
+    fn lint(name: &str, group: Option<&str>) -> LintInfo {
+        LintInfo { name: String::from(name), default_level: LintLevel::Warn,
+            group: group.map(String::from) }
+    }
+
+    fn group(name: &str, members: &[&str]) -> LintGroup {
+        LintGroup { name: String::from(name),
+            members: members.iter().map(|&member| String::from(member)).collect() }
+    }
+
+    #[test]
+    fn validate_suggests_close_names() {
+        let mut policy = SeverityPolicy::new();
+        policy.set("dead-code", Severity::Error);
+        policy.set("unused", Severity::Error);
+        policy.set("clippy::needles_return", Severity::Error);
+        policy.set("no_such_thing", Severity::Warning);
+        // Groups are known whether or not they are the first of a lint
+        policy.set("warnings", Severity::Error);
+        policy.set("clippy::all", Severity::Warning);
+        policy.set("clippy::style", Severity::Warning);
+        let table = LintTable {
+            lints: vec![lint("dead_code", Some("unused")),
+                lint("clippy::needless_return", Some("clippy::style"))],
+            groups: vec![group("warnings", &[]), group("unused", &["dead_code"]),
+                group("clippy::all", &["clippy::needless_return"]),
+                group("clippy::style", &["clippy::needless_return"]),
+                group("clippy::pedantic", &["clippy::needless_return"])],
+        };
+        policy.set("clippy::pedantic", Severity::Warning);
+        let warnings: Vec<String> = policy.validate(&table).iter().map(|w| w.to_string())
+            .collect();
+        assert_eq!(warnings, vec![
+            "unknown lint `clippy::needles_return`, did you mean `clippy::needless_return`?",
+            "unknown lint `dead-code`, did you mean `dead_code`?",
+            "unknown lint `no_such_thing`",
+        ]);
+    }
+
+    #[test]
+    fn apply_overrides_severities_by_lint() {
+        let check = parse_output("\
+src/lib.rs:2:9: 2:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+src/lib.rs:3:9: 3:10 warning: unused import, #[warn(unused_imports)] on by default
+tests/it.rs:2:9: 2:10 warning: unused variable: `y`, #[warn(unused_variables)] on by default
+tests/it.rs:4:5: 4:8 error: unresolved name `foo` [E0425]
+");
+        let mut policy = SeverityPolicy::new();
+        policy.set("unused_variables", Severity::Error);
+        let severities = |check: &Check| -> Vec<Severity> {
+            check.problems().iter().map(|p| p.severity).collect()
+        };
+        let applied = policy.apply(&check);
+        assert_eq!(severities(&applied),
+            vec![Severity::Error, Severity::Warning, Severity::Error, Severity::Error]);
+        assert!(matches!(applied, Check::Error(_)));
+
+        policy.set_test_code(Severity::Warning);
+        assert_eq!(severities(&policy.apply(&check)),
+            vec![Severity::Error, Severity::Warning, Severity::Warning, Severity::Error]);
+    }
+}
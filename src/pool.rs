@@ -0,0 +1,205 @@
+//! Coalescing of checks that are requested in quick succession, like one
+//! for every save in an editor.
+//!
+//! There is at most one running and one queued check per project. A request
+//! while a check is running queues another one, as the running check may
+//! have started before the change that prompted the request. Any further
+//! requests join the queued check, and every ticket of a check gets the
+//! same result, including when the check fails or its runner panics.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use {CheckError, CheckReport, Checker};
+
+/// The shared result of a check.
+pub type CheckResult = Arc<Result<CheckReport, CheckError>>;
+
+type Runner = dyn Fn(&Path) -> Result<CheckReport, CheckError> + Send + Sync;
+
+/// A check that tickets wait for.
+#[derive(Default)]
+struct PendingCheck {
+    result: Mutex<Option<CheckResult>>,
+    done: Condvar,
+}
+
+impl PendingCheck {
+    fn finish(&self, result: CheckResult) {
+        let mut slot = self.result.lock().unwrap_or_else(|err| err.into_inner());
+        *slot = Some(result);
+        self.done.notify_all();
+    }
+}
+
+/// The queued check of each project with a running check, if any.
+type Slots = Mutex<HashMap<PathBuf, Option<Arc<PendingCheck>>>>;
+
+/// The result of a requested check, once it has run.
+pub struct CheckTicket {
+    check: Arc<PendingCheck>,
+}
+
+impl CheckTicket {
+    /// Waits for the check to finish and returns its result.
+    pub fn wait(&self) -> CheckResult {
+        let mut result = self.check.result.lock().unwrap_or_else(|err| err.into_inner());
+        loop {
+            if let Some(ref result) = *result {
+                return result.clone();
+            }
+            result = self.check.done.wait(result).unwrap_or_else(|err| err.into_inner());
+        }
+    }
+
+    /// Returns the result, if the check has finished.
+    pub fn try_result(&self) -> Option<CheckResult> {
+        self.check.result.lock().unwrap_or_else(|err| err.into_inner()).clone()
+    }
+}
+
+/// Runs the requested checks of projects on background threads, with the
+/// options of a checker.
+#[derive(Clone)]
+pub struct CheckerPool {
+    runner: Arc<Runner>,
+    slots: Arc<Slots>,
+}
+
+impl CheckerPool {
+    /// Creates a pool that runs checks with the options of the checker.
+    pub fn new(checker: Checker) -> CheckerPool {
+        CheckerPool::with_runner(move |dir| checker.clone().dir(dir).run())
+    }
+
+    /// Creates a pool that runs checks with the function.
+    pub fn with_runner<F>(runner: F) -> CheckerPool
+            where F: Fn(&Path) -> Result<CheckReport, CheckError> + Send + Sync + 'static {
+        CheckerPool { runner: Arc::new(runner), slots: Arc::new(Mutex::new(HashMap::new())) }
+    }
+
+    /// Requests a check of the project in the directory. Requests for the
+    /// same directory (by its canonical path) are coalesced.
+    pub fn request_check<P: AsRef<Path>>(&self, dir: P) -> CheckTicket {
+        let dir = fs::canonicalize(dir.as_ref()).unwrap_or_else(|_| dir.as_ref().to_path_buf());
+        let mut slots = self.slots.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(queued) = slots.get_mut(&dir) {
+            let queued = queued.get_or_insert_with(Default::default);
+            return CheckTicket { check: queued.clone() };
+        }
+        slots.insert(dir.clone(), None);
+        let check = Arc::new(PendingCheck::default());
+
+        let (runner, slots) = (self.runner.clone(), self.slots.clone());
+        let running = check.clone();
+        thread::spawn(move || work(&*runner, &slots, &dir, running));
+        CheckTicket { check }
+    }
+}
+
+/// Runs the runner on the project, with a panic of it as an error, so that
+/// the tickets of the check don't wait forever.
+fn run_once(runner: &Runner, dir: &Path) -> Result<CheckReport, CheckError> {
+    panic::catch_unwind(AssertUnwindSafe(|| runner(dir))).unwrap_or_else(|payload| {
+        let message = payload.downcast_ref::<&str>().map(|message| String::from(*message))
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        let message = format!("the check of '{}' panicked: {}", dir.display(), message);
+        Err(CheckError::IoError(io::Error::other(message)))
+    })
+}
+
+/// Runs the checks of the project until none is queued.
+fn work(runner: &Runner, slots: &Slots, dir: &Path, mut check: Arc<PendingCheck>) {
+    loop {
+        check.finish(Arc::new(run_once(runner, dir)));
+        let mut slots = slots.lock().unwrap_or_else(|err| err.into_inner());
+        match slots.get_mut(dir).and_then(Option::take) {
+            Some(next) => check = next,
+            None => {
+                slots.remove(dir);
+                return;
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use Check;
+
+    #[test]
+    fn rapid_requests_coalesce_into_one_queued_check() {
+        let spawns = Arc::new(AtomicUsize::new(0));
+        let counter = spawns.clone();
+        let pool = CheckerPool::with_runner(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(300));
+            Ok(CheckReport::new(Check::Perfect))
+        });
+        let first = pool.request_check("pool-project");
+        let requests: Vec<_> = (0..8).map(|_| {
+            let pool = pool.clone();
+            thread::spawn(move || pool.request_check("pool-project"))
+        }).collect();
+        let tickets: Vec<CheckTicket> = requests.into_iter()
+            .map(|request| request.join().unwrap())
+            .collect();
+
+        let first = first.wait();
+        let results: Vec<CheckResult> = tickets.iter().map(CheckTicket::wait).collect();
+        assert_eq!(spawns.load(Ordering::SeqCst), 2);
+        assert!(results.iter().all(|result| Arc::ptr_eq(result, &results[0])));
+        assert!(! Arc::ptr_eq(&first, &results[0]));
+
+        // The pool is idle again afterwards
+        assert!(pool.request_check("pool-project").wait().is_ok());
+        assert_eq!(spawns.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn every_ticket_gets_the_failure() {
+        let pool = CheckerPool::with_runner(|_| {
+            thread::sleep(Duration::from_millis(100));
+            Err(CheckError::InvalidDirectory(PathBuf::from("missing")))
+        });
+        let tickets: Vec<CheckTicket> = (0..3).map(|_| pool.request_check("missing")).collect();
+        for ticket in &tickets {
+            assert!(matches!(*ticket.wait(), Err(CheckError::InvalidDirectory(_))));
+        }
+        assert!(tickets[0].try_result().is_some());
+    }
+
+    #[test]
+    fn panicking_runners_fail_the_tickets_and_free_the_project() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = runs.clone();
+        let pool = CheckerPool::with_runner(move |_| {
+            if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                thread::sleep(Duration::from_millis(100));
+                panic!("runner bug");
+            }
+            Ok(CheckReport::new(Check::Perfect))
+        });
+        let first = pool.request_check("panicking-project");
+        let queued = pool.request_check("panicking-project");
+        match *first.wait() {
+            Err(ref err @ CheckError::IoError(_)) => {
+                assert!(err.to_string().contains("panicked: runner bug"), "{}", err);
+            },
+            ref other => panic!("Expected the panic as an error, got {:?}", other),
+        }
+        // The queued check still runs, and so do later ones
+        assert!(queued.wait().is_ok());
+        assert!(pool.request_check("panicking-project").wait().is_ok());
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+}
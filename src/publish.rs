@@ -0,0 +1,492 @@
+//! Publishing check results at a conventional location in the target
+//! directory, so that several tools can share one check of a project.
+//!
+//! The file has one JSON object per line: a header with the metadata of the
+//! report, then one line per problem. It is replaced as a whole by renaming
+//! a complete temporary file over it, so readers never see a file that is
+//! partly written, and concurrent writers can't interleave their problems.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, UNIX_EPOCH};
+
+use grouping::module_path;
+use json::Value;
+use {Applicability, BoundStep, Check, CheckReport, Checker, Confidence, Note,
+    ProblemDescription, SanitizeRules, Severity, Suggestion, SuggestionTarget, HEURISTIC_NAMES};
+use stable_hash;
+
+/// Where the diagnostics are published, relative to the target directory.
+pub const PUBLISHED_FILE: &str = "lintparser/diagnostics.jsonl";
+
+/// Returns where the diagnostics of the project under the root are
+/// published: in the target directory that cargo builds it in (see
+/// ```Checker::target_dir_in_effect```), by default
+/// ```target/lintparser/diagnostics.jsonl```.
+pub fn published_path<P: AsRef<Path>>(root: P) -> PathBuf {
+    Checker::new().dir(root.as_ref()).target_dir_in_effect().join(PUBLISHED_FILE)
+}
+
+const FORMAT: &str = "lintparser-diagnostics";
+const VERSION: u64 = 1;
+
+fn note_to_json(note: &Note) -> Value {
+    Value::object(vec![
+        ("line", Value::from(note.start_line)),
+        ("col", Value::from(note.start_col)),
+        ("end_line", Value::from(note.end_line)),
+        ("end_col", Value::from(note.end_col)),
+        ("message", Value::from(&note.message[..])),
+        ("byte_start", Value::from(note.byte_start)),
+        ("byte_end", Value::from(note.byte_end)),
+        ("file", Value::from(note.file.as_deref())),
+    ])
+}
+
+fn note_from_json(value: &Value) -> Option<Note> {
+    // Reports published before byte offsets were have none
+    let offset = |key| match value.get(key) {
+        None | Some(&Value::Null) => Some(None),
+        Some(offset) => offset.as_usize().map(Some),
+    };
+    // Reports published before the files of notes were have none
+    let file = match value.get("file") {
+        None | Some(&Value::Null) => None,
+        Some(file) => Some(Arc::from(file.as_str()?)),
+    };
+    Some(Note {
+        byte_start: offset("byte_start")?,
+        byte_end: offset("byte_end")?,
+        file,
+        ..Note::new(
+            value.get("line")?.as_usize()?,
+            value.get("col")?.as_usize()?,
+            value.get("end_line")?.as_usize()?,
+            value.get("end_col")?.as_usize()?,
+            value.get("message")?.as_str()?,
+        )
+    })
+}
+
+fn notes_from_json(value: &Value) -> Option<Vec<Note>> {
+    value.as_array()?.iter().map(note_from_json).collect()
+}
+
+fn suggestion_to_json(suggestion: &Suggestion) -> Value {
+    let applicability = match suggestion.applicability {
+        Applicability::MachineApplicable => "machine-applicable",
+        Applicability::MaybeIncorrect => "maybe-incorrect",
+        Applicability::HasPlaceholders => "has-placeholders",
+        Applicability::Unspecified => "unspecified",
+    };
+    let target = match suggestion.target {
+        SuggestionTarget::Span => "span",
+        SuggestionTarget::CrateRoot => "crate-root",
+        SuggestionTarget::FileTop => "file-top",
+    };
+    Value::object(vec![
+        ("span", note_to_json(&suggestion.span)),
+        ("replacement", Value::from(&suggestion.replacement[..])),
+        ("applicability", Value::from(applicability)),
+        ("target", Value::from(target)),
+    ])
+}
+
+fn suggestion_from_json(value: &Value) -> Option<Suggestion> {
+    let applicability = match value.get("applicability")?.as_str()? {
+        "machine-applicable" => Applicability::MachineApplicable,
+        "maybe-incorrect" => Applicability::MaybeIncorrect,
+        "has-placeholders" => Applicability::HasPlaceholders,
+        "unspecified" => Applicability::Unspecified,
+        _ => return None,
+    };
+    let target = match value.get("target")?.as_str()? {
+        "span" => SuggestionTarget::Span,
+        "crate-root" => SuggestionTarget::CrateRoot,
+        "file-top" => SuggestionTarget::FileTop,
+        _ => return None,
+    };
+    Some(Suggestion {
+        span: note_from_json(value.get("span")?)?,
+        replacement: String::from(value.get("replacement")?.as_str()?),
+        applicability,
+        target,
+    })
+}
+
+fn bound_step_to_json(step: &BoundStep) -> Value {
+    Value::object(vec![
+        ("description", Value::from(&step.description[..])),
+        ("span", step.span.as_ref().map_or(Value::Null, note_to_json)),
+        ("in_dependency", Value::from(step.in_dependency)),
+    ])
+}
+
+fn bound_step_from_json(value: &Value) -> Option<BoundStep> {
+    Some(BoundStep {
+        description: String::from(value.get("description")?.as_str()?),
+        span: match *value.get("span")? {
+            Value::Null => None,
+            ref span => Some(note_from_json(span)?),
+        },
+        in_dependency: value.get("in_dependency")?.as_bool()?,
+    })
+}
+
+fn problem_to_json(problem: &ProblemDescription) -> Value {
+    let severity = problem.severity.name();
+    let emitted_ms = problem.emitted_at.map(|at| at.as_millis() as u64);
+    Value::object(vec![
+        ("file", Value::from(&problem.filepath[..])),
+        ("severity", Value::from(severity)),
+        ("message", note_to_json(&problem.message)),
+        ("help", Value::from(problem.help.iter().map(note_to_json).collect::<Vec<_>>())),
+        ("notes", Value::from(problem.notes.iter().map(note_to_json).collect::<Vec<_>>())),
+        ("suggestions", Value::from(problem.suggestions.iter().map(suggestion_to_json)
+            .collect::<Vec<_>>())),
+        ("emitted_ms", Value::from(emitted_ms)),
+        ("package", Value::from(problem.during_package.clone())),
+        ("target", Value::from(problem.target.clone())),
+        ("in_test_code", Value::from(problem.in_test_code)),
+        ("code", Value::from(problem.code.clone())),
+        ("additional_spans", Value::from(problem.additional_primary_spans.iter()
+            .map(note_to_json)
+            .collect::<Vec<_>>())),
+        ("bound_chain", Value::from(problem.bound_chain.iter().map(bound_step_to_json)
+            .collect::<Vec<_>>())),
+        ("source_line", Value::from(problem.source_line.clone())),
+        ("drifted", Value::from(problem.drifted)),
+        ("rendered", Value::from(problem.rendered.clone())),
+        ("count", Value::from(problem.count)),
+        ("targets", Value::from(problem.targets.iter().map(|target| Value::from(&target[..]))
+            .collect::<Vec<_>>())),
+        ("heuristics", Value::from(match problem.parse_confidence {
+            Confidence::Exact => Vec::new(),
+            Confidence::Heuristic(ref names) => names.iter().map(|&name| Value::from(name))
+                .collect(),
+        })),
+    ])
+}
+
+fn problem_from_json(value: &Value) -> Option<ProblemDescription> {
+    let severity = Severity::from_name(value.get("severity")?.as_str()?)?;
+    let emitted_at = match *value.get("emitted_ms")? {
+        Value::Null => None,
+        ref ms => Some(Duration::from_millis(ms.as_u64()?)),
+    };
+    let during_package = match *value.get("package")? {
+        Value::Null => None,
+        ref package => Some(String::from(package.as_str()?)),
+    };
+    // Reports published before targets were have none
+    let target = match value.get("target") {
+        None | Some(&Value::Null) => None,
+        Some(target) => Some(String::from(target.as_str()?)),
+    };
+    // Reports published before codes were have none
+    let code = match value.get("code") {
+        None | Some(&Value::Null) => None,
+        Some(code) => Some(String::from(code.as_str()?)),
+    };
+    let in_test_code = match *value.get("in_test_code")? {
+        Value::Null => None,
+        ref in_test_code => Some(in_test_code.as_bool()?),
+    };
+    // Heuristics that this version doesn't know are left out
+    let heuristics: Vec<&'static str> = value.get("heuristics")?.as_array()?.iter()
+        .filter_map(|name| HEURISTIC_NAMES.iter().find(|&&known| Some(known) == name.as_str()))
+        .cloned()
+        .collect();
+    let suggestions = value.get("suggestions")?.as_array()?.iter()
+        .map(suggestion_from_json)
+        .collect::<Option<Vec<_>>>()?;
+    // Reports published before bound chains were have none
+    let bound_chain = match value.get("bound_chain") {
+        None => Vec::new(),
+        Some(steps) => steps.as_array()?.iter()
+            .map(bound_step_from_json)
+            .collect::<Option<Vec<_>>>()?,
+    };
+    // Reports published before drift was found have neither
+    let source_line = match value.get("source_line") {
+        None | Some(&Value::Null) => None,
+        Some(line) => Some(String::from(line.as_str()?)),
+    };
+    let drifted = match value.get("drifted") {
+        None => false,
+        Some(drifted) => drifted.as_bool()?,
+    };
+    // Reports published before the text of rustc was kept have none
+    let rendered = match value.get("rendered") {
+        None | Some(&Value::Null) => None,
+        Some(text) => Some(String::from(text.as_str()?)),
+    };
+    // Reports published before duplicates were merged have neither
+    let count = match value.get("count") {
+        None => 1,
+        Some(count) => count.as_usize()?,
+    };
+    let targets = match value.get("targets") {
+        None => Vec::new(),
+        Some(targets) => targets.as_array()?.iter()
+            .map(|target| target.as_str().map(String::from))
+            .collect::<Option<Vec<_>>>()?,
+    };
+    let filepath: Arc<str> = Arc::from(value.get("file")?.as_str()?);
+    let mut problem = ProblemDescription {
+        module: module_path(&filepath),
+        filepath,
+        severity,
+        message: note_from_json(value.get("message")?)?,
+        help: notes_from_json(value.get("help")?)?,
+        notes: notes_from_json(value.get("notes")?)?,
+        suggestions,
+        emitted_at,
+        during_package,
+        target,
+        in_test_code,
+        code,
+        additional_primary_spans: notes_from_json(value.get("additional_spans")?)?,
+        parse_confidence: if heuristics.is_empty() {
+            Confidence::Exact
+        } else {
+            Confidence::Heuristic(heuristics)
+        },
+        from_macro: None,
+        bound_chain,
+        source_line,
+        drifted,
+        rendered,
+        count,
+        targets,
+    };
+    // The macro is read from the notes, as when the problem was parsed
+    problem.find_macro_origin();
+    Some(problem)
+}
+
+fn header_to_json(report: &CheckReport, hashes: &BTreeMap<String, u64>) -> Value {
+    let time = report.time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    Value::object(vec![
+        ("format", Value::from(FORMAT)),
+        ("version", Value::from(VERSION)),
+        ("time", Value::from(time.as_secs())),
+        ("commit", Value::from(report.commit.clone())),
+        ("correlation_id", Value::from(report.correlation_id.clone())),
+        ("timed_out", Value::from(report.timed_out)),
+        ("cancelled", Value::from(report.cancelled)),
+        ("problems", Value::from(report.check.problems().len())),
+        ("digest", Value::from(format!("{:016x}", report.check.digest()))),
+        ("source_hashes", Value::object(hashes.iter()
+            .map(|(path, hash)| (&path[..], Value::from(format!("{:016x}", hash))))
+            .collect())),
+    ])
+}
+
+fn invalid(line: usize) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData,
+        format!("line {} of the published diagnostics is invalid", line))
+}
+
+fn optional_string(value: Option<&Value>) -> Option<Option<String>> {
+    match value? {
+        Value::Null => Some(None),
+        value => Some(Some(String::from(value.as_str()?))),
+    }
+}
+
+/// Parses the published diagnostics into a report.
+pub fn parse_published(text: &str) -> io::Result<CheckReport> {
+    let mut lines = text.lines();
+    let header = lines.next().and_then(|line| Value::parse(line).ok())
+        .filter(|header| header.get("format").and_then(Value::as_str) == Some(FORMAT)
+            && header.get("version").and_then(Value::as_u64) == Some(VERSION))
+        .ok_or_else(|| invalid(1))?;
+    let mut problems = Vec::new();
+    for (i, line) in lines.enumerate() {
+        let problem = Value::parse(line).ok().as_ref().and_then(problem_from_json)
+            .ok_or_else(|| invalid(i + 2))?;
+        problems.push(problem);
+    }
+    let read_header = || -> Option<CheckReport> {
+        if header.get("problems")?.as_usize()? != problems.len() {
+            return None;
+        }
+        let mut report = CheckReport::new(Check::from_problems(problems.clone()));
+        report.time = UNIX_EPOCH + Duration::from_secs(header.get("time")?.as_u64()?);
+        report.commit = optional_string(header.get("commit"))?;
+        report.correlation_id = optional_string(header.get("correlation_id"))?;
+        report.timed_out = header.get("timed_out")?.as_bool()?;
+        report.cancelled = header.get("cancelled")?.as_bool()?;
+        // Reports published before the hashes were have none
+        if let Some(hashes) = header.get("source_hashes") {
+            for (path, hash) in hashes.as_object()? {
+                let hash = u64::from_str_radix(hash.as_str()?, 16).ok()?;
+                report.source_hashes.insert(path.clone(), hash);
+            }
+        }
+        Some(report)
+    };
+    read_header().ok_or_else(|| invalid(1))
+}
+
+/// Writes the report in the published format, with the problems redacted
+/// with the rules, if any.
+pub fn published_text(report: &CheckReport, sanitize: Option<&SanitizeRules>) -> String {
+    published_text_with(report, sanitize, &report.source_hashes)
+}
+
+/// Writes the report in the published format with the hashes of the files
+/// of its problems.
+fn published_text_with(report: &CheckReport, sanitize: Option<&SanitizeRules>,
+        hashes: &BTreeMap<String, u64>) -> String {
+    let hashes = match sanitize {
+        Some(rules) => hashes.iter().map(|(path, &hash)| (rules.apply(path), hash)).collect(),
+        None => hashes.clone(),
+    };
+    let mut text = header_to_json(report, &hashes).to_string();
+    text.push('\n');
+    let sanitized = sanitize.map(|rules| report.check.sanitized(rules));
+    for problem in sanitized.as_ref().unwrap_or(&report.check).problems() {
+        text.push_str(&problem_to_json(problem).to_string());
+        text.push('\n');
+    }
+    text
+}
+
+/// Publishes the report of the project under the root at the path, as
+/// ```CheckReport::publish``` does, for checkers that know their target
+/// directory.
+pub fn publish_report(report: &CheckReport, root: &Path, path: PathBuf,
+        sanitize: Option<&SanitizeRules>) -> io::Result<PathBuf> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut hashes = report.source_hashes.clone();
+    for problem in report.check.problems() {
+        if let Ok(source) = fs::read_to_string(root.join(&*problem.filepath)) {
+            hashes.insert(String::from(&*problem.filepath), stable_hash(&source));
+        }
+    }
+    let text = published_text_with(report, sanitize, &hashes);
+    // The temporary file is unique, so that concurrent writers each
+    // rename a complete file of their own
+    let mut temp = path.clone().into_os_string();
+    temp.push(format!(".{}-{}.tmp", process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)));
+    fs::write(&temp, text)?;
+    fs::rename(&temp, &path)?;
+    Ok(path)
+}
+
+impl CheckReport {
+    /// Publishes the report for other tools at ```published_path```, in the
+    /// target directory of the project under the root, replacing the
+    /// previous one, and returns the path of the file. The problems are
+    /// redacted with the rules, if any. The hashes of the files of the
+    /// problems are published with them, for finding drift when the report
+    /// is loaded (see ```CheckReport::mark_drift```).
+    pub fn publish<P: AsRef<Path>>(&self, root: P, sanitize: Option<&SanitizeRules>)
+            -> io::Result<PathBuf> {
+        publish_report(self, root.as_ref(), published_path(root.as_ref()), sanitize)
+    }
+}
+
+impl Check {
+    /// Publishes the check as a report of a check that was just run (see
+    /// ```CheckReport::publish```).
+    pub fn publish<P: AsRef<Path>>(&self, root: P, sanitize: Option<&SanitizeRules>)
+            -> io::Result<PathBuf> {
+        CheckReport::new(Check::from_problems(self.problems().to_vec())).publish(root, sanitize)
+    }
+
+    /// Loads the report published for the project under the root, at
+    /// ```published_path```. Its ```time``` tells how stale it is.
+    pub fn load_published<P: AsRef<Path>>(root: P) -> io::Result<CheckReport> {
+        parse_published(&fs::read_to_string(published_path(root))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::thread;
+    use parse_output;
+
+    const OUTPUT: &str = "\
+src/lib.rs:2:9: 2:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+src/lib.rs:3:5: 3:12 error: box expression syntax is experimental
+src/lib.rs:3:5: 3:12 help: add `#![feature(box_syntax)]` to the crate attributes to enable
+";
+
+    fn temp_root(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("lintparser-publish-{}-{}", name, process::id()))
+    }
+
+    #[test]
+    fn published_reports_round_trip() {
+        let root = temp_root("round-trip");
+        let mut report = CheckReport::new(parse_output(OUTPUT));
+        report.commit = Some(String::from("4f2e1c9"));
+        report.timed_out = true;
+        let path = report.publish(&root, None).unwrap();
+        let loaded = Check::load_published(&root);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(path, published_path(&root));
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.check.to_snapshot(), report.check.to_snapshot());
+        assert_eq!(loaded.check.problems()[1].help[0].message,
+            "add `#![feature(box_syntax)]` to the crate attributes to enable");
+        assert_eq!(loaded.check.problems()[1].suggestions[0].target, SuggestionTarget::CrateRoot);
+        assert_eq!(loaded.check.problems()[1].parse_confidence,
+            Confidence::Heuristic(vec!["suggestion-from-help"]));
+        assert_eq!(loaded.commit, report.commit);
+        assert!(loaded.timed_out && ! loaded.cancelled);
+        let seconds = |report: &CheckReport| report.time.duration_since(UNIX_EPOCH).unwrap()
+            .as_secs();
+        assert_eq!(seconds(&loaded), seconds(&report));
+    }
+
+    #[test]
+    fn reports_are_published_in_the_target_directory_of_the_config() {
+        let root = temp_root("target-dir");
+        fs::create_dir_all(root.join(".cargo")).unwrap();
+        fs::write(root.join(".cargo/config.toml"), "[build]\ntarget-dir = \"out\"\n").unwrap();
+        let path = parse_output(OUTPUT).publish(&root, None).unwrap();
+        let loaded = Check::load_published(&root);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(path, root.join("out/lintparser/diagnostics.jsonl"));
+        assert_eq!(loaded.unwrap().check.problems().len(), 2);
+    }
+
+    #[test]
+    fn concurrent_publishers_leave_a_complete_file() {
+        let root = temp_root("concurrent");
+        let writers: Vec<_> = (0..8).map(|i| {
+            let root = root.clone();
+            thread::spawn(move || {
+                let check = parse_output(&OUTPUT.repeat(i + 1));
+                for _ in 0..10 {
+                    check.publish(&root, None).unwrap();
+                }
+            })
+        }).collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        let loaded = Check::load_published(&root);
+        let leftovers = fs::read_dir(published_path(&root).parent().unwrap()).unwrap().count();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(loaded.unwrap().check.problems().len() % 2, 0);
+        assert_eq!(leftovers, 1);
+    }
+}
@@ -0,0 +1,285 @@
+//! Human-readable reports of checks.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use cascade::is_folded;
+use paths::shorten_path;
+use {CascadeRules, Check, ErrorCluster, Note, PathCompare, SanitizeRules};
+
+/// Options for rendering a check as a report.
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// Replace help and note messages that were already shown for an earlier
+    /// problem with a reference to their first occurrence.
+    pub dedup_children: bool,
+    /// How paths are compared when grouping the problems by file.
+    pub path_compare: PathCompare,
+    /// Fold the errors that follow from an earlier error into it, with the
+    /// given rules (see ```Check::cluster_cascades```).
+    pub collapse_cascades: Option<CascadeRules>,
+    /// Group the problems by module instead of by file (see
+    /// ```Check::by_module```).
+    pub group_by_module: bool,
+    /// Redact the problems with the rules before rendering them.
+    pub sanitize: Option<SanitizeRules>,
+    /// Show the paths of files under the directory relative to it.
+    pub path_base: Option<PathBuf>,
+    /// Shorten the paths of files to at most the number of columns, e.g.
+    /// the width of the terminal (see ```shorten_path```).
+    pub path_width: Option<usize>,
+}
+
+impl RenderOptions {
+    /// Returns the path of a file as the options show it: relative to
+    /// ```path_base``` and shortened to ```path_width```, if they are given.
+    pub fn shown_path(&self, path: &str) -> String {
+        match (&self.path_base, self.path_width) {
+            (&None, None) => String::from(path),
+            (base, width) => {
+                shorten_path(Path::new(path), base.as_deref(), width.unwrap_or(usize::MAX))
+            },
+        }
+    }
+}
+
+/// The first occurrence of a help or note message in a report.
+struct FirstOccurrence<'a> {
+    filepath: &'a str,
+    note: &'a Note,
+}
+
+impl Check {
+    /// Returns the summary line of the check (see ```Check::summary_line```),
+    /// followed by where its first problem of the worst severity is, like
+    /// ```lintparser: 1 errors, 2 warnings, 2 files; first error at
+    /// src/lib.rs:2:5```, with the path shown and redacted as in a report.
+    pub fn summary_line_with(&self, options: &RenderOptions) -> String {
+        let mut line = self.summary_line();
+        let worst = self.worst_severity();
+        let first = self.problems().iter().find(|problem| Some(problem.severity) == worst);
+        if let Some(first) = first {
+            line.push_str(&format!("; first {} at {}:{}:{}", first.severity.name(),
+                options.shown_path(&first.filepath), first.message.start_line,
+                first.message.start_col));
+        }
+        match options.sanitize {
+            Some(ref rules) => rules.apply(&line),
+            None => line,
+        }
+    }
+
+    /// Renders the problems as a report grouped by file (or module), in the
+    /// order in which the files were first reported.
+    pub fn render(&self, options: &RenderOptions) -> String {
+        if let Some(ref rules) = options.sanitize {
+            let options = RenderOptions { sanitize: None, ..options.clone() };
+            return self.sanitized(rules).render(&options);
+        }
+        let groups = if options.group_by_module {
+            self.by_module()
+        } else {
+            self.by_file(options.path_compare)
+        };
+        let clusters: Vec<ErrorCluster> = match options.collapse_cascades {
+            Some(ref rules) => self.cluster_cascades_with(rules),
+            None => Vec::new(),
+        };
+        let shown = |path: &str| options.shown_path(path);
+
+        let mut seen: HashMap<(&str, String), FirstOccurrence> = HashMap::new();
+        let mut out = String::new();
+        for (group, problems) in groups {
+            if options.group_by_module {
+                let _ = writeln!(out, "{}", group);
+            } else {
+                let _ = writeln!(out, "{}", shown(group));
+            }
+            for problem in problems {
+                if is_folded(&clusters, problem) {
+                    continue;
+                }
+                let filepath = if options.group_by_module { &problem.filepath } else { group };
+                let severity = problem.severity.name();
+                let _ = writeln!(out, "  {}:{}: {}: {}", problem.message.start_line, 
+                    problem.message.start_col, severity, problem.message.message);
+                let children = problem.help.iter().map(|note| ("help", note))
+                    .chain(problem.notes.iter().map(|note| ("note", note)));
+                for (kind, note) in children {
+                    if options.dedup_children {
+                        let key = (kind, normalize_message(&note.message));
+                        if let Some(first) = seen.get(&key) {
+                            let _ = writeln!(out, "    {}: same as above ({}:{}:{})", kind, 
+                                shown(first.filepath), first.note.start_line,
+                                first.note.start_col);
+                            continue;
+                        }
+                        seen.insert(key, FirstOccurrence { filepath, note });
+                    }
+                    let _ = writeln!(out, "    {}: {}:{}: {}", kind, note.start_line, 
+                        note.start_col, note.message);
+                }
+                let folded = clusters.iter()
+                    .find(|cluster| ::std::ptr::eq(cluster.root, problem))
+                    .map_or(0, |cluster| cluster.cascade.len());
+                if folded > 0 {
+                    let _ = writeln!(out, "    ... and {} follow-on errors", folded);
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Collapses the whitespace of a message, so that messages only differing
+/// in line wrapping or indentation are considered the same.
+fn normalize_message(message: &str) -> String {
+    message.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {ProblemDescription, Severity};
+
+    const FEATURE_HELP: &str = "add `#![feature(box_syntax)]` to the crate attributes to enable";
+
+    fn feature_error(filepath: &str, line: usize, help: &str) -> ProblemDescription {
+        let mut problem = ProblemDescription::new(filepath, line, 13, line, 20, 
+            "box expression syntax is experimental", vec![Note::new(line, 13, line, 20, help)], 
+            vec![]);
+        problem.severity = Severity::Error;
+        problem
+    }
+
+    #[test]
+    fn render_groups_by_file() {
+        let check = Check::Error(vec![
+            feature_error("src/main.rs", 2, FEATURE_HELP),
+            feature_error("src/lib.rs", 4, FEATURE_HELP),
+            feature_error("src/main.rs", 7, FEATURE_HELP),
+        ]);
+        let report = check.render(&RenderOptions::default());
+        assert_eq!(report, format!("\
+src/main.rs
+  2:13: error: box expression syntax is experimental
+    help: 2:13: {help}
+  7:13: error: box expression syntax is experimental
+    help: 7:13: {help}
+src/lib.rs
+  4:13: error: box expression syntax is experimental
+    help: 4:13: {help}
+", help = FEATURE_HELP));
+    }
+
+    #[test]
+    fn render_shortens_paths() {
+        let check = Check::Error(vec![
+            feature_error("/home/me/app/src/main.rs", 2, FEATURE_HELP),
+            feature_error("/home/me/deps/vendor/crate/src/lib.rs", 4, FEATURE_HELP),
+        ]);
+        let options = RenderOptions {
+            dedup_children: true,
+            path_base: Some(PathBuf::from("/home/me/app")),
+            path_width: Some(20),
+            ..RenderOptions::default()
+        };
+        assert_eq!(check.render(&options), format!("\
+src/main.rs
+  2:13: error: box expression syntax is experimental
+    help: 2:13: {help}
+…/crate/src/lib.rs
+  4:13: error: box expression syntax is experimental
+    help: same as above (src/main.rs:2:13)
+", help = FEATURE_HELP));
+    }
+
+    #[test]
+    fn summary_lines_show_the_first_of_the_worst_problems() {
+        let mut warning = feature_error("/home/me/app/src/main.rs", 2, FEATURE_HELP);
+        warning.severity = Severity::Warning;
+        let check = Check::Error(vec![
+            warning,
+            feature_error("/home/me/deps/vendor/crate/src/lib.rs", 4, FEATURE_HELP),
+            feature_error("/home/me/app/src/main.rs", 7, FEATURE_HELP),
+        ]);
+        let options = RenderOptions {
+            path_base: Some(PathBuf::from("/home/me/app")),
+            path_width: Some(20),
+            ..RenderOptions::default()
+        };
+        assert_eq!(check.summary_line_with(&options),
+            "lintparser: 2 errors, 1 warnings, 2 files; first error at …/crate/src/lib.rs:4:13");
+        assert_eq!(Check::Perfect.summary_line_with(&options), "lintparser: clean");
+    }
+
+    #[test]
+    fn render_groups_by_module() {
+        let check = Check::Error(vec![
+            feature_error("src/config/loader.rs", 2, FEATURE_HELP),
+            feature_error("src/main.rs", 4, FEATURE_HELP),
+            feature_error("src/config/loader/mod.rs", 7, FEATURE_HELP),
+        ]);
+        let options = RenderOptions {
+            group_by_module: true,
+            dedup_children: true,
+            ..RenderOptions::default()
+        };
+        assert_eq!(check.render(&options), format!("\
+config::loader
+  2:13: error: box expression syntax is experimental
+    help: 2:13: {help}
+  7:13: error: box expression syntax is experimental
+    help: same as above (src/config/loader.rs:2:13)
+crate
+  4:13: error: box expression syntax is experimental
+    help: same as above (src/config/loader.rs:2:13)
+", help = FEATURE_HELP));
+    }
+
+    #[test]
+    fn render_dedups_children_by_normalized_message() {
+        let check = Check::Error(vec![
+            feature_error("src/main.rs", 2, FEATURE_HELP),
+            feature_error("src/main.rs", 7, &FEATURE_HELP.replace(" to the", "\n    to the")),
+            feature_error("src/main.rs", 9, "a different help"),
+        ]);
+        let options = RenderOptions { dedup_children: true, ..RenderOptions::default() };
+        let report = check.render(&options);
+        assert_eq!(report, format!("\
+src/main.rs
+  2:13: error: box expression syntax is experimental
+    help: 2:13: {help}
+  7:13: error: box expression syntax is experimental
+    help: same as above (src/main.rs:2:13)
+  9:13: error: box expression syntax is experimental
+    help: 9:13: a different help
+", help = FEATURE_HELP));
+        // The problems themselves are untouched
+        assert_eq!(check.problems()[1].help.len(), 1);
+    }
+
+    #[test]
+    fn render_collapses_cascades() {
+        let mut first = feature_error("src/main.rs", 2, FEATURE_HELP);
+        first.message.message = String::from("expected one of `;` or `}`, found `let`");
+        let check = Check::Error(vec![
+            first,
+            feature_error("src/main.rs", 4, FEATURE_HELP),
+            feature_error("src/main.rs", 40, FEATURE_HELP),
+        ]);
+        let options = RenderOptions {
+            collapse_cascades: Some(CascadeRules::default()),
+            ..RenderOptions::default()
+        };
+        assert_eq!(check.render(&options), format!("\
+src/main.rs
+  2:13: error: expected one of `;` or `}}`, found `let`
+    help: 2:13: {help}
+    ... and 1 follow-on errors
+  40:13: error: box expression syntax is experimental
+    help: 40:13: {help}
+", help = FEATURE_HELP));
+    }
+}
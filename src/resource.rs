@@ -0,0 +1,98 @@
+//! The time and memory that a run of cargo took, for tracking the cost of a
+//! build. The CPU times and memory are only known with the
+//! ```resource-usage``` feature on Unix, where cargo is waited for with
+//! ```wait4```.
+
+use std::io;
+use std::process::{Child, ExitStatus};
+use std::time::{Duration, Instant};
+
+/// The resources that cargo used during a check, including the compilers
+/// that it ran.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceUsage {
+    /// How long cargo ran.
+    pub wall_time: Duration,
+    /// The CPU time spent in user mode, if the platform tells.
+    pub user_time: Option<Duration>,
+    /// The CPU time spent in the kernel, if the platform tells.
+    pub system_time: Option<Duration>,
+    /// The largest resident memory of cargo or of one of the processes it
+    /// ran, in bytes, if the platform tells.
+    pub max_rss: Option<u64>,
+}
+
+/// Waits for the child that was started at the instant, and returns its
+/// status with the resources it used.
+pub fn wait_with_usage(child: &mut Child, started: Instant)
+        -> io::Result<(ExitStatus, ResourceUsage)> {
+    let (status, mut usage) = wait(child)?;
+    usage.wall_time = started.elapsed();
+    Ok((status, usage))
+}
+
+#[cfg(all(unix, feature = "resource-usage"))]
+fn wait(child: &mut Child) -> io::Result<(ExitStatus, ResourceUsage)> {
+    use std::mem;
+    use std::os::unix::process::ExitStatusExt;
+    use libc;
+
+    let mut status = 0;
+    // SAFETY: rusage is plain data, for which all zeroes are valid
+    let mut rusage: libc::rusage = unsafe { mem::zeroed() };
+    loop {
+        // SAFETY: the pointers are to the locals, which outlive the call
+        let pid = unsafe {
+            libc::wait4(child.id() as libc::pid_t, &mut status, 0, &mut rusage)
+        };
+        if pid >= 0 {
+            break;
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+    let time = |time: libc::timeval| {
+        Duration::new(time.tv_sec as u64, time.tv_usec as u32 * 1000)
+    };
+    // Linux gives kilobytes, and macOS bytes
+    let rss_unit = if cfg!(target_os = "macos") { 1 } else { 1024 };
+    Ok((ExitStatus::from_raw(status), ResourceUsage {
+        wall_time: Duration::from_secs(0),
+        user_time: Some(time(rusage.ru_utime)),
+        system_time: Some(time(rusage.ru_stime)),
+        max_rss: Some(rusage.ru_maxrss as u64 * rss_unit),
+    }))
+}
+
+#[cfg(not(all(unix, feature = "resource-usage")))]
+fn wait(child: &mut Child) -> io::Result<(ExitStatus, ResourceUsage)> {
+    Ok((child.wait()?, ResourceUsage {
+        wall_time: Duration::from_secs(0),
+        user_time: None,
+        system_time: None,
+        max_rss: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::{Command, Stdio};
+
+    #[test]
+    fn usage_is_measured_when_waiting() {
+        let started = Instant::now();
+        let mut child = Command::new("cargo").arg("--version")
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+        let (status, usage) = wait_with_usage(&mut child, started).unwrap();
+        assert!(status.success());
+        assert!(usage.wall_time > Duration::from_secs(0));
+        let measured = cfg!(all(unix, feature = "resource-usage"));
+        assert_eq!(usage.user_time.is_some(), measured);
+        assert_eq!(usage.max_rss.is_some_and(|rss| rss > 1024), measured);
+    }
+}
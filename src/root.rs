@@ -0,0 +1,243 @@
+//! Finding the project of a file, for editors that start from the file
+//! rather than from a project directory.
+//!
+//! The manifests are only read as far as needed, for their
+//! ```[workspace]``` table and its ```members``` and ```exclude``` keys,
+//! and ```package.workspace```. A package is only said to be in a workspace
+//! that it is sure to be a member of.
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use paths::glob_matches;
+use {check_file, CheckError, Checker, FileCheck};
+
+/// The package of a file, and the workspace it is a member of.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectRoot {
+    /// The ```Cargo.toml``` of the package.
+    pub manifest: PathBuf,
+    /// The root of the workspace, or ```None``` if the package isn't in one.
+    /// A package with a ```[workspace]``` of its own is its root.
+    pub workspace_root: Option<PathBuf>,
+}
+
+impl ProjectRoot {
+    /// Returns the directory to run cargo in for the file: the root of the
+    /// workspace, or else the directory of the package.
+    pub fn dir(&self) -> &Path {
+        match self.workspace_root {
+            Some(ref root) => root,
+            None => self.manifest.parent().unwrap_or(&self.manifest),
+        }
+    }
+}
+
+/// Strips the comment from a line of TOML, outside of strings.
+pub fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (i, ch) in line.char_indices() {
+        match (quote, ch) {
+            (None, '"') | (None, '\'') => quote = Some(ch),
+            (Some(open), _) if open == ch => quote = None,
+            (None, '#') => return &line[..i],
+            _ => {},
+        }
+    }
+    line
+}
+
+/// Returns the lines of the table of the manifest without their comments,
+/// or ```None``` if it has no such table. A subtable like
+/// ```[workspace.dependencies]``` also makes the table exist.
+pub fn table<'a>(manifest: &'a str, name: &str) -> Option<Vec<&'a str>> {
+    let mut exists = false;
+    let mut current = "";
+    let mut lines = Vec::new();
+    for line in manifest.lines().map(strip_comment) {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            current = trimmed.trim_start_matches('[').trim_end_matches(']').trim();
+            exists |= current == name || current.strip_prefix(name)
+                .is_some_and(|rest| rest.starts_with('.'));
+        } else if current == name {
+            lines.push(trimmed);
+        }
+    }
+    Some(lines).filter(|_| exists)
+}
+
+/// Returns the text of the value of the key in the lines of a table, with
+/// the lines of an array that continues over several lines.
+pub fn value(lines: &[&str], key: &str) -> Option<String> {
+    let start = lines.iter().position(|line| {
+        line.split_once('=').is_some_and(|(name, _)| name.trim().trim_matches('"') == key)
+    })?;
+    let mut value = String::from(lines[start].split_once('=')?.1.trim());
+    let open = |text: &str| text.matches('[').count() > text.matches(']').count();
+    for line in &lines[start + 1..] {
+        if ! open(&value) {
+            break;
+        }
+        value.push(' ');
+        value.push_str(line);
+    }
+    Some(value)
+}
+
+/// Returns the strings of a value, like the paths of an array.
+pub fn strings(value: &str) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut rest = value;
+    while let Some(start) = rest.find(['"', '\'']) {
+        let quote = rest[start..].chars().next().unwrap_or('"');
+        let text = &rest[start + 1..];
+        match text.find(quote) {
+            Some(end) => {
+                strings.push(String::from(&text[..end]));
+                rest = &text[end + 1..];
+            },
+            None => break,
+        }
+    }
+    strings
+}
+
+/// Lexically resolves the ```.``` and ```..``` of a path.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir => {
+                normalized.pop();
+            },
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+/// Returns the root of the workspace of the package in the directory, from
+/// its manifest and the manifests above it.
+fn workspace_root(package: &Path, manifest: &str) -> Option<PathBuf> {
+    if table(manifest, "workspace").is_some() {
+        return Some(package.to_path_buf());
+    }
+    let explicit = table(manifest, "package")
+        .and_then(|lines| value(&lines, "workspace"))
+        .and_then(|value| strings(&value).into_iter().next());
+    if let Some(root) = explicit {
+        return Some(normalize(&package.join(root)));
+    }
+    // Cargo takes the first workspace above the package
+    let (dir, text) = package.ancestors().skip(1).find_map(|dir| {
+        let text = fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+        Some((dir, text)).filter(|(_, text)| table(text, "workspace").is_some())
+    })?;
+    table(&text, "workspace").and_then(|lines| {
+        let relative = package.strip_prefix(dir).ok()?;
+        let listed = |key| value(&lines, key).map_or_else(Vec::new, |value| strings(&value));
+        let member = listed("members").iter().any(|glob| glob_matches(glob, relative));
+        let excluded = listed("exclude").iter()
+            .any(|path| relative.starts_with(normalize(Path::new(path))));
+        Some(dir.to_path_buf()).filter(|_| member && ! excluded)
+    })
+}
+
+/// Returns the path with its directories resolved, but not the file, so
+/// that a link stays where it is.
+fn resolve_directories(path: &Path) -> Option<PathBuf> {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) if ! path.is_dir() => {
+            let parent = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+            Some(fs::canonicalize(parent).ok()?.join(name))
+        },
+        _ => fs::canonicalize(path).ok(),
+    }
+}
+
+/// Finds the package of the file (or directory) by walking up from it to
+/// the first ```Cargo.toml```, and the workspace it is a member of. A
+/// symbolic link belongs to the project that it is in, not to the one of
+/// its target. Returns ```None``` for paths outside of every package.
+pub fn find_project_root(start: &Path) -> Option<ProjectRoot> {
+    let start = resolve_directories(start)?;
+    let package = start.ancestors().find(|dir| dir.join("Cargo.toml").is_file())?;
+    let manifest = package.join("Cargo.toml");
+    let text = fs::read_to_string(&manifest).ok()?;
+    Some(ProjectRoot { workspace_root: workspace_root(package, &text), manifest })
+}
+
+/// Runs ```cargo check``` on the project of the file and returns the
+/// problems in the file, as ```check_file``` does. A file outside of every
+/// package is an invalid directory.
+pub fn cargo_check_for_file<P: AsRef<Path>>(file: P) -> Result<FileCheck, CheckError> {
+    let file = file.as_ref();
+    let root = find_project_root(file)
+        .ok_or_else(|| CheckError::InvalidDirectory(file.to_path_buf()))?;
+    let file = resolve_directories(file).unwrap_or_else(|| file.to_path_buf());
+    check_file(root.dir(), file, &Checker::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    #[test]
+    fn roots_are_found_in_every_layout() {
+        let root = env::temp_dir().join(format!("lintparser-root-{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let write = |path: &str, text: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        };
+        write("ws/Cargo.toml", "[workspace]\nmembers = [\n    \"crates/*\", # all of them\n]\n\
+            exclude = [\"crates/old\"]\n");
+        write("ws/crates/core/Cargo.toml", "[package]\nname = \"core\"\n");
+        write("ws/crates/core/src/lib.rs", "");
+        write("ws/crates/old/Cargo.toml", "[package]\nname = \"old\"\n");
+        write("ws/crates/old/src/lib.rs", "");
+        write("ws/crates/nested/Cargo.toml", "[package]\nname = \"nested\"\n\n\
+            [workspace.dependencies]\n");
+        write("ws/crates/nested/inner/Cargo.toml", "[package]\nname = \"inner\"\n\
+            workspace = \"..\"\n");
+        write("ws/crates/nested/inner/src/main.rs", "");
+        write("ws/tools/gen/Cargo.toml", "[package]\nname = \"gen\"\n");
+        write("ws/tools/gen/src/main.rs", "");
+        write("outside/notes.rs", "");
+        let root = fs::canonicalize(&root).unwrap();
+        let find = |path: &str| find_project_root(&root.join(path))
+            .map(|found| (found.manifest.strip_prefix(&root).unwrap().to_path_buf(),
+                found.workspace_root.map(|dir| dir.strip_prefix(&root).unwrap().to_path_buf())));
+        let found = |manifest: &str, workspace: Option<&str>| {
+            Some((PathBuf::from(manifest), workspace.map(PathBuf::from)))
+        };
+
+        assert_eq!(find("ws/crates/core/src/lib.rs"),
+            found("ws/crates/core/Cargo.toml", Some("ws")));
+        assert_eq!(find("ws/crates/core"), found("ws/crates/core/Cargo.toml", Some("ws")));
+        assert_eq!(find("ws/crates/old/src/lib.rs"), found("ws/crates/old/Cargo.toml", None));
+        assert_eq!(find("ws/tools/gen/src/main.rs"), found("ws/tools/gen/Cargo.toml", None));
+        // A member with a workspace of its own is its root
+        assert_eq!(find("ws/crates/nested/Cargo.toml"),
+            found("ws/crates/nested/Cargo.toml", Some("ws/crates/nested")));
+        assert_eq!(find("ws/crates/nested/inner/src/main.rs"),
+            found("ws/crates/nested/inner/Cargo.toml", Some("ws/crates/nested")));
+        assert_eq!(find("outside/notes.rs"), None);
+        assert_eq!(find("ws/crates/missing.rs"), found("ws/Cargo.toml", Some("ws")));
+
+        #[cfg(unix)]
+        {
+            // A link into another package belongs to the package it is in
+            ::std::os::unix::fs::symlink(root.join("ws/tools/gen/src/main.rs"),
+                root.join("ws/crates/core/src/gen.rs")).unwrap();
+            assert_eq!(find("ws/crates/core/src/gen.rs"),
+                found("ws/crates/core/Cargo.toml", Some("ws")));
+        }
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
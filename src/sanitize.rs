@@ -0,0 +1,283 @@
+//! Redacting the paths and names of the machine that ran a check from its
+//! problems, before they are sent elsewhere.
+
+use std::env;
+use std::path::Path;
+
+use {Check, CheckReport, Note, ProblemDescription};
+
+/// The redactions to apply to problems. The exporters take them as an
+/// option, so that no export can leave them out by mistake.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SanitizeRules {
+    /// The path prefixes to replace, and their replacements.
+    prefixes: Vec<(String, String)>,
+    usernames: Vec<String>,
+    drop_suggestions: bool,
+    drop_snippets: bool,
+}
+
+impl SanitizeRules {
+    /// Creates rules that don't redact anything.
+    pub fn new() -> SanitizeRules {
+        SanitizeRules::default()
+    }
+
+    /// Creates rules that replace the home directory with ```$HOME``` and
+    /// mask the name of the user, as read from the environment.
+    pub fn from_env() -> SanitizeRules {
+        let mut rules = SanitizeRules::new();
+        if let Some(home) = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")) {
+            rules = rules.replace_prefix(Path::new(&home), "$HOME");
+        }
+        if let Some(user) = env::var("USER").ok().or_else(|| env::var("USERNAME").ok()) {
+            rules = rules.mask_username(user);
+        }
+        rules
+    }
+
+    /// Creates the rules of ```from_env```, which also replace the root of
+    /// the workspace with ```$WORKSPACE```.
+    pub fn for_workspace<P: AsRef<Path>>(root: P) -> SanitizeRules {
+        SanitizeRules::from_env().replace_prefix(root.as_ref(), "$WORKSPACE")
+    }
+
+    /// Creates the rules of ```for_workspace``` with the root of the
+    /// project of the report, or the rules of ```from_env``` if the report
+    /// doesn't know it.
+    pub fn for_report(report: &CheckReport) -> SanitizeRules {
+        match report.root {
+            Some(ref root) => SanitizeRules::for_workspace(root),
+            None => SanitizeRules::from_env(),
+        }
+    }
+
+    /// Replaces the paths under the prefix, in the paths and messages of the
+    /// problems. The longest matching prefix wins.
+    pub fn replace_prefix<P: AsRef<Path>, T: Into<String>>(mut self, prefix: P, replacement: T)
+            -> SanitizeRules {
+        let prefix = prefix.as_ref().to_string_lossy();
+        let prefix = prefix.trim_end_matches(['/', '\\']);
+        if ! prefix.is_empty() {
+            self.prefixes.push((String::from(prefix), replacement.into()));
+            self.prefixes.sort_by_key(|(prefix, _)| ::std::cmp::Reverse(prefix.len()));
+        }
+        self
+    }
+
+    /// Replaces the name, where it is a word of its own, with ```$USER```.
+    pub fn mask_username<T: Into<String>>(mut self, name: T) -> SanitizeRules {
+        let name = name.into();
+        if ! name.is_empty() {
+            self.usernames.push(name);
+        }
+        self
+    }
+
+    /// Whether to drop the suggestions of the problems, whose replacements
+    /// are snippets of the source.
+    pub fn drop_suggestions(mut self, drop: bool) -> SanitizeRules {
+        self.drop_suggestions = drop;
+        self
+    }
+
+    /// Whether to drop the rendered text and the source line of the
+    /// problems, which are snippets of the source.
+    pub fn drop_snippets(mut self, drop: bool) -> SanitizeRules {
+        self.drop_snippets = drop;
+        self
+    }
+
+    /// Returns the text with the rules applied.
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = String::from(text);
+        for (prefix, replacement) in &self.prefixes {
+            text = replace_paths(&text, prefix, replacement);
+        }
+        for name in &self.usernames {
+            text = replace_word(&text, name, "$USER");
+        }
+        text
+    }
+
+    fn apply_note(&self, note: &Note) -> Note {
+        Note {
+            message: self.apply(&note.message),
+            file: note.file.as_ref().map(|file| self.apply(file).into()),
+            ..note.clone()
+        }
+    }
+
+    fn apply_problem(&self, problem: &ProblemDescription) -> ProblemDescription {
+        let mut sanitized = problem.clone();
+        sanitized.filepath = self.apply(&problem.filepath).into();
+        sanitized.message = self.apply_note(&problem.message);
+        sanitized.help = problem.help.iter().map(|note| self.apply_note(note)).collect();
+        sanitized.notes = problem.notes.iter().map(|note| self.apply_note(note)).collect();
+        sanitized.additional_primary_spans = problem.additional_primary_spans.iter()
+            .map(|note| self.apply_note(note))
+            .collect();
+        for step in &mut sanitized.bound_chain {
+            step.description = self.apply(&step.description);
+            step.span = step.span.as_ref().map(|note| self.apply_note(note));
+        }
+        sanitized.source_line = problem.source_line.as_ref()
+            .filter(|_| ! self.drop_snippets)
+            .map(|line| self.apply(line));
+        sanitized.rendered = problem.rendered.as_ref()
+            .filter(|_| ! self.drop_snippets)
+            .map(|text| self.apply(text));
+        if self.drop_suggestions {
+            sanitized.suggestions.clear();
+        }
+        for suggestion in &mut sanitized.suggestions {
+            suggestion.span = self.apply_note(&suggestion.span);
+            suggestion.replacement = self.apply(&suggestion.replacement);
+        }
+        sanitized
+    }
+}
+
+/// Replaces the prefix where it starts a path: at the start of the text or
+/// after a character that can't be part of a path, and followed by a
+/// separator or the end of the path.
+fn replace_paths(text: &str, prefix: &str, replacement: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(prefix) {
+        let before = rest[..start].chars().last().or_else(|| out.chars().last());
+        let after = rest[start + prefix.len()..].chars().next();
+        let starts_path = before.is_none_or(|ch| ch.is_whitespace() || "`'\"(".contains(ch));
+        let ends_component = after.is_none_or(|ch| ! ch.is_alphanumeric() && ch != '_'
+            && ch != '-' && ch != '.');
+        out.push_str(&rest[..start]);
+        if starts_path && ends_component {
+            out.push_str(replacement);
+        } else {
+            out.push_str(prefix);
+        }
+        rest = &rest[start + prefix.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Replaces the word where it isn't part of a longer word.
+fn replace_word(text: &str, word: &str, replacement: &str) -> String {
+    let is_word = |ch: char| ch.is_alphanumeric() || ch == '_' || ch == '-' || ch == '.';
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(word) {
+        let before = rest[..start].chars().last().or_else(|| out.chars().last());
+        let after = rest[start + word.len()..].chars().next();
+        out.push_str(&rest[..start]);
+        if before.is_some_and(is_word) || after.is_some_and(is_word) {
+            out.push_str(word);
+        } else {
+            out.push_str(replacement);
+        }
+        rest = &rest[start + word.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+impl Check {
+    /// Returns the check with the redactions of the rules applied to the
+    /// paths, messages, help, notes and suggestions of its problems.
+    pub fn sanitized(&self, rules: &SanitizeRules) -> Check {
+        Check::from_problems(self.problems().iter()
+            .map(|problem| rules.apply_problem(problem))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::process;
+    use {parse_output, RenderOptions, SnapshotOptions};
+
+    const OUTPUT: &str = "\
+/home/alice/work/app/src/lib.rs:2:9: 2:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+/home/alice/work/app/src/lib.rs:2:9: 2:10 help: consider using `_x` instead
+/home/alice/.cargo/registry/src/log-0.4/src/lib.rs:4:1: 4:9 note: defined in /home/alice/.cargo/registry/src/log-0.4/src/lib.rs, owned by alice
+/home/alice/work/app/src/main.rs:1:5: 1:8 error: couldn't read `/home/alice/work/app-data/x` as alice-bot [E0425]
+";
+
+    fn rules() -> SanitizeRules {
+        SanitizeRules::new()
+            .replace_prefix("/home/alice", "$HOME")
+            .replace_prefix("/home/alice/work/app/", "$WORKSPACE")
+            .mask_username("alice")
+    }
+
+    #[test]
+    fn paths_and_names_are_redacted() {
+        let check = parse_output(OUTPUT).sanitized(&rules());
+        let problems = check.problems();
+        assert_eq!(&*problems[0].filepath, "$WORKSPACE/src/lib.rs");
+        assert_eq!(problems[0].notes[0].message,
+            "defined in $HOME/.cargo/registry/src/log-0.4/src/lib.rs, owned by $USER");
+        // A sibling of the workspace isn't in it, and longer names are kept
+        assert_eq!(problems[1].message.message,
+            "couldn't read `$HOME/work/app-data/x` as alice-bot [E0425]");
+        assert!(parse_output(OUTPUT).sanitized(&SanitizeRules::new()).problems()[0].filepath
+            .starts_with("/home/alice"));
+    }
+
+    #[test]
+    fn no_export_leaks_the_home_directory() {
+        let check = parse_output(OUTPUT);
+        let rules = rules();
+        let sanitize = || Some(rules.clone());
+        let root = ::std::env::temp_dir().join(format!("lintparser-sanitize-{}", process::id()));
+        let path = CheckReport::new(parse_output(OUTPUT)).publish(&root, Some(&rules)).unwrap();
+        let published = fs::read_to_string(path);
+        fs::remove_dir_all(&root).unwrap();
+        let exports = vec![
+            published.unwrap(),
+            check.to_azure_devops(Some(&rules)),
+            check.to_snapshot_with(&SnapshotOptions { sanitize: sanitize(), ..Default::default() }),
+            check.render(&RenderOptions { sanitize: sanitize(), ..Default::default() }),
+        ];
+        for export in &exports {
+            assert!(export.contains("$WORKSPACE/src/lib.rs"), "{}", export);
+            assert!(! export.contains("/home/alice") && ! export.contains("by alice"), "{}",
+                export);
+        }
+        assert!(check.to_azure_devops(None).contains("/home/alice"));
+        #[cfg(feature = "json")]
+        {
+            let tree = check.to_json_value(Some(&rules)).to_string();
+            assert!(tree.contains("$WORKSPACE/src/lib.rs") && ! tree.contains("/home/alice"));
+        }
+        #[cfg(feature = "lsp")]
+        {
+            let files = ::lsp::check_to_lsp(&check, Path::new("/"), Some(&rules));
+            assert!(files.keys().all(|uri| ! uri.contains("/home/alice")), "{:?}", files);
+            assert!(files.values().flatten()
+                .all(|diagnostic| ! diagnostic.message.contains("/home/alice")));
+        }
+    }
+
+    #[test]
+    fn snippets_can_be_dropped() {
+        let mut check = parse_output(OUTPUT);
+        if let Check::Error(ref mut problems) = check {
+            problems[0].rendered = Some(String::from("warning: unused variable: `x`"));
+            problems[0].source_line = Some(String::from("    let x = 5;"));
+        }
+        let kept = check.sanitized(&rules());
+        assert!(kept.problems()[0].rendered.is_some() && kept.problems()[0].source_line.is_some());
+        let dropped = check.sanitized(&rules().drop_snippets(true));
+        assert_eq!((&dropped.problems()[0].rendered, &dropped.problems()[0].source_line),
+            (&None, &None));
+        // The root of a report run by a checker is the workspace
+        let mut report = CheckReport::new(Check::Perfect);
+        report.root = Some(::std::path::PathBuf::from("/home/alice/work/app"));
+        let sanitized = parse_output(OUTPUT).sanitized(&SanitizeRules::for_report(&report));
+        assert_eq!(&*sanitized.problems()[0].filepath, "$WORKSPACE/src/lib.rs");
+    }
+}
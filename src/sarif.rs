@@ -0,0 +1,191 @@
+//! Output as SARIF 2.1.0, which GitHub code scanning and other dashboards
+//! read.
+
+use std::path::{Path, PathBuf};
+
+use json::Value;
+use paths::uri_reference;
+use {Check, Note, ProblemDescription, SanitizeRules, Severity};
+
+/// The schema of SARIF 2.1.0.
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Options for writing SARIF.
+#[derive(Debug, Clone, Default)]
+pub struct SarifOptions {
+    /// The root of the project, which the URIs of files under it are
+    /// relative to (as ```%SRCROOT%```).
+    pub root: Option<PathBuf>,
+    /// Redact the problems with the rules before writing them.
+    pub sanitize: Option<SanitizeRules>,
+}
+
+/// Returns the physical location of the span in the file.
+fn location(file: &str, note: &Note, root: Option<&Path>) -> Value {
+    let path = Path::new(file);
+    let artifact = match root.and_then(|root| path.strip_prefix(root).ok()) {
+        Some(relative) => vec![
+            ("uri", Value::from(uri_reference(&relative.to_string_lossy()))),
+            ("uriBaseId", Value::from("%SRCROOT%")),
+        ],
+        None => vec![("uri", Value::from(uri_reference(file)))],
+    };
+    let mut region = vec![
+        ("startLine", Value::from(note.start_line)),
+        ("startColumn", Value::from(note.start_col)),
+        ("endLine", Value::from(note.end_line)),
+        ("endColumn", Value::from(note.end_col)),
+    ];
+    if let (Some(start), Some(end)) = (note.byte_start, note.byte_end) {
+        region.push(("byteOffset", Value::from(start)));
+        region.push(("byteLength", Value::from(end.saturating_sub(start))));
+    }
+    Value::object(vec![("physicalLocation", Value::object(vec![
+        ("artifactLocation", Value::object(artifact)),
+        ("region", Value::object(region)),
+    ]))])
+}
+
+/// Returns the SARIF result of the problem.
+fn result(problem: &ProblemDescription, root: Option<&Path>) -> Value {
+    let level = match problem.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Note | Severity::Help => "note",
+    };
+    let mut members = Vec::new();
+    if let Some(rule) = problem.error_code().or_else(|| problem.lint()) {
+        members.push(("ruleId", Value::from(rule)));
+    }
+    members.push(("level", Value::from(level)));
+    members.push(("message", Value::object(vec![
+        ("text", Value::from(&problem.message.message[..])),
+    ])));
+    let mut locations = vec![location(&problem.filepath, &problem.message, root)];
+    locations.extend(problem.additional_primary_spans.iter()
+        .map(|note| location(note.file.as_deref().unwrap_or(&problem.filepath), note, root)));
+    members.push(("locations", Value::from(locations)));
+    // The children without a span only have their message, and the ones
+    // with a span in an unknown file are left out
+    let related: Vec<Value> = problem.help.iter().map(|note| ("help", note))
+        .chain(problem.notes.iter().map(|note| ("note", note)))
+        .filter(|&(_, note)| note.start_line == 0 || note.file.is_some())
+        .enumerate()
+        .map(|(id, (kind, note))| {
+            let message = Value::object(vec![
+                ("text", Value::from(format!("{}: {}", kind, note.message))),
+            ]);
+            let mut related = match note.file {
+                Some(ref file) if note.start_line > 0 => match location(file, note, root) {
+                    Value::Object(members) => members,
+                    _ => Vec::new(),
+                },
+                _ => Vec::new(),
+            };
+            related.insert(0, (String::from("id"), Value::from(id)));
+            related.push((String::from("message"), message));
+            Value::Object(related)
+        })
+        .collect();
+    if ! related.is_empty() {
+        members.push(("relatedLocations", Value::from(related)));
+    }
+    Value::object(members)
+}
+
+impl Check {
+    /// Returns the problems as a SARIF log of one run of the tool (see
+    /// ```to_sarif_with```).
+    pub fn to_sarif(&self, tool_name: &str) -> String {
+        self.to_sarif_with(tool_name, &SarifOptions::default())
+    }
+
+    /// Returns the problems as a SARIF log of one run of the tool, with a
+    /// result for each problem. The rule of a result is the error code or
+    /// lint of its problem, and its help and notes are related locations.
+    pub fn to_sarif_with(&self, tool_name: &str, options: &SarifOptions) -> String {
+        if let Some(ref rules) = options.sanitize {
+            let options = SarifOptions { root: options.root.clone(), sanitize: None };
+            return self.sanitized(rules).to_sarif_with(tool_name, &options);
+        }
+        let root = options.root.as_deref();
+        let mut rules: Vec<&str> = self.problems().iter()
+            .filter_map(|problem| problem.error_code().or_else(|| problem.lint()))
+            .collect();
+        rules.sort_unstable();
+        rules.dedup();
+        let driver = Value::object(vec![
+            ("name", Value::from(tool_name)),
+            ("rules", Value::from(rules.iter()
+                .map(|&rule| Value::object(vec![("id", Value::from(rule))]))
+                .collect::<Vec<_>>())),
+        ]);
+        let run = Value::object(vec![
+            ("tool", Value::object(vec![("driver", driver)])),
+            ("results", Value::from(self.problems().iter()
+                .map(|problem| result(problem, root))
+                .collect::<Vec<_>>())),
+        ]);
+        Value::object(vec![
+            ("$schema", Value::from(SCHEMA)),
+            ("version", Value::from("2.1.0")),
+            ("runs", Value::from(vec![run])),
+        ]).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parse_output;
+
+    #[test]
+    fn problems_become_results_of_a_run() {
+        let mut problems = parse_output("\
+error[E0425]: cannot find value `x` in this scope
+ --> src/lib.rs:2:5
+  |
+2 |     x
+  |     ^ not found in this scope
+  |
+help: a local variable with a similar name exists
+ --> src/lib.rs:2:5
+  |
+2 |     y
+  |     ^
+warning: unused variable: `z`
+ --> src/main.rs:3:9
+  |
+3 |     let z = 1;
+  |         ^
+  |
+  = note: `#[warn(unused_variables)]` on by default
+").problems().to_vec();
+        problems[0].filepath = "/project/src/lib.rs".into();
+        // Help in another file, and help in a file that isn't known
+        problems[0].help[0].file = Some("/project/src/other.rs".into());
+        let unknown = problems[0].help[0].clone();
+        problems[0].help.push(Note { file: None, ..unknown });
+        let check = Check::from_problems(problems);
+        let options = SarifOptions { root: Some(PathBuf::from("/project")), sanitize: None };
+        let log = Value::parse(&check.to_sarif_with("lintparser", &options)).unwrap();
+
+        let keys: Vec<&str> = log.as_object().unwrap().iter().map(|(key, _)| &key[..])
+            .collect();
+        assert_eq!(keys, ["$schema", "version", "runs"]);
+        assert_eq!(log.get("version").and_then(Value::as_str), Some("2.1.0"));
+        let runs = log.get("runs").and_then(Value::as_array).unwrap();
+        assert_eq!(runs.len(), 1);
+        let driver = runs[0].get("tool").and_then(|tool| tool.get("driver")).unwrap();
+        assert_eq!(driver.get("name").and_then(Value::as_str), Some("lintparser"));
+        assert_eq!(driver.get("rules").unwrap().to_string(),
+            r#"[{"id":"E0425"},{"id":"unused_variables"}]"#);
+        let results = runs[0].get("results").and_then(Value::as_array).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].to_string(), r#"{"ruleId":"E0425","level":"error","message":{"text":"cannot find value `x` in this scope [E0425]"},"locations":[{"physicalLocation":{"artifactLocation":{"uri":"src/lib.rs","uriBaseId":"%SRCROOT%"},"region":{"startLine":2,"startColumn":5,"endLine":2,"endColumn":6}}}],"relatedLocations":[{"id":0,"physicalLocation":{"artifactLocation":{"uri":"src/other.rs","uriBaseId":"%SRCROOT%"},"region":{"startLine":2,"startColumn":5,"endLine":2,"endColumn":6}},"message":{"text":"help: a local variable with a similar name exists"}}]}"#);
+        assert_eq!(results[1].get("level").and_then(Value::as_str), Some("warning"));
+        assert_eq!(results[1].get("relatedLocations").unwrap().to_string(),
+            r#"[{"id":0,"message":{"text":"note: `#[warn(unused_variables)]` on by default"}}]"#);
+        assert_eq!(uri_reference("src\\my file.rs"), "src/my%20file.rs");
+    }
+}
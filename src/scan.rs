@@ -0,0 +1,322 @@
+//! Lightweight scanning of the Rust source files of a project, without a
+//! full parser.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use CargoConfig;
+
+/// Returns the ```.rs``` files under the root, sorted, skipping hidden
+/// directories, ```target``` (and the ```build.target-dir``` of the config
+/// files) and the names listed in the ```.gitignore``` file of the root.
+/// Only plain names and ```/```-anchored paths of the ignore file are
+/// understood; globs and negations are skipped.
+pub fn rust_files(root: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut ignored = vec![root.join("target")];
+    let target_dir = CargoConfig::discover(root).target_dir;
+    if let Some(relative) = target_dir.as_ref()
+            .and_then(|dir| dir.strip_prefix(fs::canonicalize(root).ok()?).ok()) {
+        ignored.push(root.join(relative));
+    }
+    let mut ignored_names = Vec::new();
+    if let Ok(gitignore) = fs::read_to_string(root.join(".gitignore")) {
+        for line in gitignore.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') || line.starts_with('!')
+                    || line.contains('*') || line.contains('?') || line.contains('[') {
+                continue;
+            }
+            let pattern = line.trim_end_matches('/');
+            if pattern.starts_with('/') || pattern.contains('/') {
+                ignored.push(root.join(pattern.trim_start_matches('/')));
+            } else {
+                ignored_names.push(String::from(pattern));
+            }
+        }
+    }
+    let mut files = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let name = path.file_name().map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            if name.starts_with('.') || ignored.contains(&path) || ignored_names.contains(&name) {
+                continue;
+            }
+            if path.is_dir() {
+                dirs.push(path);
+            } else if name.ends_with(".rs") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Replaces the contents of comments and string and character literals
+/// with spaces, keeping the line breaks so that lines stay where they are.
+pub fn blank_comments_and_strings(source: &str) -> String {
+    let chars: Vec<char> = source.chars().collect();
+    let mut out = String::with_capacity(source.len());
+    let blank = |out: &mut String, ch: char| out.push(if ch == '\n' { '\n' } else { ' ' });
+    let mut i = 0;
+    while i < chars.len() {
+        let ch = chars[i];
+        let next = chars.get(i + 1).cloned();
+        if ch == '/' && next == Some('/') {
+            while i < chars.len() && chars[i] != '\n' {
+                blank(&mut out, chars[i]);
+                i += 1;
+            }
+        } else if ch == '/' && next == Some('*') {
+            let mut depth = 0;
+            while i < chars.len() {
+                if chars[i] == '/' && chars.get(i + 1) == Some(&'*') {
+                    depth += 1;
+                    out.push_str("  ");
+                    i += 2;
+                } else if chars[i] == '*' && chars.get(i + 1) == Some(&'/') {
+                    depth -= 1;
+                    out.push_str("  ");
+                    i += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    blank(&mut out, chars[i]);
+                    i += 1;
+                }
+            }
+        } else if let Some(hashes) = raw_string_start(&chars, i) {
+            // r"..." or r#"..."#, with any number of hashes
+            let open = hashes + 2;
+            for _ in 0..open {
+                out.push(' ');
+            }
+            i += open;
+            while i < chars.len() {
+                if chars[i] == '"' && chars[i + 1..].iter().take(hashes).filter(|&&c| c == '#')
+                        .count() == hashes {
+                    for _ in 0..hashes + 1 {
+                        out.push(' ');
+                    }
+                    i += hashes + 1;
+                    break;
+                }
+                blank(&mut out, chars[i]);
+                i += 1;
+            }
+        } else if ch == '"' {
+            out.push(' ');
+            i += 1;
+            while i < chars.len() && chars[i] != '"' {
+                if chars[i] == '\\' && i + 1 < chars.len() {
+                    blank(&mut out, chars[i]);
+                    i += 1;
+                }
+                blank(&mut out, chars[i]);
+                i += 1;
+            }
+            if i < chars.len() {
+                out.push(' ');
+                i += 1;
+            }
+        } else if ch == '\'' && (next == Some('\\') || chars.get(i + 2) == Some(&'\'')) {
+            // A character literal rather than a lifetime
+            out.push(' ');
+            i += 1;
+            while i < chars.len() && chars[i] != '\'' && chars[i] != '\n' {
+                if chars[i] == '\\' && i + 1 < chars.len() {
+                    out.push(' ');
+                    i += 1;
+                }
+                blank(&mut out, chars[i]);
+                i += 1;
+            }
+            if i < chars.len() && chars[i] == '\'' {
+                out.push(' ');
+                i += 1;
+            }
+        } else {
+            out.push(ch);
+            i += 1;
+        }
+    }
+    out
+}
+
+/// Which lines of the source ```count_lines``` counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineCount {
+    /// Every line.
+    All,
+    /// The lines with more than whitespace.
+    NonBlank,
+    /// The lines with code, which are neither blank nor only comments. The
+    /// lines of string literals are code.
+    Code,
+}
+
+/// Returns the number of lines of the Rust files under the root (see
+/// ```rust_files``` for the ignored paths).
+pub fn count_lines<P: AsRef<Path>>(root: P, count: LineCount) -> io::Result<usize> {
+    let mut lines = 0;
+    for path in rust_files(root.as_ref())? {
+        lines += source_lines(&fs::read_to_string(&path)?, count);
+    }
+    Ok(lines)
+}
+
+/// Where the scan of ```code_lines``` is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScanState {
+    Code,
+    LineComment,
+    /// In a block comment, nested to the depth.
+    BlockComment(usize),
+    String,
+    /// In a raw string literal, closed by a quote and the number of hashes.
+    RawString(usize),
+}
+
+/// Returns the number of lines of the source that are counted.
+pub fn source_lines(source: &str, count: LineCount) -> usize {
+    match count {
+        LineCount::All => source.lines().count(),
+        LineCount::NonBlank => source.lines().filter(|line| ! line.trim().is_empty()).count(),
+        LineCount::Code => code_lines(source),
+    }
+}
+
+fn code_lines(source: &str) -> usize {
+    use self::ScanState::*;
+    let chars: Vec<char> = source.chars().collect();
+    let mut state = Code;
+    let mut lines = 0;
+    let mut has_code = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let ch = chars[i];
+        let next = chars.get(i + 1).cloned();
+        if ch == '\n' {
+            lines += has_code as usize;
+            has_code = false;
+            if state == LineComment {
+                state = Code;
+            }
+            i += 1;
+            continue;
+        }
+        match state {
+            Code if ch == '/' && next == Some('/') => state = LineComment,
+            Code if ch == '/' && next == Some('*') => {
+                state = BlockComment(1);
+                i += 1;
+            },
+            Code => {
+                has_code |= ! ch.is_whitespace();
+                if let Some(hashes) = raw_string_start(&chars, i) {
+                    state = RawString(hashes);
+                    i += hashes + 1;
+                } else if ch == '"' {
+                    state = String;
+                } else if ch == '\'' && (next == Some('\\') || chars.get(i + 2) == Some(&'\'')) {
+                    // A character literal rather than a lifetime
+                    let start = if next == Some('\\') { i + 3 } else { i + 2 };
+                    let end = (start..chars.len()).find(|&j| chars[j] == '\'' || chars[j] == '\n');
+                    i = end.unwrap_or(chars.len()).max(i + 1);
+                    if chars.get(i) == Some(&'\n') {
+                        continue;
+                    }
+                }
+            },
+            LineComment => {},
+            BlockComment(depth) => if ch == '/' && next == Some('*') {
+                state = BlockComment(depth + 1);
+                i += 1;
+            } else if ch == '*' && next == Some('/') {
+                state = if depth == 1 { Code } else { BlockComment(depth - 1) };
+                i += 1;
+            },
+            String => {
+                has_code = true;
+                if ch == '\\' {
+                    i += 1;
+                } else if ch == '"' {
+                    state = Code;
+                }
+            },
+            RawString(hashes) => {
+                has_code = true;
+                if ch == '"' && chars[i + 1..].iter().take(hashes).filter(|&&c| c == '#')
+                        .count() == hashes {
+                    state = Code;
+                    i += hashes;
+                }
+            },
+        }
+        i += 1;
+    }
+    lines + has_code as usize
+}
+
+/// Returns the number of hashes if a raw string literal starts at ```i```.
+fn raw_string_start(chars: &[char], i: usize) -> Option<usize> {
+    if chars[i] != 'r' {
+        return None;
+    }
+    if i > 0 && (chars[i - 1].is_alphanumeric() || chars[i - 1] == '_') && chars[i - 1] != 'b' {
+        return None;
+    }
+    if i > 1 && chars[i - 1] == 'b' && (chars[i - 2].is_alphanumeric() || chars[i - 2] == '_') {
+        return None;
+    }
+    let hashes = chars[i + 1..].iter().take_while(|&&c| c == '#').count();
+    if chars.get(i + 1 + hashes) == Some(&'"') {
+        Some(hashes)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blanks_comments_and_literals_but_keeps_lines() {
+        let source = "let a = \"// not a comment\"; // comment\n\
+            /* block /* nested */ still */ let b = 'x';\n\
+            let c = r##\"raw \"# quote\"##; fn f<'a>(x: &'a str) {}\n\
+            let d = '\\''; let e = b\"bytes\";";
+        let blanked = blank_comments_and_strings(source);
+        assert_eq!(blanked.lines().count(), 4);
+        let words: Vec<&str> = blanked.split_whitespace().collect();
+        assert_eq!(words, vec!["let", "a", "=", ";", "let", "b", "=", ";", "let", "c", "=", ";",
+            "fn", "f<'a>(x:", "&'a", "str)", "{}", "let", "d", "=", ";", "let", "e", "=", "b", ";"]);
+    }
+
+    #[test]
+    fn code_lines_skip_comments_but_not_strings() {
+        let source = "\
+//! Module docs
+fn main() {
+
+    /* a block comment
+       over lines /* nested */
+       with \"// quotes\" */ let a = 1;
+    let url = \"http://example.com\"; // a comment
+    let text = \"
+// inside a string\";
+    let raw = r#\"/* not a comment\"#; let c = '\"';
+    /* closed */
+}
+";
+        assert_eq!(source_lines(source, LineCount::All), 12);
+        assert_eq!(source_lines(source, LineCount::NonBlank), 11);
+        assert_eq!(source_lines(source, LineCount::Code), 7);
+        assert_eq!(source_lines("let s = '\\''; // x\n// y", LineCount::Code), 1);
+    }
+}
@@ -0,0 +1,180 @@
+//! A canonical text format of checks for golden files, designed to give
+//! small and readable diffs.
+//!
+//! The format starts with a header line, followed by one line per problem
+//! with tab-separated fields in a fixed order (tabs shown as ```\t```):
+//!
+//! ```text
+//! # lintparser snapshot v1
+//! warning\tsrc/lib.rs:2:9\tunused_variables\tunused variable: `x`, #[warn(unused_variables)] on by default
+//! ```
+//!
+//! The fields are the severity, the normalized path with the start of the
+//! span, the lint (or ```-```) and the message, with tabs, newlines and
+//! backslashes escaped. The lines are sorted, so the order in which cargo
+//! reported the problems doesn't matter. Help and notes are left out.
+
+use std::path::Path;
+
+use {normalize_path, Check, ProblemDescription, SanitizeRules, Severity};
+
+const HEADER: &str = "# lintparser snapshot v1";
+
+/// Options for writing snapshots.
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotOptions {
+    /// Write the positions as ```LL:CC```, so that moving code around
+    /// doesn't change the snapshot.
+    pub mask_positions: bool,
+    /// Redact the problems with the rules before writing them.
+    pub sanitize: Option<SanitizeRules>,
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            out.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+fn snapshot_line(problem: &ProblemDescription, options: &SnapshotOptions) -> String {
+    let severity = problem.severity.name();
+    let position = if options.mask_positions {
+        String::from("LL:CC")
+    } else {
+        format!("{}:{}", problem.message.start_line, problem.message.start_col)
+    };
+    let mut path = normalize_path(Path::new(""), &problem.filepath).join("/");
+    if problem.filepath.starts_with('/') {
+        path.insert(0, '/');
+    }
+    format!("{}\t{}:{}\t{}\t{}", severity, escape(&path), position,
+        escape(problem.lint().unwrap_or("-")), escape(&problem.message.message))
+}
+
+/// Parses a problem line of a snapshot.
+fn parse_snapshot_line(line: &str) -> Option<ProblemDescription> {
+    let mut fields = line.splitn(4, '\t');
+    let severity = Severity::from_name(fields.next()?)?;
+    let location = fields.next()?;
+    let _lint = fields.next()?;
+    let message = unescape(fields.next()?);
+
+    // The path may contain colons, but the position can't
+    let mut parts = location.rsplitn(3, ':');
+    let col = parts.next()?;
+    let line = parts.next()?;
+    let filepath = unescape(parts.next()?);
+    // Masked positions are read as 0
+    let line = line.parse().unwrap_or(0);
+    let col = col.parse().unwrap_or(0);
+    let mut problem = ProblemDescription::new(filepath, line, col, line, col, message,
+        vec![], vec![]);
+    problem.severity = severity;
+    Some(problem)
+}
+
+impl Check {
+    /// Writes the check as a snapshot with the positions of the problems.
+    pub fn to_snapshot(&self) -> String {
+        self.to_snapshot_with(&SnapshotOptions::default())
+    }
+
+    /// Writes the check as a snapshot.
+    pub fn to_snapshot_with(&self, options: &SnapshotOptions) -> String {
+        if let Some(ref rules) = options.sanitize {
+            let options = SnapshotOptions { sanitize: None, ..options.clone() };
+            return self.sanitized(rules).to_snapshot_with(&options);
+        }
+        let mut lines: Vec<String> = self.problems().iter()
+            .map(|problem| snapshot_line(problem, options))
+            .collect();
+        lines.sort();
+        let mut snapshot = format!("{}\n", HEADER);
+        for line in lines {
+            snapshot.push_str(&line);
+            snapshot.push('\n');
+        }
+        snapshot
+    }
+
+    /// Reads a snapshot back. This is lossy: the problems have no help,
+    /// notes or end positions, and masked positions are read as 0. Lines
+    /// that can't be read are skipped.
+    pub fn from_snapshot(snapshot: &str) -> Check {
+        Check::from_problems(snapshot.lines()
+            .filter(|line| ! line.starts_with('#'))
+            .filter_map(parse_snapshot_line)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parse_output;
+
+    const OUTPUT: &str = "\
+src/main.rs:4:5: 4:8 error: unresolved name `foo` [E0425]
+src/main.rs:4:5: 4:8 help: did you mean `for`?
+./src\\lib.rs:2:9: 2:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+src/lib.rs:1:1: 3:2 warning: function is never used: `a`, #[warn(dead_code)] on by default
+";
+
+    const GOLDEN: &str = "\
+# lintparser snapshot v1
+error\tsrc/main.rs:4:5\t-\tunresolved name `foo` [E0425]
+warning\tsrc/lib.rs:1:1\tdead_code\tfunction is never used: `a`, #[warn(dead_code)] on by default
+warning\tsrc/lib.rs:2:9\tunused_variables\tunused variable: `x`, #[warn(unused_variables)] on by default
+";
+
+    const GOLDEN_MASKED: &str = "\
+# lintparser snapshot v1
+error\tsrc/main.rs:LL:CC\t-\tunresolved name `foo` [E0425]
+warning\tsrc/lib.rs:LL:CC\tdead_code\tfunction is never used: `a`, #[warn(dead_code)] on by default
+warning\tsrc/lib.rs:LL:CC\tunused_variables\tunused variable: `x`, #[warn(unused_variables)] on by default
+";
+
+    #[test]
+    fn snapshots_match_the_golden_files() {
+        let check = parse_output(OUTPUT);
+        assert_eq!(check.to_snapshot(), GOLDEN);
+        let masked = SnapshotOptions { mask_positions: true, ..SnapshotOptions::default() };
+        assert_eq!(check.to_snapshot_with(&masked), GOLDEN_MASKED);
+        assert_eq!(Check::Perfect.to_snapshot(), "# lintparser snapshot v1\n");
+    }
+
+    #[test]
+    fn snapshots_read_back_structurally() {
+        let mut problem = ProblemDescription::new(String::from("C:\\src\\a.rs"), 7, 3, 7, 4,
+            String::from("tab\there\nand a \\ backslash"), vec![], vec![]);
+        problem.severity = Severity::Error;
+        let snapshot = Check::Error(vec![problem]).to_snapshot();
+        assert_eq!(Check::from_snapshot(&snapshot).to_snapshot(), snapshot);
+
+        let check = Check::from_snapshot(GOLDEN_MASKED);
+        match check {
+            Check::Error(ref problems) => assert_eq!(problems.len(), 3),
+            ref other => panic!("Expected errors, got {:?}", other),
+        }
+        assert_eq!(check.problems()[1].lint(), Some("dead_code"));
+        assert_eq!(check.problems()[1].message.start_line, 0);
+        let masked = SnapshotOptions { mask_positions: true, ..SnapshotOptions::default() };
+        assert_eq!(check.to_snapshot_with(&masked), GOLDEN_MASKED);
+    }
+}
@@ -0,0 +1,150 @@
+//! Totals of a check, for reports.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use allows::AllowSite;
+use {normalize_path, Check, Fixability, Severity};
+
+/// The totals of a check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CheckStats {
+    pub errors: usize,
+    pub warnings: usize,
+    /// The number of distinct files with problems.
+    pub files: usize,
+    /// The number of problems that can be fixed without a review.
+    pub fixable: usize,
+    /// The number of warnings known to be in test code.
+    pub test_only_warnings: usize,
+    /// The number of lints allowed in the source, if it was scanned.
+    pub suppressed: Option<usize>,
+}
+
+impl CheckStats {
+    /// Adds the number of allowed lints found by ```count_allows```.
+    pub fn with_allows(mut self, allows: &BTreeMap<String, Vec<AllowSite>>) -> CheckStats {
+        self.suppressed = Some(allows.values().map(Vec::len).sum());
+        self
+    }
+}
+
+/// Formats the stats as e.g. "0 errors, 14 warnings (3 fixable, 5 in tests) + 63
+/// suppressed".
+impl fmt::Display for CheckStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} errors, {} warnings", self.errors, self.warnings)?;
+        let mut details = Vec::new();
+        if self.fixable > 0 {
+            details.push(format!("{} fixable", self.fixable));
+        }
+        if self.test_only_warnings > 0 {
+            details.push(format!("{} in tests", self.test_only_warnings));
+        }
+        if ! details.is_empty() {
+            write!(f, " ({})", details.join(", "))?;
+        }
+        if let Some(suppressed) = self.suppressed {
+            write!(f, " + {} suppressed", suppressed)?;
+        }
+        Ok(())
+    }
+}
+
+impl Check {
+    /// Returns the totals of each module (see ```Check::by_module```), in
+    /// the order in which the modules were first reported.
+    pub fn stats_by_module(&self) -> Vec<(&str, CheckStats)> {
+        self.by_module().into_iter().map(|(module, problems)| {
+            let problems = problems.into_iter().cloned().collect();
+            (module, Check::from_problems(problems).stats())
+        }).collect()
+    }
+
+    /// Returns the number of problems that can be fixed without a review.
+    pub fn fixable_count(&self) -> usize {
+        self.problems().iter()
+            .filter(|problem| problem.fixability() == Fixability::Automatic)
+            .count()
+    }
+
+    /// Returns the totals of the check.
+    pub fn stats(&self) -> CheckStats {
+        let problems = self.problems();
+        let errors = problems.iter()
+            .filter(|problem| problem.severity == Severity::Error)
+            .count();
+        let mut files: Vec<Vec<String>> = problems.iter()
+            .map(|problem| normalize_path(Path::new(""), &problem.filepath))
+            .collect();
+        files.sort();
+        files.dedup();
+        CheckStats {
+            errors,
+            warnings: problems.len() - errors,
+            files: files.len(),
+            fixable: self.fixable_count(),
+            test_only_warnings: problems.iter()
+                .filter(|problem| problem.severity == Severity::Warning
+                    && problem.in_test_code == Some(true))
+                .count(),
+            suppressed: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use allows::AllowScope;
+    use std::path::PathBuf;
+    use {Applicability, Note, ProblemDescription, Suggestion, SuggestionTarget};
+
+    #[test]
+    fn stats_with_suppressed_allows() {
+        let warning = ProblemDescription::new(String::from("src/lib.rs"), 1, 1, 1, 2,
+            String::from("unused"), vec![], vec![]);
+        let check = Check::Warning(vec![warning.clone(), warning]);
+        let stats = check.stats();
+        assert_eq!(stats, CheckStats {
+            errors: 0, warnings: 2, files: 1, fixable: 0, test_only_warnings: 0, suppressed: None
+        });
+        assert_eq!(stats.to_string(), "0 errors, 2 warnings");
+        assert_eq!(check.stats_by_module(), vec![("crate", stats)]);
+
+        let site = |lint: &str| AllowSite {
+            file: PathBuf::from("src/lib.rs"), line: 1, lint: String::from(lint),
+            scope: AllowScope::Outer,
+        };
+        let mut allows = BTreeMap::new();
+        allows.insert(String::from("dead_code"), vec![site("dead_code"), site("dead_code")]);
+        allows.insert(String::from("unused_mut"), vec![site("unused_mut")]);
+        assert_eq!(stats.with_allows(&allows).to_string(), "0 errors, 2 warnings + 3 suppressed");
+    }
+
+    #[test]
+    fn fixability_follows_the_best_suggestion() {
+        let mut problem = ProblemDescription::new(String::from("src/lib.rs"), 1, 1, 1, 2,
+            String::from("unused"), vec![], vec![]);
+        let manual = problem.clone();
+        problem.suggestions.push(Suggestion {
+            span: Note::new(1, 1, 1, 2, "remove it"),
+            replacement: String::new(),
+            applicability: Applicability::HasPlaceholders,
+            target: SuggestionTarget::Span,
+        });
+        let assisted = problem.clone();
+        problem.suggestions.push(Suggestion {
+            applicability: Applicability::MachineApplicable,
+            ..problem.suggestions[0].clone()
+        });
+        assert_eq!(manual.fixability(), Fixability::Manual);
+        assert_eq!(assisted.fixability(), Fixability::Assisted);
+        assert_eq!(problem.fixability(), Fixability::Automatic);
+
+        let check = Check::Warning(vec![manual, problem.clone(), problem]);
+        assert_eq!(check.fixable_count(), 2);
+        assert_eq!(check.stats().to_string(), "0 errors, 3 warnings (2 fixable)");
+    }
+}
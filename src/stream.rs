@@ -0,0 +1,235 @@
+//! Checking a project with its problems given as cargo reports them, so
+//! that an editor can show the first ones while the rest of a workspace is
+//! still being checked.
+
+use std::collections::HashSet;
+use std::io::{self, BufRead, BufReader, Read};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdout, ExitStatus};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use checker::json_command;
+use json::Value;
+use messages::message_problem;
+use {absolute_path, cargo_failure, Check, CheckError, Checker, ParseError, ProblemDescription,
+    RawBlock, SkipReason};
+
+/// Reads the problems of the JSON messages of cargo from its lines of
+/// stdout, one at a time, for the streams that read them as cargo writes
+/// them.
+#[derive(Debug)]
+pub struct MessageParser {
+    dir: PathBuf,
+    problems: Vec<ProblemDescription>,
+    /// The index of the next line.
+    line: usize,
+    /// When the check started, which the problems are emitted after.
+    started: Instant,
+    /// The paths of the problems so far, which later problems share.
+    paths: HashSet<Arc<str>>,
+}
+
+impl MessageParser {
+    /// Creates a parser of the messages of a check of the project in the
+    /// directory, which the paths of the problems are made absolute with.
+    /// The check is taken to start now.
+    pub fn new(dir: PathBuf) -> MessageParser {
+        MessageParser {
+            dir,
+            problems: Vec::new(),
+            line: 0,
+            started: Instant::now(),
+            paths: HashSet::new(),
+        }
+    }
+
+    /// Returns the problem of the line, emitted at the time since the start
+    /// of the check, or an error with the problems so far if the line isn't
+    /// JSON. Blank lines and the other messages of cargo give nothing.
+    pub fn feed(&mut self, line: &str) -> Option<Result<ProblemDescription, ParseError>> {
+        let index = self.line;
+        self.line += 1;
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.trim().is_empty() {
+            return None;
+        }
+        let value = match Value::parse(line) {
+            Ok(value) => value,
+            Err(_) => return Some(Err(ParseError {
+                check: Check::from_problems(self.problems.clone()),
+                unrecognized: vec![RawBlock {
+                    lines: String::from(line),
+                    line_range: index..index + 1,
+                    reason: SkipReason::UnrecognizedFormat,
+                }],
+            })),
+        };
+        message_problem(&value).map(|problem| {
+            let mut problem = absolute_path(problem, &self.dir, &mut self.paths);
+            problem.emitted_at = Some(self.started.elapsed());
+            self.problems.push(problem.clone());
+            Ok(problem)
+        })
+    }
+
+    /// Returns the check of the problems so far with the exit status of
+    /// cargo, given its stderr. As with ```cargo_check_in```, a failed check
+    /// is only an error if the output says why.
+    pub fn finish(&mut self, status: ExitStatus, stderr: &str)
+            -> Result<(Check, ExitStatus), CheckError> {
+        let check = Check::from_problems(mem::take(&mut self.problems));
+        match cargo_failure(&self.dir, status.code(), stderr, &check) {
+            Some(err) if ! status.success() => Err(err),
+            _ => Ok((check, status)),
+        }
+    }
+}
+
+/// A running ```cargo check``` that yields the problems of its JSON messages
+/// as cargo writes them, with absolute paths. A message has a whole
+/// diagnostic with its help and notes, so each problem is complete. Lines
+/// that aren't JSON are errors, which have the problems so far.
+///
+/// Dropping the stream before ```finish``` kills cargo.
+pub struct CheckStream {
+    child: Child,
+    reaped: bool,
+    stdout: BufReader<ChildStdout>,
+    /// The thread that reads stderr, so that cargo never blocks on it.
+    stderr: Option<JoinHandle<String>>,
+    parser: MessageParser,
+    ended: bool,
+    read_error: Option<io::Error>,
+}
+
+/// Starts ```cargo check``` on the current directory, with its problems
+/// given as they are found (see ```cargo_check_stream_in```).
+pub fn cargo_check_stream() -> Result<CheckStream, CheckError> {
+    cargo_check_stream_in(".")
+}
+
+/// Starts ```cargo check``` on the project in the directory, which must
+/// contain a ```Cargo.toml```, with its problems given as they are found.
+pub fn cargo_check_stream_in<P: AsRef<Path>>(dir: P) -> Result<CheckStream, CheckError> {
+    let (mut command, dir) = json_command(&Checker::new().dir(dir.as_ref()))?;
+    let child = command.spawn().map_err(CheckError::spawning_cargo)?;
+    Ok(CheckStream::of_child(child, dir))
+}
+
+impl CheckStream {
+    /// Reads the messages of the child, which has its output piped, as the
+    /// ones of a check of the project in the directory.
+    fn of_child(mut child: Child, dir: PathBuf) -> CheckStream {
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        let stderr = child.stderr.take().map(|mut stderr| thread::spawn(move || {
+            let mut bytes = Vec::new();
+            let _ = stderr.read_to_end(&mut bytes);
+            String::from_utf8_lossy(&bytes).into_owned()
+        }));
+        CheckStream {
+            parser: MessageParser::new(dir),
+            child,
+            reaped: false,
+            stdout,
+            stderr,
+            ended: false,
+            read_error: None,
+        }
+    }
+
+    /// Reads the rest of the output and waits for cargo, and returns the
+    /// check of all the problems with the exit status of cargo. As with
+    /// ```cargo_check_in```, a failed check is only an error if the output
+    /// says why.
+    pub fn finish(mut self) -> Result<(Check, ExitStatus), CheckError> {
+        for _ in self.by_ref() {}
+        if let Some(err) = self.read_error.take() {
+            return Err(err.into());
+        }
+        let status = self.child.wait()?;
+        self.reaped = true;
+        let stderr = self.stderr.take()
+            .and_then(|stderr| stderr.join().ok())
+            .unwrap_or_default();
+        self.parser.finish(status, &stderr)
+    }
+}
+
+impl Iterator for CheckStream {
+    type Item = Result<ProblemDescription, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut bytes = Vec::new();
+        while ! self.ended {
+            bytes.clear();
+            match self.stdout.read_until(b'\n', &mut bytes) {
+                Ok(0) => self.ended = true,
+                Ok(_) => {},
+                Err(err) => {
+                    self.read_error = Some(err);
+                    self.ended = true;
+                },
+            }
+            if self.ended {
+                break;
+            }
+            let line = String::from_utf8_lossy(&bytes);
+            if let Some(result) = self.parser.feed(&line) {
+                return Some(result);
+            }
+        }
+        None
+    }
+}
+
+impl Drop for CheckStream {
+    fn drop(&mut self) {
+        // Reap cargo, so that it doesn't linger as a zombie
+        if ! self.reaped {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::{Command, Stdio};
+    use std::time::Duration;
+
+    #[test]
+    fn lines_are_parsed_one_at_a_time() {
+        let message = r#"{"reason":"compiler-message","package_id":"app 0.1.0 (path+file:///app)","target":{"kind":["lib"],"name":"app"},"message":{"rendered":"warning: unused variable: `x`\n","$message_type":"diagnostic","children":[],"level":"warning","message":"unused variable: `x`","spans":[{"byte_end":26,"byte_start":25,"column_end":10,"column_start":9,"expansion":null,"file_name":"src/lib.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}],"code":{"code":"unused_variables","explanation":null}}}"#;
+        let mut parser = MessageParser::new(PathBuf::from("/app"));
+        assert!(parser.feed("\n").is_none());
+        assert!(parser.feed(r#"{"reason":"build-finished","success":true}"#).is_none());
+        let problem = parser.feed(&format!("{}\r\n", message)).unwrap().unwrap();
+        assert_eq!(Path::new(&*problem.filepath), Path::new("/app/src/lib.rs"));
+        let err = parser.feed("Compiling app").unwrap().unwrap_err();
+        assert_eq!(err.check.problems(), [problem]);
+        assert_eq!(err.unrecognized[0].line_range, 3..4);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn problems_are_emitted_at_the_time_they_are_read() {
+        let message = r#"{"reason":"compiler-message","message":{"children":[],"level":"warning","message":"unused variable: `x`","spans":[{"column_end":10,"column_start":9,"file_name":"src/lib.rs","is_primary":true,"line_end":2,"line_start":2}]}}"#;
+        let child = Command::new("sh")
+            .args(["-c", &format!("echo '{0}'; sleep 0.5; echo '{0}'", message)])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stream = CheckStream::of_child(child, PathBuf::from("/app"));
+        let times: Vec<Duration> = stream.map(|problem| problem.unwrap().emitted_at.unwrap())
+            .collect();
+        assert_eq!(times.len(), 2);
+        assert!(times[0] < Duration::from_millis(400), "{:?}", times);
+        assert!(times[1] >= Duration::from_millis(500), "{:?}", times);
+    }
+}
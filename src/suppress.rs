@@ -0,0 +1,64 @@
+//! Suggestions to silence a problem with an ```#[allow(...)]``` attribute.
+
+use {Applicability, Note, ProblemDescription, Suggestion, SuggestionTarget};
+
+impl ProblemDescription {
+    /// Returns the line an ```#[allow(...)]``` attribute for the problem is
+    /// inserted above. For ```dead_code``` warnings about a part of an item
+    /// (like a field that is never read) this is the line of the enclosing
+    /// item, as that is where the attribute silences the lint.
+    pub fn suppression_line(&self) -> usize {
+        match self.enclosing_item() {
+            Some(item) if self.lint() == Some("dead_code") => item.start_line,
+            _ => self.message.start_line,
+        }
+    }
+
+    /// Returns a suggestion to insert an ```#[allow(...)]``` attribute for the
+    /// lint of the problem into the source of its file, with the indentation
+    /// of the line it goes above. Problems without a lint can't be silenced.
+    pub fn suppression(&self, source: &str) -> Option<Suggestion> {
+        let lint = self.lint()?;
+        let line = self.suppression_line();
+        let text = source.lines().nth(line.checked_sub(1)?)?;
+        let indentation = &text[..text.len() - text.trim_start().len()];
+        Some(Suggestion {
+            span: Note::new(line, 1, line, 1, format!("allow `{}`", lint)),
+            replacement: format!("{}#[allow({})]\n", indentation, lint),
+            applicability: Applicability::MachineApplicable,
+            target: SuggestionTarget::Span,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parse_output;
+
+    const SOURCE: &str = "\
+mod config {
+    pub struct Config {
+        pub host: String,
+        port: u16,
+    }
+}
+";
+
+    #[test]
+    fn dead_fields_are_suppressed_on_their_struct() {
+        let check = parse_output("\
+src/config.rs:4:9: 4:13 warning: field `port` is never read, #[warn(dead_code)] on by default
+src/config.rs:2:16: 2:22 note: field in this struct
+src/config.rs:3:13: 3:17 warning: unused variable: `host`, #[warn(unused_variables)] on by default
+src/config.rs:3:13: 3:17 warning: this field is odd
+");
+        let problems = check.problems();
+        let field = problems[0].suppression(SOURCE).unwrap();
+        assert_eq!((field.span.start_line, field.span.start_col), (2, 1));
+        assert_eq!(field.replacement, "    #[allow(dead_code)]\n");
+        let variable = problems[1].suppression(SOURCE).unwrap();
+        assert_eq!(variable.span.start_line, 3);
+        assert_eq!(variable.replacement, "        #[allow(unused_variables)]\n");
+        assert!(problems[2].suppression(SOURCE).is_none());
+    }
+}
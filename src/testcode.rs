@@ -0,0 +1,174 @@
+//! Finding out which problems are in code that only tests use, as they
+//! matter less for the code that is shipped.
+//!
+//! This is best-effort: files under ```tests/``` and ```benches/``` are test
+//! code, and within other files only top-level ```#[cfg(test)] mod name {
+//! ... }``` modules are found. Test modules in their own file
+//! (```#[cfg(test)] mod tests;```), nested ones and other test-only cfgs
+//! aren't recognized.
+
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use scan::blank_comments_and_strings;
+use {normalize_path, Check};
+
+/// Whether the path is in a ```tests``` or ```benches``` directory.
+pub fn in_test_directory(path: &str) -> bool {
+    normalize_path(Path::new(""), path).iter()
+        .rev()
+        .skip(1)
+        .any(|component| component == "tests" || component == "benches")
+}
+
+/// Whether the text between a ```#[cfg(test)]``` attribute and a ```{``` is
+/// the start of a module, like ```pub(crate) mod tests```.
+fn is_module_start(text: &str) -> bool {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    words.len() >= 2 && words[words.len() - 2] == "mod"
+        && words[..words.len() - 2].iter().all(|word| word.starts_with("pub")
+            || word.starts_with("#["))
+}
+
+/// Returns the line ranges (1-based, from the ```mod``` line to the closing
+/// brace) of the top-level ```#[cfg(test)]``` modules of the source.
+pub fn test_module_lines(source: &str) -> Vec<Range<usize>> {
+    let blanked = blank_comments_and_strings(source);
+    let chars: Vec<char> = blanked.chars().collect();
+    let mut modules = Vec::new();
+    let mut line = 1;
+    let mut depth = 0usize;
+    // Where the text after a #[cfg(test)] attribute starts
+    let mut after_cfg: Option<usize> = None;
+    let mut module_start: Option<usize> = None;
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\n' => line += 1,
+            '#' if depth == 0 && chars.get(i + 1) == Some(&'[') => {
+                let end = (i..chars.len()).find(|&j| chars[j] == ']').unwrap_or(chars.len());
+                let attribute: String = chars[i..end].iter()
+                    .filter(|ch| ! ch.is_whitespace())
+                    .collect();
+                if attribute == "#[cfg(test)" {
+                    after_cfg = Some(end + 1);
+                }
+                line += chars[i..end].iter().filter(|&&ch| ch == '\n').count();
+                i = end;
+            },
+            '{' => {
+                if depth == 0 {
+                    let text = after_cfg.take().map(|start| {
+                        chars[start.min(i)..i].iter().collect::<String>()
+                    });
+                    if text.is_some_and(|text| is_module_start(&text)) {
+                        module_start = Some(line);
+                    }
+                }
+                depth += 1;
+            },
+            '}' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    if let Some(start) = module_start.take() {
+                        modules.push(start..line + 1);
+                    }
+                }
+            },
+            ';' if depth == 0 => after_cfg = None,
+            _ => {},
+        }
+        i += 1;
+    }
+    modules
+}
+
+impl Check {
+    /// Sets whether the problems are in test code, for the problems whose
+    /// file (relative to the root) can be read and hasn't been classified
+    /// by its path yet.
+    pub fn mark_test_code<P: AsRef<Path>>(self, root: P) -> Check {
+        let root = root.as_ref();
+        let mut modules: HashMap<PathBuf, Option<Vec<Range<usize>>>> = HashMap::new();
+        let problems = self.problems().iter().map(|problem| {
+            let mut problem = problem.clone();
+            if problem.in_test_code.is_none() {
+                let path = root.join(&*problem.filepath);
+                let ranges = modules.entry(path.clone()).or_insert_with(|| {
+                    fs::read_to_string(&path).ok().map(|source| test_module_lines(&source))
+                });
+                if let Some(ref ranges) = *ranges {
+                    let line = problem.message.start_line;
+                    problem.in_test_code = Some(ranges.iter().any(|range| range.contains(&line)));
+                }
+            }
+            problem
+        }).collect();
+        Check::from_problems(problems)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+    use parse_output;
+
+    const SOURCE: &str = "\
+fn main() {
+    let s = \"#[cfg(test)] mod fake {\";
+}
+
+#[cfg(test)]
+#[allow(unused)]
+pub(crate) mod tests {
+    fn helper() {
+        if true { }
+    }
+}
+
+#[cfg(test)]
+mod out_of_line;
+
+#[cfg(test)]
+fn only_in_tests() {
+    mod inner {}
+}
+
+#[cfg(not(test))]
+mod other {
+}
+";
+
+    #[test]
+    fn only_top_level_test_modules_are_found() {
+        assert_eq!(test_module_lines(SOURCE), vec![7..12]);
+        assert_eq!(test_module_lines("#[cfg( test )] mod t { }"), vec![1..2]);
+        assert!(test_module_lines("mod a {\n#[cfg(test)]\nmod b {}\n}").is_empty());
+    }
+
+    #[test]
+    fn problems_are_marked_by_directory_and_module() {
+        let root = env::temp_dir().join(format!("lintparser-testcode-{}", process::id()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/lib.rs"), SOURCE).unwrap();
+        let check = parse_output("\
+src/lib.rs:2:9: 2:10 warning: unused variable: `s`, #[warn(unused_variables)] on by default
+src/lib.rs:8:5: 8:20 warning: function `helper` is never used, #[warn(dead_code)] on by default
+tests/it.rs:1:1: 1:2 warning: unused import, #[warn(unused_imports)] on by default
+src/missing.rs:1:1: 1:2 warning: unused import, #[warn(unused_imports)] on by default
+");
+        assert_eq!(check.problems()[2].in_test_code, Some(true));
+        let check = check.mark_test_code(&root);
+        fs::remove_dir_all(&root).unwrap();
+
+        let marks: Vec<Option<bool>> = check.problems().iter()
+            .map(|problem| problem.in_test_code)
+            .collect();
+        assert_eq!(marks, vec![Some(false), Some(true), Some(true), None]);
+        assert_eq!(check.stats().test_only_warnings, 2);
+    }
+}
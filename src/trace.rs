@@ -0,0 +1,169 @@
+//! Trace events of checks, tagged with the correlation ID of their check so
+//! that concurrent checks can be told apart.
+
+use std::fmt;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use Fallback;
+
+/// What happened during a check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceKind {
+    /// Cargo was started with the arguments.
+    Started { args: Vec<String> },
+    /// The check deviated from what was requested.
+    Fallback(Fallback),
+    /// Cargo exited.
+    CargoExited { success: bool },
+    /// Cargo was killed, as the check timed out or was cancelled.
+    Killed { timed_out: bool },
+    /// The output of cargo was parsed.
+    Parsed { problems: usize, raw_blocks: usize },
+    /// The check failed transiently, and is run again after the backoff.
+    Retrying { attempt: usize, backoff: Duration },
+    /// Cargo started on another unit, with ```Checker::progress```.
+    Progress(Progress),
+}
+
+/// How far a check has come, by the units that cargo started on.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Progress {
+    pub completed_units: usize,
+    /// The estimated number of units, if it could be estimated.
+    pub total_units: Option<usize>,
+    /// The package of the last unit that cargo started on.
+    pub current_package: String,
+}
+
+impl Progress {
+    /// Counts the unit of a progress line of cargo like ```Checking a v0.1.0
+    /// (/work/a)``` or ```Fresh a v0.1.0```, and returns whether it was one.
+    pub fn advance(&mut self, line: &str) -> bool {
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("Checking"), Some(package)) | (Some("Compiling"), Some(package))
+                    | (Some("Fresh"), Some(package)) => {
+                self.completed_units += 1;
+                self.current_package = String::from(package);
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Returns the estimated percentage of the check that is done, which
+    /// only grows and never exceeds 100, or ```None``` without an estimate
+    /// of the units.
+    pub fn percent(&self) -> Option<u8> {
+        let total = self.total_units?;
+        if total == 0 {
+            return Some(100);
+        }
+        Some((self.completed_units.min(total) * 100 / total) as u8)
+    }
+}
+
+/// An event of a check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEvent {
+    pub correlation_id: String,
+    pub kind: TraceKind,
+}
+
+/// Formats the event as a log line, e.g. ```[3f9a1c02] cargo exited (success)```.
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] ", self.correlation_id)?;
+        match self.kind {
+            TraceKind::Started { ref args } => write!(f, "started: cargo {}", args.join(" ")),
+            TraceKind::Fallback(ref fallback) => write!(f, "fallback: {:?}", fallback),
+            TraceKind::CargoExited { success } => write!(f, "cargo exited ({})",
+                if success { "success" } else { "failure" }),
+            TraceKind::Killed { timed_out } => write!(f, "cargo killed ({})",
+                if timed_out { "timed out" } else { "cancelled" }),
+            TraceKind::Parsed { problems, raw_blocks } => write!(f,
+                "parsed {} problems and {} raw blocks", problems, raw_blocks),
+            TraceKind::Retrying { attempt, backoff } => write!(f,
+                "retrying (attempt {}) in {:?}", attempt, backoff),
+            TraceKind::Progress(ref progress) => {
+                write!(f, "progress: {} units", progress.completed_units)?;
+                if let (Some(total), Some(percent)) = (progress.total_units, progress.percent()) {
+                    write!(f, " of {} ({}%)", total, percent)?;
+                }
+                write!(f, ", at {}", progress.current_package)
+            },
+        }
+    }
+}
+
+/// Sends the events of a single check.
+pub struct Tracer<'a> {
+    pub correlation_id: String,
+    sink: Option<&'a Sender<TraceEvent>>,
+}
+
+impl<'a> Tracer<'a> {
+    pub fn new(correlation_id: String, sink: Option<&'a Sender<TraceEvent>>) -> Tracer<'a> {
+        Tracer { correlation_id, sink }
+    }
+
+    /// Sends an event, if there is a sink. A sink that hung up is ignored.
+    pub fn emit(&self, kind: TraceKind) {
+        if let Some(sink) = self.sink {
+            let _ = sink.send(TraceEvent { correlation_id: self.correlation_id.clone(), kind });
+        }
+    }
+}
+
+/// Generates a short ID that is unlikely to repeat, from the time, the
+/// process and a counter.
+pub fn generate_id() -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+    let key = format!("{}-{}-{}", nanos, process::id(), COUNTER.fetch_add(1, Ordering::Relaxed));
+    // 32-bit FNV-1a
+    let mut hash: u32 = 0x811c_9dc5;
+    for byte in key.bytes() {
+        hash ^= u32::from(byte);
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    format!("{:08x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_counts_units_and_stays_within_100_percent() {
+        let mut progress = Progress { total_units: Some(3), ..Progress::default() };
+        let lines = ["   Compiling libc v0.2.150", "warning: unused import",
+            "       Fresh cfg-if v1.0.0", "    Checking a v0.1.0 (/work/a)",
+            "    Checking b v0.1.0 (/work/b)"];
+        let mut percents = Vec::new();
+        for line in &lines {
+            if progress.advance(line) {
+                percents.push(progress.percent());
+            }
+        }
+        assert_eq!(percents, vec![Some(33), Some(66), Some(100), Some(100)]);
+        assert_eq!(progress.current_package, "b");
+        let event = TraceEvent { correlation_id: String::from("3f9a1c02"),
+            kind: TraceKind::Progress(progress) };
+        assert_eq!(event.to_string(), "[3f9a1c02] progress: 4 units of 3 (100%), at b");
+        assert_eq!(Progress::default().percent(), None);
+    }
+
+    #[test]
+    fn generated_ids_are_short_and_distinct() {
+        let ids: Vec<String> = (0..100).map(|_| generate_id()).collect();
+        assert!(ids.iter().all(|id| id.len() == 8));
+        let mut unique = ids.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), ids.len());
+    }
+}
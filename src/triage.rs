@@ -0,0 +1,208 @@
+//! Triage decisions about problems, keyed by their fingerprints, so that
+//! reviewed problems can be told apart from new ones.
+//!
+//! The database is a JSON object with one member per fingerprint, written
+//! one member per line so that it diffs well when it is committed.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use json::Value;
+use {Check, ProblemDescription};
+
+/// What was decided about a problem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    WontFix,
+    /// The problem will be fixed by the given version or milestone.
+    FixBy(String),
+    FalsePositive,
+}
+
+impl Decision {
+    /// Parses a decision as written by its ```Display```: ```wontfix```,
+    /// ```false-positive``` or ```fix-by:<version>```.
+    pub fn parse(text: &str) -> Option<Decision> {
+        match text {
+            "wontfix" => Some(Decision::WontFix),
+            "false-positive" => Some(Decision::FalsePositive),
+            _ => match text.strip_prefix("fix-by:") {
+                Some(version) if ! version.is_empty() => Some(Decision::FixBy(String::from(version))),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Decision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Decision::WontFix => write!(f, "wontfix"),
+            Decision::FixBy(ref version) => write!(f, "fix-by:{}", version),
+            Decision::FalsePositive => write!(f, "false-positive"),
+        }
+    }
+}
+
+/// A recorded decision, with the reviewer's note.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TriageEntry {
+    pub decision: Decision,
+    pub note: String,
+}
+
+/// A database of triage decisions, backed by a JSON file.
+#[derive(Debug)]
+pub struct TriageDb {
+    path: PathBuf,
+    entries: BTreeMap<String, TriageEntry>,
+}
+
+impl TriageDb {
+    /// Opens the database at the given path, which is created when it is
+    /// first saved if it doesn't exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<TriageDb> {
+        let path = path.as_ref().to_path_buf();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
+                return Ok(TriageDb { path, entries: BTreeMap::new() });
+            },
+            Err(err) => return Err(err),
+        };
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData,
+            format!("'{}' is not a triage database", path.display()));
+        let value = Value::parse(&text).map_err(|_| invalid())?;
+        let mut entries = BTreeMap::new();
+        for (fingerprint, entry) in value.as_object().ok_or_else(invalid)? {
+            let decision = entry.get("decision").and_then(Value::as_str)
+                .and_then(Decision::parse)
+                .ok_or_else(invalid)?;
+            let note = entry.get("note").and_then(Value::as_str).unwrap_or("");
+            entries.insert(fingerprint.clone(), TriageEntry { decision, note: String::from(note) });
+        }
+        Ok(TriageDb { path, entries })
+    }
+
+    /// Returns the decisions, by fingerprint.
+    pub fn entries(&self) -> &BTreeMap<String, TriageEntry> {
+        &self.entries
+    }
+
+    /// Returns the decision about a problem, if one was made.
+    pub fn get(&self, problem: &ProblemDescription) -> Option<&TriageEntry> {
+        self.entries.get(&problem.fingerprint())
+    }
+
+    /// Records (or replaces) the decision for a fingerprint.
+    pub fn set<F: Into<String>, N: Into<String>>(&mut self, fingerprint: F, decision: Decision,
+            note: N) {
+        self.entries.insert(fingerprint.into(), TriageEntry { decision, note: note.into() });
+    }
+
+    /// Removes the decision for a fingerprint, and returns it.
+    pub fn remove(&mut self, fingerprint: &str) -> Option<TriageEntry> {
+        self.entries.remove(fingerprint)
+    }
+
+    /// Returns the fingerprints with decisions that don't match any problem
+    /// of the check anymore.
+    pub fn stale(&self, check: &Check) -> Vec<&str> {
+        let fingerprints: Vec<String> = check.problems().iter()
+            .map(ProblemDescription::fingerprint)
+            .collect();
+        self.entries.keys()
+            .filter(|fingerprint| ! fingerprints.contains(fingerprint))
+            .map(|fingerprint| &fingerprint[..])
+            .collect()
+    }
+
+    /// Writes the database to its file, replacing it in a single step.
+    pub fn save(&self) -> io::Result<()> {
+        let mut text = String::from("{");
+        for (i, (fingerprint, entry)) in self.entries.iter().enumerate() {
+            let entry = Value::object(vec![
+                ("decision", Value::from(entry.decision.to_string())),
+                ("note", Value::from(&entry.note[..])),
+            ]);
+            text.push_str(if i == 0 { "\n  " } else { ",\n  " });
+            text.push_str(&format!("{}: {}", Value::from(&fingerprint[..]), entry));
+        }
+        text.push_str("\n}\n");
+        if let Some(dir) = self.path.parent() {
+            if ! dir.as_os_str().is_empty() {
+                fs::create_dir_all(dir)?;
+            }
+        }
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        fs::write(&temp, text)?;
+        fs::rename(&temp, &self.path)
+    }
+}
+
+impl Check {
+    /// Pairs each problem with the decision recorded for it, if any.
+    pub fn annotate_triage(&self, db: &TriageDb) -> Vec<(ProblemDescription, Option<Decision>)> {
+        self.problems().iter()
+            .map(|problem| (problem.clone(), db.get(problem).map(|entry| entry.decision.clone())))
+            .collect()
+    }
+
+    /// Returns the check without the problems that have a decision, e.g. for
+    /// exporting only the problems that still need a review.
+    pub fn without_triaged(&self, db: &TriageDb) -> Check {
+        Check::from_problems(self.problems().iter()
+            .filter(|problem| db.get(problem).is_none())
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn problem(message: &str) -> ProblemDescription {
+        ProblemDescription::new(String::from("src/lib.rs"), 1, 1, 1, 2, String::from(message),
+            vec![], vec![])
+    }
+
+    #[test]
+    fn decisions_round_trip_through_text() {
+        for decision in &[Decision::WontFix, Decision::FixBy(String::from("v2.0")),
+                Decision::FalsePositive] {
+            assert_eq!(Decision::parse(&decision.to_string()).as_ref(), Some(decision));
+        }
+        assert_eq!(Decision::parse("fix-by:"), None);
+        assert_eq!(Decision::parse("later"), None);
+    }
+
+    #[test]
+    fn decisions_persist_and_annotate_problems() {
+        let path = env::temp_dir().join(format!("lintparser-triage-{}.json", ::std::process::id()));
+        let _ = fs::remove_file(&path);
+        let check = Check::Warning(vec![problem("unused"), problem("unused \"quoted\"")]);
+        let reviewed = check.problems()[0].fingerprint();
+
+        let mut db = TriageDb::open(&path).unwrap();
+        db.set(&reviewed[..], Decision::FixBy(String::from("v2.0")), "after the \"rewrite\"");
+        db.set("0000000000000000", Decision::WontFix, "");
+        db.save().unwrap();
+        let db = TriageDb::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(db.entries().len(), 2);
+        assert_eq!(db.entries()[&reviewed].note, "after the \"rewrite\"");
+        let decisions: Vec<Option<Decision>> = check.annotate_triage(&db).into_iter()
+            .map(|(_, decision)| decision)
+            .collect();
+        assert_eq!(decisions, vec![Some(Decision::FixBy(String::from("v2.0"))), None]);
+        assert_eq!(db.stale(&check), vec!["0000000000000000"]);
+        assert_eq!(check.without_triaged(&db).problems().len(), 1);
+    }
+}
@@ -0,0 +1,178 @@
+//! The units that cargo says it compiled, checked or found fresh, for seeing
+//! what a check rebuilt and why it was slow.
+
+use std::path::PathBuf;
+
+use {CheckReport, RawBlock, SkipReason};
+
+/// What cargo did with a unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verb {
+    Compiling,
+    Checking,
+    /// The unit was up to date, which cargo only says with ```--verbose```.
+    Fresh,
+}
+
+impl Verb {
+    /// Whether the unit was built rather than reused.
+    pub fn is_rebuilt(self) -> bool {
+        self != Verb::Fresh
+    }
+}
+
+/// A progress line of cargo like ```Compiling foo v1.2.3 (/work/foo)```.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnitLine {
+    pub verb: Verb,
+    pub package: String,
+    /// The version, without the ```v```.
+    pub version: String,
+    /// The directory of a local package, or ```None``` for packages from a
+    /// registry or a git repository.
+    pub path: Option<PathBuf>,
+}
+
+/// Returns the parenthesized groups of the text, like ```(/work/a (copy))
+/// (build script)```, or ```None``` if it has anything else.
+fn groups(text: &str) -> Option<Vec<&str>> {
+    let mut groups = Vec::new();
+    let mut rest = text.trim();
+    while ! rest.is_empty() {
+        if ! rest.starts_with('(') {
+            return None;
+        }
+        let mut depth = 0;
+        let end = rest.char_indices().find(|&(_, ch)| {
+            match ch {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {},
+            }
+            depth == 0
+        })?.0;
+        groups.push(&rest[1..end]);
+        rest = rest[end + 1..].trim_start();
+    }
+    Some(groups)
+}
+
+/// Returns the directory of a source like ```/work/foo``` or
+/// ```file:///work/foo```, or ```None``` for other sources and annotations
+/// like ```https://github.com/a/foo#1a2b3c4d``` or ```proc-macro```.
+fn local_path(source: &str) -> Option<PathBuf> {
+    if let Some(path) = source.strip_prefix("file://") {
+        return Some(PathBuf::from(path));
+    }
+    let bytes = source.as_bytes();
+    let is_drive = bytes.len() > 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+        && (bytes[2] == b'\\' || bytes[2] == b'/');
+    if source.starts_with('/') || source.starts_with("\\\\") || is_drive {
+        Some(PathBuf::from(source))
+    } else {
+        None
+    }
+}
+
+/// Parses a progress line of a unit, or returns ```None``` for other lines.
+fn unit_line(line: &str) -> Option<UnitLine> {
+    let line = line.trim();
+    let (verb, rest) = line.split_once(' ')?;
+    let verb = match verb {
+        "Compiling" => Verb::Compiling,
+        "Checking" => Verb::Checking,
+        "Fresh" => Verb::Fresh,
+        _ => return None,
+    };
+    let mut words = rest.trim_start().splitn(3, ' ');
+    let package = words.next()?;
+    let version = words.next()?.strip_prefix('v')?;
+    if ! version.starts_with(|ch: char| ch.is_ascii_digit()) {
+        return None;
+    }
+    let path = groups(words.next().unwrap_or(""))?.into_iter().find_map(local_path);
+    Some(UnitLine {
+        verb,
+        package: String::from(package),
+        version: String::from(version),
+        path,
+    })
+}
+
+/// Returns the units of the progress lines that the parser ignored, in the
+/// order of the output.
+pub fn unit_lines(raw_blocks: &[RawBlock]) -> Vec<UnitLine> {
+    raw_blocks.iter()
+        .filter(|block| block.reason == SkipReason::Ignored)
+        .flat_map(|block| block.lines.lines())
+        .filter_map(unit_line)
+        .collect()
+}
+
+impl CheckReport {
+    /// Returns the packages that cargo compiled or checked, each once, in
+    /// the order of the output.
+    pub fn rebuilt_packages(&self) -> Vec<&str> {
+        self.packages(true)
+    }
+
+    /// Returns the packages that cargo found fresh and didn't rebuild, each
+    /// once, in the order of the output. Cargo only says so with
+    /// ```--verbose```.
+    pub fn fresh_packages(&self) -> Vec<&str> {
+        self.packages(false)
+    }
+
+    fn packages(&self, rebuilt: bool) -> Vec<&str> {
+        let mut packages: Vec<&str> = Vec::new();
+        for unit in self.units.iter().filter(|unit| unit.verb.is_rebuilt() == rebuilt) {
+            if ! packages.contains(&&unit.package[..]) {
+                packages.push(&unit.package);
+            }
+        }
+        packages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parse_output_lenient;
+
+    #[test]
+    fn units_are_read_in_every_form() {
+        let report = parse_output_lenient("\
+   Compiling proc-macro2 v1.0.70
+   Compiling serde_derive v1.0.193 (proc-macro)
+   Compiling ring v0.17.7 (build script)
+       Fresh cfg-if v1.0.0
+   Compiling fork v0.2.0 (https://github.com/a/fork?branch=dev#1a2b3c4d)
+    Checking core v0.1.0 (/work/my app (copy)/core)
+    Checking app v0.1.0 (file:///work/app)
+src/lib.rs:1:5: 1:8 error: unresolved name `foo` [E0425]
+   Compiling ring v0.17.7
+       Fresh libc v0.2.150 (C:\\work\\libc) (build script)
+error: could not compile `app` (lib) due to 1 previous error
+");
+        assert_eq!(report.check.problems().len(), 1);
+        let units: Vec<(Verb, &str, &str, Option<&str>)> = report.units.iter()
+            .map(|unit| (unit.verb, &unit.package[..], &unit.version[..],
+                unit.path.as_ref().and_then(|path| path.to_str())))
+            .collect();
+        assert_eq!(units, vec![
+            (Verb::Compiling, "proc-macro2", "1.0.70", None),
+            (Verb::Compiling, "serde_derive", "1.0.193", None),
+            (Verb::Compiling, "ring", "0.17.7", None),
+            (Verb::Fresh, "cfg-if", "1.0.0", None),
+            (Verb::Compiling, "fork", "0.2.0", None),
+            (Verb::Checking, "core", "0.1.0", Some("/work/my app (copy)/core")),
+            (Verb::Checking, "app", "0.1.0", Some("/work/app")),
+            (Verb::Compiling, "ring", "0.17.7", None),
+            (Verb::Fresh, "libc", "0.2.150", Some("C:\\work\\libc")),
+        ]);
+        assert_eq!(report.rebuilt_packages(),
+            vec!["proc-macro2", "serde_derive", "ring", "fork", "core", "app"]);
+        assert_eq!(report.fresh_packages(), vec!["cfg-if", "libc"]);
+        assert_eq!(unit_line("    Checking 3 units"), None);
+    }
+}
@@ -0,0 +1,121 @@
+//! Checking the parser of the human format against the JSON messages of
+//! cargo, whose ```rendered``` text is the human format of the same
+//! diagnostic.
+
+use std::fmt;
+use std::path::Path;
+
+use {parse_output_lenient, Check, ProblemDescription};
+
+/// A part of a problem that the human parser reads differently from the
+/// rendered text of the problem than its JSON message has it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedMismatch {
+    /// The index of the problem in the check.
+    pub index: usize,
+    /// The part, like ```"message"``` or ```"start_col"```, or
+    /// ```"problems"``` when the text isn't read as one problem.
+    pub field: &'static str,
+    /// The part as the JSON message has it.
+    pub json: String,
+    /// The part as the human parser reads it from the rendered text.
+    pub rendered: String,
+}
+
+/// Formats the mismatch, e.g. ```problem 0: start_col is 9 in the JSON
+/// message, but 10 in the rendered text```.
+impl fmt::Display for RenderedMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "problem {}: {} is {} in the JSON message, but {} in the rendered text",
+            self.index, self.field, self.json, self.rendered)
+    }
+}
+
+/// Returns the parts of the problems that differ, by name.
+fn fields(json: &ProblemDescription, rendered: &ProblemDescription)
+        -> Vec<(&'static str, String, String)> {
+    let notes = |problem: &ProblemDescription| format!("{:?}", problem.notes.iter()
+        .map(|note| &note.message[..])
+        .collect::<Vec<_>>());
+    // The rendered path is relative, where the JSON one may have been made
+    // absolute
+    let same_file = json.filepath == rendered.filepath
+        || (! rendered.filepath.is_empty()
+            && Path::new(&*json.filepath).ends_with(&*rendered.filepath));
+    let mut fields = vec![
+        ("severity", json.severity.name().to_string(), rendered.severity.name().to_string()),
+        ("start_line", json.message.start_line.to_string(),
+            rendered.message.start_line.to_string()),
+        ("start_col", json.message.start_col.to_string(), rendered.message.start_col.to_string()),
+        ("message", format!("{:?}", json.message.message),
+            format!("{:?}", rendered.message.message)),
+        ("error_code", format!("{:?}", json.error_code()), format!("{:?}", rendered.error_code())),
+        ("notes", notes(json), notes(rendered)),
+    ];
+    if ! same_file {
+        fields.insert(0, ("filepath", json.filepath.to_string(), rendered.filepath.to_string()));
+    }
+    fields.retain(|(_, json, rendered)| json != rendered);
+    fields
+}
+
+impl Check {
+    /// Parses the rendered text of each problem from JSON messages with the
+    /// human parser, and returns where the two disagree, so that the human
+    /// parser can be validated against the output of every check. Problems
+    /// without a rendered text are skipped.
+    ///
+    /// The severity, file, start, message, error code and notes are
+    /// compared. The help isn't, as rustc renders much of it as labels of
+    /// the source, which the human parser doesn't read, and neither is the
+    /// end, which the short format doesn't have.
+    pub fn verify_rendered(&self) -> Vec<RenderedMismatch> {
+        let mut mismatches = Vec::new();
+        for (index, problem) in self.problems().iter().enumerate() {
+            let rendered = match problem.rendered {
+                Some(ref rendered) => parse_output_lenient(rendered).check,
+                None => continue,
+            };
+            let fields = match rendered.problems() {
+                [parsed] => fields(problem, parsed),
+                problems => vec![("problems", String::from("1"), problems.len().to_string())],
+            };
+            mismatches.extend(fields.into_iter().map(|(field, json, rendered)| {
+                RenderedMismatch { index, field, json, rendered }
+            }));
+        }
+        mismatches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parse_json_messages;
+
+    /// A message whose rendered text puts the warning a line below its span.
+    const MESSAGE: &str = r#"{"reason":"compiler-message","package_id":"app 0.1.0 (path+file:///app)","target":{"kind":["lib"],"name":"app"},"message":{"rendered":"warning: unused variable: `x`\n --> src/lib.rs:3:9\n  |\n3 |     let x = 1;\n  |         ^ help: if this is intentional, prefix it with an underscore: `_x`\n  |\n  = note: `#[warn(unused_variables)]` on by default\n\n","$message_type":"diagnostic","children":[{"children":[],"code":null,"level":"note","message":"`#[warn(unused_variables)]` on by default","rendered":null,"spans":[]},{"children":[],"code":null,"level":"help","message":"if this is intentional, prefix it with an underscore","rendered":null,"spans":[{"byte_end":26,"byte_start":25,"column_end":10,"column_start":9,"expansion":null,"file_name":"src/lib.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":"_x","suggestion_applicability":"MachineApplicable","text":[]}]}],"level":"warning","message":"unused variable: `x`","spans":[{"byte_end":26,"byte_start":25,"column_end":10,"column_start":9,"expansion":null,"file_name":"src/lib.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}],"code":{"code":"unused_variables","explanation":null}}}"#;
+
+    #[test]
+    fn disagreements_with_the_rendered_text_are_found() {
+        let check = parse_json_messages(MESSAGE);
+        let mismatches = check.verify_rendered();
+        assert_eq!(mismatches, [RenderedMismatch {
+            index: 0,
+            field: "start_line",
+            json: String::from("2"),
+            rendered: String::from("3"),
+        }]);
+        assert_eq!(mismatches[0].to_string(),
+            "problem 0: start_line is 2 in the JSON message, but 3 in the rendered text");
+
+        // Agreeing, with the path made absolute
+        let mut problems = check.problems().to_vec();
+        let rendered = problems[0].rendered.take().unwrap().replace("lib.rs:3:9", "lib.rs:2:9");
+        problems[0].rendered = Some(rendered);
+        problems[0].filepath = "/app/src/lib.rs".into();
+        assert_eq!(Check::from_problems(problems.clone()).verify_rendered(), []);
+        problems[0].rendered = Some(String::from("not a diagnostic"));
+        assert_eq!(Check::from_problems(problems).verify_rendered()[0].field, "problems");
+    }
+}
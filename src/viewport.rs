@@ -0,0 +1,126 @@
+//! The problems of a check in a stable order for scrolling lists, where only
+//! the problems in view are rendered.
+
+use std::ops::Range;
+use std::path::Path;
+
+use {Check, PathCompare, ProblemDescription, RenderOptions};
+
+/// The problems of a check sorted by file and position. The order is
+/// computed once, when the view is created, for any number of ranges and
+/// lookups. As the view borrows the check, the check can't change while
+/// the order is in use: a changed check needs a new view.
+#[derive(Debug, Clone)]
+pub struct SortedProblems<'a> {
+    problems: Vec<&'a ProblemDescription>,
+    /// The keys of the paths of the problems, in the same order.
+    keys: Vec<String>,
+    compare: PathCompare,
+}
+
+impl<'a> SortedProblems<'a> {
+    /// Returns the number of problems.
+    pub fn len(&self) -> usize {
+        self.problems.len()
+    }
+
+    /// Whether there are no problems.
+    pub fn is_empty(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// Returns the problem at the index of the sorted order.
+    pub fn get(&self, index: usize) -> Option<&'a ProblemDescription> {
+        self.problems.get(index).cloned()
+    }
+
+    /// Returns a line of text for each of the problems in the range of the
+    /// sorted order, like ```src/lib.rs:2:9: warning: unused variable```,
+    /// with the paths shown as in a report. The range is clamped to the
+    /// problems. Only the first line of a message is shown.
+    pub fn render_range(&self, range: Range<usize>, options: &RenderOptions) -> Vec<String> {
+        let end = range.end.min(self.problems.len());
+        let start = range.start.min(end);
+        self.problems[start..end].iter().map(|problem| {
+            let line = format!("{}:{}:{}: {}: {}", options.shown_path(&problem.filepath),
+                problem.message.start_line, problem.message.start_col, problem.severity.name(),
+                problem.message.message.lines().next().unwrap_or(""));
+            match options.sanitize {
+                Some(ref rules) => rules.apply(&line),
+                None => line,
+            }
+        }).collect()
+    }
+
+    /// Returns the index of the first problem in the file whose span covers
+    /// the line, for the problem under the cursor of an editor.
+    pub fn find_index_at(&self, file: &Path, line: usize) -> Option<usize> {
+        let key = self.compare.key(&file.to_string_lossy());
+        let first = self.keys.partition_point(|other| *other < key);
+        let count = self.keys[first..].partition_point(|other| *other == key);
+        let in_file = &self.problems[first..first + count];
+        // Spans that start after the line can't cover it
+        let before = in_file.partition_point(|problem| problem.message.start_line <= line);
+        in_file[..before].iter()
+            .position(|problem| problem.message.end_line >= line)
+            .map(|index| first + index)
+    }
+}
+
+impl Check {
+    /// Returns the problems sorted by file, then by position, with the
+    /// paths compared as given. Problems at the same position keep the
+    /// order of the check.
+    pub fn sorted<'a>(&'a self, compare: PathCompare) -> SortedProblems<'a> {
+        let mut problems: Vec<(String, &ProblemDescription)> = self.problems().iter()
+            .map(|problem| (compare.key(&problem.filepath), problem))
+            .collect();
+        problems.sort_by(|a, b| (&a.0, a.1.message.start_line, a.1.message.start_col)
+            .cmp(&(&b.0, b.1.message.start_line, b.1.message.start_col)));
+        let (keys, problems) = problems.into_iter().unzip();
+        SortedProblems { problems, keys, compare }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use {parse_output, SanitizeRules};
+
+    #[test]
+    fn views_render_and_find_problems_in_sorted_order() {
+        let check = parse_output("\
+/home/alice/src/main.rs:9:5: 9:8 error: unresolved name `foo` [E0425]
+/home/alice/src/lib.rs:4:1: 7:2 warning: function is never used: `helper`, #[warn(dead_code)] on by default
+/home/alice/src/lib.rs:2:9: 2:10 warning: unused variable: `x`, #[warn(unused_variables)] on by default
+/home/alice/src/lib.rs:6:9: 6:10 warning: unused variable: `y`, #[warn(unused_variables)] on by default
+");
+        let sorted = check.sorted(PathCompare::Sensitive);
+        let options = RenderOptions {
+            sanitize: Some(SanitizeRules::new().replace_prefix("/home/alice", "$HOME")),
+            ..RenderOptions::default()
+        };
+        assert_eq!(sorted.render_range(1..3, &options), vec![
+            "$HOME/src/lib.rs:4:1: warning: function is never used: `helper`, \
+                #[warn(dead_code)] on by default",
+            "$HOME/src/lib.rs:6:9: warning: unused variable: `y`, #[warn(unused_variables)] on \
+                by default",
+        ]);
+        let shortened = RenderOptions {
+            path_base: Some(PathBuf::from("/home/alice")),
+            ..RenderOptions::default()
+        };
+        assert_eq!(sorted.render_range(3..4, &shortened),
+            ["src/main.rs:9:5: error: unresolved name `foo` [E0425]"]);
+        assert_eq!(sorted.render_range(3..10, &RenderOptions::default()).len(), 1);
+        assert!(sorted.render_range(7..10, &RenderOptions::default()).is_empty());
+
+        let lib = Path::new("/home/alice/src/./lib.rs");
+        let found: Vec<Option<usize>> = (1..9).map(|line| sorted.find_index_at(lib, line))
+            .collect();
+        assert_eq!(found, vec![None, Some(0), None, Some(1), Some(1), Some(1), Some(1), None]);
+        assert_eq!(sorted.find_index_at(Path::new("/home/alice/src/main.rs"), 9), Some(3));
+        assert_eq!(sorted.find_index_at(Path::new("/home/alice/src/other.rs"), 9), None);
+    }
+}
@@ -0,0 +1,261 @@
+//! Checking a project again whenever its files change, with a callback for
+//! the results that are worth telling about.
+//!
+//! The files are polled: the modification times of the Rust files of the
+//! project and of its manifest are compared at every interval, and a check
+//! is run when one of them has changed, or a file was added or removed.
+//! The report of every check is published (see ```CheckReport::publish```)
+//! unless the checker was told not to.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+use pool::CheckResult;
+use publish::{publish_report, PUBLISHED_FILE};
+use scan::rust_files;
+use {CancelToken, Check, CheckError, CheckReport, Checker};
+
+/// How often the files of a watched project are polled for changes.
+pub const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Which results of a watched project are passed to the callback. Checks
+/// that fail to run are always passed on, though only at the rate of
+/// ```Quiet```.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyPolicy {
+    /// Every result.
+    Always,
+    /// The results whose problems differ from those of the previous check,
+    /// by the ```Check::digest``` of the checks.
+    OnChange,
+    /// The results with problems that the previous check didn't have, by
+    /// ```Check::diff```. Fixed problems alone aren't passed on.
+    OnRegression,
+    /// At most one result in each period of the duration. The latest of the
+    /// results that were held back is passed on when the period is over.
+    Quiet(Duration),
+}
+
+/// Decides which results are passed on under a policy, given the results
+/// before them.
+#[derive(Debug)]
+struct Notifier {
+    policy: NotifyPolicy,
+    /// The problems of the previous result that had them.
+    previous: Option<Check>,
+    /// When a result was last passed on.
+    notified: Option<Instant>,
+    /// Whether the latest result was held back by ```Quiet```.
+    held: bool,
+}
+
+impl Notifier {
+    fn new(policy: NotifyPolicy) -> Notifier {
+        Notifier { policy, previous: None, notified: None, held: false }
+    }
+
+    /// Returns whether the period of ```Quiet``` is over at the instant.
+    fn quiet_over(&self, now: Instant) -> bool {
+        match self.policy {
+            NotifyPolicy::Quiet(period) => {
+                self.notified.is_none_or(|notified| now.duration_since(notified) >= period)
+            },
+            _ => true,
+        }
+    }
+
+    /// Returns whether the result at the instant is passed on, and
+    /// remembers it for the next ones.
+    fn notify(&mut self, result: &Result<CheckReport, CheckError>, now: Instant) -> bool {
+        let empty = Check::from_problems(Vec::new());
+        let previous = self.previous.as_ref();
+        let notify = match (self.policy, result) {
+            (NotifyPolicy::Quiet(_), _) => self.quiet_over(now),
+            (_, &Err(_)) | (NotifyPolicy::Always, _) => true,
+            (NotifyPolicy::OnChange, Ok(report)) => {
+                previous.map(Check::digest) != Some(report.check.digest())
+            },
+            (NotifyPolicy::OnRegression, Ok(report)) => {
+                ! report.check.diff(previous.unwrap_or(&empty)).introduced.is_empty()
+            },
+        };
+        if let Ok(ref report) = *result {
+            self.previous = Some(Check::from_problems(report.check.problems().to_vec()));
+        }
+        if notify {
+            self.notified = Some(now);
+        }
+        self.held = ! notify && matches!(self.policy, NotifyPolicy::Quiet(_));
+        notify
+    }
+
+    /// Returns whether the latest result, which was held back, is passed on
+    /// at the instant, as its period is over.
+    fn notify_held(&mut self, now: Instant) -> bool {
+        let notify = self.held && self.quiet_over(now);
+        if notify {
+            self.notified = Some(now);
+            self.held = false;
+        }
+        notify
+    }
+}
+
+/// The modification times of the files that a check of the project
+/// depends on.
+fn file_times(dir: &Path) -> Vec<(PathBuf, Option<SystemTime>)> {
+    let mut files = rust_files(dir).unwrap_or_default();
+    files.push(dir.join("Cargo.toml"));
+    files.sort();
+    files.into_iter().map(|path| {
+        let modified = fs::metadata(&path).and_then(|metadata| metadata.modified()).ok();
+        (path, modified)
+    }).collect()
+}
+
+/// A project that is being watched, which is stopped when this is dropped.
+pub struct WatchHandle {
+    last: Arc<Mutex<Option<CheckResult>>>,
+    stop: CancelToken,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WatchHandle {
+    /// Returns the result of the latest check, including when the policy
+    /// didn't pass it to the callback.
+    pub fn last_check(&self) -> Option<CheckResult> {
+        self.last.lock().unwrap_or_else(|err| err.into_inner()).clone()
+    }
+
+    /// Stops watching, cancelling a running check, and waits for the
+    /// watcher to finish.
+    pub fn stop(mut self) {
+        self.join();
+    }
+
+    fn join(&mut self) {
+        self.stop.cancel();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+/// Runs the checks of the checker in the directory on another thread, as
+/// ```Checker::watch``` describes, publishing their reports if told to.
+pub fn watch<F>(checker: Checker, dir: PathBuf, publish: bool, policy: NotifyPolicy,
+        mut callback: F) -> WatchHandle
+        where F: FnMut(&Result<CheckReport, CheckError>) + Send + 'static {
+    let stop = CancelToken::new();
+    let last = Arc::new(Mutex::new(None));
+    let checker = checker.cancel_token(stop.clone());
+    let (thread_stop, thread_last) = (stop.clone(), last.clone());
+    let thread = thread::spawn(move || {
+        let mut notifier = Notifier::new(policy);
+        let mut times = None;
+        let mut latest = None;
+        while ! thread_stop.is_cancelled() {
+            let current = file_times(&dir);
+            if times.as_ref() != Some(&current) {
+                times = Some(current);
+                let result = checker.run();
+                // A cancelled check is not a result of the project
+                if thread_stop.is_cancelled() {
+                    break;
+                }
+                if let (true, Ok(report)) = (publish, &result) {
+                    // The watcher goes on without it; the next check tries again
+                    let path = checker.target_dir_in_effect().join(PUBLISHED_FILE);
+                    let _ = publish_report(report, &dir, path, None);
+                }
+                let result = Arc::new(result);
+                *thread_last.lock().unwrap_or_else(|err| err.into_inner()) = Some(result.clone());
+                if notifier.notify(&result, Instant::now()) {
+                    callback(&result);
+                }
+                latest = Some(result);
+            } else if notifier.notify_held(Instant::now()) {
+                if let Some(ref result) = latest {
+                    callback(result);
+                }
+            }
+            thread::sleep(WATCH_INTERVAL);
+        }
+    });
+    WatchHandle { last, stop, thread: Some(thread) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parse_output;
+
+    fn result(output: &str) -> Result<CheckReport, CheckError> {
+        Ok(CheckReport::new(parse_output(output)))
+    }
+
+    #[test]
+    fn policies_pick_the_results_to_pass_on() {
+        let x = "src/lib.rs:2:9: 2:10 warning: unused variable: `x`\n";
+        let y = "src/lib.rs:3:9: 3:10 warning: unused variable: `y`\n";
+        let moved_x = "src/lib.rs:5:9: 5:10 warning: unused variable: `x`\n";
+        // Seconds after the start, and the results then
+        let script = [
+            (0, result(x)),
+            (1, result(x)),
+            (2, result(&format!("{}{}", x, y))),
+            (3, result(y)),
+            (9, result(y)),
+            (10, Err(CheckError::Cancelled)),
+            (11, result(moved_x)),
+            (12, result("")),
+        ];
+        let start = Instant::now();
+        let notified = |policy| {
+            let mut notifier = Notifier::new(policy);
+            script.iter().enumerate()
+                .filter(|&(_, &(seconds, ref result))| {
+                    notifier.notify(result, start + Duration::from_secs(seconds))
+                })
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(notified(NotifyPolicy::Always), vec![0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(notified(NotifyPolicy::OnChange), vec![0, 2, 3, 5, 6, 7]);
+        // The warning of `x` is back after it was fixed, on another line
+        assert_eq!(notified(NotifyPolicy::OnRegression), vec![0, 2, 5, 6]);
+        // The error within the period is held back like the rest
+        assert_eq!(notified(NotifyPolicy::Quiet(Duration::from_secs(5))), vec![0, 4]);
+    }
+
+    #[test]
+    fn quiet_results_that_were_held_back_are_passed_on_later() {
+        let start = Instant::now();
+        let at = |seconds| start + Duration::from_secs(seconds);
+        let mut notifier = Notifier::new(NotifyPolicy::Quiet(Duration::from_secs(5)));
+        assert!(notifier.notify(&result(""), at(0)));
+        assert!(! notifier.notify_held(at(1)));
+        assert!(! notifier.notify(&Err(CheckError::Cancelled), at(2)));
+        assert!(! notifier.notify(&result(""), at(3)));
+        assert!(! notifier.notify_held(at(4)));
+        assert!(notifier.notify_held(at(5)));
+        // Once, and its period starts then
+        assert!(! notifier.notify_held(at(12)));
+        assert!(! notifier.notify(&result(""), at(9)));
+        assert!(notifier.notify(&result(""), at(10)));
+
+        let mut notifier = Notifier::new(NotifyPolicy::OnChange);
+        assert!(notifier.notify(&result(""), at(0)));
+        assert!(! notifier.notify(&result(""), at(1)));
+        assert!(! notifier.notify_held(at(9)));
+    }
+}
@@ -0,0 +1,276 @@
+//! The members of a workspace and their dependencies on each other, for
+//! telling members that are clean from members that weren't checked.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use json::Value;
+use {CheckError, RawBlock, SkipReason};
+
+/// How a workspace member fared in a check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemberStatus {
+    /// The member was checked (its problems, if any, are in the check).
+    Checked,
+    /// The member failed to compile.
+    Failed,
+    /// The member wasn't checked, because a member it depends on (directly
+    /// or not) failed to compile.
+    Blocked { dependency: String },
+}
+
+/// The members of a workspace and the members each of them depends on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkspaceGraph {
+    dependencies: BTreeMap<String, Vec<String>>,
+}
+
+impl WorkspaceGraph {
+    /// Creates an empty graph.
+    pub fn new() -> WorkspaceGraph {
+        WorkspaceGraph::default()
+    }
+
+    /// Reads the graph of the workspace in the directory, with
+    /// ```cargo metadata```.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<WorkspaceGraph, CheckError> {
+        WorkspaceGraph::from_metadata(&cargo_metadata(dir.as_ref())?)
+            .ok_or_else(|| CheckError::InvalidDirectory(dir.as_ref().to_path_buf()))
+    }
+
+    /// Builds the graph from the output of ```cargo metadata --no-deps```.
+    fn from_metadata(metadata: &str) -> Option<WorkspaceGraph> {
+        let metadata = Value::parse(metadata).ok()?;
+        let packages = metadata.get("packages")?.as_array()?;
+        let names: Vec<&str> = packages.iter()
+            .filter_map(|package| package.get("name").and_then(Value::as_str))
+            .collect();
+        let mut graph = WorkspaceGraph::new();
+        for package in packages {
+            let dependencies = package.get("dependencies")?.as_array()?.iter()
+                .filter_map(|dependency| dependency.get("name").and_then(Value::as_str))
+                .filter(|name| names.contains(name));
+            graph.add_member(package.get("name")?.as_str()?, dependencies);
+        }
+        Some(graph)
+    }
+
+    /// Adds a member and the members it depends on directly.
+    pub fn add_member<N, I>(&mut self, name: N, dependencies: I)
+            where N: Into<String>, I: IntoIterator, I::Item: Into<String> {
+        self.dependencies.insert(name.into(),
+            dependencies.into_iter().map(Into::into).collect());
+    }
+
+    /// Classifies every member, given the members that failed to compile.
+    pub fn classify(&self, failed: &[String]) -> BTreeMap<String, MemberStatus> {
+        self.dependencies.keys()
+            .map(|member| {
+                let status = if failed.contains(member) {
+                    MemberStatus::Failed
+                } else if let Some(dependency) = self.failed_dependency(member, failed) {
+                    MemberStatus::Blocked { dependency }
+                } else {
+                    MemberStatus::Checked
+                };
+                (member.clone(), status)
+            })
+            .collect()
+    }
+
+    /// Returns a failed member that the member depends on, if any.
+    fn failed_dependency(&self, member: &str, failed: &[String]) -> Option<String> {
+        let mut visited: Vec<&str> = vec![member];
+        let mut pending: Vec<&str> = vec![member];
+        while let Some(current) = pending.pop() {
+            for dependency in self.dependencies.get(current).into_iter().flatten() {
+                if failed.contains(dependency) {
+                    return Some(dependency.clone());
+                }
+                if ! visited.contains(&&dependency[..]) {
+                    visited.push(dependency);
+                    pending.push(dependency);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Returns the output of ```cargo metadata --no-deps``` for the workspace in
+/// the directory.
+pub fn cargo_metadata(dir: &Path) -> Result<String, CheckError> {
+    // The metadata is written to stdout
+    let output = Command::new("cargo")
+        .args(["metadata", "--no-deps", "--format-version", "1"])
+        .current_dir(dir)
+        .output()?;
+    if ! output.status.success() {
+        return Err(CheckError::InvalidDirectory(dir.to_path_buf()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Estimates the number of units that a check of the workspace in the
+/// directory goes through, as the number of packages in its resolved
+/// dependency graph according to ```cargo metadata```.
+pub fn estimate_units(dir: &Path) -> Option<usize> {
+    let output = Command::new("cargo").args(["metadata", "--format-version", "1"])
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+    if ! output.status.success() {
+        return None;
+    }
+    resolved_packages(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Returns the number of nodes in the resolved graph of the metadata.
+fn resolved_packages(metadata: &str) -> Option<usize> {
+    let metadata = Value::parse(metadata).ok()?;
+    Some(metadata.get("resolve")?.get("nodes")?.as_array()?.len())
+}
+
+/// A unit of a package that cargo says could not be compiled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedUnit {
+    pub package: String,
+    /// The kind of target, like ```lib``` or ```bin "cli"```, which is empty
+    /// for older versions of cargo that don't say it.
+    pub target: String,
+    /// The number of errors, which the oldest versions of cargo don't say.
+    pub error_count: Option<usize>,
+}
+
+/// Formats the unit as e.g. ```mypkg (bin "cli")```.
+impl fmt::Display for FailedUnit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.package)?;
+        if ! self.target.is_empty() {
+            write!(f, " ({})", self.target)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a line like ```error: could not compile `name` (lib) due to 2
+/// previous errors; 1 warning emitted```, or the older ```error: could not
+/// compile `name` due to previous error``` and ```error: Could not compile
+/// `name`.```.
+fn failed_unit(line: &str) -> Option<FailedUnit> {
+    let rest = line.strip_prefix("error: could not compile `")
+        .or_else(|| line.strip_prefix("error: Could not compile `"))?;
+    let end = rest.find('`')?;
+    let package = String::from(&rest[..end]);
+    let mut rest = rest[end + 1..].trim_start();
+    let mut target = String::new();
+    if rest.starts_with('(') {
+        let end = rest.find(") due to").or_else(|| rest.rfind(')'))?;
+        target = String::from(&rest[1..end]);
+        rest = rest[end + 1..].trim_start();
+    }
+    let error_count = rest.strip_prefix("due to ").and_then(|cause| {
+        let cause = cause.split(';').next().unwrap_or(cause);
+        match cause.split_whitespace().next() {
+            Some("previous") => Some(1),
+            Some(count) => count.parse().ok(),
+            None => None,
+        }
+    });
+    Some(FailedUnit { package, target, error_count })
+}
+
+/// Returns the units that the output says could not be compiled, in the
+/// order of the output.
+pub fn failed_units(raw_blocks: &[RawBlock]) -> Vec<FailedUnit> {
+    raw_blocks.iter()
+        .filter(|block| block.reason == SkipReason::Terminator)
+        .filter_map(|block| failed_unit(block.lines.trim()))
+        .collect()
+}
+
+/// Returns the packages that the output says could not be compiled, from
+/// lines like ```error: could not compile `name` (lib) due to 2 previous
+/// errors```.
+pub fn failed_packages(raw_blocks: &[RawBlock]) -> Vec<String> {
+    let mut failed: Vec<String> = failed_units(raw_blocks).into_iter()
+        .map(|unit| unit.package)
+        .collect();
+    failed.sort();
+    failed.dedup();
+    failed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parse_output_lenient;
+
+    #[test]
+    fn members_behind_a_failed_dependency_are_blocked() {
+        let mut graph = WorkspaceGraph::new();
+        graph.add_member("core", Vec::<String>::new());
+        graph.add_member("io", vec!["core"]);
+        graph.add_member("app", vec!["io"]);
+        graph.add_member("tools", Vec::<String>::new());
+
+        let report = parse_output_lenient("\
+src/lib.rs:1:5: 1:8 error: unresolved name `foo` [E0425]
+error: aborting due to previous error
+error: could not compile `core` (lib) due to 1 previous error
+");
+        let failed = failed_packages(&report.raw_blocks);
+        assert_eq!(failed, vec![String::from("core")]);
+        let members = graph.classify(&failed);
+        let blocked = MemberStatus::Blocked { dependency: String::from("core") };
+        assert_eq!(members["core"], MemberStatus::Failed);
+        assert_eq!(members["io"], blocked);
+        assert_eq!(members["app"], blocked);
+        assert_eq!(members["tools"], MemberStatus::Checked);
+    }
+
+    #[test]
+    fn units_are_estimated_from_the_resolved_graph() {
+        assert_eq!(resolved_packages(r#"{"packages":[],"resolve":{"nodes":[{"id":"a"},{"id":"b"}],"root":null}}"#),
+            Some(2));
+        assert_eq!(resolved_packages(r#"{"packages":[],"resolve":null}"#), None);
+    }
+
+    #[test]
+    fn failed_units_are_read_in_every_wording() {
+        let report = parse_output_lenient("\
+error: could not compile `core` (lib) due to 2 previous errors
+error: could not compile `app` (bin \"cli\") due to 1 previous error; 3 warnings emitted
+error: could not compile `io` (lib test) due to previous error
+error: could not compile `old` due to 4 previous errors
+error: Could not compile `oldest`.
+");
+        let unit = |package: &str, target: &str, error_count| FailedUnit {
+            package: String::from(package), target: String::from(target), error_count,
+        };
+        assert_eq!(report.failed_units, vec![
+            unit("core", "lib", Some(2)),
+            unit("app", "bin \"cli\"", Some(1)),
+            unit("io", "lib test", Some(1)),
+            unit("old", "", Some(4)),
+            unit("oldest", "", None),
+        ]);
+        assert_eq!(report.summary_line(), "lintparser: clean; failed: core (lib), \
+            app (bin \"cli\"), io (lib test), old, oldest; digest cbf29ce484222325");
+    }
+
+    #[test]
+    fn graph_from_metadata_keeps_workspace_dependencies() {
+        let graph = WorkspaceGraph::from_metadata(r#"{"packages": [
+            {"name": "core", "dependencies": [{"name": "libc"}]},
+            {"name": "app", "dependencies": [{"name": "core"}, {"name": "serde"}]}
+        ], "workspace_members": []}"#).unwrap();
+        let mut expected = WorkspaceGraph::new();
+        expected.add_member("core", Vec::<String>::new());
+        expected.add_member("app", vec!["core"]);
+        assert_eq!(graph, expected);
+    }
+}
@@ -0,0 +1,56 @@
+//! The ways that other crates use problems, notes and suggestions, which
+//! must keep compiling when fields or variants are added to them.
+
+extern crate lintparser;
+
+use lintparser::{parse_check_output, Applicability, Check, Note, ProblemDescription, Severity,
+    Suggestion, SuggestionTarget};
+
+#[test]
+fn problems_are_built_read_and_matched_from_outside() {
+    let span = Note::new(2, 9, 2, 10, "unused variable: `x`").with_byte_range(20, 21);
+    let problem = ProblemDescription::new("src/lib.rs", 2, 9, 2, 10, "unused variable: `x`",
+            vec![Note::new(2, 9, 2, 10, "if this is intentional, prefix it with an underscore")],
+            vec![])
+        .with_severity(Severity::Error)
+        .with_code("unused_variables")
+        .with_suggestion(Suggestion::new(span.clone(), "_x", Applicability::MachineApplicable))
+        .with_primary_span(Note::new(3, 9, 3, 10, "unused variable: `x`"))
+        .with_target("app", "lib");
+
+    // The fields are read and set as before
+    let mut edited = problem.clone();
+    edited.message.message.push_str(" here");
+    edited.drifted = true;
+    assert_eq!((edited.message.byte_start, &edited.message.message[..]),
+        (None, "unused variable: `x` here"));
+    assert_eq!((problem.severity, problem.code.as_deref(), problem.target.as_deref()),
+        (Severity::Error, Some("unused_variables"), Some("lib")));
+    assert_eq!(problem.suggestions[0].span.byte_end, Some(21));
+    assert_eq!(problem.suggestions[0].target, SuggestionTarget::Span);
+    let import = Suggestion::new(Note::new(1, 1, 1, 1, "consider importing"),
+            "use std::fmt;\n", Applicability::MaybeIncorrect)
+        .with_target(SuggestionTarget::FileTop);
+    assert_eq!((import.target, &import.replacement[..]),
+        (SuggestionTarget::FileTop, "use std::fmt;\n"));
+    assert_eq!(problem.additional_primary_spans.len(), 1);
+
+    // Patterns name the fields they need and leave the rest
+    let check = Check::from_problems(vec![problem]);
+    let lines: Vec<usize> = check.problems().iter().map(|problem| match *problem {
+        ProblemDescription { severity: Severity::Error, message: Note { start_line, .. }, .. } => {
+            start_line
+        },
+        ProblemDescription { .. } => 0,
+    }).collect();
+    assert_eq!(lines, [2]);
+    let severities: Vec<&str> = check.problems().iter().map(|problem| match problem.severity {
+        Severity::Error | Severity::Warning => "reported",
+        _ => "other",
+    }).collect();
+    assert_eq!(severities, ["reported"]);
+    let parsed = parse_check_output("src/lib.rs:4:1: 4:2 warning: function is never used: `f`\n")
+        .unwrap();
+    let ProblemDescription { ref filepath, .. } = parsed.problems()[0];
+    assert_eq!(&**filepath, "src/lib.rs");
+}
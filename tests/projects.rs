@@ -0,0 +1,533 @@
+//! Checks of the sample projects in ```tests/projects```.
+
+extern crate lintparser;
+#[cfg(feature = "tokio")]
+extern crate futures_core;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+
+use std::env;
+use std::fs;
+use std::panic;
+use std::process;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use lintparser::{parse_output_lenient, Applicability, SuggestionTarget};
+use lintparser::{annotate_source_with, assert_no_new_problems, cargo_build_in, cargo_check_in,
+    cargo_check_stream_in, cargo_test_compile_in, check_file, parse_json_messages, render_snippet,
+    update_baseline, AnnotateOptions, AnnotationStyle, CargoConfig, Check, CheckError, Checker,
+    Note, NotifyPolicy, PathCompare, ProblemDescription, RenderOptions, ReviewOptions, Severity};
+
+fn project(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/projects").join(name)
+}
+
+/// Returns the check without the times that its problems were emitted at,
+/// which only streamed checks have.
+fn untimed(check: &Check) -> Check {
+    Check::from_problems(check.problems().iter().map(|problem| {
+        let mut problem = problem.clone();
+        problem.emitted_at = None;
+        problem
+    }).collect())
+}
+
+/// The path of the problem relative to the project, which it is absolute in.
+fn position<'a>(problem: &'a ProblemDescription, project: &Path)
+        -> (&'a Path, usize, usize) {
+    let path = Path::new(&*problem.filepath);
+    assert!(path.is_absolute(), "{}", problem.filepath);
+    let root = fs::canonicalize(project).unwrap();
+    (path.strip_prefix(root).unwrap(), problem.message.start_line, problem.message.start_col)
+}
+
+#[test]
+fn clean_project_is_perfect() {
+    let check = cargo_check_in(project("clean")).unwrap();
+    assert!(matches!(check, Check::Perfect), "{:?}", check);
+}
+
+#[test]
+fn warnings_project_has_two_warnings() {
+    let dir = project("warnings");
+    let check = cargo_check_in(&dir).unwrap();
+    let problems = match check {
+        Check::Warning(ref problems) => problems,
+        ref other => panic!("Expected warnings, got {:?}", other),
+    };
+    assert_eq!(problems.len(), 2);
+    assert!(problems.iter().all(|problem| problem.severity == Severity::Warning));
+    assert_eq!(position(&problems[0], &dir), (Path::new("src/main.rs"), 2, 9));
+    assert!(problems[0].message.message.starts_with("unused variable: `unused`"));
+    assert_eq!(position(&problems[1], &dir), (Path::new("src/main.rs"), 6, 4));
+    assert_eq!(problems[1].message.message, "function `helper` is never used");
+    // The absolute paths are diffed relative to the project
+    let diff = check.suggestions_to_diff(&dir).unwrap();
+    assert!(diff.starts_with("diff --git a/src/main.rs b/src/main.rs\n"), "{}", diff);
+    assert!(diff.contains("+    let _unused = 5;\n"), "{}", diff);
+}
+
+#[test]
+fn errors_project_has_two_errors() {
+    let dir = project("errors");
+    let check = cargo_check_in(&dir).unwrap();
+    let problems = match check {
+        Check::Error(ref problems) => problems,
+        ref other => panic!("Expected errors, got {:?}", other),
+    };
+    assert_eq!(problems.len(), 2);
+    let mismatch = problems.iter().find(|problem| problem.error_code() == Some("E0308")).unwrap();
+    assert_eq!(position(mismatch, &dir), (Path::new("src/main.rs"), 2, 22));
+    let missing = problems.iter().find(|problem| problem.error_code() == Some("E0425")).unwrap();
+    assert_eq!(position(missing, &dir), (Path::new("src/main.rs"), 3, 5));
+    // The problems in a file share its path
+    assert!(Arc::ptr_eq(&problems[0].filepath, &problems[1].filepath));
+}
+
+#[test]
+fn imports_are_suggested_in_both_formats() {
+    let dir = project("imports");
+    // A target directory of its own, so that cargo renders the messages
+    // in the human format rather than replaying them
+    let target = env::temp_dir().join(format!("lintparser-imports-{}", process::id()));
+    let output = process::Command::new("cargo")
+        .arg("check")
+        .current_dir(&dir)
+        .env("CARGO_TARGET_DIR", &target)
+        .output()
+        .unwrap();
+    let _ = fs::remove_dir_all(&target);
+    let human = parse_output_lenient(&String::from_utf8_lossy(&output.stderr)).check;
+    let json = cargo_check_in(&dir).unwrap();
+    // The JSON messages insert a blank line after the import as well
+    let imports = |problem: &ProblemDescription| -> Vec<String> {
+        problem.suggestions.iter()
+            .filter(|suggestion| suggestion.replacement.starts_with("use "))
+            .map(|suggestion| String::from(suggestion.replacement.trim_end()))
+            .collect()
+    };
+    let codes: Vec<Option<&str>> = human.problems().iter().map(|problem| problem.error_code())
+        .collect();
+    assert_eq!(codes, [Some("E0425"), Some("E0433"), Some("E0425"), Some("E0599")]);
+    for (human, json) in human.problems().iter().zip(json.problems()) {
+        assert!(! imports(human).is_empty(), "{}", human);
+        // The text only shows the first few candidates
+        assert!(imports(json).starts_with(&imports(human)), "{}", human);
+        assert!(human.suggestions.iter().all(|suggestion| {
+            suggestion.target == SuggestionTarget::FileTop
+                && suggestion.applicability == Applicability::MaybeIncorrect
+        }));
+    }
+    assert_eq!(imports(&human.problems()[0]), ["use std::collections::HashMap;"]);
+}
+
+#[test]
+fn errors_of_test_code_are_only_found_by_compiling_the_tests() {
+    let dir = project("testonly");
+    assert!(matches!(cargo_check_in(&dir).unwrap(), Check::Perfect));
+    assert!(matches!(cargo_build_in(&dir).unwrap(), Check::Perfect));
+    let check = cargo_test_compile_in(&dir).unwrap();
+    let problems = check.problems();
+    assert_eq!(problems.len(), 1, "{:?}", check);
+    assert_eq!(problems[0].message.message, "mismatched types [E0308]");
+    assert_eq!(position(&problems[0], &dir), (Path::new("src/lib.rs"), 9, 27));
+    assert_eq!(problems[0].target.as_deref(), Some("lib"));
+}
+
+#[test]
+fn errors_of_the_linker_are_problems_of_a_build() {
+    let dir = project("linking");
+    assert!(matches!(cargo_check_in(&dir).unwrap(), Check::Perfect));
+    let check = cargo_build_in(&dir).unwrap();
+    let problems = check.problems();
+    assert_eq!(problems.len(), 1, "{:?}", check);
+    let problem = &problems[0];
+    assert!(problem.is_link_error());
+    assert!(problem.message.message.starts_with("linking with `"), "{}", problem.message.message);
+    assert_eq!((&*problem.filepath, problem.message.start_line), ("", 0));
+    assert!(problem.to_string().starts_with("error: linking with `"), "{}", problem);
+    assert!(problem.notes.iter().any(|note| note.message.contains("lintparser_missing_symbol")),
+        "{:?}", problem.notes);
+}
+
+#[test]
+fn streamed_problems_are_the_problems_of_the_check() {
+    let dir = project("errors");
+    let mut stream = cargo_check_stream_in(&dir).unwrap();
+    let first = stream.next().unwrap().unwrap();
+    assert_eq!(first.severity, Severity::Error);
+    let (check, status) = stream.finish().unwrap();
+    assert!(! status.success());
+    assert_eq!(check.problems()[0], first);
+    assert!(check.problems().iter().all(|problem| problem.emitted_at.is_some()));
+    assert!(Arc::ptr_eq(&check.problems()[0].filepath, &check.problems()[1].filepath));
+    assert_eq!(untimed(&check), cargo_check_in(&dir).unwrap());
+    // A stream that is dropped early kills cargo rather than waiting for it
+    let mut stream = cargo_check_stream_in(project("warnings")).unwrap();
+    assert!(stream.next().is_some());
+    drop(stream);
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn asynchronous_checks_are_the_checks_of_the_stream() {
+    use futures_core::Stream;
+    use lintparser::{cargo_check_async_in, cargo_check_stream_async_in};
+    use std::future::{self, Future};
+    use std::pin::Pin;
+    use std::task::Poll;
+
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let dir = project("errors");
+    let check = runtime.block_on(cargo_check_async_in(&dir)).unwrap();
+    assert_eq!(untimed(&check), cargo_check_in(&dir).unwrap());
+    let mut stream = {
+        let _runtime = runtime.enter();
+        cargo_check_stream_async_in(&dir).unwrap()
+    };
+    let first = runtime.block_on(future::poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)))
+        .unwrap()
+        .unwrap();
+    assert_eq!(first.severity, Severity::Error);
+    let (streamed, status) = runtime.block_on(stream.finish()).unwrap();
+    assert!(! status.success());
+    assert_eq!(untimed(&streamed), untimed(&check));
+    // A future that is dropped while cargo runs kills it
+    let mut check = cargo_check_async_in(project("warnings"));
+    let pending = runtime.block_on(future::poll_fn(|cx| {
+        Poll::Ready(Pin::new(&mut check).poll(cx).is_pending())
+    }));
+    assert!(pending);
+    drop(check);
+    // The options of a checker apply to its asynchronous checks
+    let target = env::temp_dir().join(format!("lintparser-async-{}", process::id()));
+    let checker = Checker::new().dir(project("warnings")).target_dir(&target)
+        .rustflags(["-D", "warnings"]);
+    let denied = runtime.block_on(checker.run_async()).unwrap();
+    let _ = fs::remove_dir_all(&target);
+    assert_eq!(denied.problems().len(), 2);
+    assert!(denied.problems().iter().all(|problem| problem.severity == Severity::Error));
+}
+
+#[test]
+fn the_human_parser_reads_the_rendered_text_of_every_message() {
+    // A target directory of its own, so that cargo renders the messages
+    // afresh rather than replaying those of the other formats
+    let target = env::temp_dir().join(format!("lintparser-rendered-{}", process::id()));
+    for name in ["warnings", "errors", "spans", "unicode", "workspace"] {
+        let output = process::Command::new("cargo")
+            .args(["check", "--message-format", "json"])
+            .current_dir(project(name))
+            .env("CARGO_TARGET_DIR", &target)
+            .output()
+            .unwrap();
+        let check = parse_json_messages(&String::from_utf8_lossy(&output.stdout));
+        assert!(! check.problems().is_empty(), "{}", name);
+        let mismatches: Vec<String> = check.verify_rendered().iter()
+            .map(|mismatch| mismatch.to_string())
+            .collect();
+        assert!(mismatches.is_empty(), "{}: {:#?}", name, mismatches);
+    }
+    let _ = fs::remove_dir_all(&target);
+}
+
+#[test]
+fn problems_of_a_workspace_know_their_package() {
+    let dir = project("workspace");
+    let check = cargo_check_in(&dir).unwrap();
+    assert_eq!(check.problems().len(), 2);
+    let app = check.for_package("app");
+    assert_eq!(app.len(), 1);
+    assert_eq!(position(app[0], &dir), (Path::new("app/src/main.rs"), 2, 9));
+    assert_eq!(app[0].target.as_deref(), Some("bin \"app\""));
+    let core = check.for_package("core-lib");
+    assert_eq!(core.len(), 1);
+    assert_eq!(position(core[0], &dir), (Path::new("core/src/lib.rs"), 5, 4));
+    assert_eq!(core[0].target.as_deref(), Some("lib"));
+    // Checking a single member only has its problems, as the whole check
+    // has them
+    let report = Checker::new().dir(&dir).package("core-lib").run().unwrap();
+    assert_eq!(report.check.problems(), [core[0].clone()]);
+    let sorted = |check: &Check| {
+        let mut problems = check.problems().to_vec();
+        problems.sort_by(|a, b| a.filepath.cmp(&b.filepath));
+        problems
+    };
+    assert_eq!(sorted(&Checker::new().dir(&dir).run().unwrap().check), sorted(&check));
+    // A file of a member is found with the member as the directory
+    let member = dir.join("app/../core");
+    let file = check_file(&member, "src/lib.rs", &Checker::new()).unwrap();
+    assert_eq!(file.problems, [core[0].clone()]);
+    assert_eq!(file.overall.problems(), [core[0].clone()]);
+}
+
+#[test]
+fn problems_of_shared_code_are_merged_across_targets() {
+    let check = cargo_check_in(project("targets")).unwrap();
+    assert_eq!(check.problems().len(), 4);
+    let merged = check.dedup(PathCompare::Sensitive);
+    let problems = merged.problems();
+    assert_eq!(problems.len(), 2);
+    for problem in problems {
+        assert_eq!(problem.count, 2);
+        assert_eq!(problem.targets, ["lib", "bin \"targets\""]);
+    }
+}
+
+#[test]
+fn spans_land_on_multi_byte_characters() {
+    let dir = project("unicode");
+    let check = cargo_check_in(&dir).unwrap();
+    let span = &check.problems()[0].message;
+    assert!(span.message.starts_with("unused variable: `größe`"), "{}", span.message);
+    let source = fs::read_to_string(dir.join("src/main.rs")).unwrap();
+    let line = source.lines().nth(span.start_line - 1).unwrap();
+    let name: String = line.chars().skip(span.char_range_in(line).start)
+        .take(span.char_range_in(line).len())
+        .collect();
+    assert_eq!(name, "größe");
+    let bytes = span.byte_start.unwrap()..span.byte_end.unwrap();
+    assert_eq!(&source[bytes.clone()], "größe");
+    assert_eq!(span.to_byte_range(&source), Some(bytes));
+}
+
+/// Returns the text of the source that the span covers.
+fn covered(source: &str, note: &Note) -> Option<String> {
+    note.to_byte_range(source).map(|range| String::from(&source[range]))
+}
+
+/// Returns the text that every consumer of spans says that the span of the
+/// problem covers, by the name of the consumer. Each feature that reads or
+/// writes columns adds itself here, so that an off-by-one in any of them
+/// fails the conformance of spans. The formats that only keep the start of
+/// a span are given the end of the original.
+fn covered_by_consumers(problem: &ProblemDescription, source: &str, dir: &Path)
+        -> Vec<(&'static str, Option<String>)> {
+    let note = &problem.message;
+    let line = source.lines().nth(note.start_line - 1).unwrap_or("");
+    let starting_at = |start_line: usize, start_col: usize| {
+        let mut moved = note.clone();
+        moved.start_line = start_line;
+        moved.start_col = start_col;
+        covered(source, &moved)
+    };
+    let position = |text: &str| {
+        let mut numbers = text.split(':').map(|number| number.trim().parse::<usize>().ok());
+        match (numbers.next(), numbers.next()) {
+            (Some(Some(line)), Some(Some(col))) => starting_at(line, col),
+            _ => None,
+        }
+    };
+    let alone = Check::from_problems(vec![problem.clone()]);
+    let mut covered_by = vec![
+        ("columns", covered(source, note)),
+        ("byte offsets", note.byte_start.zip(note.byte_end)
+            .and_then(|(start, end)| source.get(start..end))
+            .map(String::from)),
+        ("char_range_in", Some(line.chars().skip(note.char_range_in(line).start)
+            .take(note.char_range_in(line).len())
+            .collect())),
+    ];
+
+    // The carets of a snippet are under the characters of its line
+    let snippet = render_snippet(source, problem, 4).unwrap_or_default();
+    let mut snippet_lines = snippet.lines()
+        .map(|line| line.split_once(" | ").map_or("", |(_, text)| text));
+    let code = snippet_lines.next().unwrap_or("");
+    let underline = snippet_lines.next().unwrap_or("");
+    covered_by.push(("render_snippet", Some(code.chars().zip(underline.chars())
+        .filter(|&(_, marker)| marker == '^')
+        .map(|(ch, _)| ch)
+        .collect())));
+    let annotated = annotate_source_with(source, &[problem],
+        &AnnotateOptions { style: AnnotationStyle::Html, tab_width: None });
+    covered_by.push(("annotate_source_with", annotated.split_once("\">")
+        .and_then(|(_, rest)| rest.split_once("</span>"))
+        .map(|(text, _)| String::from(text))));
+
+    // The formats of reports, read back where they can be
+    alone.publish(dir, None).unwrap();
+    let published = Check::load_published(dir).unwrap();
+    covered_by.push(("publish", covered(source, &published.check.problems()[0].message)));
+    let snapshot = Check::from_snapshot(&alone.to_snapshot());
+    covered_by.push(("to_snapshot", snapshot.problems().first()
+        .and_then(|read| starting_at(read.message.start_line, read.message.start_col))));
+    let azure = alone.to_azure_devops(None);
+    let number = |key: &str| azure.split(';').find_map(|part| part.strip_prefix(key))
+        .and_then(|rest| rest.split(']').next())
+        .and_then(|number| number.parse().ok());
+    covered_by.push(("to_azure_devops", number("linenumber=").zip(number("columnnumber="))
+        .and_then(|(line, col)| starting_at(line, col))));
+    let rendered = alone.render(&RenderOptions::default());
+    covered_by.push(("render", rendered.lines().nth(1).and_then(position)));
+    #[cfg(feature = "serde")]
+    {
+        let text = serde_json::to_string(problem).unwrap();
+        let read: ProblemDescription = serde_json::from_str(&text).unwrap();
+        covered_by.push(("serde", covered(source, &read.message)));
+    }
+    covered_by
+}
+
+#[test]
+fn every_consumer_of_spans_covers_the_same_characters() {
+    let dir = project("spans");
+    let check = cargo_check_in(&dir).unwrap();
+    let human = Checker::new().dir(&dir).run().unwrap().check;
+    assert_eq!(check.problems().len(), 6);
+    assert_eq!(human.problems().len(), 6);
+    for problem in check.problems() {
+        // What the span covers is named in the message
+        let name = problem.message.message.split('`').nth(1).unwrap();
+        let source = fs::read_to_string(&*problem.filepath).unwrap();
+        let mut covered_by = covered_by_consumers(problem, &source, &dir);
+        let read = human.problems().iter()
+            .find(|read| read.message.message.starts_with(&problem.message.message))
+            .unwrap();
+        covered_by.push(("human output", covered(&source, &read.message)));
+        for suggestion in &problem.suggestions {
+            covered_by.push(("suggestion", covered(&source, &suggestion.span)));
+        }
+        for (consumer, text) in covered_by {
+            assert_eq!(text.as_deref(), Some(name), "{} of {:?}", consumer, problem.message);
+        }
+    }
+
+    // The suggestions replace the names with themselves prefixed
+    let root = fs::canonicalize(&dir).unwrap();
+    let check = Check::from_problems(check.problems().iter().map(|problem| {
+        let path = Path::new(&*problem.filepath).strip_prefix(&root).unwrap();
+        let mut problem = problem.clone();
+        problem.filepath = path.to_string_lossy().into();
+        problem
+    }).collect());
+    let diff = check.suggestions_to_diff(&root).unwrap();
+    let options = ReviewOptions { root: Some(root.clone()), ..ReviewOptions::default() };
+    let review = check.diff(&Check::Perfect).to_github_review_with("1a2b3c", &options);
+    for problem in check.problems().iter().filter(|problem| ! problem.suggestions.is_empty()) {
+        let source = fs::read_to_string(root.join(&*problem.filepath)).unwrap();
+        let start = problem.message.byte_start.unwrap();
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[start..].find(['\r', '\n']).map_or(source.len(), |i| start + i);
+        let fixed = format!("{}_{}", &source[line_start..start], &source[start..line_end]);
+        assert!(diff.lines().any(|line| line.trim_end_matches('\r') == format!("+{}", fixed)),
+            "{} in {}", fixed, diff);
+        let quoted = fixed.replace('\\', "\\\\").replace('"', "\\\"").replace('\t', "\\t");
+        assert!(review.contains(&quoted), "{} in {}", quoted, review);
+    }
+}
+
+#[test]
+fn checks_follow_the_cargo_config_of_the_project() {
+    let parent = fs::canonicalize(project("configured")).unwrap();
+    let dir = parent.join("app");
+    let config = CargoConfig::discover(&dir);
+    assert_eq!(config.rustflags, vec!["--cfg", "from_parent", "--cfg", "from_package"]);
+    assert_eq!(config.files, vec![dir.join(".cargo/config.toml"),
+        parent.join(".cargo/config.toml")]);
+    let checker = Checker::new().dir(&dir);
+    if env::var_os("CARGO_TARGET_DIR").is_none() {
+        assert_eq!(checker.target_dir_in_effect(), parent.join("target/shared"));
+    }
+    let messages = |checker: Checker| -> Vec<String> {
+        checker.run().unwrap().check.problems().iter()
+            .map(|problem| problem.message.message.clone())
+            .collect()
+    };
+    assert_eq!(messages(checker.clone()), vec!["unused variable: `configured`"]);
+    assert!(checker.target_dir_in_effect().join("debug").is_dir());
+    // The flags of the checker are added to those of the config files
+    assert_eq!(messages(checker.clone().rustflags(["--cfg", "from_checker"])),
+        vec!["unused variable: `configured`", "unused variable: `merged`"]);
+    // But those of the environment replace them
+    let replaced = checker.env("RUSTFLAGS", "--cfg from_package")
+        .rustflags(["--cfg", "from_checker"]);
+    assert!(messages(replaced).is_empty());
+}
+
+#[test]
+fn arguments_unknown_to_cargo_fail_the_check() {
+    let err = Checker::new().dir(project("clean")).arg("--no-such-flag").run().unwrap_err();
+    match err {
+        CheckError::CargoFailed { ref stderr, .. } => assert!(stderr.contains("--no-such-flag")),
+        ref other => panic!("Expected cargo to fail, got {:?}", other),
+    }
+}
+
+#[test]
+fn directories_without_a_manifest_are_invalid() {
+    // The sources of a project are a directory without its manifest
+    let sources = project("clean").join("src");
+    match cargo_check_in(&sources) {
+        Err(CheckError::InvalidDirectory(dir)) => assert_eq!(dir, sources),
+        other => panic!("Expected an invalid directory, got {:?}", other),
+    }
+    let missing = project("missing");
+    match cargo_check_in(&missing) {
+        Err(err @ CheckError::InvalidDirectory(_)) => {
+            assert!(err.to_string().contains(&*missing.to_string_lossy()), "{}", err);
+        },
+        other => panic!("Expected an invalid directory, got {:?}", other),
+    }
+    // Rather than as a cargo that can't be run
+    let err = Checker::new().dir(&missing).run().unwrap_err();
+    assert_eq!(err.kind(), "invalid_directory", "{}", err);
+    let err = check_file(&missing, "src/main.rs", &Checker::new()).unwrap_err();
+    assert_eq!(err.kind(), "invalid_directory", "{}", err);
+}
+
+#[test]
+fn the_gate_passes_once_the_baseline_has_the_problems() {
+    let dir = project("warnings");
+    let baseline = env::temp_dir().join(format!("lintparser-baseline-{}.json", process::id()));
+    let _ = fs::remove_file(&baseline);
+    let failure = panic::catch_unwind(|| assert_no_new_problems(&dir, &baseline)).unwrap_err();
+    let message = failure.downcast_ref::<String>().unwrap();
+    assert!(message.starts_with("2 problems are not in the baseline"), "{}", message);
+    assert!(message.contains("src/main.rs:2:9: warning: unused variable: `unused`"), "{}", message);
+
+    update_baseline(&dir, &baseline).unwrap();
+    assert_no_new_problems(&dir, &baseline);
+    fs::remove_file(&baseline).unwrap();
+}
+
+#[test]
+fn watched_projects_are_checked_again_when_they_change() {
+    let dir = env::temp_dir().join(format!("lintparser-watch-{}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::copy(project("warnings").join("Cargo.toml"), dir.join("Cargo.toml")).unwrap();
+    let main = fs::read_to_string(project("warnings").join("src/main.rs")).unwrap();
+    fs::write(dir.join("src/main.rs"), &main).unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    let watcher = Checker::new().dir(&dir).watch(NotifyPolicy::OnChange, move |result| {
+        let _ = sender.send(result.as_ref().map(|report| report.check.problems().len()).ok());
+    });
+    let timeout = Duration::from_secs(60);
+    assert_eq!(receiver.recv_timeout(timeout).unwrap(), Some(2));
+    let first = watcher.last_check().unwrap();
+    // Published before it was passed on
+    assert_eq!(Check::load_published(&dir).unwrap().check.problems().len(), 2);
+    fs::write(dir.join("src/main.rs"), main.replace("let unused = 5;",
+        "let unused = 5;\n    let another = 6;")).unwrap();
+    assert_eq!(receiver.recv_timeout(timeout).unwrap(), Some(3));
+
+    // Saving the file as it was checked runs a check that isn't passed on
+    let second = watcher.last_check().unwrap();
+    thread::sleep(Duration::from_millis(50));
+    let saved = fs::read_to_string(dir.join("src/main.rs")).unwrap();
+    fs::write(dir.join("src/main.rs"), saved).unwrap();
+    let started = Instant::now();
+    while Arc::ptr_eq(&watcher.last_check().unwrap(), &second) {
+        assert!(started.elapsed() < timeout, "the saved file was not checked");
+        thread::sleep(Duration::from_millis(50));
+    }
+    watcher.stop();
+    assert!(receiver.try_recv().is_err());
+    assert!(! Arc::ptr_eq(&first, &second));
+    fs::remove_dir_all(&dir).unwrap();
+}
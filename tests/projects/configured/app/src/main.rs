@@ -0,0 +1,9 @@
+#![allow(unexpected_cfgs)]
+
+// The warnings are only compiled with the flags of the config files
+fn main() {
+    #[cfg(all(from_parent, from_package))]
+    let configured = 1;
+    #[cfg(all(from_parent, from_package, from_checker))]
+    let merged = 2;
+}
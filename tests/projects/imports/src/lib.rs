@@ -0,0 +1,18 @@
+//! Uses of items that aren't imported.
+#![allow(dead_code)]
+
+fn map() -> usize {
+    let m: HashMap<u8, u8> = HashMap::new();
+    m.len()
+}
+
+fn read(file: std::fs::File) -> String {
+    let mut text = String::new();
+    let mut file = file;
+    file.read_to_string(&mut text).unwrap();
+    text
+}
+
+fn value() -> usize {
+    MAX
+}
@@ -0,0 +1,7 @@
+extern "C" {
+    fn lintparser_missing_symbol();
+}
+
+fn main() {
+    unsafe { lintparser_missing_symbol() }
+}
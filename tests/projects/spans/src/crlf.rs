@@ -0,0 +1,7 @@
+//! Lines that end in CRLF, which rustc counts in bytes as they are.
+
+pub fn used() {
+	let crlf = "\r\n";
+}
+
+fn ünused() {}
@@ -0,0 +1,12 @@
+//! Every unused variable and function is a span that all the consumers of
+//! spans must agree on: the name in the message is the text it covers.
+
+mod crlf;
+
+fn main() {
+	let tabbed = 1;
+    let größe = 2;
+    let _text = "ü→"; let after = 3;
+	let	_wide = "日本"; let 日本 = 4;
+    crlf::used();
+}
@@ -0,0 +1,3 @@
+pub fn shared() {
+    let unused = 1;
+}
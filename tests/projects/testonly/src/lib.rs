@@ -0,0 +1,12 @@
+pub fn add(a: u32, b: u32) -> u32 {
+    a + b
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn adds() {
+        let sum: String = super::add(1, 2);
+        assert_eq!(sum, "3");
+    }
+}
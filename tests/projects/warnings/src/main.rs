@@ -0,0 +1,8 @@
+fn main() {
+    let unused = 5;
+    println!("Hello, world!");
+}
+
+fn helper() -> u32 {
+    42
+}
@@ -0,0 +1,7 @@
+pub fn answer() -> u32 {
+    42
+}
+
+fn helper() -> u32 {
+    7
+}